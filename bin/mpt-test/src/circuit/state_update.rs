@@ -123,6 +123,10 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
             degree: self.mpt_circuit.degree,
             disable_preimage_check: self.mpt_circuit.disable_preimage_check,
             max_nodes: self.mpt_circuit.max_nodes,
+            compress_state_selectors: self.mpt_circuit.compress_state_selectors,
+            extract_subexpressions: self.mpt_circuit.extract_subexpressions,
+            expose_roots: self.mpt_circuit.expose_roots,
+            lookup_tables: self.mpt_circuit.lookup_tables,
         }
     }
 
@@ -527,6 +531,10 @@ impl StateUpdateCircuit<Fr> {
             degree,
             max_nodes,
             disable_preimage_check,
+            compress_state_selectors: false,
+            extract_subexpressions: false,
+            expose_roots: false,
+            lookup_tables: Default::default(),
             _marker: std::marker::PhantomData,
         };
 
@@ -4,20 +4,29 @@
 mod access;
 mod block;
 mod call;
+#[cfg(test)]
+mod client_tests;
 mod execution;
 mod input_state_ref;
+mod step_hook;
+mod trace_check;
 #[cfg(test)]
 mod tracer_tests;
 mod transaction;
+mod transfer_profile;
 mod withdrawal;
 
 use self::access::gen_state_access_trace;
 use crate::{
     error::Error,
-    evm::opcodes::{gen_associated_ops, gen_associated_steps},
+    evm::{
+        opcodes::{gen_associated_ops, gen_associated_steps, is_unsupported},
+        OpcodeId,
+    },
     operation::{CallContextField, Operation, RWCounter, StartOp, RW},
-    rpc::GethClient,
+    rpc::{BlockNumber, GethClient, RateLimiter, RpcConfig},
     state_db::{self, CodeDB, StateDB},
+    state_provider::StateProvider,
 };
 pub use access::{Access, AccessSet, AccessValue, CodeSource};
 pub use block::{Block, BlockContext};
@@ -26,27 +35,36 @@ use core::fmt::Debug;
 use eth_types::{
     self, geth_types,
     sign_types::{pk_bytes_le, pk_bytes_swap_endianness, SignData},
-    Address, GethExecStep, GethExecTrace, ToWord, Word,
+    Address, GethExecStep, GethExecTrace, ToWord, Word, H256,
 };
 use ethers_providers::JsonRpcClient;
 pub use execution::{
-    CopyDataType, CopyEvent, CopyStep, ExecState, ExecStep, ExpEvent, ExpStep, NumberOrHash,
-    PrecompileEvent, PrecompileEvents, N_BYTES_PER_PAIR, N_PAIRING_PER_OP,
+    CopyDataType, CopyEvent, CopyEventOrigin, CopyStep, ExecState, ExecStep, ExpEvent, ExpStep,
+    NumberOrHash, PrecompileEvent, PrecompileEvents, N_BYTES_PER_PAIR, N_PAIRING_PER_OP,
 };
+use futures::{stream, StreamExt, TryStreamExt};
 pub use input_state_ref::CircuitInputStateRef;
 use itertools::Itertools;
 use log::warn;
+use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     collections::{HashMap, HashSet},
     ops::Deref,
 };
+pub use step_hook::{CallSummary, JsonlStepTracer, StepHook};
+pub use trace_check::check_geth_traces;
 pub use transaction::{Transaction, TransactionContext};
+pub use transfer_profile::{
+    keccak_fs, TokenTransfer, TransferBudget, TransferBudgets, TransferEstimate, TransferKind,
+    TransferProfile, TransferSample,
+};
 pub use withdrawal::{Withdrawal, WithdrawalContext};
 
 /// Runtime Config
 ///
 /// Default to mainnet block
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FeatureConfig {
     /// Zero difficulty
     pub zero_difficulty: bool,
@@ -60,6 +78,19 @@ pub struct FeatureConfig {
     /// shouldn't be included in a mainnet block. However, rollup developers might want to
     /// include invalid tx in the L2 block to support forced exit feature.
     pub invalid_tx: bool,
+    /// Allow transactions with zero gas price
+    ///
+    /// Such transactions pay neither the base fee nor a tip to the coinbase. Several L2 devnets
+    /// and account abstraction relays produce them even when the block has a non-zero base fee.
+    pub zero_gas_price: bool,
+    /// Opcodes that are not available on the network
+    ///
+    /// Witness generation fails with [`Error::UnsupportedOpcode`] when a block executes one of
+    /// them, and the EVM circuit configured with this feature rejects the steps executing them.
+    pub disabled_opcodes: Vec<OpcodeId>,
+    /// What witness generation does with the transactions executing an unsupported or a
+    /// disabled opcode
+    pub on_unsupported: UnsupportedPolicy,
 }
 
 impl Default for FeatureConfig {
@@ -69,6 +100,9 @@ impl Default for FeatureConfig {
             free_first_tx: false,
             enable_eip1559: true,
             invalid_tx: false,
+            zero_gas_price: false,
+            disabled_opcodes: Vec::new(),
+            on_unsupported: UnsupportedPolicy::Fail,
         }
     }
 }
@@ -76,12 +110,34 @@ impl Default for FeatureConfig {
 impl FeatureConfig {
     /// Check if we are mainnet config
     pub fn is_mainnet(&self) -> bool {
-        self.zero_difficulty && !self.free_first_tx && self.enable_eip1559 && !self.invalid_tx
+        self.zero_difficulty
+            && !self.free_first_tx
+            && self.enable_eip1559
+            && !self.invalid_tx
+            && !self.zero_gas_price
+            && self.disabled_opcodes.is_empty()
     }
 }
 
+/// Handling of the transactions executing an opcode not supported by the circuits, like
+/// SELFDESTRUCT, or disabled by [`FeatureConfig::disabled_opcodes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnsupportedPolicy {
+    /// Fail with [`Error::UnsupportedOpcode`]
+    #[default]
+    Fail,
+    /// Leave the transactions out of the block before handling it, so that the rest of the
+    /// block can be proven while the circuits don't support every opcode.
+    ///
+    /// The block is checked upfront, so SELFDESTRUCT skips a transaction even when it fails.
+    /// The traces of the remaining transactions must not depend on the skipped ones, otherwise
+    /// they disagree with the state left by the remaining transactions and witness generation
+    /// fails.
+    SkipTx,
+}
+
 /// Circuit Setup Parameters
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct FixedCParams {
     /// Maximum number of rw operations in the state circuit (RwTable length /
     /// number of rows). This must be at least the number of rw operations
@@ -196,6 +252,8 @@ pub struct CircuitInputBuilder<C: CircuitsParams> {
     pub block_ctx: BlockContext,
     /// Feature config
     pub feature_config: FeatureConfig,
+    /// Hook called on every generated step
+    pub step_hook: Option<Box<dyn StepHook>>,
 }
 
 impl<'a, C: CircuitsParams> CircuitInputBuilder<C> {
@@ -215,9 +273,39 @@ impl<'a, C: CircuitsParams> CircuitInputBuilder<C> {
             circuits_params: params,
             block_ctx: BlockContext::new(),
             feature_config,
+            step_hook: None,
+        }
+    }
+
+    /// Set the [`StepHook`] called on every step generated while handling a
+    /// block.
+    pub fn with_step_hook(mut self, step_hook: Box<dyn StepHook>) -> Self {
+        self.step_hook = Some(step_hook);
+        self
+    }
+
+    /// Call the [`StepHook`], if any, on `steps` executed in the call
+    /// summarized by `call`.
+    fn call_step_hook(&mut self, steps: &[ExecStep], call: &CallSummary) {
+        if let Some(step_hook) = self.step_hook.as_mut() {
+            for step in steps {
+                step_hook.on_step(step, call);
+            }
         }
     }
 
+    /// Summary of the current call of `tx_ctx` for the [`StepHook`], only
+    /// taken when there is a hook.
+    fn step_hook_call(&self, tx_ctx: &TransactionContext) -> Option<CallSummary> {
+        self.step_hook.is_some().then(|| {
+            tx_ctx
+                .calls()
+                .last()
+                .map(CallSummary::from)
+                .unwrap_or_default()
+        })
+    }
+
     /// Obtain a mutable reference to the state that the `CircuitInputBuilder`
     /// maintains, contextualized to a particular transaction and a
     /// particular execution step in that transaction.
@@ -234,6 +322,7 @@ impl<'a, C: CircuitsParams> CircuitInputBuilder<C> {
             tx,
             tx_ctx,
             max_rws: self.circuits_params.max_rws(),
+            feature_config: &self.feature_config,
         }
     }
 
@@ -300,18 +389,24 @@ impl<'a, C: CircuitsParams> CircuitInputBuilder<C> {
         is_last_tx: bool,
         tx_index: u64,
     ) -> Result<(), Error> {
+        let _span = tracing::debug_span!("handle_tx", tx_index).entered();
         let mut tx = self.new_tx(tx_index, eth_tx, !geth_trace.failed)?;
         let mut tx_ctx = TransactionContext::new(eth_tx, geth_trace, is_last_tx)?;
 
         if !geth_trace.invalid {
             // Generate BeginTx step
+            let call = self.step_hook_call(&tx_ctx);
             let begin_tx_step = gen_associated_steps(
                 &mut self.state_ref(&mut tx, &mut tx_ctx),
                 ExecState::BeginTx,
             )?;
+            if let Some(call) = call {
+                self.call_step_hook(std::slice::from_ref(&begin_tx_step), &call);
+            }
             tx.steps_mut().push(begin_tx_step);
 
             for (index, geth_step) in geth_trace.struct_logs.iter().enumerate() {
+                let call = self.step_hook_call(&tx_ctx);
                 let mut state_ref = self.state_ref(&mut tx, &mut tx_ctx);
                 log::trace!("handle {}th opcode {:?} ", index, geth_step.op);
                 let exec_steps = gen_associated_ops(
@@ -319,25 +414,37 @@ impl<'a, C: CircuitsParams> CircuitInputBuilder<C> {
                     &mut state_ref,
                     &geth_trace.struct_logs[index..],
                 )?;
+                if let Some(call) = call {
+                    self.call_step_hook(&exec_steps, &call);
+                }
                 tx.steps_mut().extend(exec_steps);
             }
 
             // Generate EndTx step
+            let call = self.step_hook_call(&tx_ctx);
             let end_tx_step =
                 gen_associated_steps(&mut self.state_ref(&mut tx, &mut tx_ctx), ExecState::EndTx)?;
+            if let Some(call) = call {
+                self.call_step_hook(std::slice::from_ref(&end_tx_step), &call);
+            }
             tx.steps_mut().push(end_tx_step);
         } else if self.feature_config.invalid_tx {
             // Generate InvalidTx step
+            let call = self.step_hook_call(&tx_ctx);
             let invalid_tx_step = gen_associated_steps(
                 &mut self.state_ref(&mut tx, &mut tx_ctx),
                 ExecState::InvalidTx,
             )?;
+            if let Some(call) = call {
+                self.call_step_hook(std::slice::from_ref(&invalid_tx_step), &call);
+            }
             tx.steps_mut().push(invalid_tx_step);
         } else {
             panic!("invalid tx support not enabled")
         }
 
         self.sdb.commit_tx();
+        tracing::debug!(steps = tx.steps().len(), "transaction handled");
         self.block.txs.push(tx);
 
         Ok(())
@@ -382,17 +489,23 @@ impl CircuitInputBuilder<FixedCParams> {
             step.bus_mapping_instance.push(op_ref);
         };
 
+        // The padding rows are numbered up to max_rws, which must not wrap
+        if max_rws > RWCounter::MAX {
+            return Err(Error::CapacityExceeded {
+                circuit: "state",
+                needed: max_rws,
+                available: RWCounter::MAX,
+            });
+        }
         // rwc index start from 1
         let total_rws = state.block_ctx.rwc.0 - 1;
         // We need at least 1 extra Start row
-        #[allow(clippy::int_plus_one)]
-        {
-            assert!(
-                total_rws + 1 <= max_rws,
-                "total_rws + 1 <= max_rws, total_rws={}, max_rws={}",
-                total_rws,
-                max_rws
-            );
+        if total_rws + 1 > max_rws {
+            return Err(Error::CapacityExceeded {
+                circuit: "state",
+                needed: total_rws + 1,
+                available: max_rws,
+            });
         }
         let (padding_start, padding_end) = (1, max_rws - total_rws); // rw counter start from 1
         push_op(
@@ -423,6 +536,20 @@ impl<C: CircuitsParams> CircuitInputBuilder<C> {
         eth_block: &EthBlock,
         geth_traces: &[eth_types::GethExecTrace],
     ) -> Result<(), Error> {
+        let _span = tracing::info_span!(
+            "handle_block",
+            block_num = eth_block.number.unwrap_or_default().as_u64()
+        )
+        .entered();
+        check_geth_traces(eth_block.transactions.len(), geth_traces)?;
+        let (eth_block, geth_traces) = match self.feature_config.on_unsupported {
+            UnsupportedPolicy::Fail => (Cow::Borrowed(eth_block), Cow::Borrowed(geth_traces)),
+            UnsupportedPolicy::SkipTx => {
+                let (eth_block, geth_traces) =
+                    skip_unsupported_txs(eth_block, geth_traces, &self.feature_config);
+                (Cow::Owned(eth_block), Cow::Owned(geth_traces))
+            }
+        };
         // accumulates gas across all txs in the block
         for (idx, tx) in eth_block.transactions.iter().enumerate() {
             let geth_trace = &geth_traces[idx];
@@ -436,8 +563,15 @@ impl<C: CircuitsParams> CircuitInputBuilder<C> {
             )?;
         }
         // set eth_block
-        self.block.eth_block = eth_block.clone();
+        self.block.eth_block = eth_block.into_owned();
         self.set_value_ops_call_context_rwc_eor();
+        tracing::info!(
+            txs = self.block.txs.len(),
+            rws = self.block_ctx.rwc.0 - 1,
+            copy_events = self.block.copy_events.len(),
+            exp_events = self.block.exp_events.len(),
+            "block handled"
+        );
         Ok(())
     }
 }
@@ -451,6 +585,8 @@ impl CircuitInputBuilder<DynamicCParams> {
         geth_traces: &[eth_types::GethExecTrace],
     ) -> Result<CircuitInputBuilder<FixedCParams>, Error> {
         self.begin_handle_block(eth_block, geth_traces)?;
+        // The transactions handled, without the skipped ones
+        let eth_block = &self.block.eth_block;
 
         // Compute subcircuits parameters
         let c_params = {
@@ -514,6 +650,7 @@ impl CircuitInputBuilder<DynamicCParams> {
             circuits_params: c_params,
             block_ctx: self.block_ctx,
             feature_config: self.feature_config,
+            step_hook: self.step_hook,
         };
 
         cib.set_end_block(c_params.max_rws)?;
@@ -521,6 +658,49 @@ impl CircuitInputBuilder<DynamicCParams> {
     }
 }
 
+/// Leave out of `eth_block` the transactions executing an opcode that fails with
+/// [`Error::UnsupportedOpcode`], with their traces.
+fn skip_unsupported_txs(
+    eth_block: &EthBlock,
+    geth_traces: &[eth_types::GethExecTrace],
+    feature_config: &FeatureConfig,
+) -> (EthBlock, Vec<eth_types::GethExecTrace>) {
+    let (transactions, geth_traces): (Vec<_>, Vec<_>) = eth_block
+        .transactions
+        .iter()
+        .zip(geth_traces)
+        .filter(|(tx, geth_trace)| {
+            let unsupported = geth_trace
+                .struct_logs
+                .iter()
+                .find(|step| is_unsupported(&step.op, feature_config));
+            if let Some(step) = unsupported {
+                tracing::warn!(
+                    tx_hash = ?tx.hash,
+                    op = ?step.op,
+                    pc = step.pc,
+                    "unsupported opcode, transaction skipped"
+                );
+            }
+            unsupported.is_none()
+        })
+        .map(|(tx, geth_trace)| (tx.clone(), geth_trace.clone()))
+        .unzip();
+    let transactions = transactions
+        .into_iter()
+        .enumerate()
+        .map(|(idx, tx)| eth_types::Transaction {
+            transaction_index: Some(idx.into()),
+            ..tx
+        })
+        .collect();
+    let eth_block = EthBlock {
+        transactions,
+        ..eth_block.clone()
+    };
+    (eth_block, geth_traces)
+}
+
 /// Return all the keccak inputs used during the processing of the current
 /// block.
 pub fn keccak_inputs(block: &Block, code_db: &CodeDB) -> Result<Vec<Vec<u8>>, Error> {
@@ -620,11 +800,41 @@ type EthBlock = eth_types::Block<eth_types::Transaction>;
 /// Struct that wraps a GethClient and contains methods to perform all the steps
 /// necessary to generate the circuit inputs for a block by querying geth for
 /// the necessary information and using the CircuitInputBuilder.
-pub struct BuilderClient<P: JsonRpcClient> {
+///
+/// The block and its traces always come from geth, while the state before the
+/// block is queried from a [`StateProvider`], which is geth itself unless
+/// another one is set with [`BuilderClient::with_state_provider`].
+pub struct BuilderClient<P: JsonRpcClient, S: StateProvider = GethClient<P>> {
     cli: GethClient<P>,
+    /// Source of the state before the block, `None` to query it from `cli`.
+    state_provider: Option<S>,
     chain_id: Word,
     circuits_params: FixedCParams,
     feature_config: FeatureConfig,
+    rpc_config: RpcConfig,
+    /// Limit of [`RpcConfig::max_requests_per_second`], shared by all the
+    /// queries of the client.
+    rate_limiter: RateLimiter,
+}
+
+/// Progress of [`BuilderClient::get_block_with_progress`].
+///
+/// The progress is kept when a query fails, so that calling
+/// [`BuilderClient::get_block_with_progress`] again with it resumes the
+/// fetching without querying again what was already fetched.
+#[derive(Debug, Clone, Default)]
+pub struct BlockFetchProgress {
+    block_num: u64,
+    block: Option<(EthBlock, Vec<eth_types::GethExecTrace>)>,
+    /// Hash and state root of the fetched history blocks, by block number.
+    history: HashMap<u64, (Word, Word)>,
+}
+
+impl BlockFetchProgress {
+    /// Number of history blocks already fetched.
+    pub fn num_history_fetched(&self) -> usize {
+        self.history.len()
+    }
 }
 
 /// Get State Accesses from TxExecTraces
@@ -650,6 +860,79 @@ pub fn get_state_accesses(
     Ok(AccessSet::from(block_access_trace))
 }
 
+/// Query a [`StateProvider`] for all accounts, storage keys, and codes from
+/// Accesses, at the state right before `block_num`. The storage keys of an
+/// account are split in several `eth_getProof` queries according to
+/// [`RpcConfig::max_keys_per_proof`]. Providers that batch requests get up to
+/// [`RpcConfig::max_batch_size`] queries at once, the others one query at a
+/// time. Up to [`RpcConfig::max_concurrency`] of these are in flight at the
+/// same time, each waiting for `rate_limiter` to let its queries through.
+pub async fn get_state<S: StateProvider>(
+    provider: &S,
+    block_num: u64,
+    access_set: AccessSet,
+    rpc_config: &RpcConfig,
+    rate_limiter: &RateLimiter,
+) -> Result<
+    (
+        Vec<eth_types::EIP1186ProofResponse>,
+        HashMap<Address, Vec<u8>>,
+    ),
+    Error,
+> {
+    let block_num = BlockNumber::from(block_num - 1);
+    let max_concurrency = rpc_config.max_concurrency.max(1);
+    let max_keys = rpc_config.max_keys_per_proof.max(1);
+    let batch_size = if provider.batches_requests() {
+        rpc_config.max_batch_size.max(1)
+    } else {
+        1
+    };
+
+    let mut queries = Vec::new();
+    for (address, key_set) in access_set.state {
+        let mut keys: Vec<Word> = key_set.into_iter().collect();
+        keys.sort();
+        if keys.is_empty() {
+            queries.push((address, keys));
+        } else {
+            queries.extend(keys.chunks(max_keys).map(|keys| (address, keys.to_vec())));
+        }
+    }
+    let chunk_proofs: Vec<Vec<eth_types::EIP1186ProofResponse>> =
+        stream::iter(queries.chunks(batch_size).map(<[_]>::to_vec))
+            .map(|queries| async move {
+                rate_limiter.acquire(queries.len()).await;
+                provider.get_proofs(queries, block_num).await
+            })
+            .buffered(max_concurrency)
+            .try_collect()
+            .await?;
+    // The queries of the keys of an account follow each other
+    let mut proofs: Vec<eth_types::EIP1186ProofResponse> = Vec::new();
+    for proof in chunk_proofs.into_iter().flatten() {
+        match proofs.last_mut() {
+            Some(last) if last.address == proof.address => {
+                last.storage_proof.extend(proof.storage_proof)
+            }
+            _ => proofs.push(proof),
+        }
+    }
+
+    let addresses: Vec<Address> = access_set.code.into_iter().collect();
+    let codes: Vec<Vec<(Address, Vec<u8>)>> =
+        stream::iter(addresses.chunks(batch_size).map(<[_]>::to_vec))
+            .map(|addresses| async move {
+                rate_limiter.acquire(addresses.len()).await;
+                let codes = provider.get_codes(addresses.clone(), block_num).await?;
+                Ok::<_, Error>(addresses.into_iter().zip(codes).collect::<Vec<_>>())
+            })
+            .buffer_unordered(max_concurrency)
+            .try_collect()
+            .await?;
+    Ok((proofs, codes.into_iter().flatten().collect()))
+}
+
 /// Build a partial StateDB from step 3
 pub fn build_state_code_db(
     proofs: Vec<eth_types::EIP1186ProofResponse>,
@@ -695,11 +978,37 @@ impl<P: JsonRpcClient> BuilderClient<P> {
 
         Ok(Self {
             cli: client,
+            state_provider: None,
             chain_id: chain_id.into(),
             circuits_params,
             feature_config,
+            rpc_config: RpcConfig::default(),
+            rate_limiter: RateLimiter::default(),
         })
     }
+}
+
+impl<P: JsonRpcClient, S: StateProvider> BuilderClient<P, S> {
+    /// Query the state before the block from `state_provider` instead of the
+    /// node.
+    pub fn with_state_provider<T: StateProvider>(self, state_provider: T) -> BuilderClient<P, T> {
+        BuilderClient {
+            cli: self.cli,
+            state_provider: Some(state_provider),
+            chain_id: self.chain_id,
+            circuits_params: self.circuits_params,
+            feature_config: self.feature_config,
+            rpc_config: self.rpc_config,
+            rate_limiter: self.rate_limiter,
+        }
+    }
+
+    /// Set the [`RpcConfig`] used to query the node.
+    pub fn with_rpc_config(mut self, rpc_config: RpcConfig) -> Self {
+        self.rpc_config = rpc_config;
+        self.rate_limiter = RateLimiter::new(rpc_config.max_requests_per_second);
+        self
+    }
 
     /// Step 1. Query geth for Block, Txs, TxExecTraces, history block hashes
     /// and previous state root.
@@ -707,42 +1016,93 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         &self,
         block_num: u64,
     ) -> Result<(EthBlock, Vec<eth_types::GethExecTrace>, Vec<Word>, Word), Error> {
-        let eth_block = self.cli.get_block_by_number(block_num.into()).await?;
-        let geth_traces = self.cli.trace_block_by_number(block_num.into()).await?;
+        self.get_block_with_progress(block_num, &mut BlockFetchProgress::default())
+            .await
+    }
 
-        // fetch up to 256 blocks
-        let mut n_blocks = std::cmp::min(256, block_num as usize);
-        let mut next_hash = eth_block.parent_hash;
-        let mut prev_state_root: Option<Word> = None;
-        let mut history_hashes = vec![Word::default(); n_blocks];
-        while n_blocks > 0 {
-            n_blocks -= 1;
-
-            // TODO: consider replacing it with `eth_getHeaderByHash`, it's faster
-            let header = self.cli.get_block_by_hash(next_hash).await?;
-
-            // set the previous state root
-            if prev_state_root.is_none() {
-                prev_state_root = Some(header.state_root.to_word());
-            }
+    /// Same as [`Self::get_block`], but recording what has been fetched in
+    /// `progress`, so that a failed fetch can be resumed.  On success the
+    /// block and its traces are moved out of `progress`.
+    pub async fn get_block_with_progress(
+        &self,
+        block_num: u64,
+        progress: &mut BlockFetchProgress,
+    ) -> Result<(EthBlock, Vec<eth_types::GethExecTrace>, Vec<Word>, Word), Error> {
+        if progress.block_num != block_num {
+            *progress = BlockFetchProgress {
+                block_num,
+                ..Default::default()
+            };
+        }
+        self.fetch_block(block_num, progress).await?;
+        let (history_hashes, prev_state_root) = self
+            .fetch_history(block_num, progress, self.rpc_config.max_concurrency)
+            .await?;
+        let (eth_block, geth_traces) = progress.block.take().expect("block fetched");
+        Ok((eth_block, geth_traces, history_hashes, prev_state_root))
+    }
+
+    /// Fetch the block and its traces into `progress` if they are missing.
+    #[tracing::instrument(skip_all, fields(block_num = block_num))]
+    async fn fetch_block(
+        &self,
+        block_num: u64,
+        progress: &mut BlockFetchProgress,
+    ) -> Result<(), Error> {
+        if progress.block.is_none() {
+            self.rate_limiter.acquire(2).await;
+            let (eth_block, geth_traces) = futures::try_join!(
+                self.cli.get_block_by_number(block_num.into()),
+                self.cli.trace_block_by_number(block_num.into())
+            )?;
+            progress.block = Some((eth_block, geth_traces));
+        }
+        Ok(())
+    }
 
-            // latest block hash is the last item
+    /// Fetch the missing history blocks into `progress`, with at most
+    /// `max_concurrency` queries in flight, and return the history hashes and
+    /// the previous state root.
+    #[tracing::instrument(skip_all, fields(block_num = block_num))]
+    async fn fetch_history(
+        &self,
+        block_num: u64,
+        progress: &mut BlockFetchProgress,
+        max_concurrency: usize,
+    ) -> Result<(Vec<Word>, Word), Error> {
+        // fetch up to 256 blocks
+        let first_block_num = block_num.saturating_sub(256);
+        let missing =
+            (first_block_num..block_num).filter(|num| !progress.history.contains_key(num));
+        let cli = &self.cli;
+        let mut headers = stream::iter(missing)
+            .map(|num| async move {
+                self.rate_limiter.acquire(1).await;
+                cli.get_block_header_by_number(num.into())
+                    .await
+                    .map(|header| (num, header))
+            })
+            .buffer_unordered(max_concurrency.max(1));
+        while let Some(result) = headers.next().await {
+            let (num, header) = result?;
             let block_hash = header
                 .hash
                 .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?
                 .to_word();
-            history_hashes[n_blocks] = block_hash;
-
-            // continue
-            next_hash = header.parent_hash;
+            progress
+                .history
+                .insert(num, (block_hash, header.state_root.to_word()));
         }
 
-        Ok((
-            eth_block,
-            geth_traces,
-            history_hashes,
-            prev_state_root.unwrap_or_default(),
-        ))
+        // latest block hash is the last item
+        let history_hashes = (first_block_num..block_num)
+            .map(|num| progress.history[&num].0)
+            .collect();
+        let prev_state_root = block_num
+            .checked_sub(1)
+            .map(|num| progress.history[&num].1)
+            .unwrap_or_default();
+        Ok((history_hashes, prev_state_root))
     }
 
     /// Step 2. Get State Accesses from TxExecTraces
@@ -753,8 +1113,8 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         get_state_accesses(eth_block, geth_traces)
     }
 
-    /// Step 3. Query geth for all accounts, storage keys, and codes from
-    /// Accesses
+    /// Step 3. Query the state provider for all accounts, storage keys, and
+    /// codes from Accesses
     pub async fn get_state(
         &self,
         block_num: u64,
@@ -766,27 +1126,46 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         ),
         Error,
     > {
-        let mut proofs = Vec::new();
-        for (address, key_set) in access_set.state {
-            let mut keys: Vec<Word> = key_set.iter().cloned().collect();
-            keys.sort();
-            let proof = self
-                .cli
-                .get_proof(address, keys, (block_num - 1).into())
+        self.fetch_state(block_num, access_set, &self.rpc_config)
+            .await
+    }
+
+    /// Query the state from the state provider, or from geth when none is set.
+    #[tracing::instrument(skip_all, fields(block_num = block_num))]
+    async fn fetch_state(
+        &self,
+        block_num: u64,
+        access_set: AccessSet,
+        rpc_config: &RpcConfig,
+    ) -> Result<
+        (
+            Vec<eth_types::EIP1186ProofResponse>,
+            HashMap<Address, Vec<u8>>,
+        ),
+        Error,
+    > {
+        match &self.state_provider {
+            Some(state_provider) => {
+                get_state(
+                    state_provider,
+                    block_num,
+                    access_set,
+                    rpc_config,
+                    &self.rate_limiter,
+                )
                 .await
-                .unwrap();
-            proofs.push(proof);
-        }
-        let mut codes: HashMap<Address, Vec<u8>> = HashMap::new();
-        for address in access_set.code {
-            let code = self
-                .cli
-                .get_code(address, (block_num - 1).into())
+            }
+            None => {
+                get_state(
+                    &self.cli,
+                    block_num,
+                    access_set,
+                    rpc_config,
+                    &self.rate_limiter,
+                )
                 .await
-                .unwrap();
-            codes.insert(address, code);
+            }
         }
-        Ok((proofs, codes))
     }
 
     /// Step 4. Build a partial StateDB from step 3
@@ -814,13 +1193,90 @@ impl<P: JsonRpcClient> BuilderClient<P> {
             code_db,
             block,
             self.circuits_params,
-            self.feature_config,
+            self.feature_config.clone(),
         );
         builder.handle_block(eth_block, geth_traces)?;
         Ok(builder)
     }
 
-    /// Perform all the steps to generate the circuit inputs
+    /// Generate the circuit inputs of the transaction `tx_hash` alone in its
+    /// block, to check that a single transaction can be proven.
+    ///
+    /// The state before the transaction is the one reported by the
+    /// `prestateTracer` of the node, so that the execution matches the trace
+    /// even when previous transactions of the block modified it.  This state
+    /// comes without merkle proofs and doesn't match the previous state root,
+    /// the block header keeps the values of the original block and the
+    /// withdrawals of the block are left out.  The circuit parameters are
+    /// derived from the transaction.
+    pub async fn gen_tx_inputs(
+        &self,
+        tx_hash: H256,
+    ) -> Result<(CircuitInputBuilder<FixedCParams>, EthBlock), Error> {
+        self.rate_limiter.acquire(1).await;
+        let mut tx = self.cli.get_transaction_by_hash(tx_hash).await?;
+        let block_num = tx
+            .block_number
+            .ok_or(Error::InternalError("transaction is not in a block"))?
+            .as_u64();
+        let mut progress = BlockFetchProgress {
+            block_num,
+            ..Default::default()
+        };
+        self.rate_limiter.acquire(3).await;
+        let (mut eth_block, geth_trace, prestate, (history_hashes, prev_state_root)) = futures::try_join!(
+            self.cli.get_block_by_number(block_num.into()),
+            self.cli.trace_tx_by_hash(tx_hash),
+            self.cli.trace_tx_prestate(tx_hash),
+            self.fetch_history(block_num, &mut progress, self.rpc_config.max_concurrency)
+        )?;
+        tx.transaction_index = Some(0.into());
+        eth_block.transactions = vec![tx];
+        eth_block.withdrawals = Some(vec![]);
+
+        let mut sdb = StateDB::new();
+        let mut code_db = CodeDB::default();
+        for (address, account) in prestate {
+            let code_hash = code_db.insert(account.code.to_vec());
+            let storage = account
+                .storage
+                .into_iter()
+                .map(|(key, value)| (key.to_word(), value.to_word()))
+                .collect();
+            sdb.set_account(
+                &address,
+                state_db::Account {
+                    nonce: account.nonce,
+                    balance: account.balance,
+                    storage,
+                    code_hash,
+                },
+            );
+        }
+
+        let block = Block::new(self.chain_id, history_hashes, prev_state_root, &eth_block)?;
+        let builder = CircuitInputBuilder::new(
+            sdb,
+            code_db,
+            block,
+            DynamicCParams {},
+            self.feature_config.clone(),
+        )
+        .handle_block(&eth_block, &[geth_trace])?;
+        Ok((builder, eth_block))
+    }
+
+    /// Perform all the steps to generate the circuit inputs.
+    ///
+    /// The history blocks are fetched while the state accesses are computed,
+    /// the state is queried and the block is handled, each fetch sharing half
+    /// of [`RpcConfig::max_concurrency`], with at least one query in flight.
+    /// Handling the block is CPU bound, so it runs on a thread of its own,
+    /// letting the executor make progress on the history fetch whatever the
+    /// runtime, tokio or not. A panic of that thread is returned as
+    /// [`Error::InternalError`]. The history is only set in the block once the block is
+    /// handled, as handling it doesn't depend on the history.
+    #[tracing::instrument(skip_all, fields(block_num = block_num))]
     pub async fn gen_inputs(
         &self,
         block_num: u64,
@@ -831,19 +1287,55 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         ),
         Error,
     > {
-        let (eth_block, geth_traces, history_hashes, prev_state_root) =
-            self.get_block(block_num).await?;
-        let access_set = Self::get_state_accesses(&eth_block, &geth_traces)?;
-        let (proofs, codes) = self.get_state(block_num, access_set).await?;
-        let (state_db, code_db) = Self::build_state_code_db(proofs, codes);
-        let builder = self.gen_inputs_from_state(
-            state_db,
-            code_db,
-            &eth_block,
-            &geth_traces,
-            history_hashes,
-            prev_state_root,
+        let mut progress = BlockFetchProgress {
+            block_num,
+            ..Default::default()
+        };
+        self.fetch_block(block_num, &mut progress).await?;
+        let (eth_block, geth_traces) = progress.block.take().expect("block fetched");
+
+        let max_concurrency = self.rpc_config.max_concurrency;
+        let history_concurrency = (max_concurrency / 2).max(1);
+        let state_rpc_config = RpcConfig {
+            max_concurrency: max_concurrency.saturating_sub(history_concurrency).max(1),
+            ..self.rpc_config
+        };
+        let ((history_hashes, prev_state_root), (mut builder, eth_block)) = futures::try_join!(
+            self.fetch_history(block_num, &mut progress, history_concurrency),
+            async {
+                let access_set = Self::get_state_accesses(&eth_block, &geth_traces)?;
+                let (proofs, codes) = self
+                    .fetch_state(block_num, access_set, &state_rpc_config)
+                    .await?;
+                let (state_db, code_db) = Self::build_state_code_db(proofs, codes);
+                let block = Block::new(self.chain_id, Vec::new(), Word::zero(), &eth_block)?;
+                let mut builder = CircuitInputBuilder::new(
+                    state_db,
+                    code_db,
+                    block,
+                    self.circuits_params,
+                    self.feature_config.clone(),
+                );
+                let (sender, receiver) = futures::channel::oneshot::channel();
+                let span = tracing::Span::current();
+                std::thread::Builder::new()
+                    .name("handle_block".to_string())
+                    .spawn(move || {
+                        let result =
+                            span.in_scope(|| builder.handle_block(&eth_block, &geth_traces));
+                        // The receiver is only gone if gen_inputs was dropped
+                        let _ = sender.send(result.map(|_| (builder, eth_block)));
+                    })
+                    .map_err(|_| Error::InternalError("can't spawn the block handling thread"))?;
+                // The sender is only dropped without sending if the thread
+                // panicked
+                receiver
+                    .await
+                    .map_err(|_| Error::InternalError("block handling thread panicked"))?
+            }
         )?;
+        builder.block.history_hashes = history_hashes;
+        builder.block.prev_state_root = prev_state_root;
         Ok((builder, eth_block))
     }
 }
@@ -0,0 +1,559 @@
+use super::*;
+use crate::{
+    mock::BlockData,
+    rpc::{BatchTransport, GethBatchClient},
+    state_provider::{InMemoryStateProvider, SnapshotStateProvider},
+};
+use async_trait::async_trait;
+use eth_types::{bytecode, geth_types::GethData, Bytes, ToBigEndian, U64};
+use ethers_providers::MockError;
+use futures::{executor::block_on, FutureExt};
+use mock::test_ctx::{helpers::*, TestContext};
+use pretty_assertions::assert_eq;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{fmt, sync::Mutex};
+
+/// JSON-RPC client answering every request with `respond(method, params)`.
+/// Requests for which `respond` returns `None` fail.
+pub(crate) struct MockRpc<F>(pub(crate) F);
+
+impl<F> fmt::Debug for MockRpc<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MockRpc")
+    }
+}
+
+#[async_trait]
+impl<F> JsonRpcClient for MockRpc<F>
+where
+    F: Fn(&str, Value) -> Option<Value> + Send + Sync,
+{
+    type Error = MockError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, MockError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params).map_err(MockError::SerdeJson)?;
+        let response = (self.0)(method, params).ok_or(MockError::EmptyResponses)?;
+        serde_json::from_value(response).map_err(MockError::SerdeJson)
+    }
+}
+
+/// JSON-RPC batch transport answering every request of a batch with
+/// `respond(method, params)`, in the reverse order of the batch, and
+/// recording the size of every batch.  Requests for which `respond` returns
+/// `None` get an error response.
+pub(crate) struct MockBatch<F> {
+    respond: F,
+    batch_sizes: Mutex<Vec<usize>>,
+}
+
+impl<F> MockBatch<F> {
+    pub(crate) fn new(respond: F) -> Self {
+        Self {
+            respond,
+            batch_sizes: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl<F> BatchTransport for MockBatch<F>
+where
+    F: Fn(&str, Value) -> Option<Value>,
+{
+    async fn send_batch(&self, batch: Value) -> Result<Value, Error> {
+        let requests = batch.as_array().expect("batch of requests");
+        self.batch_sizes.lock().unwrap().push(requests.len());
+        let responses = requests
+            .iter()
+            .rev()
+            .map(|request| {
+                let method = request["method"].as_str().unwrap();
+                match (self.respond)(method, request["params"].clone()) {
+                    Some(result) => {
+                        json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                    }
+                    None => json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": "method not found" }
+                    }),
+                }
+            })
+            .collect();
+        Ok(Value::Array(responses))
+    }
+}
+
+/// Answer `eth_getProof` and `eth_getCode` from `state`.
+fn respond_from_state(state: &InMemoryStateProvider, method: &str, params: Value) -> Option<Value> {
+    let address: Address = serde_json::from_value(params[0].clone()).ok()?;
+    let block_num: BlockNumber =
+        serde_json::from_value(params[params.as_array()?.len() - 1].clone()).ok()?;
+    // The in-memory provider answers without awaiting
+    match method {
+        "eth_getProof" => {
+            let keys: Vec<Word> = serde_json::from_value(params[1].clone()).ok()?;
+            let proof = state
+                .get_proof(address, keys, block_num)
+                .now_or_never()?
+                .ok()?;
+            Some(json!(proof))
+        }
+        "eth_getCode" => {
+            let code = state.get_code(address, block_num).now_or_never()?.ok()?;
+            Some(json!(Bytes::from(code)))
+        }
+        _ => None,
+    }
+}
+
+fn block_with_storage() -> GethData {
+    let code = bytecode! {
+        PUSH1(0x2a)
+        PUSH1(0x00)
+        SSTORE
+        PUSH1(0x01)
+        SLOAD
+        STOP
+    };
+    TestContext::<2, 1>::new(
+        None,
+        account_0_code_account_1_no_code(code),
+        tx_from_1_to_0,
+        |block, _tx| block,
+    )
+    .unwrap()
+    .into()
+}
+
+/// Block with a transfer to an account without code, whose trace has no
+/// steps, so that it can be served by [`MockRpc`].
+fn block_with_transfer() -> GethData {
+    TestContext::<2, 1>::new(
+        None,
+        account_0_code_account_1_no_code(bytecode! {}),
+        tx_from_1_to_0,
+        |block, _tx| block.number(0xcafeu64),
+    )
+    .unwrap()
+    .into()
+}
+
+/// Hash of the history block `num` served by [`history_header`].
+fn history_hash(num: u64) -> H256 {
+    H256::from_low_u64_be(num + 1)
+}
+
+/// Response to `eth_getBlockByNumber` for the header of a history block.
+fn history_header(params: &Value) -> Option<Value> {
+    let num: U64 = serde_json::from_value(params[0].clone()).ok()?;
+    Some(json!(eth_types::Block::<H256> {
+        hash: Some(history_hash(num.as_u64())),
+        number: Some(num),
+        ..Default::default()
+    }))
+}
+
+#[test]
+fn builder_client_in_memory_state() {
+    let geth_data = block_with_storage();
+    let chain_id = U64::from(geth_data.chain_id.as_u64());
+    // The node only answers the chain id: the state must come from the
+    // in-memory provider.
+    let cli = GethClient::new(MockRpc(move |method: &str, _params: Value| {
+        (method == "eth_chainId").then(|| serde_json::to_value(chain_id).unwrap())
+    }));
+    let client = block_on(BuilderClient::new(cli, FixedCParams::default()))
+        .unwrap()
+        .with_state_provider(InMemoryStateProvider::new(geth_data.accounts.clone()));
+
+    let eth_block = &geth_data.eth_block;
+    let block_num = eth_block.number.unwrap().as_u64();
+    let access_set = get_state_accesses(eth_block, &geth_data.geth_traces).unwrap();
+    let (proofs, codes) = block_on(client.get_state(block_num, access_set)).unwrap();
+    let (sdb, code_db) = build_state_code_db(proofs, codes);
+    let builder = client
+        .gen_inputs_from_state(
+            sdb,
+            code_db,
+            eth_block,
+            &geth_data.geth_traces,
+            geth_data.history_hashes.clone(),
+            Word::zero(),
+        )
+        .unwrap();
+
+    let block = BlockData::new_from_geth_data_with_params(geth_data, FixedCParams::default());
+    let mut expected = block.new_circuit_input_builder();
+    expected
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    assert_eq!(builder.block.txs.len(), expected.block.txs.len());
+    assert_eq!(builder.block.container, expected.block.container);
+}
+
+#[test]
+fn builder_client_gen_tx_inputs() {
+    let geth_data = block_with_transfer();
+    let chain_id = U64::from(geth_data.chain_id.as_u64());
+    let eth_block = geth_data.eth_block.clone();
+    let block_num = eth_block.number.unwrap();
+    let mut tx = eth_block.transactions[0].clone();
+    tx.block_number = Some(block_num);
+    let tx_hash = tx.hash;
+    let geth_trace = geth_data.geth_traces[0].clone();
+    assert!(geth_trace.struct_logs.is_empty());
+    let prestate: serde_json::Map<String, Value> = geth_data
+        .accounts
+        .iter()
+        .map(|account| {
+            let storage: serde_json::Map<String, Value> = account
+                .storage
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        format!("{:?}", H256::from(key.to_be_bytes())),
+                        json!(H256::from(value.to_be_bytes())),
+                    )
+                })
+                .collect();
+            let prestate = json!({
+                "balance": account.balance,
+                "nonce": account.nonce.as_u64(),
+                "code": account.code,
+                "storage": storage,
+            });
+            (format!("{:?}", account.address), prestate)
+        })
+        .collect();
+
+    let cli = GethClient::new(MockRpc(move |method: &str, params: Value| {
+        let response = match method {
+            "eth_chainId" => json!(chain_id),
+            "eth_getTransactionByHash" => json!(tx),
+            "eth_getBlockByNumber" if params[1] == json!(true) => json!(eth_block),
+            "eth_getBlockByNumber" => history_header(&params)?,
+            "debug_traceTransaction" if params[1].get("tracer").is_some() => {
+                Value::Object(prestate.clone())
+            }
+            "debug_traceTransaction" => json!(geth_trace),
+            _ => return None,
+        };
+        Some(response)
+    }));
+    let client = block_on(BuilderClient::new(cli, FixedCParams::default())).unwrap();
+    let (builder, tx_block) = block_on(client.gen_tx_inputs(tx_hash)).unwrap();
+
+    assert_eq!(tx_block.number, Some(block_num));
+    assert_eq!(tx_block.transactions.len(), 1);
+    assert_eq!(tx_block.transactions[0].hash, tx_hash);
+    assert_eq!(
+        builder.block.history_hashes,
+        (block_num.as_u64() - 256..block_num.as_u64())
+            .map(|num| history_hash(num).to_word())
+            .collect::<Vec<_>>()
+    );
+
+    let block = BlockData::new_from_geth_data_with_params(geth_data, FixedCParams::default());
+    let mut expected = block.new_circuit_input_builder();
+    expected
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    assert_eq!(builder.block.txs.len(), 1);
+    assert_eq!(builder.block.container, expected.block.container);
+}
+
+#[test]
+fn builder_client_gen_inputs() {
+    let geth_data = block_with_transfer();
+    assert!(geth_data.geth_traces[0].struct_logs.is_empty());
+    let chain_id = U64::from(geth_data.chain_id.as_u64());
+    let eth_block = geth_data.eth_block.clone();
+    let block_num = eth_block.number.unwrap().as_u64();
+    let geth_traces: Vec<Value> = geth_data
+        .geth_traces
+        .iter()
+        .map(|trace| json!({ "result": trace }))
+        .collect();
+    // The state comes from the in-memory provider, the node serves the block,
+    // its traces and the history.
+    let cli = GethClient::new(MockRpc(move |method: &str, params: Value| {
+        let response = match method {
+            "eth_chainId" => json!(chain_id),
+            "eth_getBlockByNumber" if params[1] == json!(true) => json!(eth_block),
+            "eth_getBlockByNumber" => history_header(&params)?,
+            "debug_traceBlockByNumber" => json!(geth_traces),
+            _ => return None,
+        };
+        Some(response)
+    }));
+    let client = block_on(BuilderClient::new(cli, FixedCParams::default()))
+        .unwrap()
+        .with_state_provider(InMemoryStateProvider::new(geth_data.accounts.clone()))
+        .with_rpc_config(RpcConfig {
+            max_concurrency: 3,
+            ..Default::default()
+        });
+    let (builder, _) = block_on(client.gen_inputs(block_num)).unwrap();
+
+    let history: Vec<_> = (block_num - 256..block_num)
+        .map(|num| history_hash(num).to_word())
+        .collect();
+    assert_eq!(builder.block.history_hashes, history);
+
+    let block = BlockData::new_from_geth_data_with_params(geth_data, FixedCParams::default());
+    let mut expected = block.new_circuit_input_builder();
+    expected
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    assert_eq!(builder.block.txs.len(), expected.block.txs.len());
+    assert_eq!(builder.block.container, expected.block.container);
+}
+
+#[test]
+fn builder_client_batched_state() {
+    let geth_data = block_with_storage();
+    let chain_id = U64::from(geth_data.chain_id.as_u64());
+    let state = InMemoryStateProvider::new(geth_data.accounts.clone());
+    let cli = || {
+        GethClient::new(MockRpc(move |method: &str, _params: Value| {
+            (method == "eth_chainId").then(|| serde_json::to_value(chain_id).unwrap())
+        }))
+    };
+    let rpc_config = RpcConfig {
+        max_keys_per_proof: 1,
+        max_batch_size: 2,
+        ..Default::default()
+    };
+    let batch_state = state.clone();
+    let client = block_on(BuilderClient::new(cli(), FixedCParams::default()))
+        .unwrap()
+        .with_state_provider(GethBatchClient::new(MockBatch::new(
+            move |method: &str, params: Value| respond_from_state(&batch_state, method, params),
+        )))
+        .with_rpc_config(rpc_config);
+    let expected_client = block_on(BuilderClient::new(cli(), FixedCParams::default()))
+        .unwrap()
+        .with_state_provider(state);
+
+    let eth_block = &geth_data.eth_block;
+    let block_num = eth_block.number.unwrap().as_u64();
+    let access_set = || get_state_accesses(eth_block, &geth_data.geth_traces).unwrap();
+    assert!(access_set().state.values().any(|keys| keys.len() > 1));
+    let (mut proofs, codes) = block_on(client.get_state(block_num, access_set())).unwrap();
+    let (mut expected_proofs, expected_codes) =
+        block_on(expected_client.get_state(block_num, access_set())).unwrap();
+
+    // The storage keys queried separately are merged in a single proof
+    proofs.sort_by_key(|proof| proof.address);
+    expected_proofs.sort_by_key(|proof| proof.address);
+    for proof in proofs.iter_mut().chain(expected_proofs.iter_mut()) {
+        proof.storage_proof.sort_by_key(|storage| storage.key);
+    }
+    assert_eq!(proofs, expected_proofs);
+    assert_eq!(codes, expected_codes);
+
+    let batch_sizes = client
+        .state_provider
+        .as_ref()
+        .unwrap()
+        .0
+        .batch_sizes
+        .lock()
+        .unwrap();
+    assert!(batch_sizes.len() > 1);
+    assert!(batch_sizes.iter().all(|size| *size <= 2));
+    assert!(batch_sizes.iter().any(|size| *size == 2));
+}
+
+#[test]
+fn builder_client_batched_state_error() {
+    let geth_data = block_with_storage();
+    let chain_id = U64::from(geth_data.chain_id.as_u64());
+    let state = InMemoryStateProvider::new(geth_data.accounts.clone());
+    let cli = GethClient::new(MockRpc(move |method: &str, _params: Value| {
+        (method == "eth_chainId").then(|| serde_json::to_value(chain_id).unwrap())
+    }));
+    // The node fails every eth_getCode of the batch
+    let client = block_on(BuilderClient::new(cli, FixedCParams::default()))
+        .unwrap()
+        .with_state_provider(GethBatchClient::new(MockBatch::new(
+            move |method: &str, params: Value| {
+                (method == "eth_getProof")
+                    .then(|| respond_from_state(&state, method, params))
+                    .flatten()
+            },
+        )));
+
+    let eth_block = &geth_data.eth_block;
+    let block_num = eth_block.number.unwrap().as_u64();
+    let access_set = get_state_accesses(eth_block, &geth_data.geth_traces).unwrap();
+    assert!(!access_set.code.is_empty());
+    let result = block_on(client.get_state(block_num, access_set));
+    assert!(matches!(result, Err(Error::RpcError(_))));
+}
+
+/// State served by every provider in [`state_providers_conformance`]: an
+/// account with code and storage and one with a balance only.
+fn conformance_accounts() -> Vec<geth_types::Account> {
+    vec![
+        geth_types::Account {
+            address: Address::from_low_u64_be(0xaa),
+            nonce: U64::from(2),
+            balance: Word::from(1000),
+            code: Bytes::from(vec![0x60, 0x00, 0x00]),
+            storage: HashMap::from([(Word::from(1), Word::from(42))]),
+        },
+        geth_types::Account {
+            address: Address::from_low_u64_be(0xbb),
+            balance: Word::from(7),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Check the answers of `provider` for the state of [`conformance_accounts`].
+/// The accounts and storage slots it doesn't hold are zero, like on a node.
+async fn check_state_provider(provider: &impl StateProvider, block_num: BlockNumber) {
+    let accounts = conformance_accounts();
+    let (with_code, without_code) = (&accounts[0], &accounts[1]);
+    let unknown = Address::from_low_u64_be(0xcc);
+    let (key, unknown_key) = (Word::from(1), Word::from(2));
+
+    let account = provider
+        .get_account(with_code.address, block_num)
+        .await
+        .unwrap();
+    assert_eq!(
+        account,
+        state_db::Account {
+            storage: HashMap::new(),
+            ..state_db::Account::from(with_code.clone())
+        }
+    );
+    assert_eq!(
+        provider.get_account(unknown, block_num).await.unwrap(),
+        state_db::Account::zero()
+    );
+
+    for (address, key, value) in [
+        (with_code.address, key, Word::from(42)),
+        (with_code.address, unknown_key, Word::zero()),
+        (unknown, key, Word::zero()),
+    ] {
+        assert_eq!(
+            provider.get_storage(address, key, block_num).await.unwrap(),
+            value
+        );
+    }
+
+    for (address, code) in [
+        (with_code.address, with_code.code.to_vec()),
+        (without_code.address, vec![]),
+        (unknown, vec![]),
+    ] {
+        assert_eq!(provider.get_code(address, block_num).await.unwrap(), code);
+    }
+
+    let proof = provider
+        .get_proof(with_code.address, vec![key, unknown_key], block_num)
+        .await
+        .unwrap();
+    assert_eq!(proof.address, with_code.address);
+    assert_eq!(proof.nonce, with_code.nonce);
+    assert_eq!(proof.balance, with_code.balance);
+    assert_eq!(proof.code_hash, account.code_hash);
+    let storage = proof
+        .storage_proof
+        .iter()
+        .map(|storage| (storage.key, storage.value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        storage,
+        vec![(key, Word::from(42)), (unknown_key, Word::zero())]
+    );
+
+    let proofs = provider
+        .get_proofs(
+            vec![(with_code.address, vec![key]), (unknown, vec![key])],
+            block_num,
+        )
+        .await
+        .unwrap();
+    assert_eq!(proofs.len(), 2);
+    assert_eq!(proofs[0].storage_proof[0].value, Word::from(42));
+    assert_eq!(proofs[1].address, unknown);
+    assert_eq!(proofs[1].code_hash, state_db::Account::zero().code_hash);
+    assert_eq!(proofs[1].storage_proof[0].value, Word::zero());
+
+    let codes = provider
+        .get_codes(vec![with_code.address, unknown], block_num)
+        .await
+        .unwrap();
+    assert_eq!(codes, vec![with_code.code.to_vec(), vec![]]);
+}
+
+/// State and code databases holding [`conformance_accounts`].
+fn conformance_snapshot_dbs() -> (StateDB, CodeDB) {
+    let mut sdb = StateDB::new();
+    let mut code_db = CodeDB::default();
+    for account in conformance_accounts() {
+        code_db.insert(account.code.to_vec());
+        sdb.set_account(&account.address, account.into());
+    }
+    (sdb, code_db)
+}
+
+#[test]
+fn state_providers_conformance() {
+    let block_num = BlockNumber::Number(U64::from(0xcafe));
+    let state = InMemoryStateProvider::new(conformance_accounts());
+    block_on(check_state_provider(&state, block_num));
+
+    let (sdb, code_db) = conformance_snapshot_dbs();
+    let snapshot = SnapshotStateProvider::new(0xcafe, sdb, code_db).with_zero_fill();
+    block_on(check_state_provider(&snapshot, block_num));
+
+    let rpc_state = state.clone();
+    let node = GethClient::new(MockRpc(move |method: &str, params: Value| {
+        respond_from_state(&rpc_state, method, params)
+    }));
+    block_on(check_state_provider(&node, block_num));
+
+    let batch_node = GethBatchClient::new(MockBatch::new(move |method: &str, params: Value| {
+        respond_from_state(&state, method, params)
+    }));
+    block_on(check_state_provider(&batch_node, block_num));
+}
+
+#[test]
+fn snapshot_state_provider_missing_entries() {
+    let block_num = BlockNumber::Number(U64::from(0xcafe));
+    let (sdb, code_db) = conformance_snapshot_dbs();
+    let snapshot = SnapshotStateProvider::new(0xcafe, sdb, code_db);
+    let address = Address::from_low_u64_be(0xaa);
+    let unknown = Address::from_low_u64_be(0xcc);
+    let (key, unknown_key) = (Word::from(1), Word::from(2));
+
+    let result = block_on(snapshot.get_account(unknown, block_num));
+    assert!(matches!(result, Err(Error::AccountNotFound(a)) if a == unknown));
+    let result = block_on(snapshot.get_code(unknown, block_num));
+    assert!(matches!(result, Err(Error::AccountNotFound(a)) if a == unknown));
+    let result = block_on(snapshot.get_storage(address, unknown_key, block_num));
+    assert!(
+        matches!(result, Err(Error::StorageKeyNotFound(a, k)) if a == address && k == unknown_key)
+    );
+    let result = block_on(snapshot.get_proof(address, vec![key, unknown_key], block_num));
+    assert!(matches!(result, Err(Error::StorageKeyNotFound(_, k)) if k == unknown_key));
+    assert_eq!(
+        block_on(snapshot.get_storage(address, key, block_num)).unwrap(),
+        Word::from(42)
+    );
+}
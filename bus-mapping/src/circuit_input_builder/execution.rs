@@ -10,10 +10,11 @@ use crate::{
 use eth_types::{evm_types::OpcodeId, sign_types::SignData, GethExecStep, Word, H256};
 use gadgets::impl_expr;
 use halo2_proofs::plonk::Expression;
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
 /// An execution step of the EVM.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ExecStep {
     /// Execution state
     pub exec_state: ExecState,
@@ -137,7 +138,7 @@ impl ExecStep {
 }
 
 /// Execution state
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ExecState {
     /// EVM Opcode ID
     Op(OpcodeId),
@@ -198,7 +199,7 @@ impl ExecState {
 }
 
 /// Defines the various source/destination types for a copy event.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum CopyDataType {
     /// When we need to pad the Copy rows of the circuit up to a certain maximum
     /// with rows that are not "useful".
@@ -243,7 +244,7 @@ pub struct CopyStep {
 }
 
 /// Defines an enum type that can hold either a number or a hash value.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NumberOrHash {
     /// Variant to indicate a number value.
     Number(usize),
@@ -254,7 +255,7 @@ pub enum NumberOrHash {
 /// Defines a copy event associated with EVM opcodes such as CALLDATACOPY,
 /// CODECOPY, CREATE, etc. More information:
 /// <https://github.com/privacy-scaling-explorations/zkevm-specs/blob/master/specs/copy-proof.md>.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CopyEvent {
     /// Represents the start address at the source of the copy event.
     pub src_addr: u64,
@@ -277,6 +278,21 @@ pub struct CopyEvent {
     pub rw_counter_start: RWCounter,
     /// Represents the list of (bytes, is_code) copied during this copy event
     pub bytes: Vec<(u8, bool)>,
+    /// Step that generated this copy event, set when the event is pushed with
+    /// [`CircuitInputStateRef::push_copy`](super::CircuitInputStateRef::push_copy)
+    pub origin: Option<CopyEventOrigin>,
+}
+
+/// Step that generated a [`CopyEvent`], to attribute copy rows to the step
+/// that looks them up when debugging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyEventOrigin {
+    /// Id of the transaction, starting at 1
+    pub tx_id: u64,
+    /// Index of the call within the transaction
+    pub call_index: usize,
+    /// Program counter of the step
+    pub pc: u64,
 }
 
 impl CopyEvent {
@@ -317,7 +333,7 @@ impl CopyEvent {
 }
 
 /// Intermediary multiplication step, representing `a * b == d (mod 2^256)`
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ExpStep {
     /// First multiplicand.
     pub a: Word,
@@ -338,7 +354,7 @@ impl From<(Word, Word, Word)> for ExpStep {
 }
 
 /// Event representing an exponentiation `a ^ b == d (mod 2^256)`.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExpEvent {
     /// Identifier for the exponentiation trace.
     pub identifier: usize,
@@ -369,7 +385,7 @@ impl Default for ExpEvent {
 }
 
 /// I/Os from all precompiled contract calls in a block.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PrecompileEvents {
     /// All events.
     pub events: Vec<PrecompileEvent>,
@@ -390,7 +406,7 @@ impl PrecompileEvents {
 }
 
 /// I/O from a precompiled contract call.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum PrecompileEvent {
     /// Represents the I/O from Ecrecover call.
     Ecrecover(SignData),
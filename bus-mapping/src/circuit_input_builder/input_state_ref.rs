@@ -2,16 +2,16 @@
 
 use super::{
     get_call_memory_offset_length, get_create_init_code, Block, BlockContext, Call, CallContext,
-    CallKind, CodeSource, CopyEvent, ExecState, ExecStep, ExpEvent, PrecompileEvent, Transaction,
-    TransactionContext,
+    CallKind, CodeSource, CopyEvent, CopyEventOrigin, ExecState, ExecStep, ExpEvent, FeatureConfig,
+    PrecompileEvent, Transaction, TransactionContext,
 };
 use crate::{
     error::{DepthError, ExecError, InsufficientBalanceError, NonceUintOverflowError},
     exec_trace::OperationRef,
     operation::{
         AccountField, AccountOp, CallContextField, CallContextOp, MemoryOp, Op, OpEnum, Operation,
-        StackOp, Target, TxAccessListAccountOp, TxLogField, TxLogOp, TxReceiptField, TxReceiptOp,
-        RW,
+        RWCounter, StackOp, Target, TxAccessListAccountOp, TxLogField, TxLogOp, TxReceiptField,
+        TxReceiptOp, RW,
     },
     precompile::{is_precompiled, PrecompileCalls},
     state_db::{CodeDB, StateDB},
@@ -20,6 +20,7 @@ use crate::{
 use eth_types::{
     evm_types::{
         gas_utils::memory_expansion_gas_cost, GasCost, MemoryAddress, OpcodeId, StackAddress,
+        MAX_CALL_DEPTH,
     },
     Address, Bytecode, GethExecStep, ToAddress, ToBigEndian, ToWord, Word, H256, U256,
 };
@@ -43,6 +44,8 @@ pub struct CircuitInputStateRef<'a> {
     pub tx_ctx: &'a mut TransactionContext,
     /// Max rw number limit
     pub max_rws: Option<usize>,
+    /// Feature config
+    pub feature_config: &'a FeatureConfig,
 }
 
 impl<'a> CircuitInputStateRef<'a> {
@@ -139,15 +142,19 @@ impl<'a> CircuitInputStateRef<'a> {
         self.check_rw_num_limit()
     }
 
-    /// Check whether rws will overflow circuit limit.
+    /// Check whether rws will overflow circuit limit.  Without a configured
+    /// limit, the rw counter is still bounded by [`RWCounter::MAX`].
     pub fn check_rw_num_limit(&self) -> Result<(), Error> {
-        if let Some(max_rws) = self.max_rws {
-            let rwc = self.block_ctx.rwc.0;
-            if rwc > max_rws {
-                log::error!("rwc > max_rws, rwc={}, max_rws={}", rwc, max_rws);
-                return Err(Error::RwsNotEnough(max_rws, rwc));
-            };
-        }
+        let max_rws = self.max_rws.unwrap_or(RWCounter::MAX);
+        let rwc = self.block_ctx.rwc.0;
+        if rwc > max_rws {
+            log::error!("rwc > max_rws, rwc={}, max_rws={}", rwc, max_rws);
+            return Err(Error::CapacityExceeded {
+                circuit: "state",
+                needed: rwc,
+                available: max_rws,
+            });
+        };
 
         Ok(())
     }
@@ -1286,11 +1293,20 @@ impl<'a> CircuitInputStateRef<'a> {
             geth_step.gas - memory_expansion_gas_cost - code_deposit_cost - constant_step_gas
         };
 
-        let caller_gas_left = geth_step_next.gas.checked_sub(gas_refund).unwrap_or_else (
-            || {
-                panic!("caller_gas_left underflow geth_step_next {geth_step_next:?}, gas_refund {gas_refund:?}, exec_step {exec_step:?}, geth_step {geth_step:?}");
+        let caller_gas_left = geth_step_next.gas.checked_sub(gas_refund).ok_or_else(|| {
+            log::error!(
+                "caller_gas_left underflow geth_step_next {geth_step_next:?}, gas_refund {gas_refund:?}, exec_step {exec_step:?}, geth_step {geth_step:?}"
+            );
+            Error::TraceMismatch {
+                tx: self.tx_ctx.id(),
+                pc: geth_step.pc,
+                op: geth_step.op,
+                reason: format!(
+                    "caller gas left {} is lower than the gas refund {}",
+                    geth_step_next.gas, gas_refund
+                ),
             }
-        );
+        })?;
 
         for (field, value) in [
             (CallContextField::IsRoot, (caller.is_root as u64).into()),
@@ -1345,9 +1361,14 @@ impl<'a> CircuitInputStateRef<'a> {
         Ok(())
     }
 
-    /// Push a copy event to the state.
-    pub fn push_copy(&mut self, step: &mut ExecStep, event: CopyEvent) {
+    /// Push a copy event generated by `step` to the state.
+    pub fn push_copy(&mut self, step: &mut ExecStep, mut event: CopyEvent) {
         step.copy_rw_counter_delta += event.rw_counter_delta();
+        event.origin = Some(CopyEventOrigin {
+            tx_id: self.tx.id,
+            call_index: step.call_index,
+            pc: step.pc,
+        });
         self.block.add_copy_event(event);
     }
 
@@ -1503,7 +1524,7 @@ impl<'a> CircuitInputStateRef<'a> {
         ) && next_result.is_zero()
             && next_pc != 0
         {
-            if step.depth == 1025 {
+            if u64::from(step.depth) == MAX_CALL_DEPTH + 1 {
                 return Ok(Some(ExecError::Depth(match step.op {
                     OpcodeId::CALL
                     | OpcodeId::CALLCODE
@@ -1567,33 +1588,23 @@ impl<'a> CircuitInputStateRef<'a> {
                 // Therefore we postpone the oog handling to the implementor of callop.
                 if is_precompiled(&code_address) {
                     let precompile_call: PrecompileCalls = code_address[19].into();
-                    match precompile_call {
-                        PrecompileCalls::Sha256
-                        | PrecompileCalls::Ripemd160
-                        | PrecompileCalls::Blake2F
-                        | PrecompileCalls::Bn128Add
-                        | PrecompileCalls::Bn128Mul
-                        | PrecompileCalls::Bn128Pairing
-                        | PrecompileCalls::Modexp => {
-                            // Log the precompile address and gas left.
-                            // Failure due to precompile being unsupported.
-                            // Failure cases are routed to `PrecompileFailed` dummy gadget.
-                            log::trace!(
-                                "Precompile failed: code_address = {}, step.gas = {}",
-                                code_address,
-                                step.gas,
-                            );
-                            return Ok(Some(ExecError::UnimplementedPrecompiles));
-                        }
-                        pre_call => {
-                            log::trace!(
-                                "Precompile call failed: addr={:?}, step.gas={:?}",
-                                pre_call,
-                                step.gas
-                            );
-                            return Ok(None);
-                        }
+                    if !precompile_call.is_supported() {
+                        // Log the precompile address and gas left.
+                        // Failure due to precompile being unsupported.
+                        // Failure cases are routed to `PrecompileFailed` dummy gadget.
+                        log::trace!(
+                            "Precompile failed: code_address = {}, step.gas = {}",
+                            code_address,
+                            step.gas,
+                        );
+                        return Ok(Some(ExecError::UnimplementedPrecompiles));
                     }
+                    log::trace!(
+                        "Precompile call failed: addr={:?}, step.gas={:?}",
+                        precompile_call,
+                        step.gas
+                    );
+                    return Ok(None);
                 }
             }
 
@@ -0,0 +1,190 @@
+//! Hooks called on the steps generated while handling a block.
+
+use super::{CallContext, ExecStep};
+use serde::Serialize;
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// Hook called on every [`ExecStep`] generated by the
+/// [`CircuitInputBuilder`](super::CircuitInputBuilder), so that debuggers and
+/// analytics can stream the steps without parsing the geth traces again.
+pub trait StepHook: Debug + Send + Sync {
+    /// Called with a generated step and a summary of the context of the call
+    /// executing it, as it was before the step.  Steps done after the root
+    /// call has ended, like `EndTx`, get an empty summary of the root call.
+    fn on_step(&mut self, step: &ExecStep, call: &CallSummary);
+}
+
+/// Summary of a [`CallContext`] given to a [`StepHook`], which is cheap to
+/// take before every step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallSummary {
+    /// Index of the call
+    pub index: usize,
+    /// Reversible write counter of the call
+    pub reversible_write_counter: usize,
+    /// Size of the call data in bytes
+    pub call_data_size: usize,
+    /// Size of the memory in bytes
+    pub memory_size: usize,
+    /// Size of the return data buffer in bytes
+    pub return_data_size: usize,
+}
+
+impl From<&CallContext> for CallSummary {
+    fn from(call_ctx: &CallContext) -> Self {
+        Self {
+            index: call_ctx.index,
+            reversible_write_counter: call_ctx.reversible_write_counter,
+            call_data_size: call_ctx.call_data.len(),
+            memory_size: call_ctx.memory.len(),
+            return_data_size: call_ctx.return_data.len(),
+        }
+    }
+}
+
+/// A hook shared behind a mutex, so that it can still be used once the
+/// [`CircuitInputBuilder`](super::CircuitInputBuilder) has handled the block.
+impl<H: StepHook> StepHook for Arc<Mutex<H>> {
+    fn on_step(&mut self, step: &ExecStep, call: &CallSummary) {
+        self.lock().expect("step hook poisoned").on_step(step, call)
+    }
+}
+
+/// Line of the trace written by [`JsonlStepTracer`]
+#[derive(Debug, Serialize)]
+struct StepRecord {
+    exec_state: String,
+    pc: u64,
+    call_index: usize,
+    rwc: usize,
+    stack_size: usize,
+    memory_size: usize,
+    gas_left: u64,
+    gas_cost: u64,
+    gas_refund: u64,
+    reversible_write_counter: usize,
+    call_data_size: usize,
+    return_data_size: usize,
+    error: Option<String>,
+}
+
+/// [`StepHook`] writing a structured trace with one json object per step.
+///
+/// Writing stops at the first error, which is returned by
+/// [`JsonlStepTracer::into_inner`].
+#[derive(Debug)]
+pub struct JsonlStepTracer<W: Write + Debug + Send + Sync> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write + Debug + Send + Sync> JsonlStepTracer<W> {
+    /// Create a tracer writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Flush the writer, or return the first error hit while writing.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()
+    }
+
+    /// Flush and return the writer, or the first error hit while writing.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_step(&mut self, step: &ExecStep, call: &CallSummary) -> io::Result<()> {
+        let record = StepRecord {
+            exec_state: format!("{:?}", step.exec_state),
+            pc: step.pc,
+            call_index: step.call_index,
+            rwc: step.rwc.0,
+            stack_size: step.stack_size,
+            memory_size: step.memory_size,
+            gas_left: step.gas_left,
+            gas_cost: step.gas_cost,
+            gas_refund: step.gas_refund,
+            reversible_write_counter: step.reversible_write_counter,
+            call_data_size: call.call_data_size,
+            return_data_size: call.return_data_size,
+            error: step.error.as_ref().map(|err| format!("{:?}", err)),
+        };
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+impl<W: Write + Debug + Send + Sync> StepHook for JsonlStepTracer<W> {
+    fn on_step(&mut self, step: &ExecStep, call: &CallSummary) {
+        if self.error.is_none() {
+            self.error = self.write_step(step, call).err();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::TestContext;
+
+    #[test]
+    fn jsonl_step_tracer() {
+        let code = bytecode! {
+            PUSH1(0x20)
+            PUSH1(0x00)
+            MSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+
+        let tracer = Arc::new(Mutex::new(JsonlStepTracer::new(Vec::new())));
+        let mut builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .with_step_hook(Box::new(tracer.clone()));
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        drop(builder);
+
+        let tracer = Arc::try_unwrap(tracer).unwrap().into_inner().unwrap();
+        let trace = String::from_utf8(tracer.into_inner().unwrap()).unwrap();
+        let steps: Vec<serde_json::Value> = trace
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let exec_states: Vec<&str> = steps
+            .iter()
+            .map(|step| step["exec_state"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            exec_states,
+            [
+                "BeginTx",
+                "Op(PUSH1)",
+                "Op(PUSH1)",
+                "Op(MSTORE)",
+                "Op(STOP)",
+                "EndTx"
+            ]
+        );
+        // MSTORE expands the memory of the call, seen by the next step
+        assert_eq!(steps[3]["memory_size"], 0);
+        assert_eq!(steps[4]["memory_size"], 32);
+    }
+}
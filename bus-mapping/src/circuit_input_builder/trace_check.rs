@@ -0,0 +1,232 @@
+//! Sanity checks of the geth traces of a block.
+//!
+//! The conversion of the geth traces into steps assumes that each trace is a
+//! valid EVM execution.  A malformed trace, as returned by some RPC providers,
+//! makes it panic deep in the conversion code.  [`check_geth_traces`] checks
+//! the invariants the conversion relies on beforehand, and reports the first
+//! broken one with the step breaking it.
+
+use crate::Error;
+use eth_types::{GethExecStep, GethExecTrace};
+
+/// Maximum number of words in the stack
+const STACK_LIMIT: usize = 1024;
+
+/// Check that there is one trace per transaction of the block, and that each
+/// trace is a consistent EVM execution:
+/// - the root call has depth 1, and the depth only increases by one after a call or a create, and
+///   only decreases by one,
+/// - the stack never exceeds its limit, and changes between two steps of a call by at most what the
+///   opcode pops and pushes,
+/// - the memory of a call never shrinks and is word aligned,
+/// - the gas of a call never increases, and decreases by the cost of the opcode except for calls
+///   and creates.
+///
+/// Memory checks are skipped for traces captured without memory.
+pub fn check_geth_traces(num_txs: usize, geth_traces: &[GethExecTrace]) -> Result<(), Error> {
+    if geth_traces.len() != num_txs {
+        return Err(Error::InvalidGethExecTrace(
+            "number of traces differs from the number of transactions",
+        ));
+    }
+    for (tx_index, geth_trace) in geth_traces.iter().enumerate() {
+        check_geth_trace(&geth_trace.struct_logs)
+            .map_err(|(step, reason)| malformed(tx_index, step, &geth_trace.struct_logs, reason))?;
+    }
+    Ok(())
+}
+
+fn malformed(tx_index: usize, step: usize, steps: &[GethExecStep], reason: String) -> Error {
+    Error::MalformedGethTrace {
+        // Same id as the transaction context of the builder
+        tx: tx_index + 1,
+        step,
+        pc: steps[step].pc,
+        op: steps[step].op,
+        reason,
+    }
+}
+
+/// Check the steps of a trace, returning the index of the step breaking an
+/// invariant with the broken invariant.
+fn check_geth_trace(steps: &[GethExecStep]) -> Result<(), (usize, String)> {
+    match steps.first() {
+        Some(first) if first.depth != 1 => {
+            return Err((0, format!("root call has depth {}", first.depth)))
+        }
+        _ => (),
+    }
+    for (index, step) in steps.iter().enumerate() {
+        if step.stack.0.len() > STACK_LIMIT {
+            return Err((index, format!("stack has {} words", step.stack.0.len())));
+        }
+        if step.memory.len() % 32 != 0 {
+            return Err((index, format!("memory has {} bytes", step.memory.len())));
+        }
+    }
+
+    // Index of the steps calling the current call and its ancestors
+    let mut callers = Vec::new();
+    for (index, pair) in steps.windows(2).enumerate() {
+        let (step, next) = (&pair[0], &pair[1]);
+        if next.depth == step.depth + 1 {
+            if !step.op.is_call_or_create() || step.error.is_some() {
+                return Err((
+                    index,
+                    format!("{:?} enters call of depth {}", step.op, next.depth),
+                ));
+            }
+            callers.push(index);
+        } else if next.depth == step.depth {
+            check_call_step(step, next, false).map_err(|reason| (index, reason))?;
+        } else if next.depth + 1 == step.depth {
+            // The call has ended, the caller resumes after the call step
+            let caller = callers
+                .pop()
+                .ok_or_else(|| (index, format!("root call returns to depth {}", next.depth)))?;
+            check_call_step(&steps[caller], next, true).map_err(|reason| (caller, reason))?;
+        } else {
+            return Err((
+                index,
+                format!("depth goes from {} to {}", step.depth, next.depth),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the transition from `step` to `next`, the following step of the same
+/// call.  `has_callee` is true when `step` ran a callee in between.
+fn check_call_step(
+    step: &GethExecStep,
+    next: &GethExecStep,
+    has_callee: bool,
+) -> Result<(), String> {
+    if step.error.is_some() {
+        return Err(format!(
+            "call continues after error {:?}",
+            step.error.as_deref().unwrap_or_default()
+        ));
+    }
+
+    // The opcode needs that many words in the stack, otherwise it underflows
+    let num_pops = step
+        .op
+        .invalid_stack_ptrs()
+        .into_iter()
+        .filter(|stack_ptr| *stack_ptr > 0)
+        .count();
+    let (stack_len, next_stack_len) = (step.stack.0.len(), next.stack.0.len());
+    if !step.stack.0.is_empty() && stack_len < num_pops {
+        return Err(format!(
+            "{:?} with {} words in the stack did not underflow",
+            step.op, stack_len
+        ));
+    }
+    if next_stack_len > stack_len + 1 || next_stack_len + num_pops < stack_len {
+        return Err(format!(
+            "{:?} changes the stack from {} to {} words",
+            step.op, stack_len, next_stack_len
+        ));
+    }
+
+    if !step.memory.is_empty() && next.memory.len() < step.memory.len() {
+        return Err(format!(
+            "{:?} shrinks the memory from {} to {} bytes",
+            step.op,
+            step.memory.len(),
+            next.memory.len()
+        ));
+    }
+
+    if next.gas > step.gas {
+        return Err(format!(
+            "{:?} increases the gas from {} to {}",
+            step.op, step.gas, next.gas
+        ));
+    }
+    if !has_callee && !step.op.is_call_or_create() && step.gas - next.gas != step.gas_cost {
+        return Err(format!(
+            "{:?} costs {} gas but gas goes from {} to {}",
+            step.op, step.gas_cost, step.gas, next.gas
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::BlockData;
+    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, Word};
+    use mock::TestContext;
+
+    fn traced_block() -> GethData {
+        let code = bytecode! {
+            PUSH1(0x20)
+            PUSH1(0x00)
+            MSTORE
+            PUSH1(0x00)
+            MLOAD
+            STOP
+        };
+        TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into()
+    }
+
+    fn check(block: &GethData) -> Result<(), Error> {
+        check_geth_traces(block.eth_block.transactions.len(), &block.geth_traces)
+    }
+
+    fn assert_malformed(block: &GethData, expected_step: usize, expected_op: OpcodeId) {
+        match check(block) {
+            Err(Error::MalformedGethTrace { tx, step, op, .. }) => {
+                assert_eq!((tx, step, op), (1, expected_step, expected_op))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn geth_trace_ok() {
+        let block = traced_block();
+        check(&block).unwrap();
+        BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+    }
+
+    #[test]
+    fn geth_trace_missing() {
+        let mut block = traced_block();
+        block.geth_traces.clear();
+        assert!(matches!(check(&block), Err(Error::InvalidGethExecTrace(_))));
+    }
+
+    #[test]
+    fn geth_trace_stack() {
+        let mut block = traced_block();
+        // The second PUSH1 pushes two words
+        block.geth_traces[0].struct_logs[2]
+            .stack
+            .0
+            .push(Word::zero());
+        assert_malformed(&block, 1, OpcodeId::PUSH1);
+    }
+
+    #[test]
+    fn geth_trace_gas() {
+        let mut block = traced_block();
+        block.geth_traces[0].struct_logs[1].gas_cost += 1;
+        assert_malformed(&block, 1, OpcodeId::PUSH1);
+    }
+
+    #[test]
+    fn geth_trace_depth() {
+        let mut block = traced_block();
+        block.geth_traces[0].struct_logs[3].depth = 2;
+        assert_malformed(&block, 2, OpcodeId::MSTORE);
+    }
+}
@@ -0,0 +1,425 @@
+//! Circuit parameters of blocks of token transfers.
+//!
+//! Most transactions of real blocks are ERC-20 and ERC-721 transfers, whose
+//! execution depends on the token contract but barely on the arguments.
+//! Such a block can be recognized from its transactions alone, before
+//! fetching its traces, and its circuit parameters estimated from a budget
+//! per transfer of each token measured on sample transfers.  This sizes a
+//! [`FixedCParams`] close to the witness instead of to the worst case block,
+//! along with the Keccak circuit and the storage proofs of the block.
+
+use super::FixedCParams;
+use eth_types::{Address, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Rw operations of a block besides its transactions: the start padding row
+/// and the lookup of the `EndBlock` step
+const BLOCK_RWS: usize = 2;
+/// Disabled and unused rows of the copy circuit
+const BLOCK_COPY_ROWS: usize = 4;
+/// keccak_f permutations of the Keccak circuit that are unusable
+const BLOCK_KECCAK_FS: usize = 2;
+/// Bytes absorbed by one keccak_f permutation
+const KECCAK_RATE: usize = 136;
+
+/// Selector of `transfer(address,uint256)` of ERC-20
+const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// Selector of `transferFrom(address,address,uint256)`, which is the same for
+/// ERC-20 and ERC-721
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+/// Token transfer call, recognized from its selector.
+///
+/// `safeTransferFrom` of ERC-721 is not recognized, since it calls the
+/// receiver when it is a contract, which can run any code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TransferKind {
+    /// ERC-20 `transfer`
+    Transfer,
+    /// ERC-20 or ERC-721 `transferFrom`
+    TransferFrom,
+}
+
+/// Token transfer recognized from the calldata of a transaction.
+///
+/// The transfer is identified by its token contract as well as by its kind,
+/// since the cost of a transfer depends on the implementation of the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TokenTransfer {
+    /// Address of the token contract
+    pub token: Address,
+    /// Transfer call
+    pub kind: TransferKind,
+}
+
+impl TokenTransfer {
+    /// Recognize the transfer done by `tx`, which must be a call without
+    /// value with the abi encoded arguments of the transfer.
+    pub fn from_tx(tx: &Transaction) -> Option<Self> {
+        let token = tx.to?;
+        if !tx.value.is_zero() {
+            return None;
+        }
+        let (selector, args) = tx.input.split_at(tx.input.len().min(4));
+        let kind = match (selector, args.len()) {
+            (s, 64) if s == TRANSFER_SELECTOR => TransferKind::Transfer,
+            (s, 96) if s == TRANSFER_FROM_SELECTOR => TransferKind::TransferFrom,
+            _ => return None,
+        };
+        Some(Self { token, kind })
+    }
+}
+
+/// Upper bound of the circuit rows used by one token transfer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferBudget {
+    /// Rw operations
+    pub rws: usize,
+    /// Rows of the copy circuit
+    pub copy_rows: usize,
+    /// Steps of the exp circuit
+    pub exp_steps: usize,
+    /// Rows of the bytecode circuit for the code run by the transfer, which
+    /// is only counted once per token in a block
+    pub bytecode: usize,
+    /// keccak_f permutations of the signature hash of the transaction and of
+    /// its SHA3 steps
+    pub keccak_fs: usize,
+    /// keccak_f permutations of the hashes of the code run by the transfer,
+    /// which are only counted once per token in a block
+    pub code_keccak_fs: usize,
+    /// Account and storage proofs of the state accessed by the transfer
+    pub storage_proofs: usize,
+    /// Trie nodes of the account and storage proofs
+    pub proof_nodes: usize,
+}
+
+impl TransferBudget {
+    /// Rows used by the transfer of `sample`.
+    fn of_sample(sample: &TransferSample) -> Self {
+        let params = &sample.params;
+        Self {
+            rws: params.max_rws.saturating_sub(BLOCK_RWS),
+            copy_rows: params.max_copy_rows.saturating_sub(BLOCK_COPY_ROWS),
+            exp_steps: params.max_exp_steps,
+            bytecode: params.max_bytecode,
+            keccak_fs: sample.keccak_fs,
+            code_keccak_fs: sample.code_keccak_fs,
+            storage_proofs: sample.proofs.len(),
+            proof_nodes: sample.proofs.iter().sum(),
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        Self {
+            rws: self.rws.max(other.rws),
+            copy_rows: self.copy_rows.max(other.copy_rows),
+            exp_steps: self.exp_steps.max(other.exp_steps),
+            bytecode: self.bytecode.max(other.bytecode),
+            keccak_fs: self.keccak_fs.max(other.keccak_fs),
+            code_keccak_fs: self.code_keccak_fs.max(other.code_keccak_fs),
+            storage_proofs: self.storage_proofs.max(other.storage_proofs),
+            proof_nodes: self.proof_nodes.max(other.proof_nodes),
+        }
+    }
+}
+
+/// Circuit usage of a token transfer proven alone, measured to calibrate
+/// the [`TransferBudget`] of its token.
+#[derive(Debug, Clone)]
+pub struct TransferSample {
+    /// Parameters computed by the
+    /// [`CircuitInputBuilder<DynamicCParams>`](super::CircuitInputBuilder)
+    /// for a block made of the transfer alone, with `max_bytecode` only
+    /// counting the code run by the transfer
+    pub params: FixedCParams,
+    /// keccak_f permutations of the signature hash of the transaction and of
+    /// its SHA3 steps
+    pub keccak_fs: usize,
+    /// keccak_f permutations of the hashes of the code run by the transfer
+    pub code_keccak_fs: usize,
+    /// Number of trie nodes of each account and storage proof of the state
+    /// accessed by the transfer
+    pub proofs: Vec<usize>,
+}
+
+/// Number of keccak_f permutations hashing `len` bytes, including the
+/// padding.
+pub fn keccak_fs(len: usize) -> usize {
+    len / KECCAK_RATE + 1
+}
+
+/// Calibrated [`TransferBudget`] of each [`TokenTransfer`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferBudgets(Vec<(TokenTransfer, TransferBudget)>);
+
+impl TransferBudgets {
+    /// Measure the budgets from sample transfers, each given with its
+    /// [`TransferSample`].
+    pub fn calibrate(samples: impl IntoIterator<Item = (TokenTransfer, TransferSample)>) -> Self {
+        let mut budgets = BTreeMap::<TokenTransfer, TransferBudget>::new();
+        for (transfer, sample) in samples {
+            let budget = budgets.entry(transfer).or_default();
+            *budget = budget.max(TransferBudget::of_sample(&sample));
+        }
+        Self(budgets.into_iter().collect())
+    }
+
+    /// Budget of `transfer`, if it was calibrated.
+    pub fn get(&self, transfer: &TokenTransfer) -> Option<&TransferBudget> {
+        self.0
+            .iter()
+            .find(|(calibrated, _)| calibrated == transfer)
+            .map(|(_, budget)| budget)
+    }
+}
+
+/// Capacity estimated for a block of token transfers by
+/// [`TransferProfile::estimate`].
+#[derive(Debug, Clone, Copy)]
+pub struct TransferEstimate {
+    /// Circuit parameters of the block
+    pub params: FixedCParams,
+    /// Account and storage proofs of the state accessed by the block, each
+    /// fetched with `eth_getProof` and proven by the MPT circuit
+    pub storage_proofs: usize,
+    /// Trie nodes of the storage proofs, which are the nodes of the MPT
+    /// circuit witness
+    pub proof_nodes: usize,
+}
+
+/// Token transfers of a block made only of token transfers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProfile {
+    /// Transfer of each transaction
+    pub transfers: Vec<TokenTransfer>,
+    /// Calldata bytes of all the transactions
+    pub calldata: usize,
+    /// Number of withdrawals of the block
+    pub withdrawals: usize,
+}
+
+impl TransferProfile {
+    /// Profile of `eth_block`, if every transaction of the block is a
+    /// [`TokenTransfer`].
+    pub fn of_block(eth_block: &eth_types::Block<Transaction>) -> Option<Self> {
+        let transfers = eth_block
+            .transactions
+            .iter()
+            .map(TokenTransfer::from_tx)
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            transfers,
+            calldata: eth_block.transactions.iter().map(|tx| tx.input.len()).sum(),
+            withdrawals: eth_block.withdrawals.as_ref().map_or(0, Vec::len),
+        })
+    }
+
+    /// Capacity of the block with the usage of each transfer bounded by its
+    /// budget in `budgets`, or `None` when a transfer was not calibrated.
+    /// `rows_per_keccak_f` is the number of rows of one keccak_f permutation
+    /// in the Keccak circuit, which depends on its configuration.  The EVM
+    /// circuit is sized dynamically.
+    ///
+    /// The estimate only holds for transfers behaving like the calibrated
+    /// samples: the witness must still be checked against the returned
+    /// parameters, which `CircuitInputBuilder::<FixedCParams>::handle_block`
+    /// does for the rw operations.
+    pub fn estimate(
+        &self,
+        budgets: &TransferBudgets,
+        rows_per_keccak_f: usize,
+    ) -> Option<TransferEstimate> {
+        let mut params = FixedCParams {
+            max_rws: BLOCK_RWS,
+            max_txs: self.transfers.len(),
+            max_withdrawals: self.withdrawals,
+            max_calldata: self.calldata,
+            max_copy_rows: BLOCK_COPY_ROWS,
+            max_exp_steps: 0,
+            max_bytecode: 0,
+            max_evm_rows: 0,
+            max_keccak_rows: 0,
+            max_vertical_circuit_rows: 0,
+        };
+        let mut keccak_fs = BLOCK_KECCAK_FS;
+        let (mut storage_proofs, mut proof_nodes) = (0, 0);
+        // Bytecode rows and code keccak_f permutations of each token
+        let mut code = BTreeMap::<Address, (usize, usize)>::new();
+        for transfer in &self.transfers {
+            let budget = budgets.get(transfer)?;
+            params.max_rws += budget.rws;
+            params.max_copy_rows += budget.copy_rows;
+            params.max_exp_steps += budget.exp_steps;
+            keccak_fs += budget.keccak_fs;
+            storage_proofs += budget.storage_proofs;
+            proof_nodes += budget.proof_nodes;
+            let (bytecode, code_keccak_fs) = code.entry(transfer.token).or_default();
+            *bytecode = (*bytecode).max(budget.bytecode);
+            *code_keccak_fs = (*code_keccak_fs).max(budget.code_keccak_fs);
+        }
+        params.max_bytecode = code.values().map(|(bytecode, _)| bytecode).sum();
+        keccak_fs += code
+            .values()
+            .map(|(_, code_keccak_fs)| code_keccak_fs)
+            .sum::<usize>();
+        params.max_keccak_rows = keccak_fs * rows_per_keccak_f;
+        Some(TransferEstimate {
+            params,
+            storage_proofs,
+            proof_nodes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::{address, Word};
+
+    const TOKEN: Address = Address::repeat_byte(0xaa);
+
+    fn transfer_calldata(to: Address, amount: Word) -> Vec<u8> {
+        let mut calldata = TRANSFER_SELECTOR.to_vec();
+        calldata.extend_from_slice(&[0; 12]);
+        calldata.extend_from_slice(to.as_bytes());
+        let mut amount_bytes = [0; 32];
+        amount.to_big_endian(&mut amount_bytes);
+        calldata.extend_from_slice(&amount_bytes);
+        calldata
+    }
+
+    fn call(token: Address, input: Vec<u8>) -> Transaction {
+        Transaction {
+            to: Some(token),
+            input: input.into(),
+            ..Default::default()
+        }
+    }
+
+    fn transfer(token: Address, kind: TransferKind) -> TokenTransfer {
+        TokenTransfer { token, kind }
+    }
+
+    #[test]
+    fn token_transfer_from_tx() {
+        let transfer_tx = call(
+            TOKEN,
+            transfer_calldata(Address::repeat_byte(1), Word::from(5)),
+        );
+        assert_eq!(
+            TokenTransfer::from_tx(&transfer_tx),
+            Some(transfer(TOKEN, TransferKind::Transfer))
+        );
+
+        let mut transfer_from = TRANSFER_FROM_SELECTOR.to_vec();
+        transfer_from.extend_from_slice(&[0; 96]);
+        let other_token = address!("0x00000000000000000000000000000000000000bb");
+        assert_eq!(
+            TokenTransfer::from_tx(&call(other_token, transfer_from)),
+            Some(transfer(other_token, TransferKind::TransferFrom))
+        );
+
+        // Calldata not matching the arguments of the selector
+        let mut truncated = transfer_tx.clone();
+        truncated.input = transfer_tx.input[..36].to_vec().into();
+        assert_eq!(TokenTransfer::from_tx(&truncated), None);
+        // Transfer with value
+        let mut with_value = transfer_tx.clone();
+        with_value.value = Word::one();
+        assert_eq!(TokenTransfer::from_tx(&with_value), None);
+        // Contract creation
+        let mut creation = transfer_tx;
+        creation.to = None;
+        assert_eq!(TokenTransfer::from_tx(&creation), None);
+        assert_eq!(TokenTransfer::from_tx(&call(TOKEN, vec![])), None);
+    }
+
+    #[test]
+    fn transfer_profile_params() {
+        let other_token = Address::repeat_byte(0xbb);
+        let transfer_tx = |token| {
+            call(
+                token,
+                transfer_calldata(Address::repeat_byte(1), Word::from(5)),
+            )
+        };
+        let mut eth_block = eth_types::Block::<Transaction> {
+            transactions: vec![
+                transfer_tx(TOKEN),
+                transfer_tx(TOKEN),
+                transfer_tx(other_token),
+            ],
+            withdrawals: Some(vec![]),
+            ..Default::default()
+        };
+        let profile = TransferProfile::of_block(&eth_block).unwrap();
+        assert_eq!(
+            profile.transfers,
+            [
+                transfer(TOKEN, TransferKind::Transfer),
+                transfer(TOKEN, TransferKind::Transfer),
+                transfer(other_token, TransferKind::Transfer),
+            ]
+        );
+
+        let sample = |token, max_rws, max_bytecode, proofs: &[usize]| {
+            (
+                transfer(token, TransferKind::Transfer),
+                TransferSample {
+                    params: FixedCParams {
+                        max_rws,
+                        max_copy_rows: BLOCK_COPY_ROWS,
+                        max_bytecode,
+                        ..Default::default()
+                    },
+                    keccak_fs: 4,
+                    code_keccak_fs: keccak_fs(max_bytecode),
+                    proofs: proofs.to_vec(),
+                },
+            )
+        };
+        // Only the samples of the first token are calibrated
+        let budgets = TransferBudgets::calibrate([
+            sample(TOKEN, 142, 900, &[8, 8, 6]),
+            sample(TOKEN, 152, 900, &[9, 8, 5]),
+        ]);
+        assert_eq!(
+            budgets.get(&transfer(TOKEN, TransferKind::Transfer)),
+            Some(&TransferBudget {
+                rws: 150,
+                copy_rows: 0,
+                exp_steps: 0,
+                bytecode: 900,
+                keccak_fs: 4,
+                code_keccak_fs: 7,
+                storage_proofs: 3,
+                proof_nodes: 22,
+            })
+        );
+        assert!(profile.estimate(&budgets, 300).is_none());
+
+        // The bytecode of a token and its hash are only counted once
+        let budgets = TransferBudgets::calibrate([
+            sample(TOKEN, 152, 900, &[9, 8, 5]),
+            sample(other_token, 502, 3000, &[8, 7]),
+        ]);
+        let estimate = profile.estimate(&budgets, 300).unwrap();
+        let params = estimate.params;
+        assert_eq!(params.max_rws, 2 * 150 + 500 + BLOCK_RWS);
+        assert_eq!(params.max_copy_rows, BLOCK_COPY_ROWS);
+        assert_eq!(params.max_bytecode, 900 + 3000);
+        assert_eq!(params.max_txs, 3);
+        assert_eq!(params.max_calldata, 3 * 68);
+        assert_eq!(
+            params.max_keccak_rows,
+            (BLOCK_KECCAK_FS + 3 * 4 + 7 + 23) * 300
+        );
+        assert_eq!(estimate.storage_proofs, 3 + 3 + 2);
+        assert_eq!(estimate.proof_nodes, 22 + 22 + 15);
+
+        eth_block.transactions.push(call(TOKEN, vec![0; 4]));
+        assert_eq!(TransferProfile::of_block(&eth_block), None);
+    }
+}
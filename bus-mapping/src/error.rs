@@ -3,6 +3,7 @@
 use core::fmt::{Display, Formatter, Result as FmtResult};
 use eth_types::{evm_types::OpcodeId, Address, GethExecStep, Word, H256};
 use ethers_providers::ProviderError;
+use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
 
 use crate::geth_errors::{
@@ -15,8 +16,9 @@ use crate::geth_errors::{
 pub enum Error {
     /// Serde de/serialization error.
     SerdeError(serde_json::error::Error),
-    /// JSON-RPC related error.
-    JSONRpcError(ProviderError),
+    /// Error returned by the node serving the block, its traces or its
+    /// state.
+    RpcError(ProviderError),
     /// OpcodeId is not a call type.
     OpcodeIdNotCallType,
     /// Account not found in the StateDB
@@ -34,14 +36,62 @@ pub enum Error {
     InvalidGethExecTrace(&'static str),
     /// Invalid [`GethExecStep`] due to an invalid/unexpected value in it.
     InvalidGethExecStep(&'static str, Box<GethExecStep>),
+    /// [`eth_types::GethExecTrace`] breaking an invariant of the EVM
+    /// execution
+    MalformedGethTrace {
+        /// Id of the transaction of the trace, starting at 1
+        tx: usize,
+        /// Index of the step breaking the invariant in the trace
+        step: usize,
+        /// Program counter of the step
+        pc: u64,
+        /// Opcode of the step
+        op: OpcodeId,
+        /// Broken invariant
+        reason: String,
+    },
+    /// Step of an [`eth_types::GethExecTrace`] disagreeing with the execution
+    /// tracked by the builder
+    TraceMismatch {
+        /// Id of the transaction executing the step
+        tx: usize,
+        /// Program counter of the step
+        pc: u64,
+        /// Opcode of the step
+        op: OpcodeId,
+        /// Disagreement between the trace and the builder
+        reason: String,
+    },
     /// Eth type related error.
     EthTypeError(eth_types::Error),
     /// EVM Execution error
     ExecutionError(ExecError),
     /// Internal Code error
     InternalError(&'static str),
-    /// Rw number overflow
-    RwsNotEnough(usize, usize),
+    /// Opcode whose execution is not supported by the circuits, like
+    /// SELFDESTRUCT, or disabled by
+    /// [`FeatureConfig::disabled_opcodes`](crate::circuit_input_builder::FeatureConfig).
+    /// The `warn-unimplemented` feature generates dummy steps for the
+    /// unsupported opcodes instead, and
+    /// [`UnsupportedPolicy::SkipTx`](crate::circuit_input_builder::UnsupportedPolicy)
+    /// leaves their transactions out of the block.
+    UnsupportedOpcode {
+        /// Unsupported opcode
+        op: OpcodeId,
+        /// Program counter of the opcode
+        pc: u64,
+        /// Id of the transaction executing the opcode
+        tx: usize,
+    },
+    /// The block needs more rows than the capacity of a circuit
+    CapacityExceeded {
+        /// Name of the circuit
+        circuit: &'static str,
+        /// Number of rows needed by the block
+        needed: usize,
+        /// Number of rows available in the circuit
+        available: usize,
+    },
 }
 
 impl From<eth_types::Error> for Error {
@@ -52,7 +102,7 @@ impl From<eth_types::Error> for Error {
 
 impl From<ProviderError> for Error {
     fn from(err: ProviderError) -> Self {
-        Error::JSONRpcError(err)
+        Error::RpcError(err)
     }
 }
 
@@ -65,7 +115,7 @@ impl Display for Error {
 impl StdError for Error {}
 
 /// Out of Gas errors by opcode
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OogError {
     /// Out of Gas for opcodes which have non-zero constant gas cost
     Constant,
@@ -137,7 +187,7 @@ impl From<&OpcodeId> for OogError {
 }
 
 /// Insufficient balance errors by opcode/state.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InsufficientBalanceError {
     /// Insufficient balance during CALL/CALLCODE opcode.
     Call,
@@ -148,7 +198,7 @@ pub enum InsufficientBalanceError {
 }
 
 /// Nonce uint overflow errors by opcode/state.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NonceUintOverflowError {
     /// Nonce uint overflow during CREATE opcode.
     Create,
@@ -157,7 +207,7 @@ pub enum NonceUintOverflowError {
 }
 
 /// Call depth errors by opcode/state.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DepthError {
     /// Call depth errors in CALL/CALLCODE opcode.
     Call,
@@ -168,7 +218,7 @@ pub enum DepthError {
 }
 
 /// EVM Execution Error
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecError {
     /// Invalid Opcode
     InvalidOpcode,
@@ -1,12 +1,12 @@
 //! Definition of each opcode of the EVM.
 use crate::{
-    circuit_input_builder::{CircuitInputStateRef, ExecState, ExecStep},
+    circuit_input_builder::{CircuitInputStateRef, ExecState, ExecStep, FeatureConfig},
     error::{DepthError, ExecError, InsufficientBalanceError, NonceUintOverflowError, OogError},
     evm::OpcodeId,
     operation::{AccountField, AccountOp, TxAccessListAccountOp},
     Error,
 };
-use core::fmt::Debug;
+use core::{fmt::Debug, marker::PhantomData};
 use eth_types::{evm_unimplemented, GethExecStep, ToAddress, ToWord, Word};
 
 mod address;
@@ -148,6 +148,42 @@ impl Opcode for Dummy {
     }
 }
 
+/// Opcode not supported by the circuits, like SELFDESTRUCT. Witness generation
+/// fails with [`Error::UnsupportedOpcode`], unless the `warn-unimplemented`
+/// feature is enabled, in which case the steps are generated by `T`.
+#[derive(Debug, Copy, Clone)]
+struct Unsupported<T>(PhantomData<T>);
+
+impl<T: Opcode> Opcode for Unsupported<T> {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        if !cfg!(feature = "warn-unimplemented") {
+            return Err(Error::UnsupportedOpcode {
+                op: geth_step.op,
+                pc: geth_step.pc,
+                tx: state.tx_ctx.id(),
+            });
+        }
+        log::warn!(
+            "Using dummy gen_associated_ops for opcode {:?}",
+            geth_step.op
+        );
+        T::gen_associated_ops(state, geth_steps)
+    }
+}
+
+/// Whether witness generation fails with [`Error::UnsupportedOpcode`] on a step
+/// executing `opcode_id` on the network of `feature_config`.  Steps failing
+/// with an error are witnessed as error states, except for the disabled
+/// opcodes, so this over-approximates them.
+pub(crate) fn is_unsupported(opcode_id: &OpcodeId, feature_config: &FeatureConfig) -> bool {
+    feature_config.disabled_opcodes.contains(opcode_id)
+        || (*opcode_id == OpcodeId::SELFDESTRUCT && !cfg!(feature = "warn-unimplemented"))
+}
+
 type FnGenAssociatedOps = fn(
     state: &mut CircuitInputStateRef,
     geth_steps: &[GethExecStep],
@@ -266,14 +302,8 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::CREATE => Create::<false>::gen_associated_ops,
         OpcodeId::CREATE2 => Create::<true>::gen_associated_ops,
         OpcodeId::RETURN | OpcodeId::REVERT => ReturnRevert::gen_associated_ops,
-        OpcodeId::SELFDESTRUCT => {
-            evm_unimplemented!("Using dummy gen_selfdestruct_ops for opcode SELFDESTRUCT");
-            DummySelfDestruct::gen_associated_ops
-        }
-        _ => {
-            evm_unimplemented!("Using dummy gen_associated_ops for opcode {:?}", opcode_id);
-            Dummy::gen_associated_ops
-        }
+        OpcodeId::SELFDESTRUCT => Unsupported::<DummySelfDestruct>::gen_associated_ops,
+        _ => Unsupported::<Dummy>::gen_associated_ops,
     }
 }
 
@@ -355,14 +385,23 @@ pub fn gen_associated_ops(
     state: &mut CircuitInputStateRef,
     geth_steps: &[GethExecStep],
 ) -> Result<Vec<ExecStep>, Error> {
+    if state.feature_config.disabled_opcodes.contains(opcode_id) {
+        return Err(Error::UnsupportedOpcode {
+            op: *opcode_id,
+            pc: geth_steps[0].pc,
+            tx: state.tx_ctx.id(),
+        });
+    }
+
+    // The memory left by the previous step must be the one of the trace
     let memory_enabled = !geth_steps.iter().all(|s| s.memory.is_empty());
-    if memory_enabled {
-        assert_eq!(
-            &state.call_ctx()?.memory,
-            &geth_steps[0].memory,
-            "last step of {:?} goes wrong",
-            opcode_id
-        );
+    if memory_enabled && state.call_ctx()?.memory != geth_steps[0].memory {
+        return Err(Error::TraceMismatch {
+            tx: state.tx_ctx.id(),
+            pc: geth_steps[0].pc,
+            op: *opcode_id,
+            reason: "memory differs from the one left by the previous step".to_string(),
+        });
     }
 
     // check if have error
@@ -373,7 +412,18 @@ pub fn gen_associated_ops(
     } else {
         None
     };
-    if let Some(exec_error) = state.get_step_err(geth_step, next_step).unwrap() {
+    let exec_error = match state.get_step_err(geth_step, next_step) {
+        Ok(exec_error) => exec_error,
+        Err(err) => {
+            return Err(Error::TraceMismatch {
+                tx: state.tx_ctx.id(),
+                pc: geth_step.pc,
+                op: geth_step.op,
+                reason: format!("can't tell the error of the step: {:?}", err),
+            })
+        }
+    };
+    if let Some(exec_error) = exec_error {
         log::warn!(
             "geth error {:?} occurred in  {:?} at pc {:?}",
             exec_error,
@@ -512,3 +562,253 @@ fn dummy_gen_selfdestruct_ops(
     state.handle_return(&mut [&mut exec_step], geth_steps, !state.call()?.is_root)?;
     Ok(vec![exec_step])
 }
+
+#[cfg(test)]
+mod error_tests {
+    use crate::{
+        circuit_input_builder::{FeatureConfig, FixedCParams, UnsupportedPolicy},
+        mock::BlockData,
+        operation::RWCounter,
+        Error,
+    };
+    use eth_types::{
+        address, bytecode,
+        evm_types::{Memory, OpcodeId},
+        geth_types::GethData,
+    };
+    use mock::{
+        eth,
+        test_ctx::{helpers::*, TestContext},
+        MOCK_ACCOUNTS,
+    };
+
+    fn handle_block(code: eth_types::Bytecode, params: FixedCParams) -> Result<(), Error> {
+        handle_block_with_feature(code, params, FeatureConfig::default())
+    }
+
+    fn handle_block_with_feature(
+        code: eth_types::Bytecode,
+        params: FixedCParams,
+        feature_config: FeatureConfig,
+    ) -> Result<(), Error> {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), params)
+            .new_circuit_input_builder_with_feature(feature_config);
+        builder.handle_block(&block.eth_block, &block.geth_traces)?;
+        Ok(())
+    }
+
+    // SELFDESTRUCT is not supported by the circuits, so witness generation
+    // fails on it unless warn-unimplemented is enabled
+    #[cfg(not(feature = "warn-unimplemented"))]
+    #[test]
+    fn selfdestruct_fails_by_default() {
+        let code = bytecode! {
+            PUSH1(0x00)
+            SELFDESTRUCT
+        };
+        let err = handle_block(code, FixedCParams::default()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::UnsupportedOpcode {
+                    op: OpcodeId::SELFDESTRUCT,
+                    pc: 2,
+                    tx: 1,
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    // Dummy steps for the statistics
+    #[cfg(feature = "warn-unimplemented")]
+    #[test]
+    fn selfdestruct_dummy_steps() {
+        let code = bytecode! {
+            PUSH1(0x00)
+            SELFDESTRUCT
+        };
+        assert!(handle_block(code, FixedCParams::default()).is_ok());
+    }
+
+    #[test]
+    fn memory_trace_mismatch() {
+        let code = bytecode! {
+            PUSH1(0x00)
+            PUSH1(0x00)
+            MSTORE
+            STOP
+        };
+        let mut block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        // MSTORE leaves a zero word in memory, not this one
+        let stop = block.geth_traces[0].struct_logs.last_mut().unwrap();
+        assert_eq!(stop.op, OpcodeId::STOP);
+        stop.memory = Memory::from(vec![0xff; 32]);
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let err = builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::TraceMismatch {
+                    tx: 1,
+                    pc: 5,
+                    op: OpcodeId::STOP,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn disabled_opcode() {
+        let code = bytecode! {
+            PUSH1(0x00)
+            PUSH0
+            STOP
+        };
+        assert!(handle_block(code.clone(), FixedCParams::default()).is_ok());
+
+        // Network without the PUSH0 opcode
+        let feature_config = FeatureConfig {
+            disabled_opcodes: vec![OpcodeId::PUSH0],
+            ..Default::default()
+        };
+        let err =
+            handle_block_with_feature(code, FixedCParams::default(), feature_config).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::UnsupportedOpcode {
+                    op: OpcodeId::PUSH0,
+                    pc: 2,
+                    tx: 1,
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn skip_tx_with_disabled_opcode() {
+        // The first transaction executes PUSH0, the second one doesn't
+        let block: GethData = TestContext::<4, 2>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x000000000000000000000000000000000cafe001"))
+                    .code(bytecode! { PUSH0 STOP });
+                accs[1]
+                    .address(address!("0x000000000000000000000000000000000cafe002"))
+                    .code(bytecode! { PUSH1(0x00) STOP });
+                accs[2].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                accs[3].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[2].address).to(accs[0].address);
+                txs[1].from(accs[3].address).to(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let handle_block = |on_unsupported| {
+            let feature_config = FeatureConfig {
+                disabled_opcodes: vec![OpcodeId::PUSH0],
+                on_unsupported,
+                ..Default::default()
+            };
+            let mut builder =
+                BlockData::new_from_geth_data_with_params(block.clone(), FixedCParams::default())
+                    .new_circuit_input_builder_with_feature(feature_config);
+            builder.handle_block(&block.eth_block, &block.geth_traces)?;
+            Ok::<_, Error>(builder)
+        };
+
+        let err = handle_block(UnsupportedPolicy::Fail).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::UnsupportedOpcode {
+                    op: OpcodeId::PUSH0,
+                    tx: 1,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+
+        let builder = handle_block(UnsupportedPolicy::SkipTx).unwrap();
+        assert_eq!(builder.block.txs.len(), 1);
+        assert_eq!(builder.block.txs[0].from, MOCK_ACCOUNTS[1]);
+        assert_eq!(builder.block.eth_block.transactions.len(), 1);
+    }
+
+    #[test]
+    fn rws_capacity_exceeded() {
+        let code = bytecode! {
+            PUSH1(0x00)
+            PUSH1(0x00)
+            MSTORE
+            STOP
+        };
+        let params = FixedCParams {
+            max_rws: 8,
+            ..Default::default()
+        };
+        let err = handle_block(code, params).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::CapacityExceeded {
+                    circuit: "state",
+                    available: 8,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn rws_capacity_above_rw_counter_bound() {
+        let code = bytecode! {
+            STOP
+        };
+        let params = FixedCParams {
+            max_rws: RWCounter::MAX + 1,
+            ..Default::default()
+        };
+        let err = handle_block(code, params).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::CapacityExceeded {
+                    circuit: "state",
+                    available: RWCounter::MAX,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+}
@@ -289,7 +289,12 @@ fn gen_end_tx_steps(state: &mut CircuitInputStateRef) -> Result<ExecStep, Error>
         false,
     )?;
 
-    let effective_tip = state.tx.gas_price - state.block.base_fee;
+    let effective_tip = if state.tx.gas_price.is_zero() && state.feature_config.zero_gas_price {
+        // Gasless transactions pay neither the base fee nor a tip.
+        Word::zero()
+    } else {
+        state.tx.gas_price - state.block.base_fee
+    };
     let (found, coinbase_account) = state.sdb.get_account(&state.block.coinbase);
     if !found {
         return Err(Error::AccountNotFound(state.block.coinbase));
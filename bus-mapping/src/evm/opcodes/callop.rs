@@ -15,7 +15,7 @@ use crate::{
 use eth_types::{
     evm_types::{
         gas_utils::{eip150_gas, memory_expansion_gas_cost},
-        GasCost, OpcodeId, GAS_STIPEND_CALL_WITH_VALUE,
+        GasCost, OpcodeId, GAS_STIPEND_CALL_WITH_VALUE, MAX_CALL_DEPTH,
     },
     GethExecStep, ToWord, Word,
 };
@@ -155,7 +155,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
 
         let is_call_or_callcode = call.kind == CallKind::Call || call.kind == CallKind::CallCode;
         let is_sufficient = caller_balance >= call.value;
-        let is_valid_depth = geth_step.depth < 1025;
+        let is_valid_depth = u64::from(geth_step.depth) <= MAX_CALL_DEPTH;
 
         // Precheck is OK when depth is in range and caller balance is sufficient
         let is_precheck_ok = is_valid_depth && (is_sufficient || !is_call_or_callcode);
@@ -389,6 +389,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
                             log_id: None,
                             rw_counter_start,
                             bytes: input_bytes.iter().map(|s| (*s, false)).collect(),
+                            origin: None,
                         },
                     );
                     Some(input_bytes)
@@ -415,6 +416,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
                             log_id: None,
                             rw_counter_start,
                             bytes: output_bytes.iter().map(|s| (*s, false)).collect(),
+                            origin: None,
                         },
                     );
                     Some(output_bytes)
@@ -444,6 +446,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
                             log_id: None,
                             rw_counter_start,
                             bytes: return_bytes.iter().map(|s| (*s, false)).collect(),
+                            origin: None,
                         },
                     );
                     Some(return_bytes)
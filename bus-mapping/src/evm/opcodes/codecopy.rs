@@ -109,6 +109,7 @@ fn gen_copy_event(
         log_id: None,
         rw_counter_start,
         bytes: copy_steps,
+        origin: None,
     })
 }
 
@@ -126,7 +127,7 @@ mod codecopy_tests {
     };
 
     use crate::{
-        circuit_input_builder::{CopyDataType, ExecState, NumberOrHash},
+        circuit_input_builder::{CopyDataType, CopyEventOrigin, ExecState, NumberOrHash},
         mock::BlockData,
         operation::{MemoryOp, StackOp, RW},
     };
@@ -222,6 +223,14 @@ mod codecopy_tests {
         assert_eq!(copy_events[0].dst_addr as usize, dst_offset);
         assert_eq!(copy_events[0].dst_type, CopyDataType::Memory);
         assert!(copy_events[0].log_id.is_none());
+        assert_eq!(
+            copy_events[0].origin,
+            Some(CopyEventOrigin {
+                tx_id: 1,
+                call_index: step.call_index,
+                pc: step.pc,
+            })
+        );
 
         for (idx, &(value, is_code)) in copy_events[0].bytes.iter().enumerate() {
             let (true_value, true_is_code) = code.get(code_offset + idx).unwrap_or_default();
@@ -8,7 +8,9 @@ use crate::{
     state_db::CodeDB,
     Error,
 };
-use eth_types::{Bytecode, GethExecStep, ToBigEndian, ToWord, Word, H160, H256};
+use eth_types::{
+    evm_types::MAX_CALL_DEPTH, Bytecode, GethExecStep, ToBigEndian, ToWord, Word, H160, H256,
+};
 use ethers_core::utils::{get_create2_address, keccak256, rlp};
 
 #[derive(Debug, Copy, Clone)]
@@ -105,8 +107,9 @@ impl<const IS_CREATE2: bool> Opcode for Create<IS_CREATE2> {
 
         // Check if an error of ErrDepth, ErrInsufficientBalance or
         // ErrNonceUintOverflow occurred.
-        let is_precheck_ok =
-            depth < 1025 && caller_balance >= callee.value && caller_nonce < u64::MAX;
+        let is_precheck_ok = depth as u64 <= MAX_CALL_DEPTH
+            && caller_balance >= callee.value
+            && caller_nonce < u64::MAX;
         if is_precheck_ok {
             // Increase caller's nonce
             state.push_op_reversible(
@@ -324,6 +327,7 @@ fn handle_copy(
             dst_addr: 0,
             log_id: None,
             bytes,
+            origin: None,
         },
     );
 
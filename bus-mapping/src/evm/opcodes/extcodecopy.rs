@@ -165,6 +165,7 @@ fn gen_copy_event(
         log_id: None,
         rw_counter_start,
         bytes: copy_steps,
+        origin: None,
     })
 }
 
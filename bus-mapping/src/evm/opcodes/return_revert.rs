@@ -194,6 +194,7 @@ fn handle_copy(
             dst_addr: destination.offset.try_into().unwrap(),
             log_id: None,
             bytes,
+            origin: None,
         },
     );
 
@@ -232,6 +233,7 @@ fn handle_create(
             dst_addr: 0,
             log_id: None,
             bytes,
+            origin: None,
         },
     );
 
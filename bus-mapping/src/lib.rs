@@ -30,4 +30,5 @@ pub mod operation;
 pub mod precompile;
 pub mod rpc;
 pub mod state_db;
+pub mod state_provider;
 pub use error::Error;
@@ -8,6 +8,7 @@ pub use container::OperationContainer;
 pub use eth_types::evm_types::{MemoryAddress, StackAddress};
 use gadgets::impl_expr;
 use halo2_proofs::plonk::Expression;
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
 use core::{cmp::Ordering, fmt, fmt::Debug};
@@ -38,7 +39,7 @@ impl RW {
 /// Wrapper type over `usize` which represents the global counter. The purpose
 /// of the `RWCounter` is to enforce that each Opcode/Instruction and Operation
 /// is unique and just executed once.
-#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct RWCounter(pub usize);
 
 impl fmt::Debug for RWCounter {
@@ -72,6 +73,11 @@ impl Default for RWCounter {
 }
 
 impl RWCounter {
+    /// Largest rw counter supported by the circuits, which decompose it into
+    /// 32-bit limbs.  Larger counters would wrap and break the ordering of the
+    /// State circuit.
+    pub const MAX: usize = u32::MAX as usize;
+
     /// Create a new RWCounter with the initial default value
     pub fn new() -> Self {
         Self(1)
@@ -92,7 +98,7 @@ impl RWCounter {
 
 /// Enum used to differentiate between EVM Stack, Memory and Storage operations.
 /// This is also used as the RwTableTag for the RwTable.
-#[derive(Debug, Clone, PartialEq, Eq, Copy, EnumIter, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, EnumIter, Hash, Serialize, Deserialize)]
 pub enum Target {
     /// Start is a padding operation.
     Start = 1,
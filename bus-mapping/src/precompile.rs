@@ -6,6 +6,7 @@ use eth_types::{
 };
 #[cfg(not(target_arch = "wasm32"))]
 use revm_precompile::{Precompile, PrecompileError, Precompiles};
+use serde::{Deserialize, Serialize};
 
 #[allow(unused_variables)]
 /// Check if address is a precompiled or not.
@@ -60,7 +61,7 @@ pub(crate) fn execute_precompiled(
 }
 
 /// Addresses of the precompiled contracts.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PrecompileCalls {
     /// Elliptic Curve Recovery
     Ecrecover = 0x01,
@@ -82,6 +83,12 @@ pub enum PrecompileCalls {
     Blake2F = 0x09,
 }
 
+/// Precompiles supported by the EVM circuit. Calls to the other precompiles
+/// are handled as
+/// [`ExecError::UnimplementedPrecompiles`](crate::error::ExecError::UnimplementedPrecompiles).
+pub const SUPPORTED_PRECOMPILES: [PrecompileCalls; 2] =
+    [PrecompileCalls::Ecrecover, PrecompileCalls::Identity];
+
 impl Default for PrecompileCalls {
     fn default() -> Self {
         Self::Ecrecover
@@ -141,6 +148,11 @@ impl PrecompileCalls {
         }
     }
 
+    /// Whether the precompile is supported by the EVM circuit.
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_PRECOMPILES.contains(self)
+    }
+
     /// Get the EVM address for this precompile call.
     pub fn address(&self) -> u64 {
         (*self).into()
@@ -229,7 +241,7 @@ impl PrecompileCallArgs {
 }
 
 /// Auxiliary data for Ecrecover
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EcrecoverAuxData {
     /// Keccak hash of the message being signed.
     pub msg_hash: Word,
@@ -286,7 +298,7 @@ impl EcrecoverAuxData {
 }
 
 /// Auxiliary data attached to an internal state for precompile verification.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PrecompileAuxData {
     /// Base precompile (used for Identity, SHA256, RIPEMD-160 and BLAKE2F).
     Base {
@@ -4,11 +4,21 @@
 use crate::Error;
 use eth_types::{
     Address, Block, Bytes, EIP1186ProofResponse, GethExecTrace, Hash, ResultGethExecTraces,
-    Transaction, Word, U64,
+    Transaction, Word, H256, U64,
 };
 pub use ethers_core::types::BlockNumber;
-use ethers_providers::JsonRpcClient;
-use serde::Serialize;
+use ethers_providers::{
+    Http, HttpRateLimitRetryPolicy, JsonRpcClient, ProviderError, RetryClient, RetryClientBuilder,
+};
+use futures_timer::Delay;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 /// Serialize a type.
 ///
@@ -20,7 +30,6 @@ pub fn serialize<T: serde::Serialize>(t: &T) -> serde_json::Value {
 }
 
 #[derive(Serialize)]
-#[doc(hidden)]
 pub(crate) struct GethLoggerConfig {
     /// enable memory capture
     #[serde(rename = "EnableMemory")]
@@ -47,6 +56,80 @@ impl Default for GethLoggerConfig {
     }
 }
 
+#[derive(Serialize)]
+pub(crate) struct GethTracerConfig {
+    /// name of the builtin tracer
+    tracer: &'static str,
+}
+
+/// Account as it was before a transaction, as reported by the
+/// `prestateTracer` of geth.  Empty fields are omitted by the tracer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct PrestateAccount {
+    /// Balance
+    #[serde(default)]
+    pub balance: Word,
+    /// Nonce
+    #[serde(default)]
+    pub nonce: u64,
+    /// Code
+    #[serde(default)]
+    pub code: Bytes,
+    /// Storage slots read or written by the transaction
+    #[serde(default)]
+    pub storage: HashMap<H256, H256>,
+}
+
+/// Limits applied when querying a node, to avoid hitting provider limits on
+/// blocks that require thousands of queries.
+///
+/// The `eth_getProof` and `eth_getCode` queries of the state of a block are
+/// sent in JSON-RPC batches of [`Self::max_batch_size`] requests by the state
+/// providers that support batching, such as [`GethBatchClient`].  Every
+/// request of a [`BuilderClient`](crate::circuit_input_builder::BuilderClient),
+/// batched or not, counts against [`Self::max_requests_per_second`], and the
+/// requests rejected by a rate limiting provider are retried with exponential
+/// backoff.
+#[derive(Debug, Clone, Copy)]
+pub struct RpcConfig {
+    /// Maximum number of requests, or batches of requests, in flight at the
+    /// same time.
+    ///
+    /// [`BuilderClient::gen_inputs`](crate::circuit_input_builder::BuilderClient::gen_inputs)
+    /// fetches the history blocks while it queries the state, and splits this
+    /// limit between the two: half of it, rounded down, goes to the history
+    /// and the rest to the state, with at least one request in flight for
+    /// each.
+    pub max_concurrency: usize,
+    /// Maximum number of storage keys requested in a single `eth_getProof`.
+    /// Accounts with more accessed keys are queried with several
+    /// `eth_getProof` requests.
+    pub max_keys_per_proof: usize,
+    /// Maximum number of requests sent in a single JSON-RPC batch.
+    pub max_batch_size: usize,
+    /// Maximum number of requests sent per second, `None` for no limit.  A
+    /// batch counts as many requests as it holds.
+    pub max_requests_per_second: Option<u32>,
+    /// Number of retries of a request that was rate limited or timed out.
+    pub max_retries: u32,
+    /// Backoff before the first retry. It grows exponentially with each
+    /// retry.
+    pub initial_backoff: Duration,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 8,
+            max_keys_per_proof: 256,
+            max_batch_size: 32,
+            max_requests_per_second: None,
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
 /// Placeholder structure designed to contain the methods that the BusMapping
 /// needs in order to enable Geth queries.
 pub struct GethClient<P: JsonRpcClient>(pub P);
@@ -62,7 +145,7 @@ impl<P: JsonRpcClient> GethClient<P> {
         self.0
             .request("eth_coinbase", ())
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))
+            .map_err(|e| Error::RpcError(e.into()))
     }
 
     /// Calls `eth_chainId` via JSON-RPC returning the chain id of the network.
@@ -71,7 +154,7 @@ impl<P: JsonRpcClient> GethClient<P> {
             .0
             .request("eth_chainId", ())
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))?;
+            .map_err(|e| Error::RpcError(e.into()))?;
         Ok(net_id.as_u64())
     }
 
@@ -83,7 +166,21 @@ impl<P: JsonRpcClient> GethClient<P> {
         self.0
             .request("eth_getBlockByHash", [hash, flag])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))
+            .map_err(|e| Error::RpcError(e.into()))
+    }
+
+    /// Calls `eth_getBlockByNumber` via JSON-RPC returning the header of the
+    /// [`Block`] and the hashes of its transactions.
+    pub async fn get_block_header_by_number(
+        &self,
+        block_num: BlockNumber,
+    ) -> Result<Block<Hash>, Error> {
+        let num = serialize(&block_num);
+        let flag = serialize(&false);
+        self.0
+            .request("eth_getBlockByNumber", [num, flag])
+            .await
+            .map_err(|e| Error::RpcError(e.into()))
     }
 
     /// Calls `eth_getBlockByNumber` via JSON-RPC returning a [`Block`]
@@ -98,7 +195,7 @@ impl<P: JsonRpcClient> GethClient<P> {
         self.0
             .request("eth_getBlockByNumber", [num, flag])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))
+            .map_err(|e| Error::RpcError(e.into()))
     }
 
     /// Calls `debug_traceBlockByHash` via JSON-RPC returning a
@@ -111,7 +208,7 @@ impl<P: JsonRpcClient> GethClient<P> {
             .0
             .request("debug_traceBlockByHash", [hash, cfg])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))?;
+            .map_err(|e| Error::RpcError(e.into()))?;
         Ok(resp.0.into_iter().map(|step| step.result).collect())
     }
 
@@ -128,10 +225,48 @@ impl<P: JsonRpcClient> GethClient<P> {
             .0
             .request("debug_traceBlockByNumber", [num, cfg])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))?;
+            .map_err(|e| Error::RpcError(e.into()))?;
         Ok(resp.0.into_iter().map(|step| step.result).collect())
     }
 
+    /// Calls `eth_getTransactionByHash` via JSON-RPC returning a
+    /// [`Transaction`] with the block it is included in.
+    pub async fn get_transaction_by_hash(&self, hash: Hash) -> Result<Transaction, Error> {
+        let hash = serialize(&hash);
+        self.0
+            .request("eth_getTransactionByHash", [hash])
+            .await
+            .map_err(|e| Error::RpcError(e.into()))
+    }
+
+    /// Calls `debug_traceTransaction` via JSON-RPC returning the
+    /// [`GethExecTrace`] of a transaction.
+    pub async fn trace_tx_by_hash(&self, hash: Hash) -> Result<GethExecTrace, Error> {
+        let hash = serialize(&hash);
+        let cfg = serialize(&GethLoggerConfig::default());
+        self.0
+            .request("debug_traceTransaction", [hash, cfg])
+            .await
+            .map_err(|e| Error::RpcError(e.into()))
+    }
+
+    /// Calls `debug_traceTransaction` with the `prestateTracer` via JSON-RPC
+    /// returning the accounts accessed by a transaction, as they were before
+    /// the transaction.
+    pub async fn trace_tx_prestate(
+        &self,
+        hash: Hash,
+    ) -> Result<HashMap<Address, PrestateAccount>, Error> {
+        let hash = serialize(&hash);
+        let cfg = serialize(&GethTracerConfig {
+            tracer: "prestateTracer",
+        });
+        self.0
+            .request("debug_traceTransaction", [hash, cfg])
+            .await
+            .map_err(|e| Error::RpcError(e.into()))
+    }
+
     /// Calls `eth_getCode` via JSON-RPC returning a contract code
     pub async fn get_code(
         &self,
@@ -144,7 +279,7 @@ impl<P: JsonRpcClient> GethClient<P> {
             .0
             .request("eth_getCode", [address, num])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))?;
+            .map_err(|e| Error::RpcError(e.into()))?;
         Ok(resp.to_vec())
     }
 
@@ -163,7 +298,7 @@ impl<P: JsonRpcClient> GethClient<P> {
         self.0
             .request("eth_getProof", [account, keys, num])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))
+            .map_err(|e| Error::RpcError(e.into()))
     }
 
     /// Calls `miner_stop` via JSON-RPC, which makes the node stop mining
@@ -172,7 +307,7 @@ impl<P: JsonRpcClient> GethClient<P> {
         self.0
             .request("miner_stop", ())
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))
+            .map_err(|e| Error::RpcError(e.into()))
     }
 
     /// Calls `miner_start` via JSON-RPC, which makes the node start mining
@@ -181,8 +316,306 @@ impl<P: JsonRpcClient> GethClient<P> {
         self.0
             .request("miner_start", [serialize(&1)])
             .await
-            .map_err(|e| Error::JSONRpcError(e.into()))
+            .map_err(|e| Error::RpcError(e.into()))
+    }
+}
+
+impl GethClient<RetryClient<Http>> {
+    /// Generates a new `GethClient` for the HTTP endpoint at `url` which
+    /// retries rate limited and timed out requests with exponential backoff,
+    /// as configured in `config`.
+    pub fn new_http_with_retries(url: &str, config: &RpcConfig) -> Result<Self, Error> {
+        let http = Http::from_str(url).map_err(|_| Error::InternalError("invalid RPC url"))?;
+        let client = RetryClientBuilder::default()
+            .rate_limit_retries(config.max_retries)
+            .timeout_retries(config.max_retries)
+            .initial_backoff(config.initial_backoff)
+            .build(http, Box::<HttpRateLimitRetryPolicy>::default());
+        Ok(Self(client))
+    }
+}
+
+/// Client-side limit of the rate of the requests sent to a node.  Clones
+/// share the same limit, so that concurrent queries are limited together.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    /// Time between two requests, `None` when the rate is not limited.
+    interval: Option<Duration>,
+    /// Time at which the next request can be sent.
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter letting at most `max_requests_per_second` requests
+    /// through per second, or every request when `None`.
+    pub fn new(max_requests_per_second: Option<u32>) -> Self {
+        Self {
+            interval: max_requests_per_second
+                .filter(|rate| *rate > 0)
+                .map(|rate| Duration::from_secs(1) / rate),
+            next: Arc::default(),
+        }
+    }
+
+    /// Wait until `requests` more requests can be sent without exceeding the
+    /// rate.
+    pub async fn acquire(&self, requests: usize) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let wait = {
+            let mut next = self.next.lock().expect("rate limiter lock poisoned");
+            let now = Instant::now();
+            let start = next.map_or(now, |next| next.max(now));
+            *next = Some(start + interval * requests as u32);
+            start - now
+        };
+        if !wait.is_zero() {
+            Delay::new(wait).await;
+        }
+    }
+}
+
+/// Transport of JSON-RPC batches: an array of requests is sent at once and
+/// answered with an array of responses.
+#[allow(async_fn_in_trait)]
+pub trait BatchTransport {
+    /// Send the JSON-RPC `batch` and return the response of the node.
+    async fn send_batch(&self, batch: Value) -> Result<Value, Error>;
+}
+
+/// [`BatchTransport`] over HTTP, which retries the batches rejected by a
+/// rate limiting provider with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct HttpBatchTransport {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    max_retries: u32,
+    initial_backoff: Duration,
+}
+
+impl HttpBatchTransport {
+    /// Create a transport to the HTTP endpoint at `url`, retrying as
+    /// configured in `config`.
+    pub fn new(url: &str, config: &RpcConfig) -> Result<Self, Error> {
+        let url = url
+            .parse()
+            .map_err(|_| Error::InternalError("invalid RPC url"))?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            max_retries: config.max_retries,
+            initial_backoff: config.initial_backoff,
+        })
+    }
+}
+
+impl BatchTransport for HttpBatchTransport {
+    async fn send_batch(&self, batch: Value) -> Result<Value, Error> {
+        let mut backoff = self.initial_backoff;
+        for retry in 0.. {
+            let response = self
+                .client
+                .post(self.url.clone())
+                .json(&batch)
+                .send()
+                .await
+                .map_err(|e| Error::RpcError(ProviderError::HTTPError(e)))?;
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                && retry < self.max_retries
+            {
+                Delay::new(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+            return response
+                .error_for_status()
+                .map_err(|e| Error::RpcError(ProviderError::HTTPError(e)))?
+                .json()
+                .await
+                .map_err(|e| Error::RpcError(ProviderError::HTTPError(e)));
+        }
+        unreachable!("the retries are bounded")
+    }
+}
+
+#[derive(Serialize)]
+struct BatchRequest<'a> {
+    jsonrpc: &'static str,
+    id: usize,
+    method: &'a str,
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    id: usize,
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+/// Client which sends the per-account `eth_getProof` and `eth_getCode` queries
+/// in JSON-RPC batches over a [`BatchTransport`].
+pub struct GethBatchClient<T: BatchTransport>(pub T);
+
+impl<T: BatchTransport> GethBatchClient<T> {
+    /// Generates a new `GethBatchClient` instance.
+    pub fn new(transport: T) -> Self {
+        Self(transport)
+    }
+
+    /// Calls `method` once for each of `params` in a single JSON-RPC batch,
+    /// returning the results in the order of `params`.  The batch fails if
+    /// any of its requests fails.
+    pub async fn request_batch<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<R>, Error> {
+        if params.is_empty() {
+            return Ok(vec![]);
+        }
+        let len = params.len();
+        let batch = params
+            .into_iter()
+            .enumerate()
+            .map(|(id, params)| BatchRequest {
+                jsonrpc: "2.0",
+                id,
+                method,
+                params,
+            })
+            .collect::<Vec<_>>();
+        let responses: Vec<BatchResponse> =
+            serde_json::from_value(self.0.send_batch(serialize(&batch)).await?)
+                .map_err(Error::SerdeError)?;
+
+        // Responses can come in any order, they are matched by id.
+        let mut results: Vec<Option<R>> = (0..len).map(|_| None).collect();
+        for response in responses {
+            if let Some(error) = response.error {
+                return Err(Error::RpcError(ProviderError::CustomError(format!(
+                    "{} request {} failed: {}",
+                    method, response.id, error
+                ))));
+            }
+            let result = results
+                .get_mut(response.id)
+                .ok_or(Error::InternalError("unexpected id in JSON-RPC batch"))?;
+            *result = Some(serde_json::from_value(response.result).map_err(Error::SerdeError)?);
+        }
+        results
+            .into_iter()
+            .map(|result| result.ok_or(Error::InternalError("missing response in JSON-RPC batch")))
+            .collect()
+    }
+
+    /// Calls `eth_getProof` via JSON-RPC for each `(account, keys)` query in a
+    /// single batch, returning the [`EIP1186ProofResponse`]s in the order of
+    /// `queries`.
+    pub async fn get_proofs(
+        &self,
+        queries: Vec<(Address, Vec<Word>)>,
+        block_num: BlockNumber,
+    ) -> Result<Vec<EIP1186ProofResponse>, Error> {
+        let num = serialize(&block_num);
+        let params = queries
+            .into_iter()
+            .map(|(account, keys)| serialize(&[serialize(&account), serialize(&keys), num.clone()]))
+            .collect();
+        self.request_batch("eth_getProof", params).await
+    }
+
+    /// Calls `eth_getCode` via JSON-RPC for each of `addresses` in a single
+    /// batch, returning the contract codes in the order of `addresses`.
+    pub async fn get_codes(
+        &self,
+        addresses: Vec<Address>,
+        block_num: BlockNumber,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let num = serialize(&block_num);
+        let params = addresses
+            .into_iter()
+            .map(|address| serialize(&[serialize(&address), num.clone()]))
+            .collect();
+        let codes: Vec<Bytes> = self.request_batch("eth_getCode", params).await?;
+        Ok(codes.into_iter().map(|code| code.to_vec()).collect())
     }
 }
 
 // Integration tests found in `integration-tests/tests/rpc.rs`.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::address;
+
+    #[test]
+    fn rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(Some(100));
+        let start = Instant::now();
+        futures::executor::block_on(async {
+            for _ in 0..5 {
+                limiter.acquire(1).await;
+            }
+            // A batch takes the slots of all its requests
+            limiter.acquire(5).await;
+        });
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let unlimited = RateLimiter::new(None);
+        let start = Instant::now();
+        futures::executor::block_on(unlimited.acquire(1000));
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn prestate_tracer_deserialization() {
+        // Response of the `prestateTracer` with the fields omitted by geth
+        // when they are empty.
+        let json = r#"{
+            "0x0000000000000000000000000000000000000002": {
+                "balance": "0x0"
+            },
+            "0x35a9f94af726f07b5162df7e828cc9dc8439e7d0": {
+                "balance": "0x7a48734599f7284",
+                "nonce": 1133
+            },
+            "0xc8ba32cab1757528daf49033e3673fae77dcf05d": {
+                "balance": "0x1",
+                "code": "0x6080",
+                "nonce": 1,
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000000": "0x000000000000000000000000000000000000000000000000000000000000002a"
+                }
+            }
+        }"#;
+        let prestate: HashMap<Address, PrestateAccount> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(prestate.len(), 3);
+        assert_eq!(
+            prestate[&address!("0x0000000000000000000000000000000000000002")],
+            PrestateAccount::default()
+        );
+        assert_eq!(
+            prestate[&address!("0x35a9f94af726f07b5162df7e828cc9dc8439e7d0")],
+            PrestateAccount {
+                balance: Word::from(0x7a48734599f7284u64),
+                nonce: 1133,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            prestate[&address!("0xc8ba32cab1757528daf49033e3673fae77dcf05d")],
+            PrestateAccount {
+                balance: Word::one(),
+                nonce: 1,
+                code: Bytes::from(vec![0x60, 0x80]),
+                storage: HashMap::from([(H256::zero(), H256::from_low_u64_be(0x2a))]),
+            }
+        );
+    }
+}
@@ -5,6 +5,7 @@ use eth_types::{geth_types, Address, BigEndianHash, Bytecode, Hash, Word, H256,
 use ethers_core::utils::keccak256;
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 lazy_static! {
@@ -22,7 +23,7 @@ lazy_static! {
 const VALUE_ZERO: Word = Word::zero();
 
 /// Memory storage for contract code by code hash.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CodeDB(HashMap<Hash, Vec<u8>>);
 
 impl CodeDB {
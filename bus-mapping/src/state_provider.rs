@@ -0,0 +1,418 @@
+//! Sources of pre-block state used during witness generation.
+//!
+//! The [`BuilderClient`](crate::circuit_input_builder::BuilderClient) needs,
+//! for every account and storage slot accessed in a block, the state right
+//! before the block. [`StateProvider`] abstracts where that state comes from
+//! so that witnesses can be generated from a live node, queried one request
+//! at a time or in JSON-RPC batches, from an in-memory test state or from a
+//! snapshot of a chain database.
+
+use crate::{
+    rpc::{BatchTransport, BlockNumber, GethBatchClient, GethClient},
+    state_db::{self, CodeDB, StateDB},
+    Error,
+};
+use eth_types::{geth_types, Address, EIP1186ProofResponse, StorageProof, Word, H256, U64};
+use ethers_providers::JsonRpcClient;
+use std::collections::HashMap;
+
+/// Read access to the state of the chain at a given block.
+///
+/// All the queries return the state *after* `block_num` has been applied.
+#[allow(async_fn_in_trait)]
+pub trait StateProvider {
+    /// Return the account at `address`. Non-existing accounts are returned
+    /// as [`state_db::Account::zero`]. The storage of the returned account is
+    /// left empty.
+    async fn get_account(
+        &self,
+        address: Address,
+        block_num: BlockNumber,
+    ) -> Result<state_db::Account, Error>;
+
+    /// Return the value stored at `key` in the storage of `address`.
+    async fn get_storage(
+        &self,
+        address: Address,
+        key: Word,
+        block_num: BlockNumber,
+    ) -> Result<Word, Error>;
+
+    /// Return the code of the account at `address`.
+    async fn get_code(&self, address: Address, block_num: BlockNumber) -> Result<Vec<u8>, Error>;
+
+    /// Return the account at `address` together with the values of `keys` in
+    /// its storage, in the format of `eth_getProof`.
+    async fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<Word>,
+        block_num: BlockNumber,
+    ) -> Result<EIP1186ProofResponse, Error>;
+
+    /// Whether [`Self::get_proofs`] and [`Self::get_codes`] answer all their
+    /// queries with a single request.  Otherwise the queries are sent one
+    /// after the other.
+    fn batches_requests(&self) -> bool {
+        false
+    }
+
+    /// Return the proof of each `(address, keys)` query, in the order of
+    /// `queries`.
+    async fn get_proofs(
+        &self,
+        queries: Vec<(Address, Vec<Word>)>,
+        block_num: BlockNumber,
+    ) -> Result<Vec<EIP1186ProofResponse>, Error> {
+        let mut proofs = Vec::with_capacity(queries.len());
+        for (address, keys) in queries {
+            proofs.push(self.get_proof(address, keys, block_num).await?);
+        }
+        Ok(proofs)
+    }
+
+    /// Return the code of each of `addresses`, in the order of `addresses`.
+    async fn get_codes(
+        &self,
+        addresses: Vec<Address>,
+        block_num: BlockNumber,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut codes = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            codes.push(self.get_code(address, block_num).await?);
+        }
+        Ok(codes)
+    }
+}
+
+impl<P: JsonRpcClient> StateProvider for GethClient<P> {
+    async fn get_account(
+        &self,
+        address: Address,
+        block_num: BlockNumber,
+    ) -> Result<state_db::Account, Error> {
+        let proof = GethClient::get_proof(self, address, vec![], block_num).await?;
+        Ok(state_db::Account {
+            nonce: proof.nonce.as_u64(),
+            balance: proof.balance,
+            storage: HashMap::new(),
+            code_hash: proof.code_hash,
+        })
+    }
+
+    async fn get_storage(
+        &self,
+        address: Address,
+        key: Word,
+        block_num: BlockNumber,
+    ) -> Result<Word, Error> {
+        let proof = GethClient::get_proof(self, address, vec![key], block_num).await?;
+        Ok(proof
+            .storage_proof
+            .first()
+            .map(|storage_proof| storage_proof.value)
+            .unwrap_or_default())
+    }
+
+    async fn get_code(&self, address: Address, block_num: BlockNumber) -> Result<Vec<u8>, Error> {
+        GethClient::get_code(self, address, block_num).await
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<Word>,
+        block_num: BlockNumber,
+    ) -> Result<EIP1186ProofResponse, Error> {
+        GethClient::get_proof(self, address, keys, block_num).await
+    }
+}
+
+impl<T: BatchTransport> StateProvider for GethBatchClient<T> {
+    async fn get_account(
+        &self,
+        address: Address,
+        block_num: BlockNumber,
+    ) -> Result<state_db::Account, Error> {
+        let proof = StateProvider::get_proof(self, address, vec![], block_num).await?;
+        Ok(state_db::Account {
+            nonce: proof.nonce.as_u64(),
+            balance: proof.balance,
+            storage: HashMap::new(),
+            code_hash: proof.code_hash,
+        })
+    }
+
+    async fn get_storage(
+        &self,
+        address: Address,
+        key: Word,
+        block_num: BlockNumber,
+    ) -> Result<Word, Error> {
+        let proof = StateProvider::get_proof(self, address, vec![key], block_num).await?;
+        Ok(proof
+            .storage_proof
+            .first()
+            .map(|storage_proof| storage_proof.value)
+            .unwrap_or_default())
+    }
+
+    async fn get_code(&self, address: Address, block_num: BlockNumber) -> Result<Vec<u8>, Error> {
+        let mut codes = GethBatchClient::get_codes(self, vec![address], block_num).await?;
+        Ok(codes.remove(0))
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<Word>,
+        block_num: BlockNumber,
+    ) -> Result<EIP1186ProofResponse, Error> {
+        let mut proofs =
+            GethBatchClient::get_proofs(self, vec![(address, keys)], block_num).await?;
+        Ok(proofs.remove(0))
+    }
+
+    fn batches_requests(&self) -> bool {
+        true
+    }
+
+    async fn get_proofs(
+        &self,
+        queries: Vec<(Address, Vec<Word>)>,
+        block_num: BlockNumber,
+    ) -> Result<Vec<EIP1186ProofResponse>, Error> {
+        GethBatchClient::get_proofs(self, queries, block_num).await
+    }
+
+    async fn get_codes(
+        &self,
+        addresses: Vec<Address>,
+        block_num: BlockNumber,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        GethBatchClient::get_codes(self, addresses, block_num).await
+    }
+}
+
+/// Build an `eth_getProof`-like response without Merkle proofs.
+fn proof_without_nodes(
+    address: Address,
+    account: &state_db::Account,
+    storage: impl Iterator<Item = (Word, Word)>,
+) -> EIP1186ProofResponse {
+    EIP1186ProofResponse {
+        address,
+        balance: account.balance,
+        code_hash: account.code_hash,
+        nonce: U64::from(account.nonce),
+        storage_hash: H256::zero(),
+        account_proof: vec![],
+        storage_proof: storage
+            .map(|(key, value)| StorageProof {
+                key,
+                value,
+                proof: vec![],
+            })
+            .collect(),
+    }
+}
+
+/// In-memory state, typically built from the accounts of a test block.
+///
+/// The state is the same for every block number. Returned proofs contain no
+/// trie nodes.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStateProvider {
+    accounts: HashMap<Address, geth_types::Account>,
+}
+
+impl InMemoryStateProvider {
+    /// Create a new provider from a list of accounts.
+    pub fn new(accounts: impl IntoIterator<Item = geth_types::Account>) -> Self {
+        Self {
+            accounts: accounts
+                .into_iter()
+                .map(|account| (account.address, account))
+                .collect(),
+        }
+    }
+}
+
+impl StateProvider for InMemoryStateProvider {
+    async fn get_account(
+        &self,
+        address: Address,
+        _block_num: BlockNumber,
+    ) -> Result<state_db::Account, Error> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .map(|account| state_db::Account {
+                storage: HashMap::new(),
+                ..state_db::Account::from(account.clone())
+            })
+            .unwrap_or_else(state_db::Account::zero))
+    }
+
+    async fn get_storage(
+        &self,
+        address: Address,
+        key: Word,
+        _block_num: BlockNumber,
+    ) -> Result<Word, Error> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&key).cloned())
+            .unwrap_or_default())
+    }
+
+    async fn get_code(&self, address: Address, _block_num: BlockNumber) -> Result<Vec<u8>, Error> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .map(|account| account.code.to_vec())
+            .unwrap_or_default())
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<Word>,
+        _block_num: BlockNumber,
+    ) -> Result<EIP1186ProofResponse, Error> {
+        let (account, storage) = match self.accounts.get(&address) {
+            Some(account) => (account.clone().into(), account.storage.clone()),
+            None => (state_db::Account::zero(), HashMap::new()),
+        };
+        Ok(proof_without_nodes(
+            address,
+            &account,
+            keys.into_iter()
+                .map(|key| (key, storage.get(&key).cloned().unwrap_or_default())),
+        ))
+    }
+}
+
+/// Read-only snapshot of a chain database at a single block, stored as a
+/// [`StateDB`] and [`CodeDB`] pair.
+///
+/// A snapshot captured with [`Self::from_proofs`] only holds the accounts and
+/// storage slots that were queried, so queries for any other one fail with
+/// [`Error::AccountNotFound`] or [`Error::StorageKeyNotFound`] instead of
+/// silently answering zero.  A snapshot of the full state answers zero for
+/// them, like a node, with [`Self::with_zero_fill`].  Queries for any block
+/// other than the snapshot one fail with [`Error::InternalError`], since the
+/// snapshot holds no history.
+#[derive(Debug, Clone)]
+pub struct SnapshotStateProvider {
+    block_num: u64,
+    sdb: StateDB,
+    code_db: CodeDB,
+    zero_fill: bool,
+}
+
+impl SnapshotStateProvider {
+    /// Create a new snapshot of the state after block `block_num`.
+    pub fn new(block_num: u64, sdb: StateDB, code_db: CodeDB) -> Self {
+        Self {
+            block_num,
+            sdb,
+            code_db,
+            zero_fill: false,
+        }
+    }
+
+    /// Create a new snapshot from previously dumped `eth_getProof` responses
+    /// and account codes.
+    pub fn from_proofs(
+        block_num: u64,
+        proofs: Vec<EIP1186ProofResponse>,
+        codes: HashMap<Address, Vec<u8>>,
+    ) -> Self {
+        let (sdb, code_db) = crate::circuit_input_builder::build_state_code_db(proofs, codes);
+        Self::new(block_num, sdb, code_db)
+    }
+
+    /// Answer zero for the accounts and storage slots missing from the
+    /// snapshot, which must then hold the full state of the block.
+    pub fn with_zero_fill(mut self) -> Self {
+        self.zero_fill = true;
+        self
+    }
+
+    fn check_block_num(&self, block_num: BlockNumber) -> Result<(), Error> {
+        match block_num {
+            BlockNumber::Number(num) if num.as_u64() == self.block_num => Ok(()),
+            BlockNumber::Latest => Ok(()),
+            _ => Err(Error::InternalError(
+                "block not available in state snapshot",
+            )),
+        }
+    }
+
+    fn account(&self, address: Address) -> Result<&state_db::Account, Error> {
+        match self.sdb.get_account(&address) {
+            (false, _) if !self.zero_fill => Err(Error::AccountNotFound(address)),
+            (_, account) => Ok(account),
+        }
+    }
+
+    fn storage(&self, address: Address, key: Word) -> Result<Word, Error> {
+        match self.sdb.get_storage(&address, &key) {
+            (false, _) if !self.zero_fill => Err(Error::StorageKeyNotFound(address, key)),
+            (_, value) => Ok(*value),
+        }
+    }
+}
+
+impl StateProvider for SnapshotStateProvider {
+    async fn get_account(
+        &self,
+        address: Address,
+        block_num: BlockNumber,
+    ) -> Result<state_db::Account, Error> {
+        self.check_block_num(block_num)?;
+        Ok(state_db::Account {
+            storage: HashMap::new(),
+            ..self.account(address)?.clone()
+        })
+    }
+
+    async fn get_storage(
+        &self,
+        address: Address,
+        key: Word,
+        block_num: BlockNumber,
+    ) -> Result<Word, Error> {
+        self.check_block_num(block_num)?;
+        self.storage(address, key)
+    }
+
+    async fn get_code(&self, address: Address, block_num: BlockNumber) -> Result<Vec<u8>, Error> {
+        self.check_block_num(block_num)?;
+        let account = self.account(address)?;
+        if account.code_hash == CodeDB::empty_code_hash() {
+            return Ok(vec![]);
+        }
+        self.code_db
+            .get_from_h256(&account.code_hash)
+            .map(|code| code.code())
+            .ok_or(Error::CodeNotFound(account.code_hash))
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<Word>,
+        block_num: BlockNumber,
+    ) -> Result<EIP1186ProofResponse, Error> {
+        self.check_block_num(block_num)?;
+        let account = self.account(address)?;
+        let storage = keys
+            .into_iter()
+            .map(|key| Ok((key, self.storage(address, key)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(proof_without_nodes(address, account, storage.into_iter()))
+    }
+}
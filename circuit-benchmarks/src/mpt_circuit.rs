@@ -55,6 +55,10 @@ mod tests {
             degree: degree as usize,
             max_nodes,
             disable_preimage_check: false,
+            compress_state_selectors: false,
+            extract_subexpressions: false,
+            expose_roots: false,
+            lookup_tables: Default::default(),
             _marker: PhantomData,
         };
 
@@ -1,6 +1,11 @@
 //! EVM byte code generator
 use crate::{evm_types::OpcodeId, keccak256, Bytes, Hash, ToBigEndian, ToWord, Word};
-use std::{collections::HashMap, fmt::Display, iter, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+    iter,
+    str::FromStr,
+};
 /// Error type for Bytecode related failures
 #[derive(Debug)]
 pub enum Error {
@@ -24,6 +29,11 @@ pub struct Bytecode {
     code: Vec<BytecodeElement>,
     num_opcodes: usize,
     markers: HashMap<String, usize>,
+    /// Position in the code of the labeled JUMPDESTs
+    labels: HashMap<String, usize>,
+    /// Label references waiting for the label to be defined, with the
+    /// position of their PUSH2 data
+    label_refs: Vec<(String, usize)>,
 }
 
 impl From<Bytecode> for Bytes {
@@ -45,11 +55,18 @@ impl Bytecode {
                 .collect(),
             markers: HashMap::new(),
             num_opcodes: 0,
+            labels: HashMap::new(),
+            label_refs: Vec::new(),
         }
     }
 
     /// Get the code
     pub fn code(&self) -> Vec<u8> {
+        debug_assert!(
+            self.label_refs.is_empty(),
+            "undefined labels: {:?}",
+            self.label_refs
+        );
         self.code.iter().map(|b| b.value).collect()
     }
 
@@ -85,11 +102,22 @@ impl Bytecode {
 
     /// Append
     pub fn append(&mut self, other: &Bytecode) {
+        let offset = self.code.len();
         self.code.extend_from_slice(&other.code);
         for (key, val) in other.markers.iter() {
             self.insert_marker(key, self.num_opcodes + val);
         }
         self.num_opcodes += other.num_opcodes;
+        for (label, pos) in other.labels.iter() {
+            self.insert_label(label, offset + pos);
+        }
+        self.label_refs.extend(
+            other
+                .label_refs
+                .iter()
+                .map(|(label, pos)| (label.clone(), offset + pos)),
+        );
+        self.resolve_label_refs();
     }
 
     /// Write op
@@ -153,6 +181,93 @@ impl Bytecode {
             .unwrap_or_else(|| panic!("marker '{}' not found", marker))
     }
 
+    /// Write a JUMPDEST labeled `label`, to be used as jump destination with
+    /// [`Bytecode::push_label`].
+    pub fn jumpdest_label(&mut self, label: &str) -> &mut Self {
+        self.insert_label(label, self.code.len());
+        self.resolve_label_refs();
+        self.write_op(OpcodeId::JUMPDEST)
+    }
+
+    /// Push the position of the JUMPDEST labeled `label` with a PUSH2.  The
+    /// label can be defined before or after the push.
+    pub fn push_label(&mut self, label: &str) -> &mut Self {
+        self.label_refs
+            .push((label.to_string(), self.code.len() + 1));
+        self.push(2, 0u64);
+        self.resolve_label_refs();
+        self
+    }
+
+    /// Get the position in the code of a label
+    pub fn get_label_pos(&self, label: &str) -> usize {
+        *self
+            .labels
+            .get(label)
+            .unwrap_or_else(|| panic!("label '{}' not found", label))
+    }
+
+    fn insert_label(&mut self, label: &str, pos: usize) {
+        debug_assert!(
+            !self.labels.contains_key(label),
+            "label already used: {}",
+            label
+        );
+        debug_assert!(pos <= u16::MAX as usize, "label out of PUSH2 range");
+        self.labels.insert(label.to_string(), pos);
+    }
+
+    /// Write the position of the defined labels in the PUSH2 data of their
+    /// references
+    fn resolve_label_refs(&mut self) {
+        let label_refs = std::mem::take(&mut self.label_refs);
+        for (label, data_pos) in label_refs {
+            match self.labels.get(&label) {
+                Some(&pos) => {
+                    self.code[data_pos].value = (pos >> 8) as u8;
+                    self.code[data_pos + 1].value = pos as u8;
+                }
+                None => self.label_refs.push((label, data_pos)),
+            }
+        }
+    }
+
+    /// Disassemble the code, one instruction per line prefixed with its
+    /// position.  Labeled JUMPDESTs are annotated with their label and push
+    /// data truncated by the end of the code is shown as is.
+    pub fn disassemble(&self) -> String {
+        let label_names: HashMap<usize, &str> = self
+            .labels
+            .iter()
+            .map(|(label, pos)| (*pos, label.as_str()))
+            .collect();
+        let mut asm = String::new();
+        let mut pos = 0;
+        while pos < self.code.len() {
+            let op = OpcodeId::from(self.code[pos].value);
+            let n = op.data_len();
+            let data: Vec<u8> = self.code[pos + 1..]
+                .iter()
+                .take(n)
+                .map(|elem| elem.value)
+                .collect();
+            let instruction = if n == 0 {
+                OpcodeWithData::Opcode(op).to_string()
+            } else if data.len() == n {
+                OpcodeWithData::PushWithData(n as u8, Word::from(data.as_slice())).to_string()
+            } else {
+                format!("{:?}(truncated 0x{})", op, hex::encode(&data))
+            };
+            write!(asm, "{:>5}: {}", pos, instruction).expect("write to string");
+            if let Some(label) = label_names.get(&pos) {
+                write!(asm, " #{}", label).expect("write to string");
+            }
+            asm.push('\n');
+            pos += 1 + n;
+        }
+        asm
+    }
+
     /// Setup state
     pub fn setup_state(&mut self) -> &mut Self {
         self.append(&crate::bytecode! {
@@ -578,4 +693,59 @@ mod tests {
         };
         assert_eq!(Bytecode::from(code.code()), code);
     }
+
+    #[test]
+    fn test_bytecode_labels() {
+        let mut code = bytecode! {
+            .push_label("end")
+            JUMP
+            .jumpdest_label("loop")
+            PUSH1(1)
+            POP
+        };
+        code.append(&bytecode! {
+            .push_label("loop")
+            .push_label("end")
+            JUMP
+            .jumpdest_label("end")
+            STOP
+        });
+        assert_eq!(code.get_label_pos("loop"), 4);
+        assert_eq!(code.get_label_pos("end"), 15);
+        assert_eq!(
+            code.code(),
+            vec![
+                0x61, 0x00, 0x0f, // PUSH2(15)
+                0x56, // JUMP
+                0x5b, // JUMPDEST
+                0x60, 0x01, // PUSH1(1)
+                0x50, // POP
+                0x61, 0x00, 0x04, // PUSH2(4)
+                0x61, 0x00, 0x0f, // PUSH2(15)
+                0x56, // JUMP
+                0x5b, // JUMPDEST
+                0x00, // STOP
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bytecode_disassemble() {
+        let code = bytecode! {
+            PUSH1(0x20)
+            .jumpdest_label("start")
+            .push_label("start")
+            JUMP
+        };
+        assert_eq!(
+            code.disassemble(),
+            "    0: PUSH1(32)\n    2: JUMPDEST #start\n    3: PUSH2(2)\n    6: JUMP\n"
+        );
+
+        let truncated = Bytecode::from(vec![0x5f, 0x62, 0x01, 0x02]);
+        assert_eq!(
+            truncated.disassemble(),
+            "    0: PUSH0\n    1: PUSH3(truncated 0x0102)\n"
+        );
+    }
 }
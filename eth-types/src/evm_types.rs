@@ -22,6 +22,9 @@ pub const INIT_CODE_WORD_GAS: u64 = 2;
 pub const MAX_REFUND_QUOTIENT_OF_GAS_USED: usize = 5;
 /// Gas stipend when CALL or CALLCODE is attached with value.
 pub const GAS_STIPEND_CALL_WITH_VALUE: u64 = 2300;
+/// Maximum depth of the call stack.  The transaction frame has depth 1, and
+/// calls and creates issued from a frame deeper than this fail.
+pub const MAX_CALL_DEPTH: u64 = 1024;
 
 /// This constant ((2^32 - 1) * 32) is the highest number that can be used without overflowing the
 /// square operation of gas calculation.
@@ -15,7 +15,7 @@ use ethers_signers::{LocalWallet, Signer};
 use halo2_proofs::halo2curves::{group::ff::PrimeField, secp256k1};
 use num::Integer;
 use num_bigint::BigUint;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_with::serde_as;
 use std::collections::HashMap;
 
@@ -140,7 +140,7 @@ impl BlockConstants {
 }
 
 /// Definition of all of the constants related to an Ethereum withdrawal.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Withdrawal {
     /// Unique identifier of a withdrawal. This value starts from 0 and then increases
     /// monotonically.
@@ -154,7 +154,7 @@ pub struct Withdrawal {
 }
 
 /// Definition of all of the constants related to an Ethereum transaction.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     /// Sender address
     pub from: Address,
@@ -16,6 +16,7 @@ use halo2_proofs::{
 };
 use lazy_static::lazy_static;
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use subtle::CtOption;
 
 /// Do a secp256k1 signature with a given randomness value.
@@ -46,7 +47,8 @@ pub fn sign(
 
 /// Signature data required by the SignVerify Chip as input to verify a
 /// signature.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(into = "SignDataRepr", try_from = "SignDataRepr")]
 pub struct SignData {
     /// Secp256k1 signature point (r, s, v)
     /// v must be 0 or 1
@@ -90,6 +92,58 @@ lazy_static! {
     };
 }
 
+/// Serialized form of [`SignData`], with the field elements as little endian bytes and the
+/// identity public key as `None`.
+#[derive(Clone, Serialize, Deserialize)]
+struct SignDataRepr {
+    signature: ([u8; 32], [u8; 32], u8),
+    pk: Option<([u8; 32], [u8; 32])>,
+    msg: Bytes,
+    msg_hash: [u8; 32],
+}
+
+impl From<SignData> for SignDataRepr {
+    fn from(sign_data: SignData) -> Self {
+        let (r, s, v) = sign_data.signature;
+        let pk = Option::<Coordinates<_>>::from(sign_data.pk.coordinates())
+            .map(|pk| (pk.x().to_bytes(), pk.y().to_bytes()));
+        Self {
+            signature: (r.to_bytes(), s.to_bytes(), v),
+            pk,
+            msg: sign_data.msg,
+            msg_hash: sign_data.msg_hash.to_bytes(),
+        }
+    }
+}
+
+impl TryFrom<SignDataRepr> for SignData {
+    type Error = &'static str;
+
+    fn try_from(repr: SignDataRepr) -> Result<Self, Self::Error> {
+        let fq = |bytes: &[u8; 32]| {
+            ct_option_ok_or(secp256k1::Fq::from_bytes(bytes), "invalid secp256k1 scalar")
+        };
+        let pk = match repr.pk {
+            Some((x, y)) => {
+                let x = ct_option_ok_or(secp256k1::Fp::from_bytes(&x), "invalid public key")?;
+                let y = ct_option_ok_or(secp256k1::Fp::from_bytes(&y), "invalid public key")?;
+                ct_option_ok_or(Secp256k1Affine::from_xy(x, y), "invalid public key")?
+            }
+            None => Secp256k1Affine::identity(),
+        };
+        Ok(Self {
+            signature: (
+                fq(&repr.signature.0)?,
+                fq(&repr.signature.1)?,
+                repr.signature.2,
+            ),
+            pk,
+            msg: repr.msg,
+            msg_hash: fq(&repr.msg_hash)?,
+        })
+    }
+}
+
 impl Default for SignData {
     fn default() -> Self {
         // Hardcoded valid signature corresponding to a hardcoded private key and
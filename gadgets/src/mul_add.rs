@@ -13,16 +13,100 @@
 // | 0      | carry_hi0 | carry_hi1 | carry_hi2 | carry_hi3 | carry_hi4 |
 // | 0      | carry_hi5 | carry_hi6 | carry_hi7 | carry_hi8 | -         |
 // |--------|-----------|-----------|-----------|-----------|-----------|
+//
+// The carry bytes are range checked against a u8 lookup table shared with the
+// rest of the circuit, from the row where q_enable is enabled.
+//
+// The arithmetic of the check is exposed in [`mul_limb_products`],
+// [`mul_add_words_exprs`] and [`mul_add_words_carries`], so that gadgets
+// laying out the same check differently constrain it in the same way.
 
 use eth_types::{Field, ToLittleEndian, Word};
 use halo2_proofs::{
     circuit::{Region, Value},
-    plonk::{Advice, Column, ConstraintSystem, Error, Expression, VirtualCells},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
     poly::Rotation,
 };
+use std::ops::{Add, Mul};
 
 use crate::util::{expr_from_bytes, pow_of_two, split_u256, split_u256_limb64, Expr};
 
+/// Number of bytes of each of carry_lo and carry_hi. The carries fit in 68
+/// bits, the constraint is slightly relaxed to 72 bits.
+pub const N_BYTES_CARRY: usize = 9;
+
+/// Products of the 64-bit limbs of `a` and `b` grouped by weight: the i-th
+/// product is the sum of all the `a_j * b_k` with `j + k == i`, so that
+/// `a * b == sum(t_i * 2^(64 * i))`.
+pub fn mul_limb_products<T>(a: &[T; 4], b: &[T; 4]) -> [T; 7]
+where
+    T: Clone + Add<Output = T> + Mul<Output = T>,
+{
+    let mut products: [Option<T>; 7] = Default::default();
+    for (i, a_limb) in a.iter().enumerate() {
+        for (j, b_limb) in b.iter().enumerate() {
+            let product = a_limb.clone() * b_limb.clone();
+            products[i + j] = Some(match products[i + j].take() {
+                Some(sum) => sum + product,
+                None => product,
+            });
+        }
+    }
+    products.map(|product| product.expect("every weight has a product"))
+}
+
+/// Expressions checking `a * b + c == d (mod 2^256)`.
+#[derive(Clone, Debug)]
+pub struct MulAddWordsExprs<F> {
+    /// `t0 + t1 * 2^64 + c_lo - (d_lo + carry_lo * 2^128)`, must be zero.
+    pub check_lo: Expression<F>,
+    /// `t2 + t3 * 2^64 + c_hi + carry_lo - (d_hi + carry_hi * 2^128)`, must be
+    /// zero.
+    pub check_hi: Expression<F>,
+    /// Sum of the parts higher than 256-bit in the product.
+    pub overflow: Expression<F>,
+}
+
+/// Build the expressions checking `a * b + c == d (mod 2^256)`, where `a` and
+/// `b` are given as 64-bit limbs and `c` and `d` as 128-bit (lo, hi) halves.
+///
+/// The caller must constrain `carry_lo` and `carry_hi` to [`N_BYTES_CARRY`]
+/// bytes, otherwise the check is not sound.
+pub fn mul_add_words_exprs<F: Field>(
+    a_limbs: &[Expression<F>; 4],
+    b_limbs: &[Expression<F>; 4],
+    c: [Expression<F>; 2],
+    d: [Expression<F>; 2],
+    carry_lo: Expression<F>,
+    carry_hi: Expression<F>,
+) -> MulAddWordsExprs<F> {
+    let [c_lo, c_hi] = c;
+    let [d_lo, d_hi] = d;
+    let [t0, t1, t2, t3, t4, t5, t6] = mul_limb_products(a_limbs, b_limbs);
+
+    MulAddWordsExprs {
+        check_lo: t0 + t1 * pow_of_two::<F>(64) + c_lo
+            - (d_lo + carry_lo.clone() * pow_of_two::<F>(128)),
+        check_hi: t2 + t3 * pow_of_two::<F>(64) + c_hi + carry_lo
+            - (d_hi + carry_hi.clone() * pow_of_two::<F>(128)),
+        overflow: carry_hi + t4 + t5 + t6,
+    }
+}
+
+/// Compute the witness of carry_lo and carry_hi for
+/// `a * b + c == d (mod 2^256)`, with `words` being `[a, b, c, d]`.
+pub fn mul_add_words_carries(words: [Word; 4]) -> (Word, Word) {
+    let (a, b, c, d) = (words[0], words[1], words[2], words[3]);
+
+    let [t0, t1, t2, t3, ..] = mul_limb_products(&split_u256_limb64(&a), &split_u256_limb64(&b));
+    let (c_lo, c_hi) = split_u256(&c);
+    let (d_lo, d_hi) = split_u256(&d);
+
+    let carry_lo = (t0 + (t1 << 64) + c_lo).saturating_sub(d_lo) >> 128;
+    let carry_hi = (t2 + (t3 << 64) + c_hi + carry_lo).saturating_sub(d_hi) >> 128;
+    (carry_lo, carry_hi)
+}
+
 /// Config for the MulAddChip.
 #[derive(Clone, Debug)]
 pub struct MulAddConfig<F> {
@@ -41,6 +125,8 @@ pub struct MulAddConfig<F> {
     /// Fifth of the columns which we use over multiple rows to represent the
     /// schema described above.
     pub col4: Column<Advice>,
+    /// Denotes the range within which each carry byte should lie.
+    pub u8: Column<Fixed>,
     /// Sum of the parts higher than 256-bit in the product.
     pub overflow: Expression<F>,
 }
@@ -105,11 +191,12 @@ pub struct MulAddChip<F> {
 }
 
 impl<F: Field> MulAddChip<F> {
-    /// Configure the MulAdd chip.
-    #[allow(clippy::too_many_arguments)]
+    /// Configure the MulAdd chip, with `u8` being the column of the u8 lookup
+    /// table loaded by the circuit.
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
-        q_enable: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        q_enable: impl Fn(&mut VirtualCells<'_, F>) -> Expression<F>,
+        u8: Column<Fixed>,
     ) -> MulAddConfig<F> {
         let col0 = meta.advice_column();
         let col1 = meta.advice_column();
@@ -118,6 +205,17 @@ impl<F: Field> MulAddChip<F> {
         let col4 = meta.advice_column();
         let mut overflow = 0.expr();
 
+        // Cells of the carry bytes, over the two rows starting at `rotation`.
+        let carry_cells = |rotation: i32| {
+            [col0, col1, col2, col3, col4]
+                .map(|column| (column, rotation))
+                .into_iter()
+                .chain([col0, col1, col2, col3].map(|column| (column, rotation + 1)))
+                .collect::<Vec<_>>()
+        };
+        let carry_lo_cells = carry_cells(3);
+        let carry_hi_cells = carry_cells(5);
+
         meta.create_gate("mul add gate", |meta| {
             let q_enable = q_enable(meta);
 
@@ -126,59 +224,50 @@ impl<F: Field> MulAddChip<F> {
             let b_limbs =
                 [col0, col1, col2, col3].map(|column| meta.query_advice(column, Rotation::next()));
 
-            let c_lo = meta.query_advice(col0, Rotation(2));
-            let c_hi = meta.query_advice(col1, Rotation(2));
-            let d_lo = meta.query_advice(col2, Rotation(2));
-            let d_hi = meta.query_advice(col3, Rotation(2));
-
-            let carry_los = [col0, col1, col2, col3, col4]
-                .map(|col| meta.query_advice(col, Rotation(3)))
-                .into_iter()
-                .chain([col0, col1, col2, col3].map(|col| meta.query_advice(col, Rotation(4))))
-                .collect::<Vec<Expression<F>>>();
-            let carry_his = [col0, col1, col2, col3, col4]
-                .map(|col| meta.query_advice(col, Rotation(5)))
-                .into_iter()
-                .chain([col0, col1, col2, col3].map(|col| meta.query_advice(col, Rotation(6))))
-                .collect::<Vec<Expression<F>>>();
-
-            let carry_lo_expr = expr_from_bytes(&carry_los);
-            let carry_hi_expr = expr_from_bytes(&carry_his);
-
-            let t0 = a_limbs[0].clone() * b_limbs[0].clone();
-            let t1 =
-                a_limbs[0].clone() * b_limbs[1].clone() + a_limbs[1].clone() * b_limbs[0].clone();
-            let t2 = a_limbs[0].clone() * b_limbs[2].clone()
-                + a_limbs[1].clone() * b_limbs[1].clone()
-                + a_limbs[2].clone() * b_limbs[0].clone();
-            let t3 = a_limbs[0].clone() * b_limbs[3].clone()
-                + a_limbs[1].clone() * b_limbs[2].clone()
-                + a_limbs[2].clone() * b_limbs[1].clone()
-                + a_limbs[3].clone() * b_limbs[0].clone();
-            overflow = carry_hi_expr.clone()
-                + a_limbs[1].clone() * b_limbs[3].clone()
-                + a_limbs[2].clone() * b_limbs[2].clone()
-                + a_limbs[2].clone() * b_limbs[3].clone()
-                + a_limbs[3].clone() * b_limbs[1].clone()
-                + a_limbs[3].clone() * b_limbs[2].clone()
-                + a_limbs[3].clone() * b_limbs[3].clone();
-
-            let check_a = t0.expr() + t1.expr() * pow_of_two::<F>(64) + c_lo
-                - (d_lo + carry_lo_expr.clone() * pow_of_two::<F>(128));
-            let check_b = t2.expr() + t3.expr() * pow_of_two::<F>(64) + c_hi + carry_lo_expr
-                - (d_hi + carry_hi_expr * pow_of_two::<F>(128));
-
-            [check_a, check_b]
+            let c = [col0, col1].map(|column| meta.query_advice(column, Rotation(2)));
+            let d = [col2, col3].map(|column| meta.query_advice(column, Rotation(2)));
+
+            let [carry_los, carry_his] = [&carry_lo_cells, &carry_hi_cells].map(|cells| {
+                cells
+                    .iter()
+                    .map(|(column, rotation)| meta.query_advice(*column, Rotation(*rotation)))
+                    .collect::<Vec<Expression<F>>>()
+            });
+
+            let exprs = mul_add_words_exprs(
+                &a_limbs,
+                &b_limbs,
+                c,
+                d,
+                expr_from_bytes(&carry_los),
+                expr_from_bytes(&carry_his),
+            );
+            overflow = exprs.overflow;
+
+            [exprs.check_lo, exprs.check_hi]
                 .into_iter()
                 .map(move |poly| q_enable.clone() * poly)
         });
 
+        carry_lo_cells
+            .iter()
+            .chain(carry_hi_cells.iter())
+            .for_each(|(column, rotation)| {
+                meta.lookup_any("mul add carry range check for u8", |meta| {
+                    let q_enable = q_enable(meta);
+                    let u8_cell = meta.query_advice(*column, Rotation(*rotation));
+                    let u8_range = meta.query_fixed(u8, Rotation::cur());
+                    vec![(q_enable * u8_cell, u8_range)]
+                });
+            });
+
         MulAddConfig {
             col0,
             col1,
             col2,
             col3,
             col4,
+            u8,
             overflow,
         }
     }
@@ -201,23 +290,7 @@ impl<F: Field> MulAddChip<F> {
         let b_limbs = split_u256_limb64(&b);
         let (c_lo, c_hi) = split_u256(&c);
         let (d_lo, d_hi) = split_u256(&d);
-
-        let t0 = a_limbs[0] * b_limbs[0];
-        let t1 = a_limbs[0] * b_limbs[1] + a_limbs[1] * b_limbs[0];
-        let t2 = a_limbs[0] * b_limbs[2] + a_limbs[1] * b_limbs[1] + a_limbs[2] * b_limbs[0];
-        let t3 = a_limbs[0] * b_limbs[3]
-            + a_limbs[1] * b_limbs[2]
-            + a_limbs[2] * b_limbs[1]
-            + a_limbs[3] * b_limbs[0];
-
-        let (c_lo_minus_d_lo, _) = c_lo.overflowing_sub(d_lo);
-        let temp = t0 + (t1 << 64);
-        let (carry_lo, _) = temp.overflowing_add(c_lo_minus_d_lo);
-        let carry_lo = carry_lo >> 128;
-        let (carry_lo_minus_d_hi, _) = carry_lo.overflowing_sub(d_hi);
-        let temp = t2 + (t3 << 64) + c_hi;
-        let (carry_hi, _) = temp.overflowing_add(carry_lo_minus_d_hi);
-        let carry_hi = carry_hi >> 128;
+        let (carry_lo, carry_hi) = mul_add_words_carries(words);
 
         // a limbs.
         for (i, (column, value)) in [
@@ -384,14 +457,14 @@ mod test {
 
     use eth_types::{Field, Word};
     use halo2_proofs::{
-        circuit::SimpleFloorPlanner,
+        circuit::{SimpleFloorPlanner, Value},
         dev::MockProver,
         halo2curves::bn256::Fr as Fp,
-        plonk::{Circuit, Selector},
+        plonk::{Circuit, Column, Fixed, Selector},
     };
     use rand::Rng;
 
-    use crate::mul_add::{MulAddChip, MulAddConfig};
+    use crate::mul_add::{mul_add_words_carries, MulAddChip, MulAddConfig};
 
     macro_rules! try_test_circuit {
         ($values:expr) => {{
@@ -425,11 +498,24 @@ mod test {
         Word::from_big_endian(&rand_bytes_array::<32>())
     }
 
+    #[test]
+    fn mul_add_words_carries_fit_in_carry_bytes() {
+        for _ in 0..100 {
+            let (a, b, c) = (rand_word(), rand_word(), rand_word());
+            let (ab, _) = a.overflowing_mul(b);
+            let (d, _) = ab.overflowing_add(c);
+            let (carry_lo, carry_hi) = mul_add_words_carries([a, b, c, d]);
+            assert!(carry_lo.bits() <= 8 * super::N_BYTES_CARRY);
+            assert!(carry_hi.bits() <= 8 * super::N_BYTES_CARRY);
+        }
+    }
+
     #[test]
     fn mul_over_rows() {
         #[derive(Clone)]
         struct TestCircuitConfig<F> {
             q_enable: Selector,
+            u8: Column<Fixed>,
             mul_config: MulAddConfig<F>,
         }
 
@@ -447,9 +533,12 @@ mod test {
 
             fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
                 let q_enable = meta.complex_selector();
-                let mul_config = MulAddChip::configure(meta, |meta| meta.query_selector(q_enable));
+                let u8 = meta.fixed_column();
+                let mul_config =
+                    MulAddChip::configure(meta, |meta| meta.query_selector(q_enable), u8);
                 Self::Config {
                     q_enable,
+                    u8,
                     mul_config,
                 }
             }
@@ -460,6 +549,20 @@ mod test {
                 mut layouter: impl halo2_proofs::circuit::Layouter<F>,
             ) -> Result<(), halo2_proofs::plonk::Error> {
                 let chip = MulAddChip::construct(config.mul_config);
+                layouter.assign_region(
+                    || "u8 table",
+                    |mut region| {
+                        for i in 0..=u8::MAX {
+                            region.assign_fixed(
+                                || format!("u8 table: {}", i),
+                                config.u8,
+                                i as usize,
+                                || Value::known(F::from(i as u64)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )?;
                 layouter.assign_region(
                     || "witness",
                     |mut region| {
@@ -1,14 +1,17 @@
 package witness
 
 import (
+	"bytes"
 	"fmt"
 	"math/big"
 	"os/exec"
 	"testing"
 
 	"main/gethutil/mpt/oracle"
+	"main/gethutil/mpt/trie"
 
 	"github.com/ethereum/go-ethereum/common"
+	"github.com/ethereum/go-ethereum/crypto"
 
 	"main/gethutil/mpt/state"
 )
@@ -922,3 +925,72 @@ func TestExtNodeDeletedExtShortIsBranchFirstLevel(t *testing.T) {
 
 	ExtNodeDeleted(key1, key2, key3, "ExtNodeDeletedExtShortIsBranchFirstLevel")
 }
+
+// findAddressWithHashPrefix returns the first address `0x<tag><i>` whose hash starts with the nibbles
+// of `prefix` and differs from `other` at nibble `len(prefix)`.
+func findAddressWithHashPrefix(tag string, prefix []byte, other byte) common.Address {
+	for i := 0; ; i++ {
+		addr := common.HexToAddress(fmt.Sprintf("0x%s%d", tag, i))
+		nibbles := trie.KeybytesToHex(crypto.Keccak256(addr.Bytes()))
+		if bytes.Equal(nibbles[:len(prefix)], prefix) && nibbles[len(prefix)] != other {
+			return addr
+		}
+	}
+}
+
+// AccountExtNodeModified prepares the account trie for a modified extension node: the hashes of
+// the addresses of two accounts share five nibbles, so that they are in the branch of an extension
+// node, and the hash of the address of a third account shares only the first two of these nibbles.
+// Inserting the third account splits the extension node, deleting it merges the extension nodes back.
+func AccountExtNodeModified(isDelete bool, testName string) {
+	oracle.NodeUrl = oracle.LocalUrl
+
+	blockNum := 0
+	blockNumberParent := big.NewInt(int64(blockNum))
+	blockHeaderParent := oracle.PrefetchBlock(blockNumberParent, true, nil)
+	database := state.NewDatabase(blockHeaderParent)
+	statedb, _ := state.New(blockHeaderParent.Root, database, nil)
+	statedb.DisableLoadingRemoteAccounts()
+
+	addr1 := common.HexToAddress("0xa1")
+	nibbles1 := trie.KeybytesToHex(crypto.Keccak256(addr1.Bytes()))
+	addr2 := findAddressWithHashPrefix("a2", nibbles1[:5], nibbles1[5])
+	addr3 := findAddressWithHashPrefix("a3", nibbles1[:2], nibbles1[2])
+
+	statedb.CreateAccount(addr1)
+	statedb.CreateAccount(addr2)
+	if isDelete {
+		statedb.CreateAccount(addr3)
+		statedb.SetBalance(addr3, big.NewInt(7))
+	}
+	statedb.IntermediateRoot(false)
+
+	trieMod := TrieModification{
+		Type:    BalanceChanged,
+		Balance: big.NewInt(7),
+		Address: addr3,
+	}
+	if isDelete {
+		trieMod = TrieModification{
+			Type:    AccountDestructed,
+			Address: addr3,
+		}
+	}
+	trieModifications := []TrieModification{trieMod}
+
+	prepareWitness(testName, trieModifications, statedb)
+
+	oracle.NodeUrl = oracle.RemoteUrl
+}
+
+func TestAccountExtNodeInserted(t *testing.T) {
+	SkipIfNoGeth(t)
+	// The third account is implicitly created by setting its balance.
+	AccountExtNodeModified(false, "AccountExtNodeInserted")
+}
+
+func TestAccountExtNodeDeleted(t *testing.T) {
+	SkipIfNoGeth(t)
+	// This is the reverse operation of the case in TestAccountExtNodeInserted.
+	AccountExtNodeModified(true, "AccountExtNodeDeleted")
+}
@@ -43,7 +43,7 @@ func equipLeafWithModExtensionNode(statedb *state.StateDB, leafNode Node, addr c
 	ind := byte(keyIndex) + byte(numberOfNibbles) // where the old and new extension nodes start to be different
 	longExtNodeKey := make([]byte, len(key))
 	copy(longExtNodeKey, key)
-	// We would like to retrieve the shortened extension node from the trie via GetProof or
+	// We would like to retrieve the shortened extension node from the trie via GetProofByHash or
 	// GetStorageProof (depending whether it is an account proof or storage proof),
 	// the key where we find its underlying branch is `oldExtNodeKey`.
 	for j := ind; int(j) < keyIndex+len(longNibbles); j++ {
@@ -56,7 +56,8 @@ func equipLeafWithModExtensionNode(statedb *state.StateDB, leafNode Node, addr c
 	var proof [][]byte
 	var err error
 	if isAccountProof {
-		proof, _, _, _, _, err = statedb.GetProof(addr)
+		// The account key is the hashed address, `ky` is already hashed
+		proof, _, _, _, _, err = statedb.GetProofByHash(ky)
 	} else {
 		proof, _, _, _, _, err = statedb.GetStorageProof(addr, ky)
 	}
@@ -265,7 +265,6 @@ func obtainTwoProofsAndConvertToWitness(trieModifications []TrieModification, st
 
 			// In convertProofToWitness, we can't use account address in its original form (non-hashed), because
 			// of the "special" test for which we manually manipulate the "hashed" address and we don't have a preimage.
-			// TODO: addr is used for calling GetProof for modified extension node only, might be done in a different way
 			nodesAccount :=
 				convertProofToWitness(statedb, addr, addrh, accountProof, accountProof1, aExtNibbles1, aExtNibbles2, tMod.Key, accountAddr, aNode, true, tMod.Type == AccountDoesNotExist, false, aIsLastLeaf)
 			nodes = append(nodes, nodesAccount...)
@@ -0,0 +1,36 @@
+//! Capture a block from a node as a [`BlockFixture`].
+//!
+//! Usage: `dump_block_fixture <rpc url> <block number> [output path]`
+
+use integration_tests::{
+    block_fixture::{BlockFixture, BLOCK_FIXTURES_PATH},
+    log_init,
+};
+use log::info;
+use std::env;
+
+#[tokio::main]
+async fn main() {
+    log_init();
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <rpc url> <block number> [output path]", args[0]);
+        std::process::exit(1);
+    }
+    let block_num: u64 = args[2].parse().expect("invalid block number");
+    let path = args
+        .get(3)
+        .cloned()
+        .unwrap_or_else(|| format!("{}/{}.json", BLOCK_FIXTURES_PATH, block_num));
+
+    let fixture = BlockFixture::fetch(&args[1], block_num)
+        .await
+        .expect("cannot fetch block");
+    fixture.save(&path).expect("cannot write fixture");
+    info!(
+        "block #{} with {} txs written to {}",
+        block_num,
+        fixture.eth_block.transactions.len(),
+        path
+    );
+}
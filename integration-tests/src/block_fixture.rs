@@ -0,0 +1,57 @@
+//! Offline block fixtures.
+//!
+//! Blocks captured from a real network are stored as
+//! [`BlockFixture`]s in [`BLOCK_FIXTURES_PATH`], so that they can be verified
+//! without a node, against realistic opcode mixes.
+
+use crate::prove_block::ProveBlockError;
+use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+pub use prover::block_fixture::BlockFixture;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+/// Directory of the block fixtures, relative to the crate root.
+pub const BLOCK_FIXTURES_PATH: &str = "fixtures/blocks";
+
+/// Budgets of the token transfers found in the block fixtures, relative to
+/// the crate root.
+pub const TRANSFER_BUDGETS_PATH: &str = "fixtures/transfer_budgets.json";
+
+/// Environment variable which makes `transfer_profile_block_fixtures` write
+/// [`TRANSFER_BUDGETS_PATH`] instead of checking it.
+pub const UPDATE_TRANSFER_BUDGETS_ENV: &str = "UPDATE_TRANSFER_BUDGETS";
+
+/// Randomness used to build the witness of the circuits in the integration
+/// tests.
+pub(crate) const MOCK_RANDOMNESS: u64 = 0x100;
+
+/// Build the witness of the block of `fixture` and verify the [`SuperCircuit`]
+/// with the MockProver, returning the degree of the circuit.
+pub fn mock_verify(fixture: &BlockFixture) -> Result<u32, ProveBlockError> {
+    let builder = fixture.circuit_input_builder()?;
+    let (degree, circuit, instance) =
+        SuperCircuit::<Fr>::build_from_circuit_input_builder(&builder, Fr::from(MOCK_RANDOMNESS))?;
+    let prover =
+        MockProver::<Fr>::run(degree, &circuit, instance).map_err(ProveBlockError::Synthesis)?;
+    prover.verify_par().map_err(ProveBlockError::Verification)?;
+    Ok(degree)
+}
+
+/// Paths of the json fixtures found in `dir`, sorted by name.  A missing
+/// directory has no fixtures.
+pub fn block_fixture_paths(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().map_or(false, |ext| ext == "json"));
+    paths.sort();
+    Ok(paths)
+}
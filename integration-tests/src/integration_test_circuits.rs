@@ -1,4 +1,4 @@
-use crate::{get_client, GenDataOutput};
+use crate::{block_fixture::MOCK_RANDOMNESS, get_client, GenDataOutput};
 use bus_mapping::{
     circuit_input_builder::{BuilderClient, CircuitInputBuilder, FixedCParams},
     mock::BlockData,
@@ -45,9 +45,6 @@ use zkevm_circuits::{
     witness::{block_convert, Block},
 };
 
-/// TEST_MOCK_RANDOMNESS
-const TEST_MOCK_RANDOMNESS: u64 = 0x100;
-
 /// MAX_TXS
 const MAX_TXS: usize = 4;
 /// MAX_WITHDRAWALS
@@ -417,7 +414,7 @@ impl<C: SubCircuit<Fr> + Circuit<Fr>> IntegrationTest<C> {
             block_tag,
         );
         let mut block = block_convert(&builder).unwrap();
-        block.randomness = Fr::from(TEST_MOCK_RANDOMNESS);
+        block.randomness = Fr::from(MOCK_RANDOMNESS);
         let circuit = C::new_from_block(&block);
         let instance = circuit.instance();
 
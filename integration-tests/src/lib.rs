@@ -158,5 +158,8 @@ pub mod bindings_greeter;
 #[allow(missing_docs)]
 #[allow(clippy::all)]
 pub mod bindings_openzeppelinerc20testtoken;
+pub mod block_fixture;
 /// Common code for integration tests of circuits.
 pub mod integration_test_circuits;
+pub mod prove_block;
+pub mod prove_tx;
@@ -0,0 +1,288 @@
+//! End-to-end proving of a block fetched from a node.
+//!
+//! [`prove_block`] performs all the steps needed to prove a block: fetching
+//! the block and its traces, fetching the state accessed by it, building the
+//! witness and verifying the [`SuperCircuit`] with the MockProver or a real
+//! prover. It is the canonical end-to-end smoke test for library users.
+
+use crate::block_fixture::MOCK_RANDOMNESS;
+use bus_mapping::{
+    circuit_input_builder::{build_state_code_db, get_state_accesses, BuilderClient, FixedCParams},
+    rpc::{GethClient, RpcConfig},
+};
+use eth_types::H256;
+use halo2_proofs::{
+    dev::{MockProver, VerifyFailure},
+    halo2curves::bn256::Fr,
+    plonk::{keygen_pk, keygen_vk},
+    SerdeFormat,
+};
+use rand_core::OsRng;
+use std::{
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use zkevm_circuits::{
+    metrics::{self, MetricsSnapshot},
+    proof::Proof,
+    srs::{read_srs, srs_for_degree, SrsError},
+    super_circuit::{SubCircuitRows, SuperCircuit},
+};
+
+/// Prover used by [`prove_block_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prover {
+    /// Verify the circuit with the MockProver.
+    Mock,
+    /// Generate and verify a real proof with the SRS read from a file and
+    /// truncated to the degree of the circuit, generating the keys on the fly.
+    Real {
+        /// Path of the SRS
+        srs_path: PathBuf,
+        /// Serialization format of the SRS
+        srs_format: SerdeFormat,
+        /// Expected keccak256 hash of the SRS file, checked when given
+        srs_hash: Option<H256>,
+    },
+}
+
+/// Stage of [`prove_block_with`], reported through its progress callback
+/// once the stage starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProveBlockStage {
+    /// Fetch the block, its execution traces and the history hashes.
+    FetchTraces,
+    /// Fetch the proofs and codes of the accessed accounts.
+    FetchState,
+    /// Build the circuit inputs and the witness.
+    BuildWitness,
+    /// Generate the proving key and the proof.
+    Prove,
+    /// Verify the circuit.
+    Verify,
+}
+
+/// Result of a successful [`prove_block_with`].
+#[derive(Debug, Clone)]
+pub struct ProveBlockReport {
+    /// Number of the proven block
+    pub block_num: u64,
+    /// Number of transactions in the block
+    pub num_txs: usize,
+    /// Degree of the [`SuperCircuit`]
+    pub degree: u32,
+    /// Rows required by each sub-circuit of the [`SuperCircuit`]
+    pub rows: SubCircuitRows,
+    /// Number of read-write operations of the block
+    pub num_rws: usize,
+    /// Time spent in each stage, in order
+    pub stage_durations: Vec<(ProveBlockStage, Duration)>,
+    /// Metrics of the circuits once the block is proven
+    pub metrics: MetricsSnapshot,
+    /// Proof, only when using [`Prover::Real`]
+    pub proof: Option<Vec<u8>>,
+}
+
+/// Times the stages of [`prove_block_with`].
+#[derive(Debug, Default)]
+struct StageTimer {
+    current: Option<(ProveBlockStage, Instant)>,
+    durations: Vec<(ProveBlockStage, Duration)>,
+}
+
+impl StageTimer {
+    /// Finish the current stage, if any, and start `stage`.
+    fn start(&mut self, stage: ProveBlockStage) {
+        self.finish();
+        self.current = Some((stage, Instant::now()));
+    }
+
+    /// Finish the current stage, if any.
+    fn finish(&mut self) {
+        if let Some((stage, start)) = self.current.take() {
+            let duration = start.elapsed();
+            tracing::info!(
+                stage = ?stage,
+                duration_ms = duration.as_millis() as u64,
+                "prove block stage"
+            );
+            self.durations.push((stage, duration));
+        }
+    }
+}
+
+/// Error of [`prove_block_with`].
+#[derive(Debug)]
+pub enum ProveBlockError {
+    /// Error fetching the block or building its witness
+    Witness(zkevm_circuits::error::Error),
+    /// Error synthesizing the circuit
+    Synthesis(halo2_proofs::plonk::Error),
+    /// The MockProver found unsatisfied constraints or lookups
+    Verification(Vec<VerifyFailure>),
+    /// Error reading the SRS
+    Srs(SrsError),
+    /// Error generating the keys, or generating or verifying a proof
+    Proof(halo2_proofs::plonk::Error),
+}
+
+impl fmt::Display for ProveBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ProveBlockError {}
+
+impl From<bus_mapping::Error> for ProveBlockError {
+    fn from(err: bus_mapping::Error) -> Self {
+        Self::Witness(err.into())
+    }
+}
+
+impl From<zkevm_circuits::error::Error> for ProveBlockError {
+    fn from(err: zkevm_circuits::error::Error) -> Self {
+        Self::Witness(err)
+    }
+}
+
+impl From<SrsError> for ProveBlockError {
+    fn from(err: SrsError) -> Self {
+        Self::Srs(err)
+    }
+}
+
+/// Prove the block `block_num` of the node at `rpc_url` with the MockProver,
+/// reporting progress and metrics in the log.
+pub async fn prove_block(
+    rpc_url: &str,
+    block_num: u64,
+    params: FixedCParams,
+) -> Result<ProveBlockReport, ProveBlockError> {
+    let report = prove_block_with(rpc_url, block_num, params, Prover::Mock, |stage| {
+        log::info!("prove block #{}: {:?}", block_num, stage)
+    })
+    .await?;
+    for (name, (rows, rows_with_padding)) in report.rows.iter() {
+        log::info!(
+            "prove block #{}: {} circuit rows {} ({} with padding)",
+            block_num,
+            name,
+            rows,
+            rows_with_padding
+        );
+    }
+    for (stage, duration) in &report.stage_durations {
+        log::info!(
+            "prove block #{}: {:?} took {:?}",
+            block_num,
+            stage,
+            duration
+        );
+    }
+    if let Some(peak_memory) = report.metrics.peak_memory {
+        log::info!(
+            "prove block #{}: peak memory {} MiB",
+            block_num,
+            peak_memory >> 20
+        );
+    }
+    Ok(report)
+}
+
+/// Prove the block `block_num` of the node at `rpc_url` with `prover`, calling
+/// `progress` at the beginning of each stage.
+pub async fn prove_block_with(
+    rpc_url: &str,
+    block_num: u64,
+    params: FixedCParams,
+    prover: Prover,
+    mut progress: impl FnMut(ProveBlockStage),
+) -> Result<ProveBlockReport, ProveBlockError> {
+    let mut timer = StageTimer::default();
+    let mut progress = |stage| {
+        timer.start(stage);
+        progress(stage);
+    };
+
+    let rpc_config = RpcConfig::default();
+    let cli = GethClient::new_http_with_retries(rpc_url, &rpc_config)?;
+    let cli = BuilderClient::new(cli, params)
+        .await?
+        .with_rpc_config(rpc_config);
+
+    progress(ProveBlockStage::FetchTraces);
+    let (eth_block, geth_traces, history_hashes, prev_state_root) =
+        cli.get_block(block_num).await?;
+
+    progress(ProveBlockStage::FetchState);
+    let access_set = get_state_accesses(&eth_block, &geth_traces)?;
+    let (proofs, codes) = cli.get_state(block_num, access_set).await?;
+
+    progress(ProveBlockStage::BuildWitness);
+    let (state_db, code_db) = build_state_code_db(proofs, codes);
+    let builder = cli.gen_inputs_from_state(
+        state_db,
+        code_db,
+        &eth_block,
+        &geth_traces,
+        history_hashes,
+        prev_state_root,
+    )?;
+    let (degree, circuit, instance) =
+        SuperCircuit::<Fr>::build_from_circuit_input_builder(&builder, Fr::from(MOCK_RANDOMNESS))?;
+    let block = circuit.evm_circuit.block.as_ref().expect("block is set");
+    let rows = circuit.rows;
+    let num_rws = block.rws.0.values().map(Vec::len).sum();
+    tracing::info!(block_num, num_rws, "block rws");
+
+    let proof = match prover {
+        Prover::Mock => {
+            progress(ProveBlockStage::Verify);
+            let mock_prover = MockProver::<Fr>::run(degree, &circuit, instance)
+                .map_err(ProveBlockError::Synthesis)?;
+            mock_prover
+                .verify_par()
+                .map_err(ProveBlockError::Verification)?;
+            None
+        }
+        Prover::Real {
+            srs_path,
+            srs_format,
+            srs_hash,
+        } => {
+            progress(ProveBlockStage::Prove);
+            let srs_file = File::open(srs_path).map_err(SrsError::from)?;
+            let srs = read_srs(BufReader::new(srs_file), srs_format, srs_hash)?;
+            let general_params = srs_for_degree(&srs, degree)?;
+            let verifying_key =
+                keygen_vk(&general_params, &circuit).map_err(ProveBlockError::Proof)?;
+            let proving_key = keygen_pk(&general_params, verifying_key, &circuit)
+                .map_err(ProveBlockError::Proof)?;
+            let proof = Proof::create(&general_params, &proving_key, circuit, &instance, OsRng)
+                .map_err(ProveBlockError::Proof)?;
+
+            progress(ProveBlockStage::Verify);
+            proof
+                .verify_with_vk(&general_params, proving_key.get_vk(), &instance)
+                .map_err(ProveBlockError::Proof)?;
+            Some(proof.to_bytes())
+        }
+    };
+
+    timer.finish();
+
+    Ok(ProveBlockReport {
+        block_num,
+        num_txs: eth_block.transactions.len(),
+        degree,
+        rows,
+        num_rws,
+        stage_durations: timer.durations,
+        metrics: metrics::snapshot(),
+        proof,
+    })
+}
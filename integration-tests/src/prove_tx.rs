@@ -0,0 +1,99 @@
+//! Development proving of a single transaction fetched from a node.
+//!
+//! [`prove_tx`] builds a block containing only the given transaction, on top
+//! of the state the transaction saw, and verifies with the MockProver the
+//! sub-circuits the transaction needs.  It is meant to debug a transaction
+//! without proving the whole block it belongs to: the state is not backed by
+//! merkle proofs, so the PI circuit, which checks the state roots, is not
+//! verified.
+
+use crate::{block_fixture::MOCK_RANDOMNESS, prove_block::ProveBlockError};
+use bus_mapping::{
+    circuit_input_builder::{BuilderClient, FixedCParams},
+    rpc::{GethClient, RpcConfig},
+};
+use eth_types::H256;
+use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr, plonk::Circuit};
+use zkevm_circuits::{
+    bytecode_circuit::TestBytecodeCircuit,
+    copy_circuit::TestCopyCircuit,
+    evm_circuit::TestEvmCircuit,
+    exp_circuit::TestExpCircuit,
+    state_circuit::TestStateCircuit,
+    tx_circuit::TestTxCircuit,
+    util::{log2_ceil, SubCircuit},
+    witness::{block_convert, Block},
+};
+
+/// Result of a successful [`prove_tx`].
+#[derive(Debug, Clone)]
+pub struct ProveTxReport {
+    /// Number of the block including the transaction
+    pub block_num: u64,
+    /// Number of read-write operations of the transaction
+    pub num_rws: usize,
+    /// Name and degree of each verified sub-circuit, in order
+    pub circuits: Vec<(&'static str, u32)>,
+}
+
+/// Prove the transaction `tx_hash` of the node at `rpc_url` in isolation with
+/// the MockProver.
+///
+/// The EVM, State, Tx and Bytecode circuits are always verified, the Copy and
+/// Exp circuits only when the transaction has copy or exp events.
+pub async fn prove_tx(rpc_url: &str, tx_hash: H256) -> Result<ProveTxReport, ProveBlockError> {
+    let rpc_config = RpcConfig::default();
+    let cli = GethClient::new_http_with_retries(rpc_url, &rpc_config)?;
+    let cli = BuilderClient::new(cli, FixedCParams::default())
+        .await?
+        .with_rpc_config(rpc_config);
+
+    let (builder, eth_block) = cli.gen_tx_inputs(tx_hash).await?;
+    let mut block = block_convert::<Fr>(&builder)?;
+    block.randomness = Fr::from(MOCK_RANDOMNESS);
+    let block_num = eth_block.number.expect("block has a number").as_u64();
+
+    let mut circuits = vec![
+        ("evm", verify::<TestEvmCircuit<Fr>>(&block)?),
+        ("state", verify::<TestStateCircuit<Fr>>(&block)?),
+        ("tx", verify::<TestTxCircuit<Fr>>(&block)?),
+        ("bytecode", verify::<TestBytecodeCircuit<Fr>>(&block)?),
+    ];
+    if !block.copy_events.is_empty() {
+        circuits.push(("copy", verify::<TestCopyCircuit<Fr>>(&block)?));
+    }
+    if !block.exp_events.is_empty() {
+        circuits.push(("exp", verify::<TestExpCircuit<Fr>>(&block)?));
+    }
+    for (name, degree) in &circuits {
+        log::info!(
+            "prove tx {:?}: {} circuit verified with k = {}",
+            tx_hash,
+            name,
+            degree
+        );
+    }
+
+    Ok(ProveTxReport {
+        block_num,
+        num_rws: block.rws.0.values().map(Vec::len).sum(),
+        circuits,
+    })
+}
+
+/// Verify the sub-circuit `C` of `block` with the MockProver, using the
+/// smallest degree fitting its rows, and return the degree.
+///
+/// The rows of the block are taken into account besides the rows fixed by
+/// the circuit parameters, which can be zero, like `max_evm_rows`.
+fn verify<C: SubCircuit<Fr> + Circuit<Fr>>(block: &Block<Fr>) -> Result<u32, ProveBlockError> {
+    let (num_rows, num_rows_padded) = C::min_num_rows_block(block);
+    let degree = log2_ceil(C::unusable_rows() + num_rows.max(num_rows_padded));
+    let circuit = C::new_from_block(block);
+    let mock_prover = MockProver::<Fr>::run(degree, &circuit, circuit.instance())
+        .map_err(ProveBlockError::Synthesis)?;
+    mock_prover
+        .verify_par()
+        .map_err(ProveBlockError::Verification)?;
+    Ok(degree)
+}
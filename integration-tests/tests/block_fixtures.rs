@@ -0,0 +1,141 @@
+use bus_mapping::circuit_input_builder::{
+    keccak_fs, keccak_inputs, TokenTransfer, TransferBudgets, TransferProfile,
+};
+use halo2_proofs::halo2curves::bn256::Fr;
+use integration_tests::{
+    block_fixture::{
+        block_fixture_paths, BlockFixture, BLOCK_FIXTURES_PATH, TRANSFER_BUDGETS_PATH,
+        UPDATE_TRANSFER_BUDGETS_ENV,
+    },
+    log_init,
+};
+use log::info;
+use prover::pipeline::{self, BlockWitness};
+use std::{env, fs};
+use zkevm_circuits::keccak_circuit::KeccakCircuit;
+
+#[test]
+fn pipeline_witness_block_fixtures() {
+    log_init();
+    for path in block_fixture_paths(BLOCK_FIXTURES_PATH).unwrap() {
+        let fixture = BlockFixture::load(&path).unwrap();
+        let params = fixture.circuit_input_builder().unwrap().circuits_params;
+        let witness = pipeline::witness(&fixture, params).unwrap();
+
+        // The witness stage output is resumable from its serialized form
+        let json = serde_json::to_string(&witness).unwrap();
+        let resumed: BlockWitness = serde_json::from_str(&json).unwrap();
+        let (_, instance) = resumed.circuit();
+        let (_, expected_instance) = witness.circuit();
+        assert_eq!(instance, expected_instance, "{}", path.display());
+        assert_eq!(resumed.degree, witness.degree);
+    }
+}
+
+#[test]
+fn transfer_profile_block_fixtures() {
+    log_init();
+    let mut samples = Vec::new();
+    let mut profiled = Vec::new();
+    for path in block_fixture_paths(BLOCK_FIXTURES_PATH).unwrap() {
+        let fixture = BlockFixture::load(&path).unwrap();
+        let tx_samples = fixture.transaction_samples().unwrap();
+        samples.extend(
+            fixture
+                .eth_block
+                .transactions
+                .iter()
+                .zip(tx_samples)
+                .filter_map(|(tx, sample)| TokenTransfer::from_tx(tx).map(|t| (t, sample))),
+        );
+        if let Some(profile) = TransferProfile::of_block(&fixture.eth_block) {
+            let builder = fixture.circuit_input_builder().unwrap();
+            let block_keccak_fs: usize = keccak_inputs(&builder.block, &builder.code_db)
+                .unwrap()
+                .iter()
+                .map(|input| keccak_fs(input.len()))
+                .sum();
+            profiled.push((fixture, profile, builder.circuits_params, block_keccak_fs));
+        }
+    }
+    info!(
+        "{} token transfers, {} token transfer block fixtures",
+        samples.len(),
+        profiled.len()
+    );
+    assert!(
+        !samples.is_empty(),
+        "no token transfer in the block fixtures of {}",
+        BLOCK_FIXTURES_PATH
+    );
+    let budgets = TransferBudgets::calibrate(samples);
+
+    // The calibrated budgets are committed, so that they can be used without
+    // the fixtures.
+    if env::var_os(UPDATE_TRANSFER_BUDGETS_ENV).is_some() {
+        fs::write(
+            TRANSFER_BUDGETS_PATH,
+            serde_json::to_string_pretty(&budgets).unwrap(),
+        )
+        .unwrap();
+    }
+    let committed = fs::read_to_string(TRANSFER_BUDGETS_PATH).unwrap_or_else(|err| {
+        panic!(
+            "no transfer budgets at {} ({}), rerun with {}=1 to write them",
+            TRANSFER_BUDGETS_PATH, err, UPDATE_TRANSFER_BUDGETS_ENV
+        )
+    });
+    let committed: TransferBudgets = serde_json::from_str(&committed).unwrap();
+    assert_eq!(
+        committed, budgets,
+        "transfer budgets changed, rerun with {}=1 if intended",
+        UPDATE_TRANSFER_BUDGETS_ENV
+    );
+
+    // Every block of transfers fits in the capacity estimated from its
+    // transactions alone
+    let rows_per_keccak_f = KeccakCircuit::<Fr>::rows_per_keccak_f();
+    for (fixture, profile, params, block_keccak_fs) in profiled {
+        let block_num = fixture.eth_block.number.unwrap_or_default();
+        let estimate = profile.estimate(&budgets, rows_per_keccak_f).unwrap();
+        let keccak_capacity = KeccakCircuit::<Fr>::new(estimate.params.max_keccak_rows, vec![])
+            .capacity()
+            .unwrap();
+        let proof_nodes: usize = fixture
+            .proofs
+            .iter()
+            .map(|proof| {
+                proof.account_proof.len()
+                    + proof
+                        .storage_proof
+                        .iter()
+                        .map(|storage| storage.proof.len())
+                        .sum::<usize>()
+            })
+            .sum();
+        let storage_proofs: usize = fixture
+            .proofs
+            .iter()
+            .map(|proof| 1 + proof.storage_proof.len())
+            .sum();
+        assert!(
+            params.max_rws <= estimate.params.max_rws
+                && params.max_copy_rows <= estimate.params.max_copy_rows
+                && params.max_exp_steps <= estimate.params.max_exp_steps
+                && params.max_bytecode <= estimate.params.max_bytecode
+                && block_keccak_fs <= keccak_capacity
+                && storage_proofs <= estimate.storage_proofs
+                && proof_nodes <= estimate.proof_nodes,
+            "block #{}: {:?} with {} keccak_f, {} proofs and {} proof nodes exceeds {:?}",
+            block_num,
+            params,
+            block_keccak_fs,
+            storage_proofs,
+            proof_nodes,
+            estimate
+        );
+        fixture
+            .circuit_input_builder_with_params(estimate.params)
+            .unwrap_or_else(|err| panic!("block #{}: {:?}", block_num, err));
+    }
+}
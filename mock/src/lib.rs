@@ -18,7 +18,7 @@ mod withdrawal;
 pub(crate) use account::MockAccount;
 pub(crate) use block::MockBlock;
 pub use sha3::Sha3CodeGen;
-pub use test_ctx::TestContext;
+pub use test_ctx::{TestContext, TestContextBuilder};
 pub use test_ctx2::TestContext2;
 pub use transaction::{AddrOrWallet, MockTransaction, CORRECT_MOCK_TXS};
 
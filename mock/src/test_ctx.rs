@@ -1,6 +1,9 @@
 //! Mock types and functions to generate Test environments for ZKEVM tests
 
-use crate::{eth, MockAccount, MockBlock, MockTransaction, TestContext2};
+use crate::{
+    eth, test_ctx2::build_and_trace_block, MockAccount, MockBlock, MockTransaction, TestContext2,
+    MOCK_ACCOUNTS,
+};
 use eth_types::{
     geth_types::{Account, GethData},
     Bytecode, Error, Word,
@@ -172,11 +175,126 @@ impl<const NACC: usize, const NTX: usize> TestContext<NACC, NTX> {
     }
 }
 
+/// Builder of the [`GethData`] of a block with any number of accounts and
+/// transactions.
+///
+/// Unlike [`TestContext`], the number of accounts and transactions is not
+/// fixed upfront: each call to [`Self::account`] or [`Self::tx`] adds one,
+/// configured by the given function.
+///
+/// ## Example
+/// ```rust
+/// use eth_types::{bytecode, geth_types::GethData};
+/// use mock::{eth, test_ctx::TestContextBuilder, MOCK_ACCOUNTS};
+///
+/// let code = bytecode! {
+///     PUSH1(0x01)
+///     PUSH1(0x02)
+///     ADD
+///     STOP
+/// };
+/// let block: GethData = TestContextBuilder::new()
+///     .account(|acc| acc.address(MOCK_ACCOUNTS[0]).balance(eth(10)).code(code))
+///     .account(|acc| acc.address(MOCK_ACCOUNTS[1]).balance(eth(10)))
+///     .tx(|tx| tx.from(MOCK_ACCOUNTS[1]).to(MOCK_ACCOUNTS[0]))
+///     .block(|block| block.number(0xcafeu64))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TestContextBuilder {
+    history_hashes: Option<Vec<Word>>,
+    accounts: Vec<MockAccount>,
+    transactions: Vec<MockTransaction>,
+    block: MockBlock,
+    logger_config: LoggerConfig,
+}
+
+impl TestContextBuilder {
+    /// Create a new builder without accounts nor transactions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hashes of the previous blocks.
+    pub fn history_hashes(mut self, history_hashes: Vec<Word>) -> Self {
+        self.history_hashes = Some(history_hashes);
+        self
+    }
+
+    /// Add an account configured by `func_acc`.
+    pub fn account<FAcc>(mut self, func_acc: FAcc) -> Self
+    where
+        FAcc: FnOnce(&mut MockAccount) -> &mut MockAccount,
+    {
+        let mut account = MockAccount::default();
+        self.accounts.push(func_acc(&mut account).build());
+        self
+    }
+
+    /// Add a transaction configured by `func_tx`.  Its index and, unless set,
+    /// its nonce are assigned when building.
+    pub fn tx<FTx>(mut self, func_tx: FTx) -> Self
+    where
+        FTx: FnOnce(&mut MockTransaction) -> &mut MockTransaction,
+    {
+        let mut tx = MockTransaction::default();
+        func_tx(&mut tx);
+        self.transactions.push(tx);
+        self
+    }
+
+    /// Configure the block with `func_block`.
+    pub fn block<Fb>(mut self, func_block: Fb) -> Self
+    where
+        Fb: FnOnce(&mut MockBlock) -> &mut MockBlock,
+    {
+        func_block(&mut self.block);
+        self
+    }
+
+    /// Add the accounts and transaction of
+    /// [`TestContext::simple_ctx_with_bytecode`]: the first account of
+    /// [`static@MOCK_ACCOUNTS`] holds `code`, which is called by a
+    /// transaction from the second one.
+    pub fn bytecode(self, code: Bytecode) -> Self {
+        self.account(|acc| acc.address(MOCK_ACCOUNTS[0]).balance(eth(10)).code(code))
+            .account(|acc| acc.address(MOCK_ACCOUNTS[1]).balance(eth(10)))
+            .tx(|tx| tx.from(MOCK_ACCOUNTS[1]).to(MOCK_ACCOUNTS[0]))
+    }
+
+    /// Set the configuration of the tracer.
+    pub fn logger_config(mut self, logger_config: LoggerConfig) -> Self {
+        self.logger_config = logger_config;
+        self
+    }
+
+    /// Build the block and generate its execution traces.
+    pub fn build(self) -> Result<GethData, Error> {
+        let (chain_id, eth_block, geth_traces) = build_and_trace_block(
+            self.history_hashes.clone(),
+            &self.accounts,
+            self.transactions,
+            vec![],
+            self.block,
+            |block, _txs| block,
+            self.logger_config,
+        )?;
+
+        Ok(GethData {
+            chain_id,
+            history_hashes: self.history_hashes.unwrap_or_default(),
+            eth_block,
+            geth_traces,
+            accounts: self.accounts.into_iter().map(Account::from).collect(),
+        })
+    }
+}
+
 /// Collection of helper functions which contribute to specific routines on the
 /// builder pattern used to construct [`TestContext`]s.
 pub mod helpers {
     use super::*;
-    use crate::MOCK_ACCOUNTS;
 
     /// Generate a simple setup which adds balance to two default accounts from
     /// [`static@MOCK_ACCOUNTS`]:
@@ -204,7 +322,8 @@ pub mod helpers {
 mod tests {
     use eth_types::{address, U256, U64};
 
-    use super::{eth, TestContext};
+    use super::{eth, TestContext, TestContextBuilder};
+    use eth_types::{bytecode, geth_types::GethData};
 
     #[test]
     fn test_nonce() {
@@ -243,4 +362,22 @@ mod tests {
         assert_eq!(block.accounts[0].nonce, U64::from(0));
         assert_eq!(block.accounts[1].nonce, U64::from(100));
     }
+
+    #[test]
+    fn test_builder_matches_test_context() {
+        let code = bytecode! {
+            PUSH1(0x01)
+            PUSH1(0x02)
+            ADD
+            STOP
+        };
+        let expected: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code.clone())
+            .unwrap()
+            .into();
+        let block = TestContextBuilder::new().bytecode(code).build().unwrap();
+
+        assert_eq!(block.accounts, expected.accounts);
+        assert_eq!(block.eth_block, expected.eth_block);
+        assert_eq!(block.geth_traces, expected.geth_traces);
+    }
 }
@@ -86,36 +86,15 @@ impl<const NACC: usize, const NTX: usize, const NWD: usize> TestContext2<NACC, N
         // Build Withdrawal modifiers.
         func_wd(wd_refs);
 
-        // Sets the transaction_idx and nonce after building the tx modifiers. Hence, if user has
-        // overridden these values above using the tx modifiers, that will be ignored.
-        let mut acc_tx_count = vec![0u64; NACC];
-        transactions.iter_mut().enumerate().for_each(|(idx, tx)| {
-            let idx = u64::try_from(idx).expect("Unexpected idx conversion error");
-            tx.transaction_idx(idx);
-            if let Some((pos, from_acc)) = accounts
-                .iter()
-                .find_position(|acc| acc.address == tx.from.address())
-            {
-                if tx.nonce.is_none() {
-                    tx.nonce(from_acc.nonce + acc_tx_count[pos]);
-                }
-                if !tx.invalid {
-                    acc_tx_count[pos] += 1;
-                }
-            }
-        });
-
-        let transactions: Vec<MockTransaction> =
-            transactions.iter_mut().map(|tx| tx.build()).collect();
-
-        // Build Block modifiers
-        let mut block = MockBlock::default();
-        block.transactions.extend_from_slice(&transactions);
-        block.withdrawals.extend_from_slice(&withdrawals);
-        func_block(&mut block, transactions.clone()).build();
-
-        let chain_id = block.chain_id;
-        let block = Block::<Transaction>::from(block);
+        let (chain_id, block, geth_traces) = build_and_trace_block(
+            history_hashes.clone(),
+            &accounts,
+            transactions,
+            withdrawals,
+            MockBlock::default(),
+            func_block,
+            logger_config,
+        )?;
         let accounts: [Account; NACC] = accounts
             .iter()
             .cloned()
@@ -124,39 +103,6 @@ impl<const NACC: usize, const NTX: usize, const NWD: usize> TestContext2<NACC, N
             .try_into()
             .expect("Mismatched acc len");
 
-        let withdrawals: [Withdrawal; NWD] = withdrawals
-            .iter()
-            .cloned()
-            .map(Withdrawal::from)
-            .collect_vec()
-            .try_into()
-            .expect("Mismatched withdrawal len");
-
-        let geth_traces = gen_geth_traces(
-            chain_id,
-            block.clone(),
-            accounts.to_vec(),
-            withdrawals.to_vec(),
-            history_hashes.clone(),
-            logger_config,
-        )?;
-
-        // Don't allow invalid transactions unless explicitly allowed to avoid unrelated tests from
-        // passing simply because the test transaction was incorrectly set up.
-        for (tx, geth_trace) in transactions.iter().zip(geth_traces.iter()) {
-            if !tx.invalid && geth_trace.invalid {
-                panic!(
-                    "{:?}",
-                    Error::TracingError(geth_trace.return_value.clone()).to_string()
-                )
-            }
-            assert_eq!(
-                tx.invalid, geth_trace.invalid,
-                "tx has unexpected invalid status: {}",
-                geth_trace.return_value
-            );
-        }
-
         Ok(Self {
             chain_id,
             accounts,
@@ -196,6 +142,83 @@ impl<const NACC: usize, const NTX: usize, const NWD: usize> TestContext2<NACC, N
     }
 }
 
+/// Build the block of `transactions` sent by `accounts`, applying
+/// `func_block` to `block`, and generate its execution traces.  Returns the
+/// chain id, the block and the traces.
+///
+/// The transaction indices and the missing nonces are set here, so values set
+/// by the caller for the indices are ignored.
+pub(crate) fn build_and_trace_block<Fb>(
+    history_hashes: Option<Vec<Word>>,
+    accounts: &[MockAccount],
+    mut transactions: Vec<MockTransaction>,
+    withdrawals: Vec<MockWithdrawal>,
+    mut block: MockBlock,
+    func_block: Fb,
+    logger_config: LoggerConfig,
+) -> Result<(Word, Block<Transaction>, Vec<GethExecTrace>), Error>
+where
+    Fb: FnOnce(&mut MockBlock, Vec<MockTransaction>) -> &mut MockBlock,
+{
+    // Sets the transaction_idx and nonce after building the tx modifiers. Hence, if user has
+    // overridden these values above using the tx modifiers, that will be ignored.
+    let mut acc_tx_count = vec![0u64; accounts.len()];
+    transactions.iter_mut().enumerate().for_each(|(idx, tx)| {
+        let idx = u64::try_from(idx).expect("Unexpected idx conversion error");
+        tx.transaction_idx(idx);
+        if let Some((pos, from_acc)) = accounts
+            .iter()
+            .find_position(|acc| acc.address == tx.from.address())
+        {
+            if tx.nonce.is_none() {
+                tx.nonce(from_acc.nonce + acc_tx_count[pos]);
+            }
+            if !tx.invalid {
+                acc_tx_count[pos] += 1;
+            }
+        }
+    });
+
+    let transactions: Vec<MockTransaction> = transactions.iter_mut().map(|tx| tx.build()).collect();
+
+    // Build Block modifiers
+    block.transactions.extend_from_slice(&transactions);
+    block.withdrawals.extend_from_slice(&withdrawals);
+    func_block(&mut block, transactions.clone()).build();
+
+    let chain_id = block.chain_id;
+    let block = Block::<Transaction>::from(block);
+
+    let withdrawals: Vec<Withdrawal> = withdrawals.into_iter().map(Withdrawal::from).collect();
+
+    let geth_traces = gen_geth_traces(
+        chain_id,
+        block.clone(),
+        accounts.iter().cloned().map(Account::from).collect(),
+        withdrawals,
+        history_hashes,
+        logger_config,
+    )?;
+
+    // Don't allow invalid transactions unless explicitly allowed to avoid unrelated tests from
+    // passing simply because the test transaction was incorrectly set up.
+    for (tx, geth_trace) in transactions.iter().zip(geth_traces.iter()) {
+        if !tx.invalid && geth_trace.invalid {
+            panic!(
+                "{:?}",
+                Error::TracingError(geth_trace.return_value.clone()).to_string()
+            )
+        }
+        assert_eq!(
+            tx.invalid, geth_trace.invalid,
+            "tx has unexpected invalid status: {}",
+            geth_trace.return_value
+        );
+    }
+
+    Ok((chain_id, block, geth_traces))
+}
+
 /// Generates execution traces for the transactions included in the provided
 /// Block
 pub fn gen_geth_traces(
@@ -0,0 +1,259 @@
+//! Offline block fixtures.
+//!
+//! A [`BlockFixture`] holds everything that is fetched from a node to build
+//! the witness of a block: the block, its execution traces, the history
+//! hashes and the state accessed by the block.  Fixtures are stored as json
+//! files, so that the witness of a block captured once can be built
+//! afterwards without a node.  They are the output of the
+//! [`trace`](crate::pipeline::trace) stage of the pipeline.
+
+use bus_mapping::{
+    circuit_input_builder::{
+        build_state_code_db, get_state_accesses, keccak_fs, keccak_inputs_tx_circuit, Block,
+        BuilderClient, CircuitInputBuilder, DynamicCParams, FeatureConfig, FixedCParams,
+        TransferSample,
+    },
+    rpc::{GethBatchClient, GethClient, HttpBatchTransport, RpcConfig},
+    state_db::CodeDB,
+};
+use eth_types::{
+    geth_types::GethData, Address, EIP1186ProofResponse, GethExecTrace, StorageProof, Transaction,
+    Word, H256,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    ops::Deref,
+    path::Path,
+};
+
+/// Block with the data needed to build its witness without a node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockFixture {
+    /// Chain id of the network of the block
+    pub chain_id: u64,
+    /// Block, with its transactions
+    pub eth_block: eth_types::Block<Transaction>,
+    /// Execution trace of every transaction of the block
+    pub geth_traces: Vec<GethExecTrace>,
+    /// Hashes of the previous blocks
+    pub history_hashes: Vec<Word>,
+    /// State root of the previous block
+    pub prev_state_root: Word,
+    /// Accounts and storage slots accessed by the block, before the block
+    pub proofs: Vec<EIP1186ProofResponse>,
+    /// Codes of the accessed accounts
+    pub codes: HashMap<Address, Vec<u8>>,
+}
+
+impl BlockFixture {
+    /// Fetch the block `block_num` and the state it accesses from the node at
+    /// `rpc_url`, querying the state in JSON-RPC batches.
+    pub async fn fetch(rpc_url: &str, block_num: u64) -> Result<Self, bus_mapping::Error> {
+        let rpc_config = RpcConfig::default();
+        let cli = GethClient::new_http_with_retries(rpc_url, &rpc_config)?;
+        let chain_id = cli.get_chain_id().await?;
+        let state_provider = GethBatchClient::new(HttpBatchTransport::new(rpc_url, &rpc_config)?);
+        let cli = BuilderClient::new(cli, FixedCParams::default())
+            .await?
+            .with_state_provider(state_provider)
+            .with_rpc_config(rpc_config);
+        let (eth_block, geth_traces, history_hashes, prev_state_root) =
+            cli.get_block(block_num).await?;
+        let access_set = get_state_accesses(&eth_block, &geth_traces)?;
+        let (proofs, codes) = cli.get_state(block_num, access_set).await?;
+        Ok(Self {
+            chain_id,
+            eth_block,
+            geth_traces,
+            history_hashes,
+            prev_state_root,
+            proofs,
+            codes,
+        })
+    }
+
+    /// Fixture of a block built without a node, like the blocks of
+    /// `mock::TestContext`.  The proofs of the state have no trie nodes and
+    /// the state root of the previous block is zero.
+    pub fn from_geth_data(geth_data: GethData) -> Self {
+        let proofs = geth_data
+            .accounts
+            .iter()
+            .map(|account| EIP1186ProofResponse {
+                address: account.address,
+                balance: account.balance,
+                code_hash: CodeDB::hash(&account.code),
+                nonce: account.nonce,
+                storage_hash: H256::zero(),
+                account_proof: vec![],
+                storage_proof: account
+                    .storage
+                    .iter()
+                    .map(|(key, value)| StorageProof {
+                        key: *key,
+                        value: *value,
+                        proof: vec![],
+                    })
+                    .collect(),
+            })
+            .collect();
+        let codes = geth_data
+            .accounts
+            .iter()
+            .filter(|account| !account.code.is_empty())
+            .map(|account| (account.address, account.code.to_vec()))
+            .collect();
+        Self {
+            chain_id: geth_data.chain_id.as_u64(),
+            eth_block: geth_data.eth_block,
+            geth_traces: geth_data.geth_traces,
+            history_hashes: geth_data.history_hashes,
+            prev_state_root: Word::zero(),
+            proofs,
+            codes,
+        }
+    }
+
+    /// Load a fixture from a json file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Store the fixture in a json file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = File::create(path)?;
+        Ok(serde_json::to_writer(BufWriter::new(file), self)?)
+    }
+
+    /// Build the circuit inputs of the block, with the circuit parameters
+    /// derived from the block.
+    pub fn circuit_input_builder(
+        &self,
+    ) -> Result<CircuitInputBuilder<FixedCParams>, bus_mapping::Error> {
+        let (sdb, code_db) = build_state_code_db(self.proofs.clone(), self.codes.clone());
+        let block = Block::new(
+            self.chain_id.into(),
+            self.history_hashes.clone(),
+            self.prev_state_root,
+            &self.eth_block,
+        )?;
+        CircuitInputBuilder::new(
+            sdb,
+            code_db,
+            block,
+            DynamicCParams {},
+            FeatureConfig::default(),
+        )
+        .handle_block(&self.eth_block, &self.geth_traces)
+    }
+
+    /// Build the circuit inputs of the block for the circuit parameters
+    /// `params`.
+    pub fn circuit_input_builder_with_params(
+        &self,
+        params: FixedCParams,
+    ) -> Result<CircuitInputBuilder<FixedCParams>, bus_mapping::Error> {
+        let (sdb, code_db) = build_state_code_db(self.proofs.clone(), self.codes.clone());
+        let block = Block::new(
+            self.chain_id.into(),
+            self.history_hashes.clone(),
+            self.prev_state_root,
+            &self.eth_block,
+        )?;
+        let mut builder =
+            CircuitInputBuilder::new(sdb, code_db, block, params, FeatureConfig::default());
+        builder.handle_block(&self.eth_block, &self.geth_traces)?;
+        Ok(builder)
+    }
+
+    /// Usage of each transaction of the block proven alone, in a block
+    /// without withdrawals, on the state left by the previous transactions.
+    /// The bytecode of a transaction and its hashes only count the code it
+    /// accesses, and its proofs are those of the state it accesses before
+    /// the block.
+    pub fn transaction_samples(&self) -> Result<Vec<TransferSample>, bus_mapping::Error> {
+        let (mut sdb, mut code_db) = build_state_code_db(self.proofs.clone(), self.codes.clone());
+        let header = eth_types::Block {
+            transactions: vec![],
+            withdrawals: Some(vec![]),
+            ..self.eth_block.clone()
+        };
+        let mut samples = Vec::with_capacity(self.eth_block.transactions.len());
+        for (tx, geth_trace) in self.eth_block.transactions.iter().zip(&self.geth_traces) {
+            let mut tx = tx.clone();
+            tx.transaction_index = Some(0.into());
+            let eth_block = eth_types::Block {
+                transactions: vec![tx],
+                ..header.clone()
+            };
+            let geth_traces = [geth_trace.clone()];
+            let access_set = get_state_accesses(&eth_block, &geth_traces)?;
+            let block = Block::new(
+                self.chain_id.into(),
+                self.history_hashes.clone(),
+                self.prev_state_root,
+                &eth_block,
+            )?;
+            let builder = CircuitInputBuilder::new(
+                sdb,
+                code_db,
+                block,
+                DynamicCParams {},
+                FeatureConfig::default(),
+            )
+            .handle_block(&eth_block, &geth_traces)?;
+
+            let code_sizes = access_set
+                .code
+                .iter()
+                .filter_map(|address| {
+                    let (_, account) = builder.sdb.get_account(address);
+                    builder
+                        .code_db
+                        .get_from_h256(&account.code_hash)
+                        .map(|code| code.codesize())
+                })
+                .collect::<Vec<_>>();
+            let txs = builder
+                .block
+                .txs
+                .iter()
+                .map(|tx| tx.deref().clone())
+                .collect::<Vec<_>>();
+            let keccak_inputs = keccak_inputs_tx_circuit(&txs, self.chain_id)?;
+            let proofs = access_set
+                .state
+                .iter()
+                .filter_map(|(address, keys)| {
+                    let proof = self.proofs.iter().find(|proof| proof.address == *address)?;
+                    let storage_proofs = proof
+                        .storage_proof
+                        .iter()
+                        .filter(|storage| keys.contains(&storage.key))
+                        .map(|storage| storage.proof.len());
+                    Some(std::iter::once(proof.account_proof.len()).chain(storage_proofs))
+                })
+                .flatten()
+                .collect();
+            samples.push(TransferSample {
+                params: FixedCParams {
+                    max_bytecode: code_sizes.iter().map(|size| size + 1).sum(),
+                    ..builder.circuits_params
+                },
+                keccak_fs: keccak_inputs
+                    .iter()
+                    .chain(&builder.block.sha3_inputs)
+                    .map(|input| keccak_fs(input.len()))
+                    .sum(),
+                code_keccak_fs: code_sizes.iter().map(|size| keccak_fs(*size)).sum(),
+                proofs,
+            });
+            (sdb, code_db) = (builder.sdb, builder.code_db);
+        }
+        Ok(samples)
+    }
+}
@@ -0,0 +1,13 @@
+//! # prover
+//!
+//! Proving of blocks with the [`SuperCircuit`](zkevm_circuits::super_circuit::SuperCircuit),
+//! split in [`pipeline`] stages whose outputs are serializable.  The first
+//! stage captures a block from a node as a [`BlockFixture`](block_fixture::BlockFixture),
+//! from which its witness is built without a node.
+
+#![deny(rustdoc::broken_intra_doc_links)]
+#![deny(missing_docs)]
+#![deny(unsafe_code)]
+
+pub mod block_fixture;
+pub mod pipeline;
@@ -0,0 +1,729 @@
+//! Staged proving pipeline.
+//!
+//! The steps of proving a block are split into stages whose outputs are
+//! serializable, so that an orchestrator can run each stage on a different
+//! machine and retry a stage from the stored output of the previous one:
+//!
+//! 1. [`trace`]: fetch the block, its traces and the state it accesses into a [`BlockFixture`].
+//! 2. [`witness`]: build the witness of the [`SuperCircuit`] for fixed circuit parameters into a
+//!    [`BlockWitness`].
+//! 3. [`prove`]: generate and verify a proof of the [`SuperCircuit`] into a [`BlockProof`].
+//! 4. [`aggregate`]: verify a [`BlockProof`] in the [`RootCircuit`] and prove it with an
+//!    EVM-verifiable transcript into an [`AggregatedProof`].
+//!
+//! The proving keys only depend on the circuit parameters, and are generated
+//! once with [`keygen`] and [`root_keygen`].  [`keygen_with_checkpoints`]
+//! stores the result of each keygen phase on disk, so that an interrupted
+//! keygen resumes from the last completed phase.
+
+use crate::block_fixture::BlockFixture;
+use bus_mapping::circuit_input_builder::{CircuitInputBuilder, FixedCParams};
+use eth_types::{keccak256, H256};
+use halo2_proofs::{
+    circuit::Value,
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine},
+        group::ff::PrimeField,
+    },
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG, ParamsVerifierKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    SerdeFormat,
+};
+use rand_core::OsRng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+use zkevm_circuits::{
+    proof::Proof,
+    root_circuit::{compile, Config, EvmTranscript, NativeLoader, RootCircuit, Shplonk},
+    super_circuit::SuperCircuit,
+    witness::{block_convert, Block},
+};
+
+/// Randomness of the witness blocks built by the [`witness`] stage.
+pub const WITNESS_RANDOMNESS: u64 = 0x100;
+
+/// Error of a pipeline stage.
+#[derive(Debug)]
+pub enum PipelineError {
+    /// Error fetching the block or building its witness
+    Witness(zkevm_circuits::error::Error),
+    /// Error generating the keys, or generating or verifying a proof
+    Proof(halo2_proofs::plonk::Error),
+    /// Error building the RootCircuit from a block proof
+    Aggregation(String),
+    /// Stored instance value which is not a field element
+    InvalidInstance,
+    /// Error reading or writing a keygen checkpoint
+    Io(io::Error),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+impl From<bus_mapping::Error> for PipelineError {
+    fn from(err: bus_mapping::Error) -> Self {
+        Self::Witness(err.into())
+    }
+}
+
+impl From<zkevm_circuits::error::Error> for PipelineError {
+    fn from(err: zkevm_circuits::error::Error) -> Self {
+        Self::Witness(err)
+    }
+}
+
+impl From<halo2_proofs::plonk::Error> for PipelineError {
+    fn from(err: halo2_proofs::plonk::Error) -> Self {
+        Self::Proof(err)
+    }
+}
+
+impl From<io::Error> for PipelineError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Instance columns, with each value in its little-endian representation.
+pub type InstanceBytes = Vec<Vec<[u8; 32]>>;
+
+fn instance_to_bytes(instance: &[Vec<Fr>]) -> InstanceBytes {
+    instance
+        .iter()
+        .map(|column| column.iter().map(|value| value.to_repr()).collect())
+        .collect()
+}
+
+fn instance_from_bytes(instance: &InstanceBytes) -> Result<Vec<Vec<Fr>>, PipelineError> {
+    instance
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|bytes| {
+                    Option::from(Fr::from_repr(*bytes)).ok_or(PipelineError::InvalidInstance)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Load the output of a stage from a json file.
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> io::Result<T> {
+    let file = File::open(path)?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Store the output of a stage in a json file.
+pub fn save<T: Serialize>(path: impl AsRef<Path>, output: &T) -> io::Result<()> {
+    let file = File::create(path)?;
+    Ok(serde_json::to_writer(BufWriter::new(file), output)?)
+}
+
+/// Output of the [`witness`] stage.
+///
+/// The [`SuperCircuit`] is assigned from the witness block when needed, which
+/// doesn't replay the block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockWitness {
+    /// Witness of the block, built for its `circuits_params`
+    pub block: Block<Fr>,
+    /// Degree of the [`SuperCircuit`]
+    pub degree: u32,
+    /// Public inputs of the [`SuperCircuit`]
+    pub instance: InstanceBytes,
+}
+
+impl BlockWitness {
+    /// Number of the block
+    pub fn block_num(&self) -> u64 {
+        self.block.context.number.low_u64()
+    }
+
+    /// Circuit parameters the witness is built for
+    pub fn params(&self) -> FixedCParams {
+        self.block.circuits_params
+    }
+
+    /// Build the [`SuperCircuit`] of the block with its public inputs.
+    pub fn circuit(&self) -> (SuperCircuit<Fr>, Vec<Vec<Fr>>) {
+        let (_, circuit, instance) = SuperCircuit::<Fr>::build_from_witness_block(&self.block);
+        (circuit, instance)
+    }
+}
+
+/// Output of the [`prove`] stage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockProof {
+    /// Number of the proven block
+    pub block_num: u64,
+    /// Degree of the [`SuperCircuit`]
+    pub degree: u32,
+    /// Public inputs of the [`SuperCircuit`]
+    pub instance: InstanceBytes,
+    /// Proof with a Poseidon transcript, verifiable by the [`RootCircuit`]
+    pub proof: Vec<u8>,
+}
+
+/// Output of the [`aggregate`] stage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregatedProof {
+    /// Number of the proven block
+    pub block_num: u64,
+    /// Public inputs of the [`RootCircuit`]: the public inputs of the
+    /// [`SuperCircuit`] followed by the accumulator limbs
+    pub instance: InstanceBytes,
+    /// Proof with a Keccak transcript, verifiable by the EVM
+    pub proof: Vec<u8>,
+}
+
+/// Trace stage: fetch the block `block_num` and the state it accesses from the
+/// node at `rpc_url`.
+pub async fn trace(rpc_url: &str, block_num: u64) -> Result<BlockFixture, PipelineError> {
+    Ok(BlockFixture::fetch(rpc_url, block_num).await?)
+}
+
+/// Witness stage: build the witness of the block for the circuit parameters
+/// `params`, which must be the ones the proving key was generated for.
+pub fn witness(
+    fixture: &BlockFixture,
+    params: FixedCParams,
+) -> Result<BlockWitness, PipelineError> {
+    let builder = fixture.circuit_input_builder_with_params(params)?;
+    witness_from_builder(&builder)
+}
+
+/// Witness stage for a block already handled by `builder`.
+pub fn witness_from_builder(
+    builder: &CircuitInputBuilder<FixedCParams>,
+) -> Result<BlockWitness, PipelineError> {
+    let mut block = block_convert(builder)?;
+    block.randomness = Fr::from(WITNESS_RANDOMNESS);
+    let (degree, _, instance) = SuperCircuit::<Fr>::build_from_witness_block(&block);
+    Ok(BlockWitness {
+        block,
+        degree,
+        instance: instance_to_bytes(&instance),
+    })
+}
+
+/// Generate the proving key of the [`SuperCircuit`] for the parameters of
+/// `witness`.  Any block witness built with the same parameters can be used.
+pub fn keygen(
+    general_params: &ParamsKZG<Bn256>,
+    witness: &BlockWitness,
+) -> Result<ProvingKey<G1Affine>, PipelineError> {
+    let (circuit, _) = witness.circuit();
+    let verifying_key = keygen_vk(general_params, &circuit)?;
+    Ok(keygen_pk(general_params, verifying_key, &circuit)?)
+}
+
+/// Serialization format of the keygen checkpoints
+const CHECKPOINT_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+/// Write a checkpoint to `path` through a temporary file, so that an interrupted
+/// write never leaves a truncated checkpoint behind.
+fn write_checkpoint(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    write(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+/// Reader of a checkpoint, which tells the errors of the file apart from the
+/// errors decoding its content.
+struct CheckpointReader {
+    reader: BufReader<File>,
+    file_error: bool,
+}
+
+impl Read for CheckpointReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf).map_err(|err| {
+            if err.kind() != io::ErrorKind::Interrupted {
+                self.file_error = true;
+            }
+            err
+        })
+    }
+}
+
+/// Read the checkpoint at `path`.  A missing checkpoint is `None`, and so is a
+/// checkpoint whose content can't be decoded, which is stale.  Errors reading
+/// the file are returned.
+fn read_checkpoint<T>(
+    path: &Path,
+    read: impl FnOnce(&mut CheckpointReader) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut reader = CheckpointReader {
+        reader: BufReader::new(file),
+        file_error: false,
+    };
+    match read(&mut reader) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if reader.file_error => Err(err),
+        Err(err) => {
+            log::warn!("stale keygen checkpoint {}: {}", path.display(), err);
+            Ok(None)
+        }
+    }
+}
+
+/// Path of the checkpoint of the keygen `phase` of the circuit `circuit_id`
+/// for the parameters of degree `k`.
+fn checkpoint_path(checkpoint_dir: &Path, phase: &str, k: u32, circuit_id: &str) -> PathBuf {
+    let key = H256(keccak256(format!("{}/{}", k, circuit_id)));
+    checkpoint_dir.join(format!("{}_{:x}.bin", phase, key))
+}
+
+/// Generate the proving key of `circuit`, checkpointing each phase in
+/// `checkpoint_dir`.
+///
+/// Keygen runs in two phases: the verifying key, which commits to the fixed
+/// columns and the permutation, then the proving key, which is built from
+/// the verifying key.  The result of each phase is stored once it completes,
+/// so that a keygen interrupted in the proving key phase resumes from the
+/// stored verifying key, and a completed keygen reads the proving key back.
+/// A phase is atomic: halo2 can't resume `keygen_pk` itself.
+///
+/// The checkpoints are found by `circuit_id` and the degree of
+/// `general_params`, without synthesizing the circuit, so `circuit_id` must
+/// change whenever the constraints or the fixed assignment of the circuit
+/// may change, e.g. by including the circuit parameters and the version of
+/// the circuits.  A checkpoint that can't be decoded is generated again,
+/// while an error reading it is returned.
+pub fn keygen_with_checkpoints<C: Circuit<Fr>>(
+    general_params: &ParamsKZG<Bn256>,
+    circuit: &C,
+    circuit_id: &str,
+    checkpoint_dir: impl AsRef<Path>,
+) -> Result<ProvingKey<G1Affine>, PipelineError> {
+    let checkpoint_dir = checkpoint_dir.as_ref();
+    fs::create_dir_all(checkpoint_dir)?;
+    let k = general_params.k();
+
+    let pk_path = checkpoint_path(checkpoint_dir, "pk", k, circuit_id);
+    if let Some(proving_key) = read_checkpoint(&pk_path, |reader| {
+        ProvingKey::read::<_, C>(reader, CHECKPOINT_FORMAT, circuit.params())
+    })? {
+        return Ok(proving_key);
+    }
+
+    let vk_path = checkpoint_path(checkpoint_dir, "vk", k, circuit_id);
+    let verifying_key = match read_checkpoint(&vk_path, |reader| {
+        VerifyingKey::read::<_, C>(reader, CHECKPOINT_FORMAT, circuit.params())
+    })? {
+        Some(verifying_key) => verifying_key,
+        None => {
+            let verifying_key = keygen_vk(general_params, circuit)?;
+            write_checkpoint(&vk_path, |writer| {
+                verifying_key.write(writer, CHECKPOINT_FORMAT)
+            })?;
+            verifying_key
+        }
+    };
+
+    let proving_key = keygen_pk(general_params, verifying_key, circuit)?;
+    write_checkpoint(&pk_path, |writer| {
+        proving_key.write(writer, CHECKPOINT_FORMAT)
+    })?;
+    Ok(proving_key)
+}
+
+/// Prove stage: generate a proof of the [`SuperCircuit`] of the block and
+/// verify it.
+pub fn prove(
+    general_params: &ParamsKZG<Bn256>,
+    proving_key: &ProvingKey<G1Affine>,
+    witness: &BlockWitness,
+) -> Result<BlockProof, PipelineError> {
+    let (circuit, instance) = witness.circuit();
+    let proof = Proof::create(general_params, proving_key, circuit, &instance, OsRng)?;
+    proof.verify_with_vk(general_params, proving_key.get_vk(), &instance)?;
+    Ok(BlockProof {
+        block_num: witness.block_num(),
+        degree: witness.degree,
+        instance: witness.instance.clone(),
+        proof: proof.to_bytes(),
+    })
+}
+
+/// Verify a proof generated by the [`prove`] stage.
+pub fn verify(
+    general_params: &ParamsKZG<Bn256>,
+    verifying_key: &VerifyingKey<G1Affine>,
+    block_proof: &BlockProof,
+) -> Result<(), PipelineError> {
+    let instance = instance_from_bytes(&block_proof.instance)?;
+    Ok(Proof::from_bytes(&block_proof.proof).verify_with_vk(
+        general_params,
+        verifying_key,
+        &instance,
+    )?)
+}
+
+/// Generate the proving key of the [`RootCircuit`] aggregating the proofs of
+/// the [`SuperCircuit`] with verifying key `verifying_key`.
+pub fn root_keygen(
+    general_params: &ParamsKZG<Bn256>,
+    verifying_key: &VerifyingKey<G1Affine>,
+    root_params: &ParamsKZG<Bn256>,
+    block_proof: &BlockProof,
+) -> Result<ProvingKey<G1Affine>, PipelineError> {
+    let protocol = compile(
+        general_params,
+        verifying_key,
+        Config::kzg().with_num_instance(block_proof.instance.iter().map(Vec::len).collect()),
+    );
+    let circuit = RootCircuit::<Bn256, Shplonk<_>>::new(
+        general_params,
+        &protocol,
+        Value::unknown(),
+        Value::unknown(),
+    )
+    .map_err(|err| PipelineError::Aggregation(format!("{:?}", err)))?;
+    let root_verifying_key = keygen_vk(root_params, &circuit)?;
+    Ok(keygen_pk(root_params, root_verifying_key, &circuit)?)
+}
+
+/// Aggregate stage: verify a block proof in the [`RootCircuit`] and prove it
+/// with a Keccak transcript.
+pub fn aggregate(
+    general_params: &ParamsKZG<Bn256>,
+    verifying_key: &VerifyingKey<G1Affine>,
+    root_params: &ParamsKZG<Bn256>,
+    root_proving_key: &ProvingKey<G1Affine>,
+    block_proof: &BlockProof,
+) -> Result<AggregatedProof, PipelineError> {
+    let instance = instance_from_bytes(&block_proof.instance)?;
+    let protocol = compile(
+        general_params,
+        verifying_key,
+        Config::kzg().with_num_instance(instance.iter().map(Vec::len).collect()),
+    );
+    let circuit = RootCircuit::<Bn256, Shplonk<_>>::new(
+        general_params,
+        &protocol,
+        Value::known(&instance),
+        Value::known(block_proof.proof.as_slice()),
+    )
+    .map_err(|err| PipelineError::Aggregation(format!("{:?}", err)))?;
+    let root_instance = circuit.instance();
+    let root_instance_refs: Vec<&[Fr]> = root_instance.iter().map(Vec::as_slice).collect();
+
+    let mut transcript = EvmTranscript::<_, NativeLoader, _, _>::new(vec![]);
+    create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _, _, _>(
+        root_params,
+        root_proving_key,
+        &[circuit],
+        &[&root_instance_refs],
+        OsRng,
+        &mut transcript,
+    )?;
+    let proof = transcript.finalize();
+
+    let verifier_params: ParamsVerifierKZG<Bn256> = root_params.verifier_params().clone();
+    let mut verifier_transcript = EvmTranscript::<_, NativeLoader, _, _>::new(proof.as_slice());
+    verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, _, _, _>(
+        &verifier_params,
+        root_proving_key.get_vk(),
+        SingleStrategy::new(root_params),
+        &[&root_instance_refs],
+        &mut verifier_transcript,
+    )?;
+
+    Ok(AggregatedProof {
+        block_num: block_proof.block_num,
+        instance: instance_to_bytes(&root_instance),
+        proof,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use eth_types::{address, bytecode, geth_types::GethData, Word};
+    use ethers_signers::{LocalWallet, Signer};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{Column, ConstraintSystem, Fixed},
+    };
+    use mock::{TestContext, MOCK_CHAIN_ID};
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+    use std::collections::HashMap;
+    use zkevm_circuits::proof::circuit_digest;
+
+    const PARAMS: FixedCParams = FixedCParams {
+        max_txs: 1,
+        max_withdrawals: 5,
+        max_calldata: 32,
+        max_rws: 256,
+        max_copy_rows: 256,
+        max_exp_steps: 256,
+        max_bytecode: 512,
+        max_evm_rows: 0,
+        max_keccak_rows: 0,
+        max_vertical_circuit_rows: 0,
+    };
+
+    /// Degree of the RootCircuit aggregating a SuperCircuit proof
+    const ROOT_DEGREE: u32 = 26;
+
+    fn block_1tx() -> GethData {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let wallet_a = LocalWallet::new(&mut rng).with_chain_id(MOCK_CHAIN_ID.as_u64());
+        let addr_a = wallet_a.address();
+        let addr_b = address!("0x000000000000000000000000000000000000BBBB");
+        let wallets = HashMap::from([(addr_a, wallet_a)]);
+
+        let mut block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(addr_b)
+                    .balance(Word::from(1u64 << 20))
+                    .code(bytecode! { GAS STOP });
+                accs[1].address(addr_a).balance(Word::from(1u64 << 20));
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .gas(Word::from(1_000_000u64));
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        block.sign(&wallets);
+        block
+    }
+
+    /// Serialize and deserialize the output of a stage, as an orchestrator
+    /// passing it to the next stage would.
+    fn round_trip<T: Serialize + DeserializeOwned>(output: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(output).unwrap()).unwrap()
+    }
+
+    /// Output of the trace stage for the mock block, which stands for a block
+    /// fetched from a node.
+    fn block_fixture() -> BlockFixture {
+        round_trip(&BlockFixture::from_geth_data(block_1tx()))
+    }
+
+    fn block_witness() -> BlockWitness {
+        round_trip(&witness(&block_fixture(), PARAMS).unwrap())
+    }
+
+    fn block_proof(witness: &BlockWitness) -> (ParamsKZG<Bn256>, ProvingKey<G1Affine>, BlockProof) {
+        let general_params = ParamsKZG::<Bn256>::setup(witness.degree, OsRng);
+        let proving_key = keygen(&general_params, witness).unwrap();
+        let block_proof = round_trip(&prove(&general_params, &proving_key, witness).unwrap());
+        (general_params, proving_key, block_proof)
+    }
+
+    #[test]
+    fn instance_bytes_round_trip() {
+        let instance = vec![
+            vec![Fr::from(1), -Fr::from(1)],
+            vec![],
+            vec![Fr::from(0xcafe)],
+        ];
+        assert_eq!(
+            instance_from_bytes(&round_trip(&instance_to_bytes(&instance))).unwrap(),
+            instance
+        );
+        assert!(matches!(
+            instance_from_bytes(&vec![vec![[0xff; 32]]]),
+            Err(PipelineError::InvalidInstance)
+        ));
+    }
+
+    #[test]
+    fn pipeline_witness_round_trip() {
+        let witness = block_witness();
+        assert_eq!(witness.block_num(), 0xcafe);
+        assert_eq!(witness.params().max_rws, PARAMS.max_rws);
+
+        let (_, instance) = witness.circuit();
+        assert_eq!(instance, instance_from_bytes(&witness.instance).unwrap());
+    }
+
+    #[test]
+    fn pipeline_prove_round_trip() {
+        let witness = block_witness();
+        let (general_params, proving_key, block_proof) = block_proof(&witness);
+        assert_eq!(block_proof.block_num, 0xcafe);
+        assert_eq!(block_proof.instance, witness.instance);
+        verify(&general_params, proving_key.get_vk(), &block_proof).unwrap();
+
+        let mut wrong_proof = block_proof;
+        wrong_proof.instance[0][0] = instance_to_bytes(&[vec![Fr::from(0xbad)]])[0][0];
+        assert!(verify(&general_params, proving_key.get_vk(), &wrong_proof).is_err());
+    }
+
+    /// Circuit with a single fixed cell, whose value changes the circuit
+    /// digest.
+    #[derive(Clone)]
+    struct FixedValueCircuit(u64);
+
+    impl Circuit<Fr> for FixedValueCircuit {
+        type Config = Column<Fixed>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            meta.fixed_column()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), halo2_proofs::plonk::Error> {
+            layouter.assign_region(
+                || "fixed value",
+                |mut region| {
+                    region.assign_fixed(
+                        || "value",
+                        config,
+                        0,
+                        || Value::known(Fr::from(self.0)),
+                    )?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn keygen_checkpoints_resume_and_corrupt() {
+        let general_params = ParamsKZG::<Bn256>::setup(4, OsRng);
+        let checkpoint_dir =
+            std::env::temp_dir().join(format!("keygen_checkpoints_{}", std::process::id()));
+        let digest = |circuit: &FixedValueCircuit| {
+            circuit_digest(&keygen_vk(&general_params, circuit).unwrap())
+        };
+        let modified = |path: &Path| fs::metadata(path).unwrap().modified().unwrap();
+        let checkpointed_keygen = |circuit: &FixedValueCircuit, circuit_id: &str| {
+            keygen_with_checkpoints(&general_params, circuit, circuit_id, &checkpoint_dir)
+        };
+        let (circuit_a, circuit_b) = (FixedValueCircuit(1), FixedValueCircuit(2));
+        assert_ne!(digest(&circuit_a), digest(&circuit_b));
+        let vk_path = checkpoint_path(&checkpoint_dir, "vk", 4, "a");
+        let pk_path = checkpoint_path(&checkpoint_dir, "pk", 4, "a");
+
+        // Both phases are stored, and a completed keygen is read back.
+        let proving_key = checkpointed_keygen(&circuit_a, "a").unwrap();
+        assert_eq!(circuit_digest(proving_key.get_vk()), digest(&circuit_a));
+        let (vk_written_at, pk_written_at) = (modified(&vk_path), modified(&pk_path));
+        let pk_bytes = fs::read(&pk_path).unwrap();
+        let proving_key = checkpointed_keygen(&circuit_a, "a").unwrap();
+        assert_eq!(circuit_digest(proving_key.get_vk()), digest(&circuit_a));
+        assert_eq!(modified(&pk_path), pk_written_at);
+
+        // A keygen interrupted in the proving key phase resumes from the
+        // stored verifying key.
+        fs::remove_file(&pk_path).unwrap();
+        let proving_key = checkpointed_keygen(&circuit_a, "a").unwrap();
+        assert_eq!(circuit_digest(proving_key.get_vk()), digest(&circuit_a));
+        assert_eq!(modified(&vk_path), vk_written_at);
+        assert_eq!(fs::read(&pk_path).unwrap(), pk_bytes);
+
+        // Checkpoints are found by circuit id: another id doesn't reuse them.
+        let proving_key = checkpointed_keygen(&circuit_b, "b").unwrap();
+        assert_eq!(circuit_digest(proving_key.get_vk()), digest(&circuit_b));
+
+        // Truncated checkpoints, as left by a full disk, are generated again.
+        for path in [&vk_path, &pk_path] {
+            let bytes = fs::read(path).unwrap();
+            fs::write(path, &bytes[..bytes.len() / 2]).unwrap();
+        }
+        let proving_key = checkpointed_keygen(&circuit_a, "a").unwrap();
+        assert_eq!(circuit_digest(proving_key.get_vk()), digest(&circuit_a));
+        assert_eq!(fs::read(&pk_path).unwrap(), pk_bytes);
+
+        // A checkpoint that can't be read is an error, not a stale checkpoint.
+        fs::remove_file(&pk_path).unwrap();
+        fs::create_dir(&pk_path).unwrap();
+        assert!(matches!(
+            checkpointed_keygen(&circuit_a, "a"),
+            Err(PipelineError::Io(_))
+        ));
+
+        fs::remove_dir_all(&checkpoint_dir).unwrap();
+    }
+
+    #[test]
+    fn pipeline_trace_round_trip() {
+        let fixture = block_fixture();
+        assert_eq!(fixture.chain_id, MOCK_CHAIN_ID.as_u64());
+        assert_eq!(fixture.geth_traces.len(), 1);
+        let builder = fixture.circuit_input_builder_with_params(PARAMS).unwrap();
+        assert_eq!(builder.block.txs().len(), 1);
+    }
+
+    /// Run every stage on the mock block, passing the serialized output of
+    /// each stage to the next one.
+    #[ignore = "Due to high memory requirement"]
+    #[test]
+    fn pipeline_all_stages_round_trip() {
+        let witness = block_witness();
+        let (general_params, proving_key, block_proof) = block_proof(&witness);
+        verify(&general_params, proving_key.get_vk(), &block_proof).unwrap();
+        let root_params = ParamsKZG::<Bn256>::setup(ROOT_DEGREE, OsRng);
+        let root_proving_key = root_keygen(
+            &general_params,
+            proving_key.get_vk(),
+            &root_params,
+            &block_proof,
+        )
+        .unwrap();
+        let aggregated_proof: AggregatedProof = round_trip(
+            &aggregate(
+                &general_params,
+                proving_key.get_vk(),
+                &root_params,
+                &root_proving_key,
+                &block_proof,
+            )
+            .unwrap(),
+        );
+        assert_eq!(aggregated_proof.block_num, 0xcafe);
+        assert_eq!(
+            aggregated_proof.instance[..block_proof.instance.len()],
+            block_proof.instance[..]
+        );
+    }
+}
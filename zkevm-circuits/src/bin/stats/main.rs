@@ -26,7 +26,7 @@ use zkevm_circuits::{
         step::ExecutionState,
         EvmCircuit, EvmCircuitConfig, EvmCircuitConfigArgs,
     },
-    exp_circuit::ExpCircuitConfig,
+    exp_circuit::{ExpCircuitConfig, ExpCircuitConfigArgs},
     keccak_circuit::{KeccakCircuitConfig, KeccakCircuitConfigArgs},
     pi_circuit::{PiCircuitConfig, PiCircuitConfigArgs},
     state_circuit::{StateCircuitConfig, StateCircuitConfigArgs},
@@ -295,6 +295,7 @@ fn record_stats<F: eth_types::Field>(
             tx_table: tx_table.clone(),
             wd_table,
             keccak_table: keccak_table.clone(),
+            u16_table,
             challenges: challenges.clone(),
         },
     );
@@ -341,7 +342,13 @@ fn record_stats<F: eth_types::Field>(
         },
     );
     stats.record("state", meta);
-    let exp_circuit = ExpCircuitConfig::new(meta, exp_table);
+    let exp_circuit = ExpCircuitConfig::new(
+        meta,
+        ExpCircuitConfigArgs {
+            exp_table,
+            u8_table,
+        },
+    );
     stats.record("exp", meta);
     let evm_circuit = EvmCircuitConfig::new(
         meta,
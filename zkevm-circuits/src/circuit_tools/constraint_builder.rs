@@ -140,6 +140,8 @@ pub struct ConstraintBuilder<F, C: CellType> {
     pub stored_expressions: HashMap<usize, Vec<StoredExpression<F, C>>>,
     /// CellManager
     pub cell_manager: Option<CellManager<F, C>>,
+    /// Cell type of the byte cells, instead of `C::byte_type()`
+    pub byte_type: Option<C>,
     /// Disable macro-generated description for constraints & lookups
     /// for graph display
     pub disable_description: bool,
@@ -151,6 +153,10 @@ pub struct ConstraintBuilder<F, C: CellType> {
     pub state_context: Vec<Expression<F>>,
     /// state constraints start
     pub region_constraints_start: usize,
+    /// Extract the common subexpressions of the constraints of each region
+    pub extract_common_subexpressions: bool,
+    /// Savings of the extraction of common subexpressions, per region
+    pub subexpression_reports: Vec<SubexpressionReport>,
 }
 
 impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
@@ -168,12 +174,15 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
             tables: HashMap::new(),
             lookups: Vec::new(),
             cell_manager,
+            byte_type: None,
             disable_description: false,
             stored_expressions: HashMap::new(),
             region_id: 0,
             lookup_challenge,
             state_context: Vec::new(),
             region_constraints_start: 0,
+            extract_common_subexpressions: false,
+            subexpression_reports: Vec::new(),
         }
     }
 
@@ -187,6 +196,7 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
         self.region_id = 0;
         self.state_context.clear();
         self.region_constraints_start = 0;
+        self.subexpression_reports.clear();
         if let Some(cell_manager) = &mut self.cell_manager {
             cell_manager.restart();
         }
@@ -200,6 +210,10 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
         self.max_global_degree = max_degree;
     }
 
+    pub(crate) fn set_extract_common_subexpressions(&mut self, extract: bool) {
+        self.extract_common_subexpressions = extract;
+    }
+
     pub(crate) fn push_region(&mut self, region_id: usize, height: usize) {
         assert!(region_id != 0);
         self.region_id = region_id;
@@ -213,6 +227,9 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
     }
 
     pub(crate) fn pop_region(&mut self) {
+        if self.extract_common_subexpressions {
+            self.extract_subexpressions();
+        }
         // Apply the region condition to all constraints added in this region
         let condition = get_condition_expr(&self.state_context);
         for idx in self.region_constraints_start..self.constraints.len() {
@@ -333,7 +350,9 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
 
     pub(crate) fn query_bytes<const N: usize>(&mut self) -> [Cell<F>; N] {
         self.query_cells_dyn(
-            C::byte_type().expect("No byte type for this CellManager"),
+            self.byte_type
+                .or_else(C::byte_type)
+                .expect("No byte type for this CellManager"),
             N,
         )
         .try_into()
@@ -615,12 +634,171 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
         }
     }
 
+    /// Replaces the subexpressions repeated in the constraints of the current
+    /// region by stored expressions, when it makes the constraints smaller or
+    /// lowers the degree of the constraints of the highest degree.
+    /// The selector products of nested conditions are typical candidates.
+    fn extract_subexpressions(&mut self) {
+        let start = self.region_constraints_start;
+        let end = self.constraints.len();
+        // The constraints of the stored expressions define their cells
+        let stored_names = self
+            .stored_expressions
+            .get(&self.region_id)
+            .map(|stored| stored.iter().map(|s| s.name.clone()).collect_vec())
+            .unwrap_or_default();
+        let indices = (start..end)
+            .filter(|idx| !stored_names.iter().any(|n| n == self.constraints[*idx].0))
+            .collect_vec();
+        if indices.is_empty() {
+            return;
+        }
+        let stats = |constraints: &[(&'static str, Expression<F>)]| {
+            constraints
+                .iter()
+                .fold((0, 0), |(degree, size), (_, expr)| {
+                    (degree.max(expr.degree()), size + expression_size(expr))
+                })
+        };
+        let (degree_before, size_before) = stats(&self.constraints[start..end]);
+
+        let mut num_extracted = 0;
+        loop {
+            let max_degree = indices
+                .iter()
+                .map(|idx| self.constraints[*idx].1.degree())
+                .max()
+                .unwrap_or_default();
+            let mut counts = HashMap::new();
+            for idx in indices.iter() {
+                let expr = &self.constraints[*idx].1;
+                count_subexpressions(expr, expr.degree() == max_degree, &mut counts);
+            }
+            let best = counts
+                .into_values()
+                .filter(|(expr, count, in_max_degree)| {
+                    let size = expression_size(expr);
+                    // A stored expression costs a cell and a constraint `cell - expr`
+                    *count >= 2 && (count * (size - 1) > size + 2 || *in_max_degree)
+                })
+                .max_by_key(|(expr, count, _)| (expr.degree(), count * expression_size(expr)));
+            let expr = match best {
+                Some((expr, _, _)) => expr,
+                None => break,
+            };
+            let expr_id = expr.identifier();
+            let cell_type = C::storage_for_expr(&expr);
+            let cell = self.store_expression("common subexpression", expr, cell_type, None);
+            for idx in indices.iter() {
+                let constraint = &mut self.constraints[*idx].1;
+                *constraint = replace_subexpression(constraint, &expr_id, &cell);
+            }
+            num_extracted += 1;
+        }
+
+        let (degree_after, size_after) = stats(&self.constraints[start..]);
+        self.subexpression_reports.push(SubexpressionReport {
+            region_id: self.region_id,
+            num_extracted,
+            degree: (degree_before, degree_after),
+            size: (size_before, size_after),
+        });
+    }
+
     pub(crate) fn print_stats(&self) {
         let mut expressions = self.constraints.clone();
         expressions.sort_by(|a, b| a.1.degree().cmp(&b.1.degree()));
         for (name, expr) in expressions.iter() {
             println!("'{}': {}", name, expr.degree());
         }
+        for report in self.subexpression_reports.iter() {
+            println!("{}", report);
+        }
+    }
+}
+
+/// Savings of the extraction of the common subexpressions of a region
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubexpressionReport {
+    /// Region of the constraints
+    pub region_id: usize,
+    /// Number of subexpressions replaced by stored expressions
+    pub num_extracted: usize,
+    /// Max degree of the constraints before and after the extraction
+    pub degree: (usize, usize),
+    /// Number of nodes of the constraints before and after the extraction,
+    /// including the constraints of the stored expressions
+    pub size: (usize, usize),
+}
+
+impl std::fmt::Display for SubexpressionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "region {}: {} common subexpressions extracted, degree {} -> {}, size {} -> {}",
+            self.region_id,
+            self.num_extracted,
+            self.degree.0,
+            self.degree.1,
+            self.size.0,
+            self.size.1
+        )
+    }
+}
+
+/// Number of nodes of `expr`
+fn expression_size<F: Field>(expr: &Expression<F>) -> usize {
+    match expr {
+        Expression::Negated(a) | Expression::Scaled(a, _) => 1 + expression_size(a),
+        Expression::Sum(a, b) | Expression::Product(a, b) => {
+            1 + expression_size(a) + expression_size(b)
+        }
+        _ => 1,
+    }
+}
+
+/// Count the occurrences of the compound subexpressions of `expr` of degree 2
+/// or more, recording whether they occur in a constraint of the highest
+/// degree.
+fn count_subexpressions<F: Field>(
+    expr: &Expression<F>,
+    in_max_degree: bool,
+    counts: &mut HashMap<String, (Expression<F>, usize, bool)>,
+) {
+    let children = match expr {
+        Expression::Negated(a) | Expression::Scaled(a, _) => vec![a],
+        Expression::Sum(a, b) | Expression::Product(a, b) => vec![a, b],
+        _ => return,
+    };
+    if expr.degree() >= 2 {
+        let entry = counts
+            .entry(expr.identifier())
+            .or_insert_with(|| (expr.clone(), 0, false));
+        entry.1 += 1;
+        entry.2 |= in_max_degree;
+    }
+    for child in children {
+        count_subexpressions(child, in_max_degree, counts);
+    }
+}
+
+/// Replace the subexpressions of `expr` with the identifier `expr_id` by
+/// `replacement`
+fn replace_subexpression<F: Field>(
+    expr: &Expression<F>,
+    expr_id: &str,
+    replacement: &Expression<F>,
+) -> Expression<F> {
+    if expr.degree() >= 2 && expr.identifier() == expr_id {
+        return replacement.clone();
+    }
+    let replace = |a: &Expression<F>| Box::new(replace_subexpression(a, expr_id, replacement));
+    match expr {
+        Expression::Negated(a) => Expression::Negated(replace(a)),
+        Expression::Scaled(a, v) => Expression::Scaled(replace(a), *v),
+        Expression::Sum(a, b) => Expression::Sum(replace(a), replace(b)),
+        Expression::Product(a, b) => Expression::Product(replace(a), replace(b)),
+        _ => expr.clone(),
     }
 }
 
@@ -1484,3 +1662,48 @@ macro_rules! circuit {
         $content
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_tools::cell_manager::DefaultCellType;
+    use halo2_proofs::{halo2curves::bn256::Fr, poly::Rotation};
+
+    #[test]
+    fn extract_common_subexpressions() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let columns = [(); 4].map(|_| meta.advice_column());
+        let mut cb = ConstraintBuilder::<Fr, DefaultCellType>::new(4, None, None);
+        let mut cell_manager = CellManager::new(1, 0);
+        cell_manager.add_columns(
+            &mut meta,
+            &mut cb,
+            DefaultCellType::StoragePhase1,
+            0,
+            false,
+            4,
+        );
+        cb.set_cell_manager(cell_manager);
+        cb.set_extract_common_subexpressions(true);
+
+        meta.create_gate("test", |meta| {
+            let [a, b, c, d] = columns.map(|column| meta.query_advice(column, Rotation::cur()));
+            cb.push_region(1, 1);
+            // Nested conditions repeat the same selector product
+            let condition = a.clone() * b.clone() * c.clone();
+            cb.require_zero("a", condition.clone() * d.clone());
+            cb.require_zero("b", condition.clone() * (d.clone() - 1.expr()));
+            cb.require_zero("c", condition * (d - 2.expr()));
+            cb.pop_region();
+            cb.build_constraints()
+        });
+
+        let report = &cb.subexpression_reports[0];
+        assert_eq!(report.num_extracted, 1);
+        assert_eq!(report.degree, (4, 3));
+        assert!(report.size.1 < report.size.0);
+        // The product is stored once and its constraint is kept
+        assert_eq!(cb.get_stored_expressions(1).len(), 1);
+        assert_eq!(meta.degree(), 3);
+    }
+}
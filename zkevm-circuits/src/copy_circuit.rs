@@ -1,7 +1,6 @@
 //! The Copy circuit implements constraints and lookups for read-write steps for
 //! copied bytes while execution opcodes such as CALLDATACOPY, CODECOPY, LOGS,
 //! etc.
-pub(crate) mod util;
 
 #[cfg(any(test, feature = "test-circuits"))]
 mod dev;
@@ -43,9 +42,9 @@ use itertools::Itertools;
 use std::marker::PhantomData;
 
 // Rows to enable but not use, that can be queried safely by the last event.
-const UNUSED_ROWS: usize = 2;
+pub(crate) const UNUSED_ROWS: usize = 2;
 // Rows to disable, so they do not query into Halo2 reserved rows.
-const DISABLED_ROWS: usize = 2;
+pub(crate) const DISABLED_ROWS: usize = 2;
 
 /// The rw table shared between evm circuit and state circuit
 #[derive(Clone, Debug)]
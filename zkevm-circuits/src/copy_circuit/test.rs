@@ -104,6 +104,62 @@ fn gen_codecopy_data() -> CircuitInputBuilder<FixedCParams> {
         .unwrap()
 }
 
+fn gen_calldatacopy_padded_data() -> CircuitInputBuilder<FixedCParams> {
+    // Copy 0x40 bytes out of a 0x10 bytes calldata
+    let code = bytecode! {
+        PUSH32(Word::from(0x40))
+        PUSH32(Word::from(0x00))
+        PUSH32(Word::from(0x00))
+        CALLDATACOPY
+        STOP
+    };
+    let calldata = rand_bytes(0x10);
+    let test_ctx = TestContext::<2, 1>::new(
+        None,
+        account_0_code_account_1_no_code(code),
+        |mut txs, accs| {
+            txs[0]
+                .from(accs[1].address)
+                .to(accs[0].address)
+                .input(calldata.into());
+        },
+        |block, _txs| block.number(0xcafeu64),
+    )
+    .unwrap();
+    let block: GethData = test_ctx.into();
+    let builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap()
+}
+
+fn gen_codecopy_padded_data() -> CircuitInputBuilder<FixedCParams> {
+    // Copy 0x100 bytes out of a code of less than 0x100 bytes
+    let code = bytecode! {
+        PUSH32(Word::from(0x100))
+        PUSH32(Word::from(0x00))
+        PUSH32(Word::from(0x00))
+        CODECOPY
+        STOP
+    };
+    let test_ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap();
+    let block: GethData = test_ctx.into();
+    let builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap()
+}
+
+/// Overwrite the first byte read past the end of the source of the first copy
+/// event with a nonzero value.
+fn tamper_first_padding_byte(builder: &mut CircuitInputBuilder<FixedCParams>) {
+    let copy_event = &mut builder.block.copy_events[0];
+    let pad_idx = (copy_event.src_addr_end - copy_event.src_addr) as usize;
+    assert!(pad_idx < copy_event.bytes.len(), "copy event is not padded");
+    assert_eq!(copy_event.bytes[pad_idx].0, 0);
+    copy_event.bytes[pad_idx].0 = 0xff;
+}
+
 fn gen_extcodecopy_data() -> CircuitInputBuilder<FixedCParams> {
     let external_address = MOCK_ACCOUNTS[0];
     let code = bytecode! {
@@ -207,6 +263,20 @@ fn copy_circuit_valid_tx_log() {
     assert_eq!(test_copy_circuit_from_block(10, block), Ok(()));
 }
 
+#[test]
+fn copy_circuit_valid_calldatacopy_padded() {
+    let builder = gen_calldatacopy_padded_data();
+    let block = block_convert::<Fr>(&builder).unwrap();
+    assert_eq!(test_copy_circuit_from_block(10, block), Ok(()));
+}
+
+#[test]
+fn copy_circuit_valid_codecopy_padded() {
+    let builder = gen_codecopy_padded_data();
+    let block = block_convert::<Fr>(&builder).unwrap();
+    assert_eq!(test_copy_circuit_from_block(10, block), Ok(()));
+}
+
 #[test]
 fn copy_circuit_invalid_calldatacopy() {
     let mut builder = gen_calldatacopy_data();
@@ -287,6 +357,30 @@ fn copy_circuit_invalid_tx_log() {
     );
 }
 
+#[test]
+fn copy_circuit_invalid_calldatacopy_padding() {
+    let mut builder = gen_calldatacopy_padded_data();
+    tamper_first_padding_byte(&mut builder);
+    let block = block_convert::<Fr>(&builder).unwrap();
+
+    assert_constraint_failure(
+        test_copy_circuit_from_block(10, block),
+        "value == 0 when is_pad == 1 for read",
+    );
+}
+
+#[test]
+fn copy_circuit_invalid_codecopy_padding() {
+    let mut builder = gen_codecopy_padded_data();
+    tamper_first_padding_byte(&mut builder);
+    let block = block_convert::<Fr>(&builder).unwrap();
+
+    assert_constraint_failure(
+        test_copy_circuit_from_block(10, block),
+        "value == 0 when is_pad == 1 for read",
+    );
+}
+
 #[test]
 fn variadic_size_check() {
     let builder = gen_tx_log_data();
@@ -335,3 +429,16 @@ fn assert_error_matches(result: Result<(), Vec<VerifyFailure>>, names: Vec<&str>
         }
     }
 }
+
+fn assert_constraint_failure(result: Result<(), Vec<VerifyFailure>>, name: &str) {
+    let errors = result.expect_err("result is not an error");
+    assert!(
+        errors.iter().any(
+            |error| matches!(error, VerifyFailure::ConstraintNotSatisfied { .. })
+                && error.to_string().contains(name)
+        ),
+        "constraint \"{}\" not in {:?}",
+        name,
+        errors
+    );
+}
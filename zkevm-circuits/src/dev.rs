@@ -0,0 +1,3 @@
+//! Development utilities for debugging blocks that fail to be proven
+
+pub mod shrink;
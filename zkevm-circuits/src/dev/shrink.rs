@@ -0,0 +1,339 @@
+//! Counterexample minimizer for failing blocks.
+//!
+//! Given a [`GethData`] whose proof fails, [`shrink`] first bisects the
+//! transactions of the block and then the opcodes of every contract touched
+//! by it, re-tracing each candidate with the external tracer, until no
+//! smaller candidate keeps failing. The result is a (locally) minimal block
+//! that still reproduces the failure.
+//!
+//! [`fails_like`] keeps the candidates that fail like the input block, so
+//! that the shrinking doesn't drift to an unrelated failure.
+//!
+//! Note that dropping transactions renumbers the nonces of the remaining
+//! ones, which invalidates their signatures. Predicates that exercise the Tx
+//! circuit should re-sign the candidate with [`GethData::sign`].
+
+use crate::{
+    test_util::{Circuit, CircuitTestBuilder, CircuitTestError},
+    witness::block_convert,
+};
+use bus_mapping::mock::BlockData;
+use eth_types::{
+    bytecode::OpcodeWithData,
+    evm_types::OpcodeId,
+    geth_types::{GethData, Withdrawal},
+    Address, Bytecode, Bytes, Error, Word, U64,
+};
+use halo2_proofs::{dev::VerifyFailure, halo2curves::bn256::Fr};
+use mock::test_ctx2::{gen_geth_traces, LoggerConfig};
+use std::collections::{BTreeSet, HashMap};
+
+/// Shrink `geth_data` to a minimal block for which `still_fails` holds.
+///
+/// `still_fails` must return `true` for the input block. Candidates that
+/// cannot be re-traced are discarded.
+pub fn shrink<P>(geth_data: GethData, still_fails: P) -> GethData
+where
+    P: Fn(&GethData) -> bool,
+{
+    debug_assert!(still_fails(&geth_data), "input block does not fail");
+    let geth_data = shrink_txs(geth_data, &still_fails);
+    shrink_codes(geth_data, &still_fails)
+}
+
+/// Failure of a block in the EVM and State circuits, identified by the
+/// circuit and the names of the broken constraints, independently of the
+/// rows where they break.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The block can't be built or converted to a witness
+    Witness,
+    /// The circuit can't be synthesized
+    Synthesis(Circuit),
+    /// The circuit is not satisfied
+    Constraints {
+        /// The failing circuit
+        circuit: Circuit,
+        /// Names of the unsatisfied constraints and lookups
+        constraints: BTreeSet<String>,
+    },
+}
+
+impl Failure {
+    /// Whether `self` is the failure `original`, or a failure of the same
+    /// circuit on some of the constraints of `original`.
+    pub fn is_part_of(&self, original: &Failure) -> bool {
+        match (self, original) {
+            (
+                Failure::Constraints {
+                    circuit,
+                    constraints,
+                },
+                Failure::Constraints {
+                    circuit: original_circuit,
+                    constraints: original_constraints,
+                },
+            ) => circuit == original_circuit && constraints.is_subset(original_constraints),
+            _ => self == original,
+        }
+    }
+}
+
+/// Name of the constraint, lookup or gate of a verification failure.
+fn constraint_name(failure: &VerifyFailure) -> String {
+    match failure {
+        VerifyFailure::ConstraintNotSatisfied { constraint, .. }
+        | VerifyFailure::ConstraintPoisoned { constraint } => constraint.to_string(),
+        VerifyFailure::Lookup { name, .. } | VerifyFailure::Shuffle { name, .. } => name.clone(),
+        VerifyFailure::CellNotAssigned { gate, .. }
+        | VerifyFailure::InstanceCellNotAssigned { gate, .. } => gate.to_string(),
+        VerifyFailure::Permutation { column, .. } => format!("permutation of {}", column),
+    }
+}
+
+/// Failure of the EVM and State circuits on `geth_data`, if any.
+pub fn circuits_failure(geth_data: &GethData) -> Option<Failure> {
+    let builder = BlockData::new_from_geth_data(geth_data.clone()).new_circuit_input_builder();
+    let block = match builder
+        .handle_block(&geth_data.eth_block, &geth_data.geth_traces)
+        .map(|builder| block_convert::<Fr>(&builder))
+    {
+        Ok(Ok(block)) => block,
+        _ => return Some(Failure::Witness),
+    };
+    let err = CircuitTestBuilder::<1, 1>::new_from_block(block)
+        .run_with_result()
+        .err()?;
+    Some(match err {
+        CircuitTestError::SynthesisFailure { circuit, .. } => Failure::Synthesis(circuit),
+        CircuitTestError::VerificationFailed { circuit, reasons } => Failure::Constraints {
+            circuit,
+            constraints: reasons.iter().map(constraint_name).collect(),
+        },
+        _ => Failure::Witness,
+    })
+}
+
+/// Predicate for [`shrink`] keeping the candidates whose
+/// [`circuits_failure`] is part of the failure of `geth_data`.
+///
+/// Panics if `geth_data` doesn't fail.
+pub fn fails_like(geth_data: &GethData) -> impl Fn(&GethData) -> bool {
+    let original = circuits_failure(geth_data).expect("input block does not fail");
+    log::debug!("shrink: original failure {:?}", original);
+    move |candidate| {
+        circuits_failure(candidate).map_or(false, |failure| failure.is_part_of(&original))
+    }
+}
+
+/// Remove chunks of transactions, halving the chunk size each round, as long
+/// as the remaining block keeps failing.
+fn shrink_txs<P>(mut geth_data: GethData, still_fails: &P) -> GethData
+where
+    P: Fn(&GethData) -> bool,
+{
+    let mut chunk = geth_data.eth_block.transactions.len().div_ceil(2);
+    while chunk > 0 {
+        let mut start = 0;
+        while start < geth_data.eth_block.transactions.len() {
+            let len = geth_data.eth_block.transactions.len();
+            let end = (start + chunk).min(len);
+            let keep: Vec<usize> = (0..start).chain(end..len).collect();
+            if keep.is_empty() {
+                start = end;
+                continue;
+            }
+            match retrace(select_txs(&geth_data, &keep)) {
+                Ok(candidate) if still_fails(&candidate) => {
+                    log::debug!("shrink: removed txs {}..{}", start, end);
+                    geth_data = candidate;
+                }
+                _ => start = end,
+            }
+        }
+        chunk /= 2;
+    }
+    geth_data
+}
+
+/// Bisect, for every account with code, the shortest code prefix (terminated
+/// with `STOP`) that keeps the block failing.
+fn shrink_codes<P>(mut geth_data: GethData, still_fails: &P) -> GethData
+where
+    P: Fn(&GethData) -> bool,
+{
+    for idx in 0..geth_data.accounts.len() {
+        if geth_data.accounts[idx].code.is_empty() {
+            continue;
+        }
+        let ops: Vec<OpcodeWithData> = Bytecode::from(geth_data.accounts[idx].code.to_vec())
+            .iter()
+            .collect();
+        let (mut lo, mut hi) = (0, ops.len());
+        let mut best = None;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let mut candidate = geth_data.clone();
+            candidate.accounts[idx].code = truncate_code(&ops[..mid]);
+            match retrace(candidate) {
+                Ok(candidate) if still_fails(&candidate) => {
+                    hi = mid;
+                    best = Some(candidate);
+                }
+                _ => lo = mid + 1,
+            }
+        }
+        if let Some(candidate) = best {
+            log::debug!(
+                "shrink: code of {:?} reduced from {} to {} opcodes",
+                candidate.accounts[idx].address,
+                ops.len(),
+                hi
+            );
+            geth_data = candidate;
+        }
+    }
+    geth_data
+}
+
+/// Keep only the transactions at `keep`, fixing up their nonces and indexes.
+fn select_txs(geth_data: &GethData, keep: &[usize]) -> GethData {
+    let mut nonces: HashMap<Address, Word> = geth_data
+        .accounts
+        .iter()
+        .map(|acc| (acc.address, Word::from(acc.nonce.as_u64())))
+        .collect();
+    let mut geth_data = geth_data.clone();
+    geth_data.eth_block.transactions = keep
+        .iter()
+        .enumerate()
+        .map(|(tx_idx, &idx)| {
+            let mut tx = geth_data.eth_block.transactions[idx].clone();
+            let nonce = nonces.entry(tx.from).or_default();
+            tx.nonce = *nonce;
+            *nonce = *nonce + Word::one();
+            tx.transaction_index = Some(U64::from(tx_idx));
+            tx
+        })
+        .collect();
+    geth_data
+}
+
+/// Code consisting of `ops` followed by `STOP`.
+fn truncate_code(ops: &[OpcodeWithData]) -> Bytes {
+    let mut code = Bytecode::default();
+    for op in ops {
+        code.append_op(op.clone());
+    }
+    code.write_op(OpcodeId::STOP);
+    code.into()
+}
+
+/// Regenerate the execution traces of `geth_data`.
+fn retrace(mut geth_data: GethData) -> Result<GethData, Error> {
+    let withdrawals = geth_data
+        .eth_block
+        .withdrawals
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .map(|w| Withdrawal {
+            id: w.index.as_u64(),
+            validator_id: w.validator_index.as_u64(),
+            address: w.address,
+            amount: w.amount.as_u64(),
+        })
+        .collect();
+    geth_data.geth_traces = gen_geth_traces(
+        geth_data.chain_id,
+        geth_data.eth_block.clone(),
+        geth_data.accounts.clone(),
+        withdrawals,
+        Some(geth_data.history_hashes.clone()),
+        LoggerConfig::default(),
+    )?;
+    Ok(geth_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::bytecode;
+    use mock::{eth, TestContext, MOCK_ACCOUNTS};
+
+    #[test]
+    fn shrink_to_single_tx_and_opcode() {
+        let code = bytecode! {
+            PUSH1(0x01)
+            POP
+            SELFBALANCE
+            POP
+            PUSH1(0x02)
+            POP
+            STOP
+        };
+        let geth_data: GethData = TestContext::<3, 3>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(eth(10))
+                    .code(code);
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+                accs[2].address(MOCK_ACCOUNTS[2]).balance(eth(10));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[1].address).to(accs[2].address);
+                txs[1].from(accs[1].address).to(accs[0].address);
+                txs[2].from(accs[2].address).to(accs[1].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let uses_selfbalance = |geth_data: &GethData| {
+            geth_data.geth_traces.iter().any(|trace| {
+                trace
+                    .struct_logs
+                    .iter()
+                    .any(|step| step.op == OpcodeId::SELFBALANCE)
+            })
+        };
+        let shrunk = shrink(geth_data, uses_selfbalance);
+
+        assert_eq!(shrunk.eth_block.transactions.len(), 1);
+        assert_eq!(shrunk.eth_block.transactions[0].to, Some(MOCK_ACCOUNTS[0]));
+        // The shortest prefix that still executes SELFBALANCE is cut right
+        // after it.
+        let expected = bytecode! {
+            PUSH1(0x01)
+            POP
+            SELFBALANCE
+            STOP
+        };
+        let account = shrunk
+            .accounts
+            .iter()
+            .find(|acc| acc.address == MOCK_ACCOUNTS[0])
+            .unwrap();
+        assert_eq!(account.code.to_vec(), expected.code());
+    }
+
+    #[test]
+    fn failure_is_part_of() {
+        let constraints = |circuit, names: &[&str]| Failure::Constraints {
+            circuit,
+            constraints: names.iter().map(|name| name.to_string()).collect(),
+        };
+        let original = constraints(Circuit::EVM, &["gas_left", "stack_pointer"]);
+
+        assert!(original.is_part_of(&original));
+        assert!(constraints(Circuit::EVM, &["gas_left"]).is_part_of(&original));
+        assert!(!constraints(Circuit::EVM, &["gas_left", "pc"]).is_part_of(&original));
+        assert!(!constraints(Circuit::State, &["gas_left"]).is_part_of(&original));
+        assert!(!Failure::Witness.is_part_of(&original));
+        assert!(!Failure::Synthesis(Circuit::EVM).is_part_of(&original));
+        assert!(Failure::Witness.is_part_of(&Failure::Witness));
+    }
+}
@@ -0,0 +1,49 @@
+//! Error module for the zkevm-circuits crate
+
+use std::{error::Error as StdError, fmt};
+
+/// Error building the witness of the circuits from the inputs of a block.
+#[derive(Debug)]
+pub enum Error {
+    /// Error generating the circuit inputs of the block
+    Witness(bus_mapping::Error),
+    /// Step whose rw and gas usage differs from the usage fixed by the EVM
+    /// circuit gadget of its execution state
+    StepUsageMismatch {
+        /// Id of the transaction executing the step
+        tx: usize,
+        /// Program counter of the step
+        pc: u64,
+        /// Execution state of the step
+        execution_state: String,
+        /// Number of rws of the step
+        rw_count: usize,
+        /// Number of rws fixed by the gadget
+        expected_rw_count: usize,
+        /// Gas cost of the step
+        gas_cost: u64,
+        /// Gas cost fixed by the gadget
+        expected_gas_cost: u64,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Witness(err) => Some(err),
+            Self::StepUsageMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<bus_mapping::Error> for Error {
+    fn from(err: bus_mapping::Error) -> Self {
+        Self::Witness(err)
+    }
+}
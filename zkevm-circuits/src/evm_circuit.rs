@@ -1,43 +1,68 @@
 //! The EVM circuit implementation.
+//!
+//! The circuit itself is only built with the `evm` feature.  Its parameters,
+//! execution states, fixed table and gadgets are always built, since the other
+//! circuits and the witness share them.
 
+#[cfg(feature = "evm")]
 use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner, Value},
     plonk::*,
 };
 
+#[cfg(feature = "evm")]
 mod execution;
 pub mod param;
 pub mod step;
 pub mod table;
+#[cfg_attr(
+    not(feature = "super"),
+    allow(
+        dead_code,
+        reason = "some gadgets are only used by the circuits left out"
+    )
+)]
 pub(crate) mod util;
 
 #[cfg(test)]
 pub(crate) mod test;
-use self::step::HasExecutionState;
-#[cfg(feature = "test-circuits")]
+#[cfg(all(feature = "evm", feature = "test-circuits"))]
 pub use self::EvmCircuit as TestEvmCircuit;
 
 pub use crate::witness;
+#[cfg(feature = "evm")]
 use crate::{
-    evm_circuit::param::{MAX_STEP_HEIGHT, STEP_STATE_HEIGHT},
+    evm_circuit::{
+        param::{MAX_STEP_HEIGHT, STEP_STATE_HEIGHT},
+        step::HasExecutionState,
+    },
     table::{
         BlockTable, BytecodeTable, CopyTable, ExpTable, KeccakTable, LookupTable, RwTable,
         SigTable, TxTable, UXTable,
     },
     util::{Challenges, SubCircuit, SubCircuitConfig},
 };
+#[cfg(feature = "evm")]
 use bus_mapping::{circuit_input_builder::FeatureConfig, evm::OpcodeId};
+#[cfg(feature = "evm")]
 use eth_types::Field;
+#[cfg(feature = "evm")]
 use execution::ExecutionConfig;
+#[cfg(feature = "evm")]
 use itertools::Itertools;
+#[cfg(feature = "evm")]
 use strum::IntoEnumIterator;
+#[cfg(feature = "evm")]
 use table::FixedTableTag;
+#[cfg(feature = "evm")]
 use witness::Block;
 
+#[cfg(feature = "evm")]
 /// EvmCircuitConfig implements verification of execution trace of a block.
 #[derive(Clone, Debug)]
 pub struct EvmCircuitConfig<F> {
     fixed_table: [Column<Fixed>; 4],
+    disabled_opcodes: Vec<OpcodeId>,
     u8_table: UXTable<8>,
     u16_table: UXTable<16>,
     /// The execution config
@@ -53,6 +78,7 @@ pub struct EvmCircuitConfig<F> {
     sig_table: SigTable,
 }
 
+#[cfg(feature = "evm")]
 /// Circuit configuration arguments
 pub struct EvmCircuitConfigArgs<F: Field> {
     /// Challenge
@@ -81,6 +107,7 @@ pub struct EvmCircuitConfigArgs<F: Field> {
     pub feature_config: FeatureConfig,
 }
 
+#[cfg(feature = "evm")]
 impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
     type ConfigArgs = EvmCircuitConfigArgs<F>;
 
@@ -103,6 +130,7 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
         }: Self::ConfigArgs,
     ) -> Self {
         let fixed_table = [(); 4].map(|_| meta.fixed_column());
+        let disabled_opcodes = feature_config.disabled_opcodes.clone();
         let execution = Box::new(ExecutionConfig::configure(
             meta,
             challenges,
@@ -138,6 +166,7 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
 
         Self {
             fixed_table,
+            disabled_opcodes,
             u8_table,
             u16_table,
             execution,
@@ -153,18 +182,32 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
     }
 }
 
+#[cfg(feature = "evm")]
 impl<F: Field> EvmCircuitConfig<F> {
     /// Load fixed table
+    ///
+    /// The responsible opcodes of the table don't list the disabled opcodes of the network, so
+    /// the steps executing one of them can't be verified.
     pub fn load_fixed_table(
         &self,
         layouter: &mut impl Layouter<F>,
         fixed_table_tags: Vec<FixedTableTag>,
     ) -> Result<(), Error> {
+        let responsible_opcode_tag = F::from(FixedTableTag::ResponsibleOpcode as u64);
+        let disabled_opcodes = self
+            .disabled_opcodes
+            .iter()
+            .map(|opcode| F::from(opcode.as_u64()))
+            .collect_vec();
         layouter.assign_region(
             || "fixed table",
             |mut region| {
                 for (offset, row) in std::iter::once([F::ZERO; 4])
-                    .chain(fixed_table_tags.iter().flat_map(|tag| tag.build()))
+                    .chain(fixed_table_tags.iter().flat_map(|tag| tag.build()).filter(
+                        |row: &[F; 4]| {
+                            row[0] != responsible_opcode_tag || !disabled_opcodes.contains(&row[2])
+                        },
+                    ))
                     .enumerate()
                 {
                     for (column, value) in self.fixed_table.iter().zip_eq(row) {
@@ -178,6 +221,7 @@ impl<F: Field> EvmCircuitConfig<F> {
     }
 }
 
+#[cfg(feature = "evm")]
 /// Tx Circuit for verifying transaction signatures
 #[derive(Clone, Default, Debug)]
 pub struct EvmCircuit<F: Field> {
@@ -186,6 +230,7 @@ pub struct EvmCircuit<F: Field> {
     fixed_table_tags: Vec<FixedTableTag>,
 }
 
+#[cfg(feature = "evm")]
 impl<F: Field> EvmCircuit<F> {
     /// Return a new EvmCircuit
     pub fn new(block: Block<F>) -> Self {
@@ -239,6 +284,7 @@ impl<F: Field> EvmCircuit<F> {
     }
 }
 
+#[cfg(feature = "evm")]
 impl<F: Field> SubCircuit<F> for EvmCircuit<F> {
     type Config = EvmCircuitConfig<F>;
 
@@ -282,6 +328,7 @@ impl<F: Field> SubCircuit<F> for EvmCircuit<F> {
     }
 }
 
+#[cfg(feature = "evm")]
 /// create fixed_table_tags needed given witness block
 pub(crate) fn detect_fixed_table_tags<F: Field>(block: &Block<F>) -> Vec<FixedTableTag> {
     let need_bitwise_lookup = block.txs.iter().any(|tx| {
@@ -305,7 +352,7 @@ pub(crate) fn detect_fixed_table_tags<F: Field>(block: &Block<F>) -> Vec<FixedTa
         .collect()
 }
 
-#[cfg(any(feature = "test-util", test))]
+#[cfg(all(feature = "evm", any(feature = "test-util", test)))]
 pub(crate) mod cached {
     use super::*;
     use halo2_proofs::halo2curves::bn256::Fr;
@@ -364,6 +411,7 @@ pub(crate) mod cached {
     }
 }
 
+#[cfg(feature = "evm")]
 // Always exported because of `EXECUTION_STATE_HEIGHT_MAP`
 impl<F: Field> Circuit<F> for EvmCircuit<F> {
     type Config = (EvmCircuitConfig<F>, Challenges);
@@ -378,7 +426,7 @@ impl<F: Field> Circuit<F> for EvmCircuit<F> {
     fn params(&self) -> Self::Params {
         self.block
             .as_ref()
-            .map(|block| block.feature_config)
+            .map(|block| block.feature_config.clone())
             .unwrap_or_default()
     }
 
@@ -463,7 +511,7 @@ impl<F: Field> Circuit<F> for EvmCircuit<F> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(feature = "evm", test))]
 mod evm_circuit_stats {
     use crate::{
         evm_circuit::EvmCircuit,
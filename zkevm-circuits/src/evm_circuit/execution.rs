@@ -10,7 +10,7 @@ use super::{
 use crate::{
     evm_circuit::{
         param::{EVM_LOOKUP_COLS, MAX_STEP_HEIGHT, N_PHASE2_COLUMNS, STEP_WIDTH},
-        step::{ExecutionState, Step},
+        step::{ExecutionState, Step, StepUsage},
         table::Table,
         util::{
             constraint_builder::{
@@ -124,6 +124,8 @@ mod sload;
 mod sstore;
 mod stop;
 mod swap;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 use self::{block_ctx::BlockCtxGadget, sha3::Sha3Gadget};
 use add_sub::AddSubGadget;
@@ -244,6 +246,7 @@ pub struct ExecutionConfig<F> {
     advices: [Column<Advice>; STEP_WIDTH],
     step: Step<F>,
     pub(crate) height_map: HashMap<ExecutionState, usize>,
+    pub(crate) usage_map: HashMap<ExecutionState, StepUsage>,
     stored_expressions_map: HashMap<ExecutionState, Vec<StoredExpression<F>>>,
     debug_expressions_map: HashMap<ExecutionState, Vec<(String, Expression<F>)>>,
     instrument: Instrument,
@@ -390,6 +393,7 @@ impl<F: Field> ExecutionConfig<F> {
 
         let step_curr = Step::new(meta, advices, 0);
         let mut height_map = HashMap::new();
+        let mut usage_map = HashMap::new();
 
         meta.create_gate("Constrain execution state", |meta| {
             let q_usable = meta.query_selector(q_usable);
@@ -509,6 +513,7 @@ impl<F: Field> ExecutionConfig<F> {
                         &challenges,
                         &step_curr,
                         &mut height_map,
+                        &mut usage_map,
                         &mut stored_expressions_map,
                         &mut debug_expressions_map,
                         &mut instrument,
@@ -622,6 +627,7 @@ impl<F: Field> ExecutionConfig<F> {
             // step and presets
             step: step_curr,
             height_map,
+            usage_map,
             stored_expressions_map,
             debug_expressions_map,
             instrument,
@@ -662,6 +668,7 @@ impl<F: Field> ExecutionConfig<F> {
         challenges: &Challenges<Expression<F>>,
         step_curr: &Step<F>,
         height_map: &mut HashMap<ExecutionState, usize>,
+        usage_map: &mut HashMap<ExecutionState, StepUsage>,
         stored_expressions_map: &mut HashMap<ExecutionState, Vec<StoredExpression<F>>>,
         debug_expressions_map: &mut HashMap<ExecutionState, Vec<(String, Expression<F>)>>,
         instrument: &mut Instrument,
@@ -677,7 +684,7 @@ impl<F: Field> ExecutionConfig<F> {
                 dummy_step_next,
                 challenges,
                 G::EXECUTION_STATE,
-                feature_config,
+                feature_config.clone(),
             );
             G::configure(&mut cb);
             let (_, _, height, _) = cb.build();
@@ -706,6 +713,7 @@ impl<F: Field> ExecutionConfig<F> {
             step_curr,
             step_next,
             height_map,
+            usage_map,
             stored_expressions_map,
             debug_expressions_map,
             instrument,
@@ -728,6 +736,7 @@ impl<F: Field> ExecutionConfig<F> {
         step_curr: &Step<F>,
         step_next: &Step<F>,
         height_map: &mut HashMap<ExecutionState, usize>,
+        usage_map: &mut HashMap<ExecutionState, StepUsage>,
         stored_expressions_map: &mut HashMap<ExecutionState, Vec<StoredExpression<F>>>,
         debug_expressions_map: &mut HashMap<ExecutionState, Vec<(String, Expression<F>)>>,
         instrument: &mut Instrument,
@@ -747,6 +756,8 @@ impl<F: Field> ExecutionConfig<F> {
 
         instrument.on_gadget_built(execution_state, &cb);
 
+        let fixed_usage = cb.fixed_usage();
+
         let debug_expressions = cb.debug_expressions.clone();
 
         // Extract feature config here before cb is built.
@@ -759,6 +770,9 @@ impl<F: Field> ExecutionConfig<F> {
         );
 
         height_map.insert(execution_state, height);
+        if let Some(usage) = fixed_usage {
+            usage_map.insert(execution_state, usage);
+        }
         debug_assert!(
             !stored_expressions_map.contains_key(&execution_state),
             "execution state already configured"
@@ -873,7 +887,7 @@ impl<F: Field> ExecutionConfig<F> {
     }
 
     #[allow(clippy::too_many_arguments)]
-    fn configure_lookup(
+    pub(crate) fn configure_lookup(
         meta: &mut ConstraintSystem<F>,
         fixed_table: &dyn LookupTable<F>,
         u8_table: &dyn LookupTable<F>,
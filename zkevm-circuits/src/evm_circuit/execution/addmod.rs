@@ -228,8 +228,8 @@ impl<F: Field> ExecutionGadget<F> for AddModGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::CircuitTestBuilder;
-    use eth_types::{bytecode, evm_types::Stack, Word};
+    use crate::{evm_circuit::test::rand_word, test_util::CircuitTestBuilder};
+    use eth_types::{bytecode, evm_types::Stack, Word, U512};
     use mock::TestContext;
 
     fn test(a: Word, b: Word, n: Word, r: Option<Word>, ok: bool) {
@@ -308,4 +308,26 @@ mod test {
         test_ok_u32(2, 3, 4, Some(1));
         test_ko_u32(2, 3, 4, Some(5))
     }
+
+    /// Reference ADDMOD, with the N == 0 -> 0 rule
+    fn addmod(a: Word, b: Word, n: Word) -> Word {
+        if n.is_zero() {
+            return Word::zero();
+        }
+        let r = (U512::from(a) + U512::from(b)) % U512::from(n);
+        Word::try_from(r).unwrap()
+    }
+
+    #[test]
+    fn addmod_rand() {
+        for n in [
+            rand_word(),
+            rand_word() >> 128,
+            Word::from(rand_word().low_u64()),
+            Word::zero(),
+        ] {
+            let (a, b) = (rand_word(), rand_word());
+            test(a, b, n, Some(addmod(a, b, n)), true);
+        }
+    }
 }
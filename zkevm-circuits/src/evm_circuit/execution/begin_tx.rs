@@ -545,9 +545,14 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::{evm_circuit::test::rand_bytes, test_util::CircuitTestBuilder};
-    use bus_mapping::evm::OpcodeId;
-    use eth_types::{self, bytecode, evm_types::GasCost, word, Address, Bytecode, Word};
+    use crate::{
+        evm_circuit::test::rand_bytes,
+        table::AccountFieldTag,
+        test_util::{CircuitTestBuilder, CircuitTestError},
+        witness::Rw,
+    };
+    use bus_mapping::{evm::OpcodeId, operation::Target};
+    use eth_types::{self, bytecode, evm_types::GasCost, word, Address, Bytecode, Word, U64};
     use ethers_core::utils::get_contract_address;
     use mock::{eth, gwei, MockTransaction, TestContext, MOCK_ACCOUNTS};
     use std::vec;
@@ -852,4 +857,129 @@ mod test {
 
         CircuitTestBuilder::new_from_test_ctx(ctx).run();
     }
+
+    #[test]
+    fn begin_tx_repeated_nonce() {
+        let address = Address::repeat_byte(23);
+        let ctx = TestContext::<2, 2>::new(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(1));
+                accs[1].address(address).balance(eth(10));
+            },
+            |mut txs, _| {
+                txs[0].from(address).to(MOCK_ACCOUNTS[0]);
+                txs[1].from(address).to(MOCK_ACCOUNTS[0]);
+            },
+            |block, _| block,
+        )
+        .unwrap();
+
+        // Replay the nonce of the first tx in the second one, consistently in
+        // the tx table and in the nonce write of the caller. The EVM circuit
+        // accepts it, but the State circuit chains the nonce writes of the
+        // caller to its initial nonce.
+        let result = CircuitTestBuilder::new_from_test_ctx(ctx)
+            .block_modifier(Box::new(move |block| {
+                block.txs[1].tx.nonce = U64::zero();
+                for rw in block.rws.0.get_mut(&Target::Account).unwrap() {
+                    if let Rw::Account {
+                        account_address,
+                        field_tag: AccountFieldTag::Nonce,
+                        value,
+                        value_prev,
+                        ..
+                    } = rw
+                    {
+                        if *account_address == address && *value == Word::from(2) {
+                            *value = Word::one();
+                            *value_prev = Word::zero();
+                        }
+                    }
+                }
+            }))
+            .run_with_result();
+        assert!(matches!(
+            result,
+            Err(CircuitTestError::VerificationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn begin_tx_exact_balance() {
+        let gas_price = gwei(2);
+        let tx = mock_tx(eth(1), gas_price, vec![]);
+        let ctx = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                accs[1]
+                    .address(MOCK_ACCOUNTS[1])
+                    .balance(eth(1) + tx.gas * gas_price);
+            },
+            |mut txs, _accs| {
+                txs[0]
+                    .to(tx.to.unwrap())
+                    .from(tx.from)
+                    .gas_price(gas_price)
+                    .gas(tx.gas)
+                    .value(tx.value);
+            },
+            |block, _tx| block,
+        )
+        .unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx).run();
+    }
+
+    #[test]
+    fn begin_tx_insufficient_balance() {
+        let ctx = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+            },
+            |mut txs, _accs| {
+                txs[0]
+                    .to(MOCK_ACCOUNTS[0])
+                    .from(MOCK_ACCOUNTS[1])
+                    .value(eth(1));
+            },
+            |block, _tx| block,
+        )
+        .unwrap();
+
+        // Lower the balance of the sender below the amount subtracted by its
+        // first balance update. The subtraction can only be witnessed with an
+        // underflow, which the balance update rejects.
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .block_modifier(Box::new(|block| {
+                let rw = block
+                    .rws
+                    .0
+                    .get_mut(&Target::Account)
+                    .unwrap()
+                    .iter_mut()
+                    .find(|rw| {
+                        matches!(rw, Rw::Account {
+                            account_address,
+                            field_tag: AccountFieldTag::Balance,
+                            ..
+                        } if *account_address == MOCK_ACCOUNTS[1])
+                    })
+                    .unwrap();
+                if let Rw::Account {
+                    value, value_prev, ..
+                } = rw
+                {
+                    let delta = *value_prev - *value;
+                    *value_prev = delta - 1;
+                    *value = value_prev.overflowing_sub(delta).0;
+                }
+            }))
+            .run_with_result()
+            .unwrap_err()
+            .assert_evm_failure();
+    }
 }
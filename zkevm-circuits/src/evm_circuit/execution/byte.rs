@@ -156,6 +156,17 @@ mod test {
         test_ok(256.into(), 0x030201.into());
     }
 
+    #[test]
+    fn byte_gadget_index_boundaries() {
+        let value = Word::from_big_endian(&(1..33).collect::<Vec<_>>()[..]);
+        // Select the least significant byte
+        test_ok(31.into(), value);
+        // Select out of range bytes, including indices with non zero high bytes
+        test_ok(32.into(), value);
+        test_ok(Word::one() << 128, value);
+        test_ok(Word::MAX, value);
+    }
+
     #[test]
     fn byte_gadget_rand() {
         let index = rand_word();
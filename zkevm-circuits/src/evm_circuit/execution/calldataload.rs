@@ -282,7 +282,10 @@ impl<F: Field> ExecutionGadget<F> for CallDataLoadGadget<F> {
 mod test {
     use crate::{evm_circuit::test::rand_bytes, test_util::CircuitTestBuilder};
     use eth_types::{bytecode, Word};
-    use mock::{generate_mock_call_bytecode, MockCallBytecodeParams, TestContext};
+    use mock::{
+        generate_mock_call_bytecode, test_ctx::helpers::account_0_code_account_1_no_code,
+        MockCallBytecodeParams, TestContext,
+    };
 
     fn test_bytecode(offset: Word) -> eth_types::Bytecode {
         bytecode! {
@@ -301,6 +304,26 @@ mod test {
         .run();
     }
 
+    fn test_root_with_call_data_ok(call_data_length: usize, offset: Word) {
+        let bytecode = test_bytecode(offset);
+        let call_data = rand_bytes(call_data_length);
+
+        let ctx = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .input(call_data.into());
+            },
+            |block, _tx| block,
+        )
+        .unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx).run();
+    }
+
     fn test_internal_ok(call_data_length: usize, call_data_offset: usize, offset: Word) {
         let (addr_a, addr_b) = (mock::MOCK_ACCOUNTS[0], mock::MOCK_ACCOUNTS[1]);
 
@@ -341,6 +364,29 @@ mod test {
         test_root_ok(0x2010.into());
     }
 
+    #[test]
+    fn calldataload_gadget_root_with_call_data() {
+        // Word fully inside the call data.
+        test_root_with_call_data_ok(0x40, 0x00.into());
+        test_root_with_call_data_ok(0x40, 0x20.into());
+        // Word crossing the end of the call data, zero padded.
+        test_root_with_call_data_ok(0x40, 0x21.into());
+        test_root_with_call_data_ok(0x40, 0x3f.into());
+        // Word starting at or after the end of the call data.
+        test_root_with_call_data_ok(0x40, 0x40.into());
+        test_root_with_call_data_ok(0x40, 0x41.into());
+    }
+
+    #[test]
+    fn calldataload_gadget_internal_boundaries() {
+        // Word crossing the end of the caller's call data, zero padded.
+        test_internal_ok(0x30, 0x20, 0x20.into());
+        // Word starting at the end of the caller's call data.
+        test_internal_ok(0x30, 0x20, 0x30.into());
+        // Empty call data.
+        test_internal_ok(0x00, 0x20, 0x00.into());
+    }
+
     #[test]
     fn calldataload_gadget_internal() {
         test_internal_ok(0x20, 0x00, 0x00.into());
@@ -352,6 +398,7 @@ mod test {
     #[test]
     fn calldataload_gadget_offset_overflow() {
         test_root_ok(Word::MAX);
+        test_root_with_call_data_ok(0x40, Word::MAX);
         test_internal_ok(0x1010, 0xff, Word::MAX);
     }
 }
@@ -5,7 +5,9 @@ use crate::{
         step::ExecutionState,
         util::{
             and,
-            common_gadget::{CommonCallGadget, TransferGadget},
+            common_gadget::{
+                AllButOne64thGasGadget, CallContextGadget, CommonCallGadget, TransferGadget,
+            },
             constraint_builder::{
                 ConstrainBuilderCommon, EVMConstraintBuilder, ReversionInfo, StepStateTransition,
                 Transition::{Delta, To},
@@ -33,7 +35,8 @@ use bus_mapping::{
     precompile::{is_precompiled, PrecompileCalls},
 };
 use eth_types::{
-    evm_types::GAS_STIPEND_CALL_WITH_VALUE, Field, OpsIdentity, ToAddress, ToScalar, U256,
+    evm_types::{GAS_STIPEND_CALL_WITH_VALUE, MAX_CALL_DEPTH},
+    Field, OpsIdentity, ToAddress, ToScalar, U256,
 };
 use halo2_proofs::{circuit::Value, plonk::Error};
 use std::cmp::min;
@@ -66,7 +69,7 @@ pub(crate) struct CallOpGadget<F> {
     // check if insufficient balance case
     is_insufficient_balance: LtWordGadget<F>,
     is_depth_ok: LtGadget<F, N_BYTES_U64>,
-    one_64th_gas: ConstantDivisionGadget<F, N_BYTES_GAS>,
+    all_but_one_64th_gas: AllButOne64thGasGadget<F>,
     capped_callee_gas_left: MinMaxGadget<F, N_BYTES_GAS>,
     // check if the call is a precompile call.
     is_code_address_zero: IsZeroGadget<F>,
@@ -108,8 +111,10 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         // rwc_delta = 1
         let mut reversion_info = cb.reversion_info_read(None);
         // rwc_delta = 3
-        let [is_static, depth] = [CallContextFieldTag::IsStatic, CallContextFieldTag::Depth]
-            .map(|field_tag| cb.call_context(None, field_tag));
+        let [is_static, depth] = CallContextGadget::new(None).read(
+            cb,
+            [CallContextFieldTag::IsStatic, CallContextFieldTag::Depth],
+        );
         // rwc_delta = 5
         let current_callee_address =
             cb.call_context_read_as_word(None, CallContextFieldTag::CalleeAddress);
@@ -196,8 +201,8 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         // callee_reversion_info.rw_delta()
         let is_insufficient_balance =
             cb.is_lt_word(&caller_balance.to_word(), &call_gadget.value.to_word());
-        // depth < 1025
-        let is_depth_ok = cb.is_lt(depth.expr(), 1025.expr());
+        // depth <= MAX_CALL_DEPTH
+        let is_depth_ok = cb.is_lt(depth.expr(), (MAX_CALL_DEPTH + 1).expr());
 
         let is_precheck_ok = and::expr([
             is_depth_ok.expr(),
@@ -272,14 +277,15 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         let gas_cost = call_gadget.gas_cost_expr(is_warm_prev.expr(), is_call.expr());
         // Apply EIP 150
         let gas_available = cb.curr.state.gas_left.expr() - gas_cost.clone();
-        let one_64th_gas = cb.div_by_const(gas_available.clone(), 64);
-        let all_but_one_64th_gas = gas_available - one_64th_gas.quotient();
-        let capped_callee_gas_left =
-            cb.min_max(call_gadget.gas_expr(), all_but_one_64th_gas.clone());
+        let all_but_one_64th_gas = AllButOne64thGasGadget::construct(cb, gas_available);
+        let capped_callee_gas_left = cb.min_max(
+            call_gadget.gas_expr(),
+            all_but_one_64th_gas.all_but_one_64th_gas(),
+        );
         let callee_gas_left = select::expr(
             call_gadget.gas_is_u64.expr(),
             capped_callee_gas_left.min(),
-            all_but_one_64th_gas,
+            all_but_one_64th_gas.all_but_one_64th_gas(),
         );
 
         let stack_pointer_delta =
@@ -317,78 +323,67 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
                 );
 
                 // Write to callee's context.
-                cb.call_context_lookup_write(
-                    Some(callee_call_id.expr()),
-                    CallContextFieldTag::IsSuccess,
-                    WordLoHi::from_lo_unchecked(call_gadget.is_success.expr()),
-                );
-                cb.call_context_lookup_write(
-                    Some(callee_call_id.expr()),
-                    CallContextFieldTag::CalleeAddress,
-                    call_gadget.callee_address.to_word(),
-                );
-                for (field_tag, value) in [
-                    (CallContextFieldTag::CallerId, cb.curr.state.call_id.expr()),
-                    (
-                        CallContextFieldTag::CallDataOffset,
-                        call_gadget.cd_address.offset(),
-                    ),
-                    (
-                        CallContextFieldTag::CallDataLength,
-                        call_gadget.cd_address.length(),
-                    ),
-                    (
-                        CallContextFieldTag::ReturnDataOffset,
-                        call_gadget.rd_address.offset(),
-                    ),
+                let callee_context = [
                     (
-                        CallContextFieldTag::ReturnDataLength,
-                        call_gadget.rd_address.length(),
+                        CallContextFieldTag::IsSuccess,
+                        WordLoHi::from_lo_unchecked(call_gadget.is_success.expr()),
                     ),
-                ] {
-                    cb.call_context_lookup_write(
-                        Some(callee_call_id.expr()),
-                        field_tag,
-                        WordLoHi::from_lo_unchecked(value),
-                    );
-                }
-
-                // Save caller's call state
-                for (field_tag, value) in [
                     (
-                        CallContextFieldTag::ProgramCounter,
-                        cb.curr.state.program_counter.expr() + 1.expr(),
+                        CallContextFieldTag::CalleeAddress,
+                        call_gadget.callee_address.to_word(),
                     ),
                     (
-                        CallContextFieldTag::StackPointer,
-                        cb.curr.state.stack_pointer.expr()
-                            + select::expr(is_call.expr() + is_callcode.expr(), 6.expr(), 5.expr()),
+                        CallContextFieldTag::CallerId,
+                        WordLoHi::from_lo_unchecked(cb.curr.state.call_id.expr()),
                     ),
                     (
-                        CallContextFieldTag::GasLeft,
-                        cb.curr.state.gas_left.expr() - gas_cost.expr() - callee_gas_left.clone(),
+                        CallContextFieldTag::CallDataOffset,
+                        WordLoHi::from_lo_unchecked(call_gadget.cd_address.offset()),
                     ),
                     (
-                        CallContextFieldTag::MemorySize,
-                        memory_expansion.next_memory_word_size(),
+                        CallContextFieldTag::CallDataLength,
+                        WordLoHi::from_lo_unchecked(call_gadget.cd_address.length()),
                     ),
                     (
-                        CallContextFieldTag::ReversibleWriteCounter,
-                        cb.curr.state.reversible_write_counter.expr() + 1.expr(),
+                        CallContextFieldTag::ReturnDataOffset,
+                        WordLoHi::from_lo_unchecked(call_gadget.rd_address.offset()),
                     ),
-                    (CallContextFieldTag::LastCalleeId, callee_call_id.expr()),
-                    (CallContextFieldTag::LastCalleeReturnDataOffset, 0.expr()),
                     (
-                        CallContextFieldTag::LastCalleeReturnDataLength,
-                        precompile_return_length.expr(),
+                        CallContextFieldTag::ReturnDataLength,
+                        WordLoHi::from_lo_unchecked(call_gadget.rd_address.length()),
                     ),
-                ] {
-                    cb.call_context_lookup_write(
-                        None,
-                        field_tag,
-                        WordLoHi::from_lo_unchecked(value),
-                    );
-                }
+                ];
+                CallContextGadget::new(Some(callee_call_id.expr())).write(cb, callee_context);
+
+                // Save caller's call state
+                let caller_context = CallContextGadget::new(None);
+                let program_counter = cb.curr.state.program_counter.expr() + 1.expr();
+                let stack_pointer = cb.curr.state.stack_pointer.expr()
+                    + select::expr(is_call.expr() + is_callcode.expr(), 6.expr(), 5.expr());
+                let gas_left =
+                    cb.curr.state.gas_left.expr() - gas_cost.expr() - callee_gas_left.clone();
+                let reversible_write_counter =
+                    cb.curr.state.reversible_write_counter.expr() + 1.expr();
+                caller_context.save_state(
+                    cb,
+                    program_counter,
+                    stack_pointer,
+                    gas_left,
+                    memory_expansion.next_memory_word_size(),
+                    reversible_write_counter,
+                );
+                caller_context.write(
+                    cb,
+                    [
+                        (CallContextFieldTag::LastCalleeId, callee_call_id.expr()),
+                        (CallContextFieldTag::LastCalleeReturnDataOffset, 0.expr()),
+                        (
+                            CallContextFieldTag::LastCalleeReturnDataLength,
+                            precompile_return_length.expr(),
+                        ),
+                    ]
+                    .map(|(field_tag, value)| (field_tag, WordLoHi::from_lo_unchecked(value))),
+                );
 
                 // copy table lookup to verify the copying of bytes:
                 // - from caller's memory (`call_data_length` bytes starting at `call_data_offset`)
@@ -534,17 +529,7 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
             ]),
             |cb| {
                 // Save caller's call state
-                cb.call_context_lookup_write(
-                    None,
-                    CallContextFieldTag::LastCalleeId,
-                    WordLoHi::from_lo_unchecked(callee_call_id.expr()),
-                );
-                for field_tag in [
-                    CallContextFieldTag::LastCalleeReturnDataOffset,
-                    CallContextFieldTag::LastCalleeReturnDataLength,
-                ] {
-                    cb.call_context_lookup_write(None, field_tag, WordLoHi::zero());
-                }
+                CallContextGadget::new(None).reset_last_callee(cb, callee_call_id.expr());
 
                 // For CALL opcode, it has an extra stack pop `value` (+1) and if the value is
                 // not zero, two account write for `transfer` call (+2).
@@ -582,17 +567,7 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         // handle is_insufficient_balance or !is_depth_ok step transition
         cb.condition(not::expr(is_precheck_ok.expr()), |cb| {
             // Save caller's call state
-            cb.call_context_lookup_write(
-                None,
-                CallContextFieldTag::LastCalleeId,
-                WordLoHi::from_lo_unchecked(callee_call_id.expr()),
-            );
-            for field_tag in [
-                CallContextFieldTag::LastCalleeReturnDataOffset,
-                CallContextFieldTag::LastCalleeReturnDataLength,
-            ] {
-                cb.call_context_lookup_write(None, field_tag, WordLoHi::zero());
-            }
+            CallContextGadget::new(None).reset_last_callee(cb, callee_call_id.expr());
 
             cb.require_step_state_transition(StepStateTransition {
                 rw_counter: Delta(22.expr()),
@@ -617,39 +592,24 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
             ]),
             |cb| {
                 // Save caller's call state
-                for (field_tag, value) in [
-                    (
-                        CallContextFieldTag::ProgramCounter,
-                        cb.curr.state.program_counter.expr() + 1.expr(),
-                    ),
-                    (
-                        CallContextFieldTag::StackPointer,
-                        cb.curr.state.stack_pointer.expr() + stack_pointer_delta,
-                    ),
-                    (
-                        CallContextFieldTag::GasLeft,
-                        cb.curr.state.gas_left.expr() - gas_cost - callee_gas_left.clone(),
-                    ),
-                    (
-                        CallContextFieldTag::MemorySize,
-                        memory_expansion.next_memory_word_size(),
-                    ),
-                    (
-                        CallContextFieldTag::ReversibleWriteCounter,
-                        cb.curr.state.reversible_write_counter.expr() + 1.expr(),
-                    ),
-                ] {
-                    cb.call_context_lookup_write(
-                        None,
-                        field_tag,
-                        WordLoHi::from_lo_unchecked(value),
-                    );
-                }
+                let program_counter = cb.curr.state.program_counter.expr() + 1.expr();
+                let stack_pointer = cb.curr.state.stack_pointer.expr() + stack_pointer_delta;
+                let gas_left = cb.curr.state.gas_left.expr() - gas_cost - callee_gas_left.clone();
+                let reversible_write_counter =
+                    cb.curr.state.reversible_write_counter.expr() + 1.expr();
+                CallContextGadget::new(None).save_state(
+                    cb,
+                    program_counter,
+                    stack_pointer,
+                    gas_left,
+                    memory_expansion.next_memory_word_size(),
+                    reversible_write_counter,
+                );
 
                 // Setup next call's context.
                 let cd_address = call_gadget.cd_address.clone();
                 let rd_address = call_gadget.rd_address.clone();
-                for (field_tag, value) in [
+                let callee_context = [
                     (
                         CallContextFieldTag::CallerId,
                         WordLoHi::from_lo_unchecked(cb.curr.state.call_id.expr()),
@@ -714,9 +674,8 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
                         CallContextFieldTag::CodeHash,
                         call_gadget.callee_code_hash.to_word(),
                     ),
-                ] {
-                    cb.call_context_lookup_write(Some(callee_call_id.expr()), field_tag, value);
-                }
+                ];
+                CallContextGadget::new(Some(callee_call_id.expr())).write(cb, callee_context);
 
                 // Give gas stipend if value is not zero
                 let callee_gas_left = callee_gas_left
@@ -775,7 +734,7 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
             caller_balance,
             is_insufficient_balance,
             is_depth_ok,
-            one_64th_gas,
+            all_but_one_64th_gas,
             capped_callee_gas_left,
             // precompile related fields.
             is_code_address_zero,
@@ -819,8 +778,12 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
         let depth = rws.next().call_context_value();
         let current_callee_address = rws.next().call_context_value();
 
-        self.is_depth_ok
-            .assign(region, offset, F::from(depth.low_u64()), F::from(1025))?;
+        self.is_depth_ok.assign(
+            region,
+            offset,
+            F::from(depth.low_u64()),
+            F::from(MAX_CALL_DEPTH + 1),
+        )?;
 
         // This offset is used to change the index offset of `step.rw_indices`.
         // Since both CALL and CALLCODE have an extra stack pop `value`, and
@@ -863,8 +826,8 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
             .assign_u256(region, offset, caller_balance)?;
         self.is_insufficient_balance
             .assign(region, offset, caller_balance, value)?;
-        let is_precheck_ok =
-            depth.low_u64() < 1025 && (!(is_call || is_callcode) || caller_balance >= value);
+        let is_precheck_ok = depth.low_u64() <= MAX_CALL_DEPTH
+            && (!(is_call || is_callcode) || caller_balance >= value);
 
         // conditionally assign
         if is_call && is_precheck_ok {
@@ -951,13 +914,14 @@ impl<F: Field> ExecutionGadget<F> for CallOpGadget<F> {
             !callee_exists,
         )?;
         let gas_available: u64 = step.gas_left - gas_cost;
-        self.one_64th_gas
-            .assign(region, offset, gas_available.into())?;
+        let all_but_one_64th_gas =
+            self.all_but_one_64th_gas
+                .assign(region, offset, gas_available)?;
         self.capped_callee_gas_left.assign(
             region,
             offset,
             F::from(gas.low_u64()),
-            F::from(gas_available - gas_available / 64),
+            F::from(all_but_one_64th_gas),
         )?;
 
         let (_is_precompile_call, precompile_addr) = {
@@ -1390,6 +1354,157 @@ mod test {
             .run();
     }
 
+    #[test]
+    fn callop_value_to_nonexistent_account() {
+        let stacks = [
+            // Without value the account is not created
+            Stack::default(),
+            // With value the account is created
+            Stack {
+                value: Word::from(10).pow(18.into()),
+                ..Default::default()
+            },
+        ];
+        // The account creation is reverted together with the caller
+        for (stack, caller_is_success) in stacks.into_iter().cartesian_product([true, false]) {
+            test_ok_without_callee(caller(&OpcodeId::CALL, stack, caller_is_success));
+        }
+    }
+
+    /// Same as `test_ok` but the called address is not in the state.
+    fn test_ok_without_callee(caller: Account) {
+        let ctx = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x000000000000000000000000000000000000cafe"))
+                    .balance(Word::from(10u64.pow(19)));
+                accs[1].account(&caller);
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[0].address)
+                    .to(accs[1].address)
+                    .gas(100000.into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .params(FixedCParams {
+                max_rws: 500,
+                ..Default::default()
+            })
+            .run();
+    }
+
+    /// The transaction calls a child which writes to its storage, calls a
+    /// grandchild and writes again.  The grandchild warms an account and
+    /// writes to its storage.  Every write of a failed call, and of the
+    /// successful calls below it, must be reverted at the end of the failed
+    /// call.
+    #[test]
+    fn callop_nested_reversion() {
+        // Gas given to the grandchild, and its terminator
+        let grandchildren = [
+            (50000, OpcodeId::RETURN),
+            (50000, OpcodeId::REVERT),
+            // Out of gas on SSTORE, after warming the account
+            (5000, OpcodeId::RETURN),
+        ];
+        for ((grandchild_gas, grandchild_terminator), child_terminator) in grandchildren
+            .into_iter()
+            .cartesian_product([OpcodeId::RETURN, OpcodeId::REVERT])
+        {
+            test_nested_reversion(grandchild_gas, grandchild_terminator, child_terminator);
+        }
+    }
+
+    fn test_nested_reversion(
+        grandchild_gas: u64,
+        grandchild_terminator: OpcodeId,
+        child_terminator: OpcodeId,
+    ) {
+        let child_address = Address::repeat_byte(0xbb);
+        let grandchild_address = Address::repeat_byte(0xcc);
+        let grandchild_code = bytecode! {
+            PUSH20(Address::repeat_byte(0xdd).to_word())
+            BALANCE
+            POP
+            PUSH1(1)
+            PUSH1(1)
+            SSTORE
+            PUSH1(0)
+            PUSH1(0)
+            .write_op(grandchild_terminator)
+        };
+        let child_code = bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            SSTORE
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(grandchild_address.to_word())
+            PUSH32(Word::from(grandchild_gas))
+            CALL
+            POP
+            PUSH1(1)
+            PUSH1(2)
+            SSTORE
+            PUSH1(0)
+            PUSH1(0)
+            .write_op(child_terminator)
+        };
+        let root_code = bytecode! {
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH20(child_address.to_word())
+            PUSH32(Word::from(200000))
+            CALL
+            STOP
+        };
+
+        let ctx = TestContext::<4, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x000000000000000000000000000000000000cafe"))
+                    .balance(Word::from(10u64.pow(19)));
+                accs[1].account(&Account::mock_100_ether(root_code));
+                accs[2]
+                    .address(child_address)
+                    .code(child_code)
+                    .balance(Word::from(10u64.pow(18)));
+                accs[3]
+                    .address(grandchild_address)
+                    .code(grandchild_code)
+                    .balance(Word::from(10u64.pow(18)));
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[0].address)
+                    .to(accs[1].address)
+                    .gas(500000.into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .params(FixedCParams {
+                max_rws: 1000,
+                ..Default::default()
+            })
+            .run();
+    }
+
     fn test_recursive(opcode: &OpcodeId) {
         let is_call_or_callcode = opcode == &OpcodeId::CALL || opcode == &OpcodeId::CALLCODE;
         let mut caller_bytecode = bytecode! {
@@ -1414,7 +1529,7 @@ mod test {
             GAS
             PUSH1(100)
             GT
-            PUSH1(if is_call_or_callcode {43} else {41}) // jump dest
+            .push_label("gas_check")
             JUMPI
 
             PUSH1(0)
@@ -1434,19 +1549,18 @@ mod test {
             SUB
             .write_op(*opcode)
 
-            JUMPDEST // 41 for static_call, 43 for call
+            .jumpdest_label("gas_check")
             GAS
             PUSH1(1)
             AND
-            PUSH1(if is_call_or_callcode {56} else {54})
+            .push_label("stop")
             JUMPI
 
             PUSH1(0)
             PUSH1(0)
             REVERT
 
-            // 56 or 54 for call or static_call
-            JUMPDEST
+            .jumpdest_label("stop")
             STOP
         });
         test_ok(
@@ -1457,20 +1571,36 @@ mod test {
 
     #[test]
     fn test_depth() {
-        let callee_code = bytecode! {
-            PUSH1(0x00)
+        test_depth_bomb(&OpcodeId::CALL);
+    }
+
+    #[test]
+    fn test_depth_staticcall() {
+        test_depth_bomb(&OpcodeId::STATICCALL);
+    }
+
+    /// Recursive call to self until the call depth limit is reached.  The call
+    /// at the limit pushes 0 and the execution continues.
+    fn test_depth_bomb(opcode: &OpcodeId) {
+        let is_call_or_callcode = opcode == &OpcodeId::CALL || opcode == &OpcodeId::CALLCODE;
+        let mut callee_code = bytecode! {
             PUSH1(0x00)
             PUSH1(0x00)
             PUSH1(0x00)
             PUSH1(0x00)
+        };
+        if is_call_or_callcode {
+            callee_code.push(1, U256::zero());
+        }
+        callee_code.append(&bytecode! {
             ADDRESS
             PUSH2(0xffff)
             GAS
             SUB
-            CALL
+            .write_op(*opcode)
             PUSH1(0x01)
             SUB
-        };
+        });
 
         let ctx = TestContext::<2, 1>::new(
             None,
@@ -2,12 +2,12 @@ use crate::{
     evm_circuit::{
         execution::ExecutionGadget,
         param::{
-            N_BYTES_ACCOUNT_ADDRESS, N_BYTES_GAS, N_BYTES_MEMORY_ADDRESS, N_BYTES_MEMORY_WORD_SIZE,
-            N_BYTES_U64, N_BYTES_WORD,
+            N_BYTES_ACCOUNT_ADDRESS, N_BYTES_MEMORY_ADDRESS, N_BYTES_MEMORY_WORD_SIZE, N_BYTES_U64,
+            N_BYTES_WORD,
         },
         step::ExecutionState,
         util::{
-            common_gadget::TransferGadget,
+            common_gadget::{AllButOne64thGasGadget, CallContextGadget, TransferGadget},
             constraint_builder::{
                 ConstrainBuilderCommon, EVMConstraintBuilder, ReversionInfo, StepStateTransition,
                 Transition::{Delta, To},
@@ -33,7 +33,7 @@ use bus_mapping::{
     circuit_input_builder::CopyDataType, evm::OpcodeId, operation::Target, state_db::CodeDB,
 };
 use eth_types::{
-    evm_types::{GasCost, INIT_CODE_WORD_GAS},
+    evm_types::{GasCost, INIT_CODE_WORD_GAS, MAX_CALL_DEPTH},
     Field, OpsIdentity, ToBigEndian, ToScalar, ToWord, U256,
 };
 use ethers_core::utils::keccak256;
@@ -77,7 +77,7 @@ pub(crate) struct CreateGadget<F, const IS_CREATE2: bool, const S: ExecutionStat
     not_address_collision: IsZeroWordGadget<F, WordLoHi<Expression<F>>>,
 
     memory_expansion: MemoryExpansionGadget<F, 1, N_BYTES_MEMORY_WORD_SIZE>,
-    gas_left: ConstantDivisionGadget<F, N_BYTES_GAS>,
+    gas_left: AllButOne64thGasGadget<F>,
 }
 
 impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<F>
@@ -103,8 +103,8 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
         let is_success = cb.query_bool();
 
         // read from call context
-        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
-        let depth = cb.call_context(None, CallContextFieldTag::Depth);
+        let [tx_id, depth] = CallContextGadget::new(None)
+            .read(cb, [CallContextFieldTag::TxId, CallContextFieldTag::Depth]);
         let mut reversion_info = cb.reversion_info_read(None);
 
         let keccak_output = cb.query_word32();
@@ -143,7 +143,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
         );
 
         // Pre-check: call depth, user's nonce and user's balance
-        let is_depth_in_range = cb.is_lt(depth.expr(), 1025.expr());
+        let is_depth_in_range = cb.is_lt(depth.expr(), (MAX_CALL_DEPTH + 1).expr());
         let is_insufficient_balance = cb.is_lt_word(&caller_balance.to_word(), &value.to_word());
         let is_nonce_in_range = cb.is_lt(caller_nonce.expr(), u64::MAX.expr());
         let is_precheck_ok = and::expr([
@@ -168,8 +168,8 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
             );
         let gas_cost = GasCost::CREATE.expr() + memory_expansion.gas_cost() + keccak_gas_cost;
         let gas_remaining = cb.curr.state.gas_left.expr() - gas_cost.clone();
-        let gas_left = cb.div_by_const(gas_remaining.clone(), 64);
-        let callee_gas_left = gas_remaining - gas_left.quotient();
+        let gas_left = AllButOne64thGasGadget::construct(cb, gas_remaining);
+        let callee_gas_left = gas_left.all_but_one_64th_gas();
 
         let was_warm = cb.query_bool();
         let init_code_rlc = cb.query_cell_phase2();
@@ -229,34 +229,17 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
                 )
             });
 
-        for (field_tag, value) in [
-            (
-                CallContextFieldTag::ProgramCounter,
-                WordLoHi::from_lo_unchecked(cb.curr.state.program_counter.expr() + 1.expr()),
-            ),
-            (
-                CallContextFieldTag::StackPointer,
-                WordLoHi::from_lo_unchecked(
-                    cb.curr.state.stack_pointer.expr() + 2.expr() + is_create2.expr(),
-                ),
-            ),
-            (
-                CallContextFieldTag::GasLeft,
-                WordLoHi::from_lo_unchecked(gas_left.quotient()),
-            ),
-            (
-                CallContextFieldTag::MemorySize,
-                WordLoHi::from_lo_unchecked(memory_expansion.next_memory_word_size()),
-            ),
-            (
-                CallContextFieldTag::ReversibleWriteCounter,
-                WordLoHi::from_lo_unchecked(
-                    cb.curr.state.reversible_write_counter.expr() + 2.expr(),
-                ),
-            ),
-        ] {
-            cb.call_context_lookup_write(None, field_tag, value);
-        }
+        let program_counter = cb.curr.state.program_counter.expr() + 1.expr();
+        let stack_pointer = cb.curr.state.stack_pointer.expr() + 2.expr() + is_create2.expr();
+        let reversible_write_counter = cb.curr.state.reversible_write_counter.expr() + 2.expr();
+        CallContextGadget::new(None).save_state(
+            cb,
+            program_counter,
+            stack_pointer,
+            gas_left.one_64th_gas(),
+            memory_expansion.next_memory_word_size(),
+            reversible_write_counter,
+        );
 
         // We will put the initcode into bytecode circuit when is_precheck_ok.
         // Inside the bytecode, there will be a keccak codehash lookup.
@@ -288,17 +271,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
         // ErrNonceUintOverflow occurred.
         cb.condition(not::expr(is_precheck_ok.expr()), |cb| {
             // Save caller's call state
-            cb.call_context_lookup_write(
-                None,
-                CallContextFieldTag::LastCalleeId,
-                WordLoHi::from_lo_unchecked(callee_call_id.expr()),
-            );
-            for field_tag in [
-                CallContextFieldTag::LastCalleeReturnDataOffset,
-                CallContextFieldTag::LastCalleeReturnDataLength,
-            ] {
-                cb.call_context_lookup_write(None, field_tag, WordLoHi::zero());
-            }
+            CallContextGadget::new(None).reset_last_callee(cb, callee_call_id.expr());
 
             cb.require_step_state_transition(StepStateTransition {
                 rw_counter: Delta(cb.rw_counter_offset()),
@@ -413,17 +386,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
 
                 // handle state transition if empty init code
                 cb.condition(not::expr(init_code.has_length()), |cb| {
-                    cb.call_context_lookup_write(
-                        None,
-                        CallContextFieldTag::LastCalleeId,
-                        WordLoHi::from_lo_unchecked(callee_call_id.expr()),
-                    );
-                    for field_tag in [
-                        CallContextFieldTag::LastCalleeReturnDataOffset,
-                        CallContextFieldTag::LastCalleeReturnDataLength,
-                    ] {
-                        cb.call_context_lookup_write(None, field_tag, WordLoHi::zero());
-                    }
+                    CallContextGadget::new(None).reset_last_callee(cb, callee_call_id.expr());
                     cb.require_step_state_transition(StepStateTransition {
                         rw_counter: Delta(cb.rw_counter_offset()),
                         program_counter: Delta(1.expr()),
@@ -456,17 +419,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
             and::expr([is_precheck_ok, not::expr(not_address_collision.expr())]),
             |cb| {
                 // Save caller's call state
-                cb.call_context_lookup_write(
-                    None,
-                    CallContextFieldTag::LastCalleeId,
-                    WordLoHi::from_lo_unchecked(callee_call_id.expr()),
-                );
-                for field_tag in [
-                    CallContextFieldTag::LastCalleeReturnDataOffset,
-                    CallContextFieldTag::LastCalleeReturnDataLength,
-                ] {
-                    cb.call_context_lookup_write(None, field_tag, WordLoHi::zero());
-                }
+                CallContextGadget::new(None).reset_last_callee(cb, callee_call_id.expr());
 
                 cb.require_step_state_transition(StepStateTransition {
                     rw_counter: Delta(cb.rw_counter_offset()),
@@ -474,7 +427,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
                     stack_pointer: Delta(2.expr() + is_create2.expr()),
                     reversible_write_counter: Delta(2.expr()),
                     memory_word_size: To(memory_expansion.next_memory_word_size()),
-                    gas_left: To(gas_left.quotient()),
+                    gas_left: To(gas_left.one_64th_gas()),
                     ..StepStateTransition::default()
                 });
             },
@@ -557,8 +510,9 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
         // Pre-check: call depth, user's nonce and user's balance
         let caller_balance = rws.next().account_balance_pair().1;
         let caller_nonce = rws.next().account_nonce_pair().1.low_u64();
-        let is_precheck_ok =
-            call.depth < 1025 && caller_balance >= value && caller_nonce < u64::MAX;
+        let is_precheck_ok = call.depth as u64 <= MAX_CALL_DEPTH
+            && caller_balance >= value
+            && caller_nonce < u64::MAX;
 
         self.caller_balance
             .assign_u256(region, offset, caller_balance)?;
@@ -627,7 +581,7 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
             };
         let gas_left =
             step.gas_left - GasCost::CREATE - memory_expansion_gas_cost - initcode_gas_cost;
-        self.gas_left.assign(region, offset, gas_left.into())?;
+        self.gas_left.assign(region, offset, gas_left)?;
         self.callee_reversion_info.assign(
             region,
             offset,
@@ -718,8 +672,12 @@ impl<F: Field, const IS_CREATE2: bool, const S: ExecutionState> ExecutionGadget<
 
         self.is_insufficient_balance
             .assign(region, offset, caller_balance, value)?;
-        self.is_depth_in_range
-            .assign(region, offset, F::from(call.depth as u64), F::from(1025))?;
+        self.is_depth_in_range.assign(
+            region,
+            offset,
+            F::from(call.depth as u64),
+            F::from(MAX_CALL_DEPTH + 1),
+        )?;
         self.is_nonce_in_range
             .assign(region, offset, F::from(caller_nonce), F::from(u64::MAX))?;
 
@@ -5,7 +5,7 @@ use crate::{
         step::ExecutionState,
         util::{
             common_gadget::{TransferToGadget, UpdateBalanceGadget},
-            constraint_builder::EVMConstraintBuilder,
+            constraint_builder::{ConstrainBuilderCommon, EVMConstraintBuilder},
             math_gadget::{
                 AddWordsGadget, ConstantDivisionGadget, IsZeroWordGadget, MinMaxGadget,
                 MulWordByU64Gadget,
@@ -17,11 +17,11 @@ use crate::{
     },
     table::{AccountFieldTag, BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag},
     util::{
-        word::{WordExpr, WordLoHi, WordLoHiCell},
+        word::{Word32Cell, WordExpr, WordLoHi, WordLoHiCell},
         Expr,
     },
 };
-use eth_types::{evm_types::MAX_REFUND_QUOTIENT_OF_GAS_USED, Field};
+use eth_types::{evm_types::MAX_REFUND_QUOTIENT_OF_GAS_USED, Field, Word};
 use halo2_proofs::{circuit::Value, plonk::Error};
 
 #[derive(Clone, Debug)]
@@ -35,6 +35,8 @@ pub(crate) struct EndTxGadget<F> {
     tx_caller_address: WordLoHiCell<F>,
     gas_fee_refund: UpdateBalanceGadget<F, 2, true>,
     sub_gas_price_by_base_fee: AddWordsGadget<F, 2, true>,
+    // Only configured with `FeatureConfig::zero_gas_price`
+    gasless: Option<GaslessGadget<F>>,
     mul_effective_tip_by_gas_used: MulWordByU64Gadget<F>,
     coinbase: WordLoHiCell<F>,
     coinbase_code_hash: WordLoHiCell<F>,
@@ -44,6 +46,13 @@ pub(crate) struct EndTxGadget<F> {
     end_tx: EndTxHelperGadget<F>,
 }
 
+/// Cells used to support transactions with zero gas price
+#[derive(Clone, Debug)]
+struct GaslessGadget<F> {
+    tip_plus_base_fee: Word32Cell<F>,
+    gas_price_is_zero: IsZeroWordGadget<F, Word32Cell<F>>,
+}
+
 impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
     const NAME: &'static str = "EndTx";
 
@@ -99,8 +108,39 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             cb.block_lookup(tag.expr(), None, value);
         }
         let effective_tip = cb.query_word32();
-        let sub_gas_price_by_base_fee =
-            AddWordsGadget::construct(cb, [effective_tip.clone(), base_fee], tx_gas_price);
+        let (sub_gas_price_by_base_fee, gasless) = if cb.feature_config.zero_gas_price {
+            // Transactions with zero gas price pay no tip, otherwise
+            // effective_tip + base_fee == gas_price
+            let tip_plus_base_fee = cb.query_word32();
+            let sub_gas_price_by_base_fee = AddWordsGadget::construct(
+                cb,
+                [effective_tip.clone(), base_fee],
+                tip_plus_base_fee.clone(),
+            );
+            let gas_price_is_zero = cb.is_zero_word(&tx_gas_price);
+            cb.condition(gas_price_is_zero.expr(), |cb| {
+                cb.require_zero_word("effective_tip == 0", effective_tip.to_word());
+            });
+            cb.condition(1.expr() - gas_price_is_zero.expr(), |cb| {
+                cb.require_equal_word(
+                    "effective_tip + base_fee == gas_price",
+                    tip_plus_base_fee.to_word(),
+                    tx_gas_price.to_word(),
+                );
+            });
+            (
+                sub_gas_price_by_base_fee,
+                Some(GaslessGadget {
+                    tip_plus_base_fee,
+                    gas_price_is_zero,
+                }),
+            )
+        } else {
+            (
+                AddWordsGadget::construct(cb, [effective_tip.clone(), base_fee], tx_gas_price),
+                None,
+            )
+        };
         let mul_effective_tip_by_gas_used =
             MulWordByU64Gadget::construct(cb, effective_tip, gas_used.clone());
         let coinbase_reward = TransferToGadget::construct(
@@ -130,6 +170,7 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             tx_caller_address,
             gas_fee_refund,
             sub_gas_price_by_base_fee,
+            gasless,
             mul_effective_tip_by_gas_used,
             coinbase,
             coinbase_code_hash,
@@ -187,14 +228,27 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             vec![gas_fee_refund],
             caller_balance,
         )?;
-        let effective_tip = tx.gas_price - block.context.base_fee;
+        let effective_tip = if tx.gas_price.is_zero() && self.gasless.is_some() {
+            Word::zero()
+        } else {
+            tx.gas_price - block.context.base_fee
+        };
         let coinbase_reward = effective_tip * gas_used;
+        let tip_plus_base_fee = effective_tip + block.context.base_fee;
         self.sub_gas_price_by_base_fee.assign(
             region,
             offset,
             [effective_tip, block.context.base_fee],
-            tx.gas_price,
+            tip_plus_base_fee,
         )?;
+        if let Some(gasless) = &self.gasless {
+            gasless
+                .tip_plus_base_fee
+                .assign_u256(region, offset, tip_plus_base_fee)?;
+            gasless
+                .gas_price_is_zero
+                .assign_u256(region, offset, tx.gas_price)?;
+        }
         self.mul_effective_tip_by_gas_used.assign(
             region,
             offset,
@@ -231,7 +285,10 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
 mod test {
 
     use crate::{table::CallContextFieldTag, test_util::CircuitTestBuilder};
-    use bus_mapping::{circuit_input_builder::FixedCParams, operation::Target};
+    use bus_mapping::{
+        circuit_input_builder::{FeatureConfig, FixedCParams},
+        operation::Target,
+    };
     use eth_types::{self, bytecode, Word};
     use itertools::Itertools;
     use mock::{
@@ -412,6 +469,29 @@ mod test {
         );
     }
 
+    #[test]
+    fn end_tx_zero_gas_price() {
+        let ctx = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            |mut txs, accs| {
+                txs[0]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .gas_price(Word::zero());
+            },
+            |block, _tx| block,
+        )
+        .unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .feature(FeatureConfig {
+                zero_gas_price: true,
+                ..Default::default()
+            })
+            .run();
+    }
+
     #[test]
     fn end_tx_consistent_tx_id_write() {
         // check there is no consecutive txid write with same txid in rw_table
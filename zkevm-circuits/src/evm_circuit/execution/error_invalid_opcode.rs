@@ -1,7 +1,6 @@
 use crate::evm_circuit::{
     execution::ExecutionGadget,
     step::ExecutionState,
-    table::{FixedTableTag, Lookup},
     util::{
         common_gadget::CommonErrorGadget, constraint_builder::EVMConstraintBuilder, CachedRegion,
         Cell,
@@ -27,17 +26,7 @@ impl<F: Field> ExecutionGadget<F> for ErrorInvalidOpcodeGadget<F> {
 
     fn configure(cb: &mut EVMConstraintBuilder<F>) -> Self {
         let opcode = cb.query_cell();
-        cb.add_lookup(
-            "Responsible opcode lookup",
-            Lookup::Fixed {
-                tag: FixedTableTag::ResponsibleOpcode.expr(),
-                values: [
-                    Self::EXECUTION_STATE.as_u64().expr(),
-                    opcode.expr(),
-                    0.expr(),
-                ],
-            },
-        );
+        cb.responsible_opcode_lookup(opcode.expr(), 0.expr());
 
         let common_error_gadget = CommonErrorGadget::construct(cb, opcode.expr(), 0.expr());
 
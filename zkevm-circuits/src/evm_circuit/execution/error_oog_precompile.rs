@@ -253,6 +253,17 @@ mod test {
                         - 1).to_word(),
                     ..Default::default()
                 },
+                PrecompileCallArgs {
+                    name: "ecrecover out of gas",
+                    setup_code: bytecode! {},
+                    call_data_offset: 0x00.into(),
+                    call_data_length: 0x80.into(),
+                    ret_offset: 0x00.into(),
+                    ret_size: 0x20.into(),
+                    address: PrecompileCalls::Ecrecover.address().to_word(),
+                    gas: (PrecompileCalls::Ecrecover.base_gas_cost() - 1).to_word(),
+                    ..Default::default()
+                },
             ]
         };
     }
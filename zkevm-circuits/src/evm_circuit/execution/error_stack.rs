@@ -2,7 +2,6 @@ use crate::{
     evm_circuit::{
         execution::ExecutionGadget,
         step::ExecutionState,
-        table::{FixedTableTag, Lookup},
         util::{
             common_gadget::CommonErrorGadget, constraint_builder::EVMConstraintBuilder,
             CachedRegion, Cell,
@@ -28,17 +27,7 @@ impl<F: Field> ExecutionGadget<F> for ErrorStackGadget<F> {
     fn configure(cb: &mut EVMConstraintBuilder<F>) -> Self {
         let opcode = cb.query_cell();
 
-        cb.add_lookup(
-            "Responsible opcode lookup for invalid stack pointer",
-            Lookup::Fixed {
-                tag: FixedTableTag::ResponsibleOpcode.expr(),
-                values: [
-                    Self::EXECUTION_STATE.as_u64().expr(),
-                    opcode.expr(),
-                    cb.curr.state.stack_pointer.expr(),
-                ],
-            },
-        );
+        cb.responsible_opcode_lookup(opcode.expr(), cb.curr.state.stack_pointer.expr());
 
         let common_error_gadget = CommonErrorGadget::construct(cb, opcode.expr(), 0.expr());
 
@@ -156,7 +156,7 @@ impl<F: Field> ExecutionGadget<F> for ExtcodesizeGadget<F> {
 #[cfg(test)]
 mod test {
     use crate::{evm_circuit::test::rand_bytes, test_util::CircuitTestBuilder};
-    use eth_types::{bytecode, geth_types::Account, Bytecode, ToWord};
+    use eth_types::{bytecode, geth_types::Account, Bytecode, ToWord, U256, U64};
     use mock::{
         generate_mock_call_bytecode, MockCallBytecodeParams, TestContext, MOCK_1_ETH,
         MOCK_ACCOUNTS, MOCK_CODES,
@@ -192,6 +192,27 @@ mod test {
         test_ok(&account, true);
     }
 
+    #[test]
+    fn test_extcodesize_gadget_existing_account_without_code() {
+        // Non empty accounts without code are read with the empty code hash, whose
+        // length must be looked up in the bytecode table.
+        let balance_only_account = Account {
+            address: MOCK_ACCOUNTS[4],
+            balance: U256::from(200),
+            ..Default::default()
+        };
+        let nonce_only_account = Account {
+            address: MOCK_ACCOUNTS[4],
+            nonce: U64::from(200),
+            ..Default::default()
+        };
+
+        for account in [balance_only_account, nonce_only_account] {
+            test_ok(&account, false);
+            test_ok(&account, true);
+        }
+    }
+
     fn test_ok(account: &Account, is_warm: bool) {
         let account_exists = !account.is_empty();
 
@@ -228,7 +249,11 @@ mod test {
                 accs[1].address(addr_a).code(code_a);
                 // Set code if account exists.
                 if account_exists {
-                    accs[2].address(account.address).code(account.code.clone());
+                    accs[2]
+                        .address(account.address)
+                        .code(account.code.clone())
+                        .balance(account.balance)
+                        .nonce(account.nonce.as_u64());
                 } else {
                     accs[2].address(mock::MOCK_ACCOUNTS[2]).balance(*MOCK_1_ETH);
                 }
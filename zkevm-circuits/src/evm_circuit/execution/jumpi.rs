@@ -152,6 +152,16 @@ mod test {
         test_ok(1 << 11, 0.into());
     }
 
+    #[test]
+    fn jumpi_gadget_condition_in_one_limb() {
+        // Nonzero only in the high limb
+        test_ok(68, Word::one() << 128);
+        test_ok(68, Word::one() << 255);
+        test_ok(68, Word::MAX ^ (Word::MAX >> 128));
+        // Nonzero only in the low limb
+        test_ok(68, Word::MAX >> 128);
+    }
+
     #[test]
     #[ignore]
     fn jumpi_gadget_huge_bytecode() {
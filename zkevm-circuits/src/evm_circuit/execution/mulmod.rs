@@ -164,8 +164,8 @@ impl<F: Field> ExecutionGadget<F> for MulModGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::CircuitTestBuilder;
-    use eth_types::{bytecode, evm_types::Stack, Word, U256};
+    use crate::{evm_circuit::test::rand_word, test_util::CircuitTestBuilder};
+    use eth_types::{bytecode, evm_types::Stack, Word, U256, U512};
     use mock::TestContext;
 
     fn test(a: Word, b: Word, n: Word, r: Option<Word>, ok: bool) {
@@ -266,4 +266,26 @@ mod test {
         test_ok_u32(2, 3, 5, Some(1));
         test_ko_u32(2, 3, 5, Some(5));
     }
+
+    /// Reference MULMOD, with the N == 0 -> 0 rule
+    fn mulmod(a: Word, b: Word, n: Word) -> Word {
+        if n.is_zero() {
+            return Word::zero();
+        }
+        let r = a.full_mul(b) % U512::from(n);
+        Word::try_from(r).unwrap()
+    }
+
+    #[test]
+    fn mulmod_rand() {
+        for n in [
+            rand_word(),
+            rand_word() >> 128,
+            Word::from(rand_word().low_u64()),
+            Word::zero(),
+        ] {
+            let (a, b) = (rand_word(), rand_word());
+            test(a, b, n, Some(mulmod(a, b, n)), true);
+        }
+    }
 }
@@ -218,6 +218,7 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
 #[cfg(test)]
 mod test {
     use crate::{evm_circuit::test::rand_bytes, test_util::CircuitTestBuilder};
+    use bus_mapping::circuit_input_builder::FeatureConfig;
     use eth_types::{bytecode, evm_types::OpcodeId};
     use mock::TestContext;
 
@@ -261,6 +262,29 @@ mod test {
         test_ok(OpcodeId::PUSH16, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
     }
 
+    #[test]
+    fn push_gadget_disabled_opcode() {
+        let bytecode = bytecode! {
+            PUSH0
+            STOP
+        };
+
+        // The witness is generated for mainnet, and the circuit is configured for a network
+        // without PUSH0
+        CircuitTestBuilder::new_from_test_ctx(
+            TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode).unwrap(),
+        )
+        .block_modifier(Box::new(|block| {
+            block.feature_config = FeatureConfig {
+                disabled_opcodes: vec![OpcodeId::PUSH0],
+                ..Default::default()
+            }
+        }))
+        .run_with_result()
+        .unwrap_err()
+        .assert_evm_failure()
+    }
+
     #[test]
     fn push_gadget_rand() {
         for (idx, opcode) in vec![
@@ -28,37 +28,243 @@ use eth_types::{
     evm_types::{GasCost, INVALID_INIT_CODE_FIRST_BYTE},
     Field, ToScalar, U256,
 };
-use halo2_proofs::{circuit::Value, plonk::Error};
+use halo2_proofs::{
+    circuit::Value,
+    plonk::{Error, Expression},
+};
 
 #[derive(Clone, Debug)]
 pub(crate) struct ReturnRevertGadget<F> {
     opcode: Cell<F>,
 
     range: MemoryAddressGadget<F>,
-    deployed_code_rlc: Cell<F>,
 
     is_success: Cell<F>,
     restore_context: RestoreContextGadget<F>,
 
-    // Used to check first byte of create init code must not be 0xef (EIP-3541).
-    init_code_first_byte: Cell<F>,
-    is_init_code_first_byte_invalid: IsEqualGadget<F>,
-
-    copy_length: MinMaxGadget<F, N_BYTES_MEMORY_ADDRESS>,
     copy_rw_increase: Cell<F>,
     copy_rw_increase_is_zero: IsZeroGadget<F>,
 
-    return_data_offset: Cell<F>,
-    return_data_length: Cell<F>,
-
     memory_expansion: MemoryExpansionGadget<F, 1, N_BYTES_MEMORY_WORD_SIZE>,
+
+    code_deposit: CodeDepositGadget<F>,
+    return_data_copy: ReturnDataCopyGadget<F>,
+}
+
+/// Create context path of RETURN: the returned memory is deposited as the
+/// code of the created contract (case A of the specs).
+#[derive(Clone, Debug)]
+struct CodeDepositGadget<F> {
+    // Used to check first byte of create init code must not be 0xef (EIP-3541).
+    init_code_first_byte: Cell<F>,
+    is_init_code_first_byte_invalid: IsEqualGadget<F>,
+
     code_hash: Word32Cell<F>,
+    deployed_code_rlc: Cell<F>,
 
     caller_id: Cell<F>,
     address: WordLoHiCell<F>,
     reversion_info: ReversionInfo<F>,
 }
 
+impl<F: Field> CodeDepositGadget<F> {
+    /// Constrain the deposit of the non empty code in `range`, copied with
+    /// `copy_rw_increase` memory reads.  Must be constructed under the
+    /// condition of a successful create call returning some code.
+    fn construct(
+        cb: &mut EVMConstraintBuilder<F>,
+        range: &MemoryAddressGadget<F>,
+        copy_rw_increase: Expression<F>,
+    ) -> Self {
+        // Read the first byte and check it must not be 0xef (EIP-3541).
+        let init_code_first_byte = cb.query_byte();
+        cb.memory_lookup(0.expr(), range.offset(), init_code_first_byte.expr(), None);
+        let is_init_code_first_byte_invalid = IsEqualGadget::construct(
+            cb,
+            init_code_first_byte.expr(),
+            INVALID_INIT_CODE_FIRST_BYTE.expr(),
+        );
+        cb.require_zero(
+            "First byte of create init code must not be 0xef",
+            is_init_code_first_byte_invalid.expr(),
+        );
+
+        // We don't need to place any additional constraints on code_hash because the
+        // copy circuit enforces that it is the hash of the bytes in the copy lookup.
+        let code_hash = cb.query_word32();
+        let deployed_code_rlc = cb.query_cell_phase2();
+        cb.copy_table_lookup(
+            WordLoHi::from_lo_unchecked(cb.curr.state.call_id.expr()),
+            CopyDataType::Memory.expr(),
+            code_hash.to_word(),
+            CopyDataType::Bytecode.expr(),
+            range.offset(),
+            range.address(),
+            0.expr(),
+            range.length(),
+            deployed_code_rlc.expr(),
+            copy_rw_increase,
+        );
+
+        let caller_id = cb.call_context(None, CallContextFieldTag::CallerId);
+        let address = cb.call_context_read_as_word(None, CallContextFieldTag::CalleeAddress);
+
+        let mut reversion_info = cb.reversion_info_read(None);
+
+        cb.account_write(
+            address.to_word(),
+            AccountFieldTag::CodeHash,
+            code_hash.to_word(),
+            cb.empty_code_hash(),
+            Some(&mut reversion_info),
+        );
+
+        Self {
+            init_code_first_byte,
+            is_init_code_first_byte_invalid,
+            code_hash,
+            deployed_code_rlc,
+            caller_id,
+            address,
+            reversion_info,
+        }
+    }
+
+    /// Assign the deposit of the `length` bytes of code returned by a
+    /// successful create call.  Only called on that path.
+    fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        call: &Call,
+        step: &ExecStep,
+        length: U256,
+    ) -> Result<(), Error> {
+        let values: Vec<_> = (4..4 + length.as_usize())
+            .map(|index| block.get_rws(step, index).memory_value())
+            .collect();
+        self.deployed_code_rlc.assign(
+            region,
+            offset,
+            region.keccak_rlc(&values.iter().rev().cloned().collect::<Vec<u8>>()),
+        )?;
+        let mut code_hash = CodeDB::hash(&values).to_fixed_bytes();
+        code_hash.reverse();
+        self.code_hash
+            .assign_u256(region, offset, U256::from_little_endian(&code_hash))?;
+
+        let init_code_first_byte = u64::from(block.get_rws(step, 3).memory_value());
+        self.init_code_first_byte.assign(
+            region,
+            offset,
+            Value::known(F::from(init_code_first_byte)),
+        )?;
+        self.is_init_code_first_byte_invalid.assign(
+            region,
+            offset,
+            F::from(init_code_first_byte),
+            F::from(INVALID_INIT_CODE_FIRST_BYTE.into()),
+        )?;
+
+        self.caller_id.assign(
+            region,
+            offset,
+            Value::known(call.caller_id.to_scalar().unwrap()),
+        )?;
+
+        self.address.assign_h160(region, offset, call.address)?;
+
+        self.reversion_info.assign(
+            region,
+            offset,
+            call.rw_counter_end_of_reversion,
+            call.is_persistent,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Message call context path of RETURN and REVERT: the returned memory is
+/// copied to the return data range of the caller (case D of the specs).
+#[derive(Clone, Debug)]
+struct ReturnDataCopyGadget<F> {
+    return_data_offset: Cell<F>,
+    return_data_length: Cell<F>,
+    copy_length: MinMaxGadget<F, N_BYTES_MEMORY_ADDRESS>,
+}
+
+impl<F: Field> ReturnDataCopyGadget<F> {
+    /// Constrain the copy of `range` to the return data range of the caller,
+    /// done with `copy_rw_increase` memory reads and writes.  Must be
+    /// constructed under the condition of a non root message call.
+    fn construct(
+        cb: &mut EVMConstraintBuilder<F>,
+        range: &MemoryAddressGadget<F>,
+        copy_rw_increase: Expression<F>,
+        copy_rw_increase_is_zero: Expression<F>,
+    ) -> Self {
+        let [return_data_offset, return_data_length] = [
+            CallContextFieldTag::ReturnDataOffset,
+            CallContextFieldTag::ReturnDataLength,
+        ]
+        .map(|field_tag| cb.call_context(None, field_tag));
+        let copy_length = cb.min_max(return_data_length.expr(), range.length());
+        cb.require_equal(
+            "increase rw counter twice for each memory to memory byte copied",
+            copy_length.min() + copy_length.min(),
+            copy_rw_increase.clone(),
+        );
+        cb.condition(not::expr(copy_rw_increase_is_zero), |cb| {
+            cb.copy_table_lookup(
+                WordLoHi::from_lo_unchecked(cb.curr.state.call_id.expr()),
+                CopyDataType::Memory.expr(),
+                WordLoHi::from_lo_unchecked(cb.next.state.call_id.expr()),
+                CopyDataType::Memory.expr(),
+                range.offset(),
+                range.address(),
+                return_data_offset.expr(),
+                copy_length.min(),
+                0.expr(),
+                copy_rw_increase,
+            );
+        });
+
+        Self {
+            return_data_offset,
+            return_data_length,
+            copy_length,
+        }
+    }
+
+    /// Assign the copy of `length` returned bytes to the caller of a non root
+    /// message call.  Only called on that path.
+    fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        call: &Call,
+        length: U256,
+    ) -> Result<(), Error> {
+        for (cell, value) in [
+            (&self.return_data_length, call.return_data_length.into()),
+            (&self.return_data_offset, call.return_data_offset.into()),
+        ] {
+            cell.assign(region, offset, Value::known(value))?;
+        }
+
+        self.copy_length.assign(
+            region,
+            offset,
+            F::from(call.return_data_length),
+            F::from(length.as_u64()),
+        )?;
+
+        Ok(())
+    }
+}
+
 impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
     const NAME: &'static str = "RETURN_REVERT";
 
@@ -110,67 +316,8 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
         let code_deposit_cost = is_contract_deployment.clone()
             * GasCost::CODE_DEPOSIT_BYTE_COST.expr()
             * range.length();
-        let (
-            caller_id,
-            address,
-            reversion_info,
-            code_hash,
-            deployed_code_rlc,
-            init_code_first_byte,
-            is_init_code_first_byte_invalid,
-        ) = cb.condition(is_contract_deployment.clone(), |cb| {
-            // Read the first byte and check it must not be 0xef (EIP-3541).
-            let init_code_first_byte = cb.query_byte();
-            cb.memory_lookup(0.expr(), range.offset(), init_code_first_byte.expr(), None);
-            let is_init_code_first_byte_invalid = IsEqualGadget::construct(
-                cb,
-                init_code_first_byte.expr(),
-                INVALID_INIT_CODE_FIRST_BYTE.expr(),
-            );
-            cb.require_zero(
-                "First byte of create init code must not be 0xef",
-                is_init_code_first_byte_invalid.expr(),
-            );
-
-            // We don't need to place any additional constraints on code_hash because the
-            // copy circuit enforces that it is the hash of the bytes in the copy lookup.
-            let code_hash = cb.query_word32();
-            let deployed_code_rlc = cb.query_cell_phase2();
-            cb.copy_table_lookup(
-                WordLoHi::from_lo_unchecked(cb.curr.state.call_id.expr()),
-                CopyDataType::Memory.expr(),
-                code_hash.to_word(),
-                CopyDataType::Bytecode.expr(),
-                range.offset(),
-                range.address(),
-                0.expr(),
-                range.length(),
-                deployed_code_rlc.expr(),
-                copy_rw_increase.expr(),
-            );
-
-            let caller_id = cb.call_context(None, CallContextFieldTag::CallerId);
-            let address = cb.call_context_read_as_word(None, CallContextFieldTag::CalleeAddress);
-
-            let mut reversion_info = cb.reversion_info_read(None);
-
-            cb.account_write(
-                address.to_word(),
-                AccountFieldTag::CodeHash,
-                code_hash.to_word(),
-                cb.empty_code_hash(),
-                Some(&mut reversion_info),
-            );
-
-            (
-                caller_id,
-                address,
-                reversion_info,
-                code_hash,
-                deployed_code_rlc,
-                init_code_first_byte,
-                is_init_code_first_byte_invalid,
-            )
+        let code_deposit = cb.condition(is_contract_deployment.clone(), |cb| {
+            CodeDepositGadget::construct(cb, &range, copy_rw_increase.expr())
         });
 
         // Case B in the specs.
@@ -210,40 +357,15 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
         });
 
         // Case D in the specs.
-        let (return_data_offset, return_data_length, copy_length) = cb.condition(
+        let return_data_copy = cb.condition(
             not::expr(is_create.clone()) * not::expr(is_root.clone()),
             |cb| {
-                let [return_data_offset, return_data_length] = [
-                    CallContextFieldTag::ReturnDataOffset,
-                    CallContextFieldTag::ReturnDataLength,
-                ]
-                .map(|field_tag| cb.call_context(None, field_tag));
-                let copy_length = cb.min_max(return_data_length.expr(), range.length());
-                cb.require_equal(
-                    "increase rw counter twice for each memory to memory byte copied",
-                    copy_length.min() + copy_length.min(),
+                ReturnDataCopyGadget::construct(
+                    cb,
+                    &range,
                     copy_rw_increase.expr(),
-                );
-                (return_data_offset, return_data_length, copy_length)
-            },
-        );
-        cb.condition(
-            not::expr(is_create.clone())
-                * not::expr(is_root.clone())
-                * not::expr(copy_rw_increase_is_zero.expr()),
-            |cb| {
-                cb.copy_table_lookup(
-                    WordLoHi::from_lo_unchecked(cb.curr.state.call_id.expr()),
-                    CopyDataType::Memory.expr(),
-                    WordLoHi::from_lo_unchecked(cb.next.state.call_id.expr()),
-                    CopyDataType::Memory.expr(),
-                    range.offset(),
-                    range.address(),
-                    return_data_offset.expr(),
-                    copy_length.min(),
-                    0.expr(),
-                    copy_rw_increase.expr(),
-                );
+                    copy_rw_increase_is_zero.expr(),
+                )
             },
         );
 
@@ -259,21 +381,13 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
         Self {
             opcode,
             range,
-            deployed_code_rlc,
             is_success,
-            init_code_first_byte,
-            is_init_code_first_byte_invalid,
-            copy_length,
             copy_rw_increase,
             copy_rw_increase_is_zero,
-            return_data_offset,
-            return_data_length,
             restore_context,
             memory_expansion,
-            code_hash,
-            address,
-            caller_id,
-            reversion_info,
+            code_deposit,
+            return_data_copy,
         }
     }
 
@@ -303,35 +417,13 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
             Value::known(F::from(call.is_success as u64)),
         )?;
 
-        if !call.is_root && !call.is_create() {
-            for (cell, value) in [
-                (&self.return_data_length, call.return_data_length.into()),
-                (&self.return_data_offset, call.return_data_offset.into()),
-            ] {
-                cell.assign(region, offset, Value::known(value))?;
-            }
-
-            self.copy_length.assign(
-                region,
-                offset,
-                F::from(call.return_data_length),
-                F::from(length.as_u64()),
-            )?;
+        let is_contract_deployment = call.is_create() && call.is_success && !length.is_zero();
+        if is_contract_deployment {
+            self.code_deposit
+                .assign(region, offset, block, call, step, length)?;
         }
-
-        if call.is_create() && call.is_success {
-            let values: Vec<_> = (4..4 + length.as_usize())
-                .map(|index| block.get_rws(step, index).memory_value())
-                .collect();
-            self.deployed_code_rlc.assign(
-                region,
-                offset,
-                region.keccak_rlc(&values.iter().rev().cloned().collect::<Vec<u8>>()),
-            )?;
-            let mut code_hash = CodeDB::hash(&values).to_fixed_bytes();
-            code_hash.reverse();
-            self.code_hash
-                .assign_u256(region, offset, U256::from_little_endian(&code_hash))?;
+        if !call.is_root && !call.is_create() {
+            self.return_data_copy.assign(region, offset, call, length)?;
         }
 
         let copy_rw_increase = if call.is_create() && call.is_success {
@@ -346,26 +438,6 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
         self.copy_rw_increase_is_zero
             .assign(region, offset, F::from(copy_rw_increase))?;
 
-        let is_contract_deployment = call.is_create() && call.is_success && !length.is_zero();
-
-        let init_code_first_byte = if is_contract_deployment {
-            block.get_rws(step, 3).memory_value()
-        } else {
-            0
-        }
-        .into();
-        self.init_code_first_byte.assign(
-            region,
-            offset,
-            Value::known(F::from(init_code_first_byte)),
-        )?;
-        self.is_init_code_first_byte_invalid.assign(
-            region,
-            offset,
-            F::from(init_code_first_byte),
-            F::from(INVALID_INIT_CODE_FIRST_BYTE.into()),
-        )?;
-
         if !call.is_root {
             let rw_counter_offset = 3 + if is_contract_deployment {
                 6 + length.as_u64()
@@ -382,21 +454,6 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
             )?;
         }
 
-        self.caller_id.assign(
-            region,
-            offset,
-            Value::known(call.caller_id.to_scalar().unwrap()),
-        )?;
-
-        self.address.assign_h160(region, offset, call.address)?;
-
-        self.reversion_info.assign(
-            region,
-            offset,
-            call.rw_counter_end_of_reversion,
-            call.is_persistent,
-        )?;
-
         Ok(())
     }
 }
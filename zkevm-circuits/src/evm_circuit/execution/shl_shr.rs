@@ -263,4 +263,17 @@ mod test {
         test_ok(OpcodeId::SHR, max_word, Word::from(129));
         test_ok(OpcodeId::SHR, rand_word(), rand_word());
     }
+
+    #[test]
+    fn shl_shr_gadget_large_shifts() {
+        let max_word = Word::MAX;
+        for opcode in [OpcodeId::SHL, OpcodeId::SHR] {
+            test_ok(opcode, max_word, Word::from(255));
+            test_ok(opcode, max_word, Word::from(256));
+            test_ok(opcode, max_word, Word::one() << 64);
+            test_ok(opcode, max_word, Word::one() << 128);
+            test_ok(opcode, max_word, Word::one() << 255);
+            test_ok(opcode, max_word, max_word);
+        }
+    }
 }
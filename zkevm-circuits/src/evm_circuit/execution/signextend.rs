@@ -245,6 +245,17 @@ mod test {
         test_ok(258.into(), 0xF00201.into(), 0xF00201.into());
     }
 
+    #[test]
+    fn signextend_gadget_index_boundaries() {
+        let value = Word::MAX >> 1;
+        // Extending from the most significant byte leaves the value unchanged
+        test_ok(31.into(), value, value);
+        // Out of range indices, including ones with non zero high bytes
+        test_ok(32.into(), value, value);
+        test_ok(Word::one() << 128, value, value);
+        test_ok(Word::MAX, value, value);
+    }
+
     #[test]
     fn signextend_gadget_rand() {
         let signextend = |index: Word, value: Word| -> Word {
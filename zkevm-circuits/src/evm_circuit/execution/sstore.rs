@@ -473,6 +473,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn sstore_gadget_committed_value_across_txs() {
+        // The first tx sets a clean slot and then dirties it. The second tx
+        // starts with the value left by the first one as committed value,
+        // updates it and then resets it to that committed value.
+        let key = Word::from(0x030201);
+        let bytecode = bytecode! {
+            PUSH1(1)
+            PUSH32(key)
+            SSTORE
+            PUSH1(2)
+            PUSH32(key)
+            SSTORE
+            STOP
+        };
+        let ctx = TestContext::<2, 2>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(10u64.pow(19)))
+                    .code(bytecode);
+                accs[1]
+                    .address(MOCK_ACCOUNTS[1])
+                    .balance(Word::from(10u64.pow(19)));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[1].address);
+                txs[1].to(accs[0].address).from(accs[1].address);
+            },
+            |block, _txs| block,
+        )
+        .unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx).run();
+    }
+
     fn test_ok(key: Word, value: Word, value_prev: Word, original_value: Word) {
         // Here we use two bytecodes to test both is_persistent(STOP) or not(REVERT)
         // Besides, in bytecode we use two SSTOREs,
@@ -0,0 +1,402 @@
+//! Harness to test a single execution gadget in isolation.
+//!
+//! [`ExecutionGadgetTestCircuit`] configures only the gadget `G`, with the
+//! lookup tables of the EVM circuit loaded from the witness block, and
+//! assigns only the steps of the block in the execution state of `G`.  Each
+//! step is followed by the state of its next step, so that the state
+//! transition constraints of the gadget are checked, but the other gadgets,
+//! the step selector and the first and last step constraints are left out.
+//! This makes the circuit much faster to configure than the EVM circuit, and
+//! the failures of a gadget easy to tell apart.
+
+use super::{ExecutionConfig, ExecutionGadget};
+use crate::{
+    evm_circuit::{
+        detect_fixed_table_tags,
+        param::{
+            EVM_LOOKUP_COLS, MAX_STEP_HEIGHT, N_PHASE2_COLUMNS, STEP_STATE_HEIGHT, STEP_WIDTH,
+        },
+        step::{ExecutionState, Step},
+        util::{constraint_builder::EVMConstraintBuilder, CachedRegion, StoredExpression},
+    },
+    table::{
+        BlockTable, BytecodeTable, CopyTable, ExpTable, KeccakTable, RwTable, SigTable, TxTable,
+        UXTable,
+    },
+    util::Challenges,
+    witness::{Block, Transaction},
+};
+use bus_mapping::circuit_input_builder::FeatureConfig;
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::{MockProver, VerifyFailure},
+    halo2curves::bn256::Fr,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, FirstPhase, Fixed, SecondPhase, Selector,
+        ThirdPhase,
+    },
+};
+use itertools::Itertools;
+use std::marker::PhantomData;
+
+/// Configuration of the [`ExecutionGadgetTestCircuit`]
+#[derive(Clone)]
+pub(crate) struct ExecutionGadgetTestConfig<F: Field, G> {
+    q_step: Selector,
+    advices: [Column<Advice>; STEP_WIDTH],
+    step: Step<F>,
+    height: usize,
+    gadget: G,
+    stored_expressions: Vec<StoredExpression<F>>,
+    fixed_table: [Column<Fixed>; 4],
+    u8_table: UXTable<8>,
+    u16_table: UXTable<16>,
+    tx_table: TxTable,
+    rw_table: RwTable,
+    bytecode_table: BytecodeTable,
+    block_table: BlockTable,
+    copy_table: CopyTable,
+    keccak_table: KeccakTable,
+    exp_table: ExpTable,
+    sig_table: SigTable,
+}
+
+/// Circuit verifying the steps of a block in the execution state of `G` with
+/// the constraints of `G` only
+pub(crate) struct ExecutionGadgetTestCircuit<F: Field, G> {
+    block: Block<F>,
+    _marker: PhantomData<G>,
+}
+
+impl<F: Field, G> ExecutionGadgetTestCircuit<F, G> {
+    pub(crate) fn new(block: Block<F>) -> Self {
+        Self {
+            block,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Field, G: ExecutionGadget<F> + Clone> Circuit<F> for ExecutionGadgetTestCircuit<F, G> {
+    type Config = (ExecutionGadgetTestConfig<F, G>, Challenges);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = FeatureConfig;
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(Block::default())
+    }
+
+    fn params(&self) -> Self::Params {
+        self.block.feature_config.clone()
+    }
+
+    fn configure_with_params(meta: &mut ConstraintSystem<F>, params: Self::Params) -> Self::Config {
+        let tx_table = TxTable::construct(meta);
+        let rw_table = RwTable::construct(meta);
+        let bytecode_table = BytecodeTable::construct(meta);
+        let block_table = BlockTable::construct(meta);
+        let q_copy_table = meta.fixed_column();
+        let copy_table = CopyTable::construct(meta, q_copy_table);
+        let keccak_table = KeccakTable::construct(meta);
+        let exp_table = ExpTable::construct(meta);
+        let u8_table = UXTable::construct(meta);
+        let u16_table = UXTable::construct(meta);
+        let sig_table = SigTable::construct(meta);
+        let fixed_table = [(); 4].map(|_| meta.fixed_column());
+        let challenges = Challenges::construct(meta);
+        let challenges_exprs = challenges.exprs(meta);
+
+        let q_step = meta.complex_selector();
+        let advices = [(); STEP_WIDTH]
+            .iter()
+            .enumerate()
+            .map(|(n, _)| {
+                if n < EVM_LOOKUP_COLS {
+                    meta.advice_column_in(ThirdPhase)
+                } else if n < EVM_LOOKUP_COLS + N_PHASE2_COLUMNS {
+                    meta.advice_column_in(SecondPhase)
+                } else {
+                    meta.advice_column_in(FirstPhase)
+                }
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let step_curr = Step::new(meta, advices, 0);
+
+        // Configure the gadget with the max height first to find out its height,
+        // like the EVM circuit does
+        let height = {
+            let dummy_step_next = Step::new(meta, advices, MAX_STEP_HEIGHT);
+            let mut cb = EVMConstraintBuilder::new(
+                meta,
+                step_curr.clone(),
+                dummy_step_next,
+                &challenges_exprs,
+                G::EXECUTION_STATE,
+                params,
+            );
+            G::configure(&mut cb);
+            let (_, _, height, _) = cb.build();
+            height
+        };
+        let step_next = Step::new(meta, advices, height);
+        let mut cb = EVMConstraintBuilder::new(
+            meta,
+            step_curr.clone(),
+            step_next,
+            &challenges_exprs,
+            G::EXECUTION_STATE,
+            params,
+        );
+        let gadget = G::configure(&mut cb);
+        let (constraints, stored_expressions, _, _) = cb.build();
+
+        // No assigned step is the first or the last one of the block
+        let step_constraints = constraints
+            .step
+            .into_iter()
+            .chain(constraints.not_step_last)
+            .collect_vec();
+        if !step_constraints.is_empty() {
+            meta.create_gate(G::NAME, |meta| {
+                let q_step = meta.query_selector(q_step);
+                step_constraints
+                    .into_iter()
+                    .map(move |(name, constraint)| (name, q_step.clone() * constraint))
+            });
+        }
+
+        ExecutionConfig::configure_lookup(
+            meta,
+            &fixed_table,
+            &u8_table,
+            &u16_table,
+            &tx_table,
+            &rw_table,
+            &bytecode_table,
+            &block_table,
+            &copy_table,
+            &keccak_table,
+            &exp_table,
+            &sig_table,
+            &challenges_exprs,
+            &step_curr.cell_manager,
+        );
+
+        (
+            ExecutionGadgetTestConfig {
+                q_step,
+                advices,
+                step: step_curr,
+                height,
+                gadget,
+                stored_expressions,
+                fixed_table,
+                u8_table,
+                u16_table,
+                tx_table,
+                rw_table,
+                bytecode_table,
+                block_table,
+                copy_table,
+                keccak_table,
+                exp_table,
+                sig_table,
+            },
+            challenges,
+        )
+    }
+
+    fn configure(_meta: &mut ConstraintSystem<F>) -> Self::Config {
+        unreachable!();
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let block = &self.block;
+        let (config, challenges) = config;
+        let challenges = challenges.values(&mut layouter);
+
+        config.tx_table.load(
+            &mut layouter,
+            &block.txs,
+            block.circuits_params.max_txs,
+            block.circuits_params.max_calldata,
+        )?;
+        config.rw_table.load(
+            &mut layouter,
+            &block.rws.table_assignments(),
+            block.circuits_params.max_rws,
+        )?;
+        config
+            .bytecode_table
+            .load(&mut layouter, block.bytecodes.clone())?;
+        config.block_table.load(&mut layouter, &block.context)?;
+        config.copy_table.load(&mut layouter, block, &challenges)?;
+        config
+            .keccak_table
+            .dev_load(&mut layouter, &block.sha3_inputs, &challenges)?;
+        config.exp_table.load(&mut layouter, block)?;
+        config.u8_table.load(&mut layouter)?;
+        config.u16_table.load(&mut layouter)?;
+        config.sig_table.dev_load(&mut layouter, block)?;
+        layouter.assign_region(
+            || "fixed table",
+            |mut region| {
+                for (offset, row) in std::iter::once([F::ZERO; 4])
+                    .chain(
+                        detect_fixed_table_tags(block)
+                            .iter()
+                            .flat_map(|tag| tag.build()),
+                    )
+                    .enumerate()
+                {
+                    for (column, value) in config.fixed_table.iter().zip_eq(row) {
+                        region.assign_fixed(|| "", *column, offset, || Value::known(value))?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        let dummy_tx = Transaction::default();
+        let last_call = block
+            .txs
+            .last()
+            .map(|tx| tx.calls()[0].clone())
+            .unwrap_or_default();
+        let steps = block
+            .txs
+            .iter()
+            .flat_map(|tx| {
+                tx.steps()
+                    .iter()
+                    .map(move |step| (tx, &tx.calls()[step.call_index], step))
+            })
+            .chain(std::iter::once((
+                &dummy_tx,
+                &last_call,
+                &block.end_block_last,
+            )))
+            .collect_vec();
+
+        layouter.assign_region(
+            || G::NAME,
+            |mut region| {
+                let mut offset = 0;
+                for (index, (transaction, call, step)) in steps.iter().enumerate() {
+                    if step.execution_state() != G::EXECUTION_STATE {
+                        continue;
+                    }
+                    config.q_step.enable(&mut region, offset)?;
+                    let region = &mut CachedRegion::<'_, '_, F>::new(
+                        &mut region,
+                        &challenges,
+                        config.advices.to_vec(),
+                        MAX_STEP_HEIGHT * 3,
+                        offset,
+                    );
+                    if let Some((_, call_next, step_next)) = steps.get(index + 1) {
+                        config.step.assign_exec_step(
+                            region,
+                            offset + config.height,
+                            block,
+                            call_next,
+                            step_next,
+                        )?;
+                    }
+                    config
+                        .step
+                        .assign_exec_step(region, offset, block, call, step)?;
+                    config.gadget.assign_exec_step(
+                        region,
+                        offset,
+                        block,
+                        transaction,
+                        call,
+                        step,
+                    )?;
+                    for stored_expression in &config.stored_expressions {
+                        stored_expression.assign(region, offset)?;
+                    }
+                    // Leave room for the state of the next step
+                    offset += config.height + STEP_STATE_HEIGHT;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Verify the steps of `block` in the execution state of `G` with the
+/// constraints of `G` only.
+///
+/// Panics if `block` has no such step, to avoid tests passing vacuously.
+pub(crate) fn run_execution_gadget_test<G: ExecutionGadget<Fr> + Clone>(
+    block: Block<Fr>,
+) -> Result<(), Vec<VerifyFailure>> {
+    let num_steps = block
+        .txs
+        .iter()
+        .flat_map(|tx| tx.steps())
+        .filter(|step| step.execution_state() == G::EXECUTION_STATE)
+        .count();
+    assert!(
+        num_steps > 0 || G::EXECUTION_STATE == ExecutionState::EndBlock,
+        "block has no {:?} step",
+        G::EXECUTION_STATE
+    );
+
+    let k = block.get_test_degree();
+    let circuit = ExecutionGadgetTestCircuit::<Fr, G>::new(block);
+    let prover = MockProver::<Fr>::run(k, &circuit, vec![]).unwrap();
+    prover.verify_par()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evm_circuit::execution::AddSubGadget, test_util::CircuitTestBuilder, witness::Rw};
+    use bus_mapping::operation::Target;
+    use eth_types::{bytecode, Word};
+    use mock::TestContext;
+
+    fn add_block() -> Block<Fr> {
+        let bytecode = bytecode! {
+            PUSH1(1)
+            PUSH1(2)
+            ADD
+            STOP
+        };
+        let ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode).unwrap();
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .build_block()
+            .unwrap()
+    }
+
+    #[test]
+    fn execution_gadget_test_ok() {
+        run_execution_gadget_test::<AddSubGadget<Fr>>(add_block()).unwrap();
+    }
+
+    #[test]
+    fn execution_gadget_test_wrong_result() {
+        let mut block = add_block();
+        // The result of ADD is the last stack write
+        let result = block.rws.0.get_mut(&Target::Stack).unwrap().last_mut();
+        match result {
+            Some(Rw::Stack {
+                is_write: true,
+                value,
+                ..
+            }) => *value = Word::from(4),
+            rw => panic!("unexpected rw {:?}", rw),
+        }
+        assert!(run_execution_gadget_test::<AddSubGadget<Fr>>(block).is_err());
+    }
+}
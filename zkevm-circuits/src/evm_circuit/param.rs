@@ -1,12 +1,18 @@
 //! Constants and parameters for the EVM circuit
 use super::table::Table;
-use crate::evm_circuit::{step::ExecutionState, EvmCircuit};
+use crate::evm_circuit::{
+    step::{ExecutionState, StepUsage},
+    EvmCircuit,
+};
 use bus_mapping::circuit_input_builder::FeatureConfig;
 use halo2_proofs::{
     halo2curves::bn256::Fr,
     plonk::{Circuit, ConstraintSystem},
 };
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 // Step dimension
 pub(crate) const STEP_WIDTH: usize = 128;
@@ -176,21 +182,50 @@ pub(crate) const N_BYTES_WITHDRAWAL: usize = N_BYTES_U64 //id
 lazy_static::lazy_static! {
     static ref INVALID_TX_CONFIG: FeatureConfig = FeatureConfig {
         invalid_tx: true,
+        zero_gas_price: true,
         ..Default::default()
     };
     // Step slot height in evm circuit
-    // We enable the invalid_tx feature to get invalid tx's ExecutionState height
+    // We enable the invalid_tx feature to get invalid tx's ExecutionState height, and the
+    // zero_gas_price feature to get the largest EndTx height
     // We guarantee the heights of other ExecutionStates remains unchanged in the following test
-    pub(crate) static ref EXECUTION_STATE_HEIGHT_MAP : HashMap<ExecutionState, usize> = get_step_height_map(*INVALID_TX_CONFIG);
+    pub(crate) static ref EXECUTION_STATE_HEIGHT_MAP : HashMap<ExecutionState, usize> = get_step_height_map(INVALID_TX_CONFIG.clone());
+    // Rw and gas usage of the ExecutionStates whose gadget fixes it in its state transition, for
+    // each FeatureConfig the EVM circuit has been configured with
+    static ref EXECUTION_STATE_USAGE_MAPS: Mutex<Vec<(FeatureConfig, Arc<HashMap<ExecutionState, StepUsage>>)>> = Mutex::default();
+}
+
+/// Rw and gas usage of the [`ExecutionState`]s whose gadget fixes it in its
+/// state transition, in the EVM circuit configured with `feature_config`.
+pub(crate) fn execution_state_usage_map(
+    feature_config: &FeatureConfig,
+) -> Arc<HashMap<ExecutionState, StepUsage>> {
+    let mut maps = EXECUTION_STATE_USAGE_MAPS
+        .lock()
+        .expect("usage maps poisoned");
+    if let Some((_, map)) = maps.iter().find(|(config, _)| config == feature_config) {
+        return map.clone();
+    }
+    let map = Arc::new(get_step_usage_map(feature_config.clone()));
+    maps.push((feature_config.clone(), map.clone()));
+    map
 }
 fn get_step_height_map(feature_config: FeatureConfig) -> HashMap<ExecutionState, usize> {
     let mut meta = ConstraintSystem::<Fr>::default();
     let circuit = EvmCircuit::configure_with_params(&mut meta, feature_config);
     circuit.0.execution.height_map
 }
+fn get_step_usage_map(feature_config: FeatureConfig) -> HashMap<ExecutionState, StepUsage> {
+    let mut meta = ConstraintSystem::<Fr>::default();
+    let circuit = EvmCircuit::configure_with_params(&mut meta, feature_config);
+    circuit.0.execution.usage_map
+}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::evm_circuit::step::GasFormula;
+    use bus_mapping::evm::OpcodeId;
+    use eth_types::evm_types::GasCost;
 
     #[test]
     fn test_get_step_height_map() {
@@ -205,4 +240,60 @@ mod tests {
         // We show that the invalid tx feature affects none of the other execution state heights
         assert_eq!(map_invalid_tx, map_mainnet);
     }
+
+    #[test]
+    fn execution_state_registry() {
+        let mainnet_config = FeatureConfig::default();
+        let registry = ExecutionState::registry(&mainnet_config);
+        let info = |execution_state| {
+            registry
+                .iter()
+                .find(|info| info.execution_state == execution_state)
+                .unwrap()
+        };
+
+        let add_sub = info(ExecutionState::ADD_SUB);
+        assert_eq!(
+            add_sub.fixed_usage,
+            Some(StepUsage {
+                rw_count: 3,
+                gas_cost: GasCost::FASTEST
+            })
+        );
+        assert_eq!(
+            add_sub.opcodes,
+            vec![
+                (OpcodeId::ADD, GasFormula::Fixed(GasCost::FASTEST)),
+                (OpcodeId::SUB, GasFormula::Fixed(GasCost::FASTEST)),
+            ]
+        );
+
+        let call_op = info(ExecutionState::CALL_OP);
+        assert_eq!(call_op.fixed_usage, None);
+        assert!(call_op.opcodes.iter().all(|(opcode, gas)| *gas
+            == GasFormula::Computed {
+                constant: opcode.constant_gas_cost()
+            }));
+
+        let invalid_opcode = info(ExecutionState::ErrorInvalidOpcode);
+        assert!(invalid_opcode
+            .opcodes
+            .iter()
+            .all(|(_, gas)| *gas == GasFormula::GasLeft));
+
+        // The disabled opcodes are not in the registry of the network
+        let no_push0_config = FeatureConfig {
+            disabled_opcodes: vec![OpcodeId::PUSH0],
+            ..Default::default()
+        };
+        let has_push0 = |feature_config| {
+            ExecutionState::PUSH
+                .info(feature_config)
+                .opcodes
+                .iter()
+                .any(|(opcode, _)| *opcode == OpcodeId::PUSH0)
+        };
+        assert!(has_push0(&mainnet_config));
+        assert!(!has_push0(&no_push0_config));
+    }
 }
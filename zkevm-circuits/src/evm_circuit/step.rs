@@ -9,7 +9,7 @@ use super::{
 };
 use crate::{
     evm_circuit::{
-        param::{EXECUTION_STATE_HEIGHT_MAP, STEP_WIDTH},
+        param::{execution_state_usage_map, EXECUTION_STATE_HEIGHT_MAP, STEP_WIDTH},
         witness::{Block, Call, ExecStep},
     },
     util::{
@@ -19,7 +19,7 @@ use crate::{
     },
 };
 use bus_mapping::{
-    circuit_input_builder::ExecState,
+    circuit_input_builder::{ExecState, FeatureConfig},
     error::{DepthError, ExecError, InsufficientBalanceError, NonceUintOverflowError, OogError},
     evm::OpcodeId,
     precompile::PrecompileCalls,
@@ -29,6 +29,7 @@ use halo2_proofs::{
     circuit::Value,
     plonk::{Advice, Column, ConstraintSystem, Error, Expression},
 };
+use itertools::Itertools;
 use std::{fmt::Display, iter, marker::ConstParamTy};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
@@ -596,6 +597,51 @@ impl ExecutionState {
         .collect()
     }
 
+    /// Get the rw and gas usage of a step in this state, for the states whose
+    /// gadget fixes it in its state transition, in the EVM circuit configured
+    /// with `feature_config`.
+    pub fn fixed_usage(&self, feature_config: &FeatureConfig) -> Option<StepUsage> {
+        execution_state_usage_map(feature_config).get(self).copied()
+    }
+
+    /// Describe the state as its gadget constrains it in the EVM circuit
+    /// configured with `feature_config`.
+    pub fn info(&self, feature_config: &FeatureConfig) -> ExecutionStateInfo {
+        let fixed_usage = self.fixed_usage(feature_config);
+        let opcodes = self
+            .responsible_opcodes()
+            .iter()
+            .map(ResponsibleOp::opcode)
+            .unique()
+            .filter(|opcode| !feature_config.disabled_opcodes.contains(opcode))
+            .map(|opcode| {
+                let gas = if self.halts_in_exception() {
+                    GasFormula::GasLeft
+                } else if let Some(usage) = fixed_usage {
+                    GasFormula::Fixed(usage.gas_cost)
+                } else {
+                    GasFormula::Computed {
+                        constant: opcode.constant_gas_cost(),
+                    }
+                };
+                (opcode, gas)
+            })
+            .collect();
+        ExecutionStateInfo {
+            execution_state: *self,
+            opcodes,
+            fixed_usage,
+        }
+    }
+
+    /// Describe every state as its gadget constrains it in the EVM circuit
+    /// configured with `feature_config`.
+    pub fn registry(feature_config: &FeatureConfig) -> Vec<ExecutionStateInfo> {
+        Self::iter()
+            .map(|state| state.info(feature_config))
+            .collect()
+    }
+
     /// Get the state height
     pub fn get_step_height_option(&self) -> Option<usize> {
         EXECUTION_STATE_HEIGHT_MAP.get(self).copied()
@@ -633,6 +679,44 @@ impl ResponsibleOp {
     }
 }
 
+/// Rw and gas usage of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepUsage {
+    /// Number of rws of the step
+    pub rw_count: usize,
+    /// Gas cost of the step
+    pub gas_cost: u64,
+}
+
+/// Gas cost of a step executing an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasFormula {
+    /// Gas cost fixed by the gadget for every step of the state
+    Fixed(u64),
+    /// Gas cost the gadget computes from the opcode and the step: the
+    /// constant gas cost of the opcode, plus its dynamic cost if any, like the
+    /// memory expansion, the copied bytes or the accessed accounts and storage
+    /// slots
+    Computed {
+        /// Constant gas cost of the opcode
+        constant: u64,
+    },
+    /// The step halts in an exception, consuming the gas left of the call
+    GasLeft,
+}
+
+/// [`ExecutionState`] as constrained by its gadget, see
+/// [`ExecutionState::registry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionStateInfo {
+    /// Execution state
+    pub execution_state: ExecutionState,
+    /// Opcodes the state is responsible for, with their gas cost
+    pub opcodes: Vec<(OpcodeId, GasFormula)>,
+    /// Rw and gas usage of the steps, for the states whose gadget fixes it
+    pub fixed_usage: Option<StepUsage>,
+}
+
 /// Dynamic selector that generates expressions of degree 2 to select from N
 /// possible targets using N/2 + 1 cells.
 #[derive(Clone, Debug)]
@@ -9,14 +9,13 @@ use crate::{
     evm_circuit::{
         param::{N_BYTES_GAS, N_BYTES_MEMORY_WORD_SIZE},
         step::ExecutionState,
-        table::{FixedTableTag, Lookup},
         util::{
             and,
             constraint_builder::{
                 EVMConstraintBuilder, ReversionInfo, StepStateTransition,
                 Transition::{Delta, Same, To},
             },
-            math_gadget::{AddWordsGadget, RangeCheckGadget},
+            math_gadget::{AddWordsGadget, ConstantDivisionGadget, RangeCheckGadget},
             not, Cell,
         },
     },
@@ -52,18 +51,8 @@ impl<F: Field> SameContextGadget<F> {
         opcode: Cell<F>,
         step_state_transition: StepStateTransition<F>,
     ) -> Self {
+        cb.responsible_opcode_lookup(opcode.expr(), 0.expr());
         cb.opcode_lookup(opcode.expr(), 1.expr());
-        cb.add_lookup(
-            "Responsible opcode lookup",
-            Lookup::Fixed {
-                tag: FixedTableTag::ResponsibleOpcode.expr(),
-                values: [
-                    cb.execution_state().as_u64().expr(),
-                    opcode.expr(),
-                    0.expr(),
-                ],
-            },
-        );
 
         // Check gas_left is sufficient
         let sufficient_gas_left = RangeCheckGadget::construct(cb, cb.next.state.gas_left.expr());
@@ -94,6 +83,94 @@ impl<F: Field> SameContextGadget<F> {
     }
 }
 
+/// Call context rw lookups of a call, declared as lists of fields.
+///
+/// The lookups are done in the order of the given fields, and target the call
+/// context of `call_id`, or of the current call when `None`.
+pub(crate) struct CallContextGadget<F> {
+    call_id: Option<Expression<F>>,
+}
+
+impl<F: Field> CallContextGadget<F> {
+    pub(crate) fn new(call_id: Option<Expression<F>>) -> Self {
+        Self { call_id }
+    }
+
+    /// Read `field_tags`, returning one cell per field.
+    pub(crate) fn read<const N: usize>(
+        &self,
+        cb: &mut EVMConstraintBuilder<F>,
+        field_tags: [CallContextFieldTag; N],
+    ) -> [Cell<F>; N] {
+        field_tags.map(|field_tag| cb.call_context(self.call_id.clone(), field_tag))
+    }
+
+    /// Write `fields`, given as (field_tag, value) pairs.
+    pub(crate) fn write(
+        &self,
+        cb: &mut EVMConstraintBuilder<F>,
+        fields: impl IntoIterator<Item = (CallContextFieldTag, WordLoHi<Expression<F>>)>,
+    ) {
+        for (field_tag, value) in fields {
+            cb.call_context_lookup_write(self.call_id.clone(), field_tag, value);
+        }
+    }
+
+    /// Write the state of the call to restore once its callee returns:
+    /// program counter, stack pointer, gas left, memory size and reversible
+    /// write counter.
+    pub(crate) fn save_state(
+        &self,
+        cb: &mut EVMConstraintBuilder<F>,
+        program_counter: Expression<F>,
+        stack_pointer: Expression<F>,
+        gas_left: Expression<F>,
+        memory_size: Expression<F>,
+        reversible_write_counter: Expression<F>,
+    ) {
+        self.write(
+            cb,
+            [
+                (CallContextFieldTag::ProgramCounter, program_counter),
+                (CallContextFieldTag::StackPointer, stack_pointer),
+                (CallContextFieldTag::GasLeft, gas_left),
+                (CallContextFieldTag::MemorySize, memory_size),
+                (
+                    CallContextFieldTag::ReversibleWriteCounter,
+                    reversible_write_counter,
+                ),
+            ]
+            .map(|(field_tag, value)| (field_tag, WordLoHi::from_lo_unchecked(value))),
+        );
+    }
+
+    /// Write `callee_call_id` as the last callee of the call, with an empty
+    /// return data buffer.
+    pub(crate) fn reset_last_callee(
+        &self,
+        cb: &mut EVMConstraintBuilder<F>,
+        callee_call_id: Expression<F>,
+    ) {
+        self.write(
+            cb,
+            [
+                (
+                    CallContextFieldTag::LastCalleeId,
+                    WordLoHi::from_lo_unchecked(callee_call_id),
+                ),
+                (
+                    CallContextFieldTag::LastCalleeReturnDataOffset,
+                    WordLoHi::zero(),
+                ),
+                (
+                    CallContextFieldTag::LastCalleeReturnDataLength,
+                    WordLoHi::zero(),
+                ),
+            ],
+        );
+    }
+}
+
 /// Construction of step state transition that restores caller's state.
 #[derive(Clone, Debug)]
 pub(crate) struct RestoreContextGadget<F> {
@@ -144,28 +221,32 @@ impl<F: Field> RestoreContextGadget<F> {
         reversible_write_counter_increase: Expression<F>,
     ) -> Self {
         // Read caller's context for restore
-        let caller_id = cb.call_context(None, CallContextFieldTag::CallerId);
-        let [caller_is_root, caller_is_create] =
-            [CallContextFieldTag::IsRoot, CallContextFieldTag::IsCreate]
-                .map(|field_tag| cb.call_context(Some(caller_id.expr()), field_tag));
+        let [caller_id] = CallContextGadget::new(None).read(cb, [CallContextFieldTag::CallerId]);
+        let caller_context = CallContextGadget::new(Some(caller_id.expr()));
+        let [caller_is_root, caller_is_create] = caller_context.read(
+            cb,
+            [CallContextFieldTag::IsRoot, CallContextFieldTag::IsCreate],
+        );
 
         let caller_code_hash =
             cb.call_context_read_as_word(Some(caller_id.expr()), CallContextFieldTag::CodeHash);
 
         let [caller_program_counter, caller_stack_pointer, caller_gas_left, caller_memory_word_size, caller_reversible_write_counter] =
-            [
-                CallContextFieldTag::ProgramCounter,
-                CallContextFieldTag::StackPointer,
-                CallContextFieldTag::GasLeft,
-                CallContextFieldTag::MemorySize,
-                CallContextFieldTag::ReversibleWriteCounter,
-            ]
-            .map(|field_tag| cb.call_context(Some(caller_id.expr()), field_tag));
+            caller_context.read(
+                cb,
+                [
+                    CallContextFieldTag::ProgramCounter,
+                    CallContextFieldTag::StackPointer,
+                    CallContextFieldTag::GasLeft,
+                    CallContextFieldTag::MemorySize,
+                    CallContextFieldTag::ReversibleWriteCounter,
+                ],
+            );
 
         // Update caller's last callee information
         // EIP-211 CREATE/CREATE2 call successful case should set RETURNDATASIZE = 0
         let is_call_create_and_success_expr = cb.curr.state.is_create.expr() * is_success.clone();
-        for (field_tag, value) in [
+        let last_callee = [
             (
                 CallContextFieldTag::LastCalleeId,
                 cb.curr.state.call_id.expr(),
@@ -186,14 +267,10 @@ impl<F: Field> RestoreContextGadget<F> {
                     return_data_length.clone(),
                 ),
             ),
-        ] {
-            // TODO review and assure range check
-            cb.call_context_lookup_write(
-                Some(caller_id.expr()),
-                field_tag,
-                WordLoHi::from_lo_unchecked(value),
-            );
-        }
+        ]
+        .map(|(field_tag, value)| (field_tag, WordLoHi::from_lo_unchecked(value)));
+        // TODO review and assure range check
+        caller_context.write(cb, last_callee);
 
         let code_deposit_cost = cb.curr.state.is_create.expr()
             * is_success.clone()
@@ -806,6 +883,51 @@ impl<F: Field, MemAddrGadget: CommonMemoryAddressGadget<F>, const IS_SUCCESS_CAL
     }
 }
 
+/// Gas available to a callee following EIP-150: all but one 64th of the gas
+/// left to the caller after paying the cost of the call or create.
+#[derive(Clone, Debug)]
+pub(crate) struct AllButOne64thGasGadget<F> {
+    gas_available: Expression<F>,
+    one_64th_gas: ConstantDivisionGadget<F, N_BYTES_GAS>,
+}
+
+impl<F: Field> AllButOne64thGasGadget<F> {
+    pub(crate) fn construct(
+        cb: &mut EVMConstraintBuilder<F>,
+        gas_available: Expression<F>,
+    ) -> Self {
+        let one_64th_gas = cb.div_by_const(gas_available.clone(), 64);
+
+        Self {
+            gas_available,
+            one_64th_gas,
+        }
+    }
+
+    /// Gas retained by the caller
+    pub(crate) fn one_64th_gas(&self) -> Expression<F> {
+        self.one_64th_gas.quotient()
+    }
+
+    /// Maximum gas passed to the callee
+    pub(crate) fn all_but_one_64th_gas(&self) -> Expression<F> {
+        self.gas_available.clone() - self.one_64th_gas.quotient()
+    }
+
+    /// Assign the gadget and return the maximum gas passed to the callee.
+    pub(crate) fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        gas_available: u64,
+    ) -> Result<u64, Error> {
+        let (one_64th_gas, _) = self
+            .one_64th_gas
+            .assign(region, offset, gas_available.into())?;
+        Ok(gas_available - one_64th_gas as u64)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct SloadGasGadget<F> {
     gas_cost: Expression<F>,
@@ -1169,3 +1291,99 @@ impl<F: Field, const VALID_BYTES: usize> WordByteRangeGadget<F, VALID_BYTES> {
         self.not_overflow.expr()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evm_circuit::util::math_gadget::test_util::*;
+    use eth_types::{evm_types::gas_utils::eip150_gas, Word};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    /// AllButOne64thGasTestContainer:
+    /// require(all_but_one_64th_gas(a) == b)
+    #[derive(Clone)]
+    struct AllButOne64thGasTestContainer<F> {
+        gas_gadget: AllButOne64thGasGadget<F>,
+        a: Cell<F>,
+        b: Cell<F>,
+    }
+
+    impl<F: Field> MathGadgetContainer<F> for AllButOne64thGasTestContainer<F> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let a = cb.query_cell();
+            let b = cb.query_cell();
+            let gas_gadget = AllButOne64thGasGadget::construct(cb, a.expr());
+
+            cb.require_equal(
+                "correct all but one 64th gas",
+                gas_gadget.all_but_one_64th_gas(),
+                b.expr(),
+            );
+            cb.require_equal(
+                "gas is split between caller and callee",
+                gas_gadget.all_but_one_64th_gas() + gas_gadget.one_64th_gas(),
+                a.expr(),
+            );
+
+            AllButOne64thGasTestContainer { gas_gadget, a, b }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let a = witnesses[0].low_u64();
+            let b = witnesses[1].low_u64();
+            let offset = 0;
+
+            self.a.assign(region, offset, Value::known(F::from(a)))?;
+            self.b.assign(region, offset, Value::known(F::from(b)))?;
+            self.gas_gadget.assign(region, offset, a)?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_all_but_one_64th_gas_expect() {
+        for gas in [0, 63, 64, 65, 100000, u64::MAX >> 1] {
+            try_test!(
+                AllButOne64thGasTestContainer<Fr>,
+                [Word::from(gas), Word::from(gas - gas / 64)],
+                true,
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_but_one_64th_gas_unexpect() {
+        try_test!(
+            AllButOne64thGasTestContainer<Fr>,
+            [Word::from(64), Word::from(64)],
+            false,
+        );
+        try_test!(
+            AllButOne64thGasTestContainer<Fr>,
+            [Word::from(100000), Word::from(100000 - 100000 / 64 + 1)],
+            false,
+        );
+    }
+
+    /// Compare the gadget against the gas passed to the callee by the
+    /// bus-mapping, which mirrors geth, when the requested gas is not capping.
+    #[test]
+    fn test_all_but_one_64th_gas_random() {
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        for _ in 0..16 {
+            let gas = rng.gen_range(0..1u64 << 48);
+            try_test!(
+                AllButOne64thGasTestContainer<Fr>,
+                [Word::from(gas), Word::from(eip150_gas(gas, Word::MAX))],
+                true,
+            );
+        }
+    }
+}
@@ -9,7 +9,7 @@ use super::{
 use crate::{
     evm_circuit::{
         param::STACK_CAPACITY,
-        step::{ExecutionState, Step},
+        step::{ExecutionState, Step, StepUsage},
         table::{FixedTableTag, Lookup, RwValues, Table},
         util::{Cell, RandomLinearCombination},
     },
@@ -57,6 +57,29 @@ impl<F> Default for Transition<F> {
     }
 }
 
+impl<F: Field> Transition<Expression<F>> {
+    /// Returns the change of the state field when it doesn't depend on any
+    /// cell.
+    fn constant_delta(&self) -> Option<F> {
+        match self {
+            Transition::Same => Some(F::ZERO),
+            Transition::Delta(delta) => delta.evaluate(
+                &|scalar| Some(scalar),
+                &|_| None,
+                &|_| None,
+                &|_| None,
+                &|_| None,
+                &|_| None,
+                &|a| a.map(|a| -a),
+                &|a, b| Some(a? + b?),
+                &|a, b| Some(a? * b?),
+                &|a, scalar| a.map(|a| a * scalar),
+            ),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct StepStateTransition<F: Field> {
     pub(crate) rw_counter: Transition<Expression<F>>,
@@ -318,8 +341,12 @@ pub(crate) struct EVMConstraintBuilder<'a, F: Field> {
     constraints_location: ConstraintLocation,
     stored_expressions: Vec<StoredExpression<F>>,
     pub(crate) debug_expressions: Vec<(String, Expression<F>)>,
+    // Usage fixed by each step state transition, if any
+    step_usages: Vec<Option<StepUsage>>,
     meta: &'a mut ConstraintSystem<F>,
     pub(crate) feature_config: FeatureConfig,
+    // Whether the opcode of the step is already looked up in the responsible opcodes
+    has_responsible_opcode_lookup: bool,
 }
 
 impl<'a, F: Field> ConstrainBuilderCommon<F> for EVMConstraintBuilder<'a, F> {
@@ -366,7 +393,9 @@ impl<'a, F: Field> EVMConstraintBuilder<'a, F> {
             stored_expressions: Vec::new(),
             meta,
             debug_expressions: Vec::new(),
+            step_usages: Vec::new(),
             feature_config,
+            has_responsible_opcode_lookup: false,
         }
     }
 
@@ -429,6 +458,15 @@ impl<'a, F: Field> EVMConstraintBuilder<'a, F> {
         self.stack_pointer_offset.clone()
     }
 
+    /// Returns the rw and gas usage of the step when the gadget fixes it with
+    /// a single unconditional state transition.
+    pub(crate) fn fixed_usage(&self) -> Option<StepUsage> {
+        match self.step_usages[..] {
+            [usage] => usage,
+            _ => None,
+        }
+    }
+
     // Query
 
     pub(crate) fn copy<E: Expr<F>>(&mut self, value: E) -> Cell<F> {
@@ -532,6 +570,20 @@ impl<'a, F: Field> EVMConstraintBuilder<'a, F> {
         &mut self,
         step_state_transition: StepStateTransition<F>,
     ) {
+        // The usage is only fixed by a transition that applies to every step
+        let unconditional = self.condition_expr_opt().is_none()
+            && self.constraints_location == ConstraintLocation::Step;
+        let usage = step_state_transition
+            .rw_counter
+            .constant_delta()
+            .zip(step_state_transition.gas_left.constant_delta())
+            .filter(|_| unconditional)
+            .map(|(rw_count, gas_left)| StepUsage {
+                rw_count: rw_count.get_lower_32() as usize,
+                gas_cost: (-gas_left).get_lower_128() as u64,
+            });
+        self.step_usages.push(usage);
+
         macro_rules! constrain {
             ($name:tt) => {
                 match step_state_transition.$name {
@@ -713,6 +765,14 @@ impl<'a, F: Field> EVMConstraintBuilder<'a, F> {
     // Opcode
 
     pub(crate) fn opcode_lookup(&mut self, opcode: Expression<F>, is_code: Expression<F>) {
+        // The disabled opcodes of the network are missing from the responsible opcodes, so the
+        // lookup rejects them for the execution states that don't already do it.
+        if self.program_counter_offset == 0
+            && !self.has_responsible_opcode_lookup
+            && self.is_responsible_for_disabled_opcode()
+        {
+            self.responsible_opcode_lookup(opcode.clone(), 0.expr());
+        }
         self.opcode_lookup_at(
             self.curr.state.program_counter.expr() + self.program_counter_offset.expr(),
             opcode,
@@ -721,6 +781,27 @@ impl<'a, F: Field> EVMConstraintBuilder<'a, F> {
         self.program_counter_offset += 1;
     }
 
+    pub(crate) fn responsible_opcode_lookup(&mut self, opcode: Expression<F>, aux: Expression<F>) {
+        self.has_responsible_opcode_lookup = true;
+        self.add_lookup(
+            "Responsible opcode lookup",
+            Lookup::Fixed {
+                tag: FixedTableTag::ResponsibleOpcode.expr(),
+                values: [self.execution_state.as_u64().expr(), opcode, aux],
+            },
+        );
+    }
+
+    fn is_responsible_for_disabled_opcode(&self) -> bool {
+        let disabled_opcodes = &self.feature_config.disabled_opcodes;
+        !disabled_opcodes.is_empty()
+            && self
+                .execution_state
+                .responsible_opcodes()
+                .iter()
+                .any(|op| disabled_opcodes.contains(&op.opcode()))
+    }
+
     pub(crate) fn opcode_lookup_at(
         &mut self,
         index: Expression<F>,
@@ -1,14 +1,12 @@
 use crate::{
     evm_circuit::util::{
         constraint_builder::{ConstrainBuilderCommon, EVMConstraintBuilder},
-        from_bytes, pow_of_two_expr, split_u256, split_u256_limb64, CachedRegion, Cell,
-    },
-    util::{
-        word::{Word32Cell, Word4, WordExpr, WordLoHi},
-        Expr,
+        from_bytes, CachedRegion, Cell,
     },
+    util::word::{Word32Cell, Word4, WordExpr, WordLoHi},
 };
 use eth_types::{Field, ToLittleEndian, Word as U256Word};
+use gadgets::mul_add::{mul_add_words_carries, mul_add_words_exprs, N_BYTES_CARRY};
 use halo2_proofs::{
     circuit::Value,
     plonk::{Error, Expression},
@@ -44,10 +42,13 @@ use halo2_proofs::{
 ///              + a3 * b3
 /// In the cases of DIV and MOD, we need to constrain overflow == 0 outside the
 /// MulAddWordsGadget.
+///
+/// The constraints are shared with the `MulAddChip` of the exponentiation
+/// circuit, see [`gadgets::mul_add`].
 #[derive(Clone, Debug)]
 pub(crate) struct MulAddWordsGadget<F> {
-    carry_lo: [Cell<F>; 9],
-    carry_hi: [Cell<F>; 9],
+    carry_lo: [Cell<F>; N_BYTES_CARRY],
+    carry_hi: [Cell<F>; N_BYTES_CARRY],
     overflow: Expression<F>,
 }
 
@@ -56,48 +57,29 @@ impl<F: Field> MulAddWordsGadget<F> {
         let (a, b, c, d) = (words[0], words[1], words[2], words[3]);
         let carry_lo = cb.query_bytes();
         let carry_hi = cb.query_bytes();
-        let carry_lo_expr = from_bytes::expr(&carry_lo);
-        let carry_hi_expr = from_bytes::expr(&carry_hi);
 
-        let mut a_limbs = vec![];
-        let mut b_limbs = vec![];
         let word4_a: Word4<Expression<F>> = a.to_word_n();
         let word4_b: Word4<Expression<F>> = b.to_word_n();
-        for i in 0..4 {
-            a_limbs.push(word4_a.limbs[i].expr());
-            b_limbs.push(word4_b.limbs[i].expr());
-        }
-
         let word_c: WordLoHi<Expression<F>> = c.to_word();
         let word_d: WordLoHi<Expression<F>> = d.to_word();
 
-        let t0 = a_limbs[0].clone() * b_limbs[0].clone();
-        let t1 = a_limbs[0].clone() * b_limbs[1].clone() + a_limbs[1].clone() * b_limbs[0].clone();
-        let t2 = a_limbs[0].clone() * b_limbs[2].clone()
-            + a_limbs[1].clone() * b_limbs[1].clone()
-            + a_limbs[2].clone() * b_limbs[0].clone();
-        let t3 = a_limbs[0].clone() * b_limbs[3].clone()
-            + a_limbs[1].clone() * b_limbs[2].clone()
-            + a_limbs[2].clone() * b_limbs[1].clone()
-            + a_limbs[3].clone() * b_limbs[0].clone();
-        let overflow = carry_hi_expr.clone()
-            + a_limbs[1].clone() * b_limbs[3].clone()
-            + a_limbs[2].clone() * b_limbs[2].clone()
-            + a_limbs[3].clone() * b_limbs[1].clone()
-            + a_limbs[2].clone() * b_limbs[3].clone()
-            + a_limbs[3].clone() * b_limbs[2].clone()
-            + a_limbs[3].clone() * b_limbs[3].clone();
-
-        cb.require_equal(
+        let exprs = mul_add_words_exprs(
+            &word4_a.limbs,
+            &word4_b.limbs,
+            [word_c.lo(), word_c.hi()],
+            [word_d.lo(), word_d.hi()],
+            from_bytes::expr(&carry_lo),
+            from_bytes::expr(&carry_hi),
+        );
+        cb.require_zero(
             "(a * b)_lo + c_lo == d_lo + carry_lo ⋅ 2^128",
-            t0.expr() + t1.expr() * pow_of_two_expr(64) + word_c.lo().expr(),
-            word_d.lo().expr() + carry_lo_expr.clone() * pow_of_two_expr(128),
+            exprs.check_lo,
         );
-        cb.require_equal(
+        cb.require_zero(
             "(a * b)_hi + c_hi + carry_lo == d_hi + carry_hi ⋅ 2^128",
-            t2.expr() + t3.expr() * pow_of_two_expr(64) + word_c.hi().expr() + carry_lo_expr,
-            word_d.hi().expr() + carry_hi_expr * pow_of_two_expr(128),
+            exprs.check_hi,
         );
+        let overflow = exprs.overflow;
 
         Self {
             carry_lo,
@@ -112,23 +94,7 @@ impl<F: Field> MulAddWordsGadget<F> {
         offset: usize,
         words: [U256Word; 4],
     ) -> Result<(), Error> {
-        let (a, b, c, d) = (words[0], words[1], words[2], words[3]);
-
-        let a_limbs = split_u256_limb64(&a);
-        let b_limbs = split_u256_limb64(&b);
-        let (c_lo, c_hi) = split_u256(&c);
-        let (d_lo, d_hi) = split_u256(&d);
-
-        let t0 = a_limbs[0] * b_limbs[0];
-        let t1 = a_limbs[0] * b_limbs[1] + a_limbs[1] * b_limbs[0];
-        let t2 = a_limbs[0] * b_limbs[2] + a_limbs[1] * b_limbs[1] + a_limbs[2] * b_limbs[0];
-        let t3 = a_limbs[0] * b_limbs[3]
-            + a_limbs[1] * b_limbs[2]
-            + a_limbs[2] * b_limbs[1]
-            + a_limbs[3] * b_limbs[0];
-
-        let carry_lo = (t0 + (t1 << 64) + c_lo).saturating_sub(d_lo) >> 128;
-        let carry_hi = (t2 + (t3 << 64) + c_hi + carry_lo).saturating_sub(d_hi) >> 128;
+        let (carry_lo, carry_hi) = mul_add_words_carries(words);
 
         self.carry_lo
             .iter()
@@ -153,6 +119,7 @@ impl<F: Field> MulAddWordsGadget<F> {
 #[cfg(test)]
 mod tests {
     use super::{super::test_util::*, *};
+    use crate::util::Expr;
     use eth_types::{ToScalar, Word};
     use halo2_proofs::{halo2curves::bn256::Fr, plonk::Error};
 
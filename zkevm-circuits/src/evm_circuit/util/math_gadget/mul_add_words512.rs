@@ -9,6 +9,7 @@ use crate::{
     },
 };
 use eth_types::{Field, ToLittleEndian, Word};
+use gadgets::mul_add::{mul_limb_products, N_BYTES_CARRY};
 use halo2_proofs::{
     circuit::Value,
     plonk::{Error, Expression},
@@ -47,9 +48,9 @@ use halo2_proofs::{
 ///   t6 + carry_2 = d_hi
 #[derive(Clone, Debug)]
 pub(crate) struct MulAddWords512Gadget<F> {
-    carry_0: [Cell<F>; 9],
-    carry_1: [Cell<F>; 9],
-    carry_2: [Cell<F>; 9],
+    carry_0: [Cell<F>; N_BYTES_CARRY],
+    carry_1: [Cell<F>; N_BYTES_CARRY],
+    carry_2: [Cell<F>; N_BYTES_CARRY],
 }
 
 impl<F: Field> MulAddWords512Gadget<F> {
@@ -68,33 +69,14 @@ impl<F: Field> MulAddWords512Gadget<F> {
         let carry_2_expr = from_bytes::expr(&carry_2);
 
         // Split input words in limbs
-        let mut a_limbs = vec![];
-        let mut b_limbs = vec![];
         let word4_a: Word4<Expression<F>> = words[0].to_word_n();
         let word4_b: Word4<Expression<F>> = words[1].to_word_n();
-        for i in 0..4 {
-            a_limbs.push(word4_a.limbs[i].expr());
-            b_limbs.push(word4_b.limbs[i].expr());
-        }
 
         let (d_lo, d_hi) = words[2].to_word().to_lo_hi();
         let (e_lo, e_hi) = words[3].to_word().to_lo_hi();
 
         // Limb multiplication
-        let t0 = a_limbs[0].clone() * b_limbs[0].clone();
-        let t1 = a_limbs[0].clone() * b_limbs[1].clone() + a_limbs[1].clone() * b_limbs[0].clone();
-        let t2 = a_limbs[0].clone() * b_limbs[2].clone()
-            + a_limbs[1].clone() * b_limbs[1].clone()
-            + a_limbs[2].clone() * b_limbs[0].clone();
-        let t3 = a_limbs[0].clone() * b_limbs[3].clone()
-            + a_limbs[1].clone() * b_limbs[2].clone()
-            + a_limbs[2].clone() * b_limbs[1].clone()
-            + a_limbs[3].clone() * b_limbs[0].clone();
-        let t4 = a_limbs[1].clone() * b_limbs[3].clone()
-            + a_limbs[2].clone() * b_limbs[2].clone()
-            + a_limbs[3].clone() * b_limbs[1].clone();
-        let t5 = a_limbs[2].clone() * b_limbs[3].clone() + a_limbs[3].clone() * b_limbs[2].clone();
-        let t6 = a_limbs[3].clone() * b_limbs[3].clone();
+        let [t0, t1, t2, t3, t4, t5, t6] = mul_limb_products(&word4_a.limbs, &word4_b.limbs);
 
         if let Some(c) = addend {
             let c = c.to_word();
@@ -148,21 +130,11 @@ impl<F: Field> MulAddWords512Gadget<F> {
     ) -> Result<(), Error> {
         let (a, b, d, e) = (words[0], words[1], words[2], words[3]);
 
-        let a_limbs = split_u256_limb64(&a);
-        let b_limbs = split_u256_limb64(&b);
         let (d_lo, _d_hi) = split_u256(&d);
         let (e_lo, e_hi) = split_u256(&e);
 
-        let t0 = a_limbs[0] * b_limbs[0];
-        let t1 = a_limbs[0] * b_limbs[1] + a_limbs[1] * b_limbs[0];
-        let t2 = a_limbs[0] * b_limbs[2] + a_limbs[1] * b_limbs[1] + a_limbs[2] * b_limbs[0];
-        let t3 = a_limbs[0] * b_limbs[3]
-            + a_limbs[1] * b_limbs[2]
-            + a_limbs[2] * b_limbs[1]
-            + a_limbs[3] * b_limbs[0];
-
-        let t4 = a_limbs[1] * b_limbs[3] + a_limbs[2] * b_limbs[2] + a_limbs[3] * b_limbs[1];
-        let t5 = a_limbs[2] * b_limbs[3] + a_limbs[3] * b_limbs[2];
+        let [t0, t1, t2, t3, t4, t5, _] =
+            mul_limb_products(&split_u256_limb64(&a), &split_u256_limb64(&b));
 
         let (carry_0, carry_1) = if let Some(c) = addend {
             let (c_lo, c_hi) = split_u256(&c);
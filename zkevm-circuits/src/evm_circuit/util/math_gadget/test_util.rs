@@ -12,7 +12,6 @@ use crate::{
             constraint_builder::EVMConstraintBuilder, rlc, CachedRegion, StoredExpression,
             LOOKUP_CONFIG,
         },
-        Advice, Column, Fixed,
     },
     table::LookupTable,
     util::{cell_manager::CellType, Challenges},
@@ -22,7 +21,10 @@ pub(crate) use halo2_proofs::circuit::{Layouter, Value};
 use halo2_proofs::{
     circuit::SimpleFloorPlanner,
     dev::MockProver,
-    plonk::{Circuit, ConstraintSystem, Error, FirstPhase, SecondPhase, Selector, ThirdPhase},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, FirstPhase, Fixed, SecondPhase, Selector,
+        ThirdPhase,
+    },
 };
 
 pub(crate) const WORD_LOW_MAX: Word = U256([u64::MAX, u64::MAX, 0, 0]);
@@ -7,7 +7,6 @@ use crate::{
         util::{
             constraint_builder::EVMConstraintBuilder, CachedRegion, StoredExpression, LOOKUP_CONFIG,
         },
-        Advice, Column,
     },
     util::Challenges,
 };
@@ -17,7 +16,10 @@ pub(crate) use halo2_proofs::circuit::Layouter;
 use halo2_proofs::{
     circuit::SimpleFloorPlanner,
     dev::MockProver,
-    plonk::{Circuit, ConstraintSystem, Error, FirstPhase, SecondPhase, Selector, ThirdPhase},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, FirstPhase, SecondPhase, Selector,
+        ThirdPhase,
+    },
 };
 
 pub(crate) trait MemoryGadgetContainer<F: Field>: Clone {
@@ -1,6 +1,6 @@
-use bus_mapping::precompile::PrecompileCalls;
+use bus_mapping::precompile::{PrecompileCalls, SUPPORTED_PRECOMPILES};
 use eth_types::Field;
-use gadgets::util::{not, Expr};
+use gadgets::util::{not, sum, Expr};
 use halo2_proofs::plonk::Expression;
 
 use crate::evm_circuit::step::{ExecutionState, ExecutionState::ErrorOutOfGasPrecompile};
@@ -37,40 +37,54 @@ impl<F: Field> PrecompileGadget<F> {
     ) -> Self {
         let address = BinaryNumberGadget::construct(cb, callee_address.expr());
 
-        let conditions = vec![
-            address.value_equals(PrecompileCalls::Identity),
-            address.value_equals(PrecompileCalls::Ecrecover),
-            // match more precompiles
-        ]
-        .into_iter()
-        .map(|cond| {
-            cond.expr() * not::expr(cb.next.execution_state_selector([ErrorOutOfGasPrecompile]))
-        })
-        .collect::<Vec<_>>();
+        // Bus-mapping routes calls to the precompiles without a gadget to
+        // `ErrorPrecompileFailed` before reaching the call gadget.
+        cb.require_equal(
+            "callee is a supported precompile",
+            sum::expr(
+                SUPPORTED_PRECOMPILES
+                    .iter()
+                    .map(|&precompile| address.value_equals(precompile)),
+            ),
+            1.expr(),
+        );
 
-        let next_states = vec![
-            ExecutionState::PrecompileIdentity, // add more precompile execution states
-            ExecutionState::PrecompileEcrecover,
+        // Constraints on the call to each supported precompile, verified
+        // together with the transition to its execution state.
+        let precompiles: Vec<(PrecompileCalls, BoxedClosure<F>)> = vec![
+            (
+                PrecompileCalls::Ecrecover,
+                Box::new(|cb| {
+                    cb.require_equal(
+                        "ECRecover: input length is 128 bytes",
+                        cd_length.clone(),
+                        128.expr(),
+                    );
+                }),
+            ),
+            (
+                PrecompileCalls::Identity,
+                Box::new(|cb| {
+                    cb.require_equal(
+                        "input length and precompile return length are the same",
+                        cd_length.clone(),
+                        precompile_return_length.clone(),
+                    );
+                }),
+            ),
         ];
+        debug_assert_eq!(precompiles.len(), SUPPORTED_PRECOMPILES.len());
 
-        let constraints: Vec<BoxedClosure<F>> = vec![
-            Box::new(|cb| {
-                // Identity
-                cb.require_equal(
-                    "input length and precompile return length are the same",
-                    cd_length.clone(),
-                    precompile_return_length.clone(),
-                );
-            }),
-            Box::new(|cb| {
-                // EcRecover
-                cb.require_equal(
-                    "ECRecover: input length is 128 bytes",
-                    cd_length.clone(),
-                    128.expr(),
-                );
-            }),
-        ];
+        let is_next_oog = cb.next.execution_state_selector([ErrorOutOfGasPrecompile]);
+        let (conditions, next_states, constraints) = precompiles.into_iter().fold(
+            (vec![], vec![], vec![]),
+            |(mut conditions, mut next_states, mut constraints), (precompile, constraint)| {
+                conditions.push(address.value_equals(precompile) * not::expr(is_next_oog.expr()));
+                next_states.push(ExecutionState::from(precompile));
+                constraints.push(constraint);
+                (conditions, next_states, constraints)
+            },
+        );
 
         cb.constrain_mutually_exclusive_next_step(conditions, next_states, constraints);
 
@@ -10,7 +10,7 @@ pub use dev::ExpCircuit as TestExpCircuit;
 
 use crate::{
     evm_circuit::util::constraint_builder::BaseConstraintBuilder,
-    table::{ExpTable, LookupTable},
+    table::{ExpTable, LookupTable, UXTable},
     util::{Challenges, SubCircuit, SubCircuitConfig},
     witness,
 };
@@ -43,24 +43,46 @@ pub struct ExpCircuitConfig<F> {
     pub parity_check: MulAddConfig<F>,
 }
 
+/// Circuit configuration arguments
+pub struct ExpCircuitConfigArgs {
+    /// ExpTable
+    pub exp_table: ExpTable,
+    /// U8Table, to range check the carries of the multiplication gadgets
+    pub u8_table: UXTable<8>,
+}
+
 impl<F: Field> SubCircuitConfig<F> for ExpCircuitConfig<F> {
-    type ConfigArgs = ExpTable;
+    type ConfigArgs = ExpCircuitConfigArgs;
 
     /// Return a new ExpCircuitConfig
-    fn new(meta: &mut ConstraintSystem<F>, exp_table: Self::ConfigArgs) -> Self {
+    fn new(
+        meta: &mut ConstraintSystem<F>,
+        Self::ConfigArgs {
+            exp_table,
+            u8_table,
+        }: Self::ConfigArgs,
+    ) -> Self {
         let q_usable = meta.complex_selector();
-        let mul_gadget = MulAddChip::configure(meta, |meta| {
-            and::expr([
-                meta.query_selector(q_usable),
-                meta.query_fixed(exp_table.is_step, Rotation::cur()),
-            ])
-        });
-        let parity_check = MulAddChip::configure(meta, |meta| {
-            and::expr([
-                meta.query_selector(q_usable),
-                meta.query_fixed(exp_table.is_step, Rotation::cur()),
-            ])
-        });
+        let mul_gadget = MulAddChip::configure(
+            meta,
+            |meta| {
+                and::expr([
+                    meta.query_selector(q_usable),
+                    meta.query_fixed(exp_table.is_step, Rotation::cur()),
+                ])
+            },
+            u8_table.into(),
+        );
+        let parity_check = MulAddChip::configure(
+            meta,
+            |meta| {
+                and::expr([
+                    meta.query_selector(q_usable),
+                    meta.query_fixed(exp_table.is_step, Rotation::cur()),
+                ])
+            },
+            u8_table.into(),
+        );
 
         // multiplier <- 2^64
         let two = U256::from(2);
@@ -1,8 +1,8 @@
 pub use super::ExpCircuit;
 
 use crate::{
-    exp_circuit::ExpCircuitConfig,
-    table::ExpTable,
+    exp_circuit::{ExpCircuitConfig, ExpCircuitConfigArgs},
+    table::{ExpTable, UXTable},
     util::{Challenges, SubCircuit, SubCircuitConfig},
 };
 use eth_types::Field;
@@ -12,7 +12,7 @@ use halo2_proofs::{
 };
 
 impl<F: Field> Circuit<F> for ExpCircuit<F> {
-    type Config = (ExpCircuitConfig<F>, Challenges);
+    type Config = (ExpCircuitConfig<F>, UXTable<8>, Challenges);
     type FloorPlanner = SimpleFloorPlanner;
     type Params = ();
 
@@ -22,16 +22,25 @@ impl<F: Field> Circuit<F> for ExpCircuit<F> {
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         let exp_table = ExpTable::construct(meta);
+        let u8_table = UXTable::construct(meta);
         let challenges = Challenges::construct(meta);
-        (ExpCircuitConfig::new(meta, exp_table), challenges)
+        let config = ExpCircuitConfig::new(
+            meta,
+            ExpCircuitConfigArgs {
+                exp_table,
+                u8_table,
+            },
+        );
+        (config, u8_table, challenges)
     }
 
     fn synthesize(
         &self,
-        (config, challenges): Self::Config,
+        (config, u8_table, challenges): Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
         let challenges = challenges.values(&mut layouter);
+        u8_table.load(&mut layouter)?;
         self.synthesize_sub(&config, &challenges, &mut layouter)
     }
 }
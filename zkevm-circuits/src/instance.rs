@@ -1,6 +1,11 @@
 //! The instance definition.
+//!
+//! The types here only hold data, but they are built on the primitive types
+//! of `eth-types` and `bus-mapping`, which depend on `halo2_proofs` and
+//! `std`. Moving them to a `no_std` crate for light clients first needs those
+//! primitive types to be available without the proving stack.
 
-use bus_mapping::circuit_input_builder::Withdrawal;
+use bus_mapping::circuit_input_builder::{FixedCParams, Withdrawal};
 use eth_types::{geth_types::BlockConstants, BigEndianHash, Field, Keccak};
 use std::{iter, ops::Deref};
 
@@ -12,6 +17,12 @@ use crate::{util::word::WordLoHi, witness::Block};
 pub(super) const ZERO_BYTE_GAS_COST: u64 = 4;
 pub(super) const NONZERO_BYTE_GAS_COST: u64 = 16;
 
+/// Version of the public input layout, written as the first byte of the
+/// public input hash preimage. It must be bumped on every change to the
+/// layout returned by [`PublicData::get_pi_bytes`], so that a proof can only
+/// be checked by a verifier that hashes the public inputs the same way.
+pub const PI_VERSION: u8 = 1;
+
 /// Values of the block table (as in the spec)
 #[derive(Clone, Default, Debug)]
 pub struct BlockValues {
@@ -178,6 +189,17 @@ impl PublicData {
     }
 
     /// get the serialized public data bytes
+    ///
+    /// The preimage of the public input hash is laid out as:
+    /// - header: [`PI_VERSION`] (1 byte)
+    /// - block values: coinbase, gas_limit, number, timestamp, difficulty, base_fee, chain_id,
+    ///   withdrawals_root and the 256 history hashes
+    /// - extra values: block hash, state root and previous state root
+    /// - tx table: an empty row, then `max_txs` txs as (tx_id, index, value)
+    /// - calldata padded to `max_calldata` bytes
+    /// - withdrawals padded to `max_withdrawals`
+    ///
+    /// Integers are serialized in big endian.
     pub fn get_pi_bytes(
         &self,
         max_txs: usize,
@@ -187,7 +209,7 @@ impl PublicData {
         // Assign block table
         let block_values = self.get_block_table_values();
         let result = iter::empty()
-            .chain(0u8.to_be_bytes()) // zero byte
+            .chain(PI_VERSION.to_be_bytes()) // version
             .chain(block_values.coinbase.to_fixed_bytes()) // coinbase
             .chain(block_values.gas_limit.to_be_bytes()) // gas_limit
             .chain(block_values.number.to_be_bytes()) // number
@@ -297,6 +319,42 @@ impl PublicData {
     }
 }
 
+/// Capacities of the circuit that fix the layout of the public inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PiLayout {
+    /// Maximum number of txs
+    pub max_txs: usize,
+    /// Maximum number of withdrawals
+    pub max_withdrawals: usize,
+    /// Maximum number of bytes from all txs calldata
+    pub max_calldata: usize,
+}
+
+impl From<&FixedCParams> for PiLayout {
+    fn from(params: &FixedCParams) -> Self {
+        Self {
+            max_txs: params.max_txs,
+            max_withdrawals: params.max_withdrawals,
+            max_calldata: params.max_calldata,
+        }
+    }
+}
+
+/// Instance columns of the [`SuperCircuit`](crate::super_circuit::SuperCircuit)
+/// proving `block`, in the order of the circuit's instance columns.
+///
+/// `layout` must match the circuit parameters used to build the proving key.
+pub fn instances_for<F: Field>(block: &Block<F>, layout: &PiLayout) -> Vec<Vec<F>> {
+    let rpi_digest = public_data_convert(block).get_rpi_digest_word::<F>(
+        layout.max_txs,
+        layout.max_withdrawals,
+        layout.max_calldata,
+    );
+    // The PI circuit exposes the digest of the public data, and the tx
+    // circuit has an instance column that it doesn't use.
+    vec![vec![rpi_digest.lo(), rpi_digest.hi()], vec![]]
+}
+
 /// convert witness block to public data
 pub fn public_data_convert<F: Field>(block: &Block<F>) -> PublicData {
     PublicData {
@@ -1051,11 +1051,16 @@ impl<F: Field> KeccakCircuit<F> {
         }
     }
 
+    /// The number of rows used by one keccak_f
+    pub fn rows_per_keccak_f() -> usize {
+        (NUM_ROUNDS + 1) * get_num_rows_per_round()
+    }
+
     /// The number of keccak_f's that can be done in this circuit
     pub fn capacity(&self) -> Option<usize> {
         if self.num_rows > 0 {
             // Subtract two for unusable rows
-            Some(self.num_rows / ((NUM_ROUNDS + 1) * get_num_rows_per_round()) - 2)
+            Some(self.num_rows / Self::rows_per_keccak_f() - 2)
         } else {
             None
         }
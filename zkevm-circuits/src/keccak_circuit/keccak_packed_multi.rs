@@ -10,6 +10,7 @@ use halo2_proofs::{
     plonk::{Error, Expression},
 };
 use log::debug;
+use rayon::prelude::*;
 use std::{env::var, vec};
 
 pub(crate) fn get_num_rows_per_round() -> usize {
@@ -873,6 +874,19 @@ pub(crate) fn multi_keccak<F: Field>(
     challenges: Challenges<Value<F>>,
     capacity: Option<usize>,
 ) -> Result<Vec<KeccakRow<F>>, Error> {
+    if let Some(capacity) = capacity {
+        // Reject the inputs before generating their rows, which take a lot of memory for long
+        // inputs. The padding adds at least one byte to each input.
+        let num_keccak_f: usize = bytes.iter().map(|input| input.len() / RATE + 1).sum();
+        if num_keccak_f > capacity {
+            log::error!(
+                "Keccak inputs exceed capacity.  needed_keccak_f = {}, capacity = {}",
+                num_keccak_f,
+                capacity
+            );
+            return Err(Error::BoundsFailure);
+        }
+    }
     let mut rows: Vec<KeccakRow<F>> = Vec::new();
     // Dummy first row so that the initial data is absorbed
     // The initial data doesn't really matter, `is_final` just needs to be disabled.
@@ -892,10 +906,17 @@ pub(crate) fn multi_keccak<F: Field>(
             cell_values: Vec::new(),
         });
     }
-    // Actual keccaks
-    for bytes in bytes {
-        keccak(&mut rows, bytes, challenges);
-    }
+    // Actual keccaks, generated independently per message and concatenated in
+    // input order
+    let keccak_rows: Vec<Vec<KeccakRow<F>>> = bytes
+        .par_iter()
+        .map(|bytes| {
+            let mut rows = Vec::new();
+            keccak(&mut rows, bytes, challenges);
+            rows
+        })
+        .collect();
+    rows.extend(keccak_rows.into_iter().flatten());
     if let Some(capacity) = capacity {
         let padding_rows = {
             let mut rows = Vec::new();
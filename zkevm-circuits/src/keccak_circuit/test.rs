@@ -4,8 +4,9 @@ use crate::{
     util::{unusable_rows, word::WordLoHi},
 };
 use bus_mapping::state_db::EMPTY_CODE_HASH_LE;
-use eth_types::{Field, H256, U256};
+use eth_types::{keccak256, Field, H256, U256};
 use halo2_proofs::{
+    circuit::Value,
     dev::{CellValue, MockProver},
     halo2curves::bn256::Fr,
     plonk::{Assignment, Circuit},
@@ -127,6 +128,63 @@ fn packed_multi_keccak_simple() {
     verify::<Fr>(k, inputs, digests, true);
 }
 
+#[test]
+fn packed_multi_keccak_rate_boundaries() {
+    // Inputs around the boundary of the second absorbed block, where the padding spans the end
+    // of one block or starts a new one.
+    let k = 14;
+    let inputs: Vec<Vec<u8>> = [2 * RATE - 1, 2 * RATE, 2 * RATE + 1]
+        .into_iter()
+        .map(|len| (0..len).map(|i| i as u8).collect())
+        .collect();
+    let digests = inputs
+        .iter()
+        .map(|input| hex::encode(keccak256(input)))
+        .collect();
+    verify::<Fr>(k, inputs, digests, true);
+}
+
+#[test]
+fn keccak_witness_large_input() {
+    let input: Vec<u8> = (0..1 << 14).map(|i| (i * 7) as u8).collect();
+    let challenges = Challenges::mock(Value::known(Fr::from(0x100)), Value::known(Fr::from(0x100)));
+    let rows = multi_keccak(&[input.clone()], challenges, None).unwrap();
+
+    // The padding always adds at least one byte
+    let num_keccak_f = input.len() / RATE + 1;
+    assert_eq!(
+        rows.len(),
+        (1 + num_keccak_f * (NUM_ROUNDS + 1)) * get_num_rows_per_round()
+    );
+    let last_final_row = rows.iter().filter(|row| row.is_final).last().unwrap();
+    assert_eq!(last_final_row.length, input.len());
+    let (lo, hi) = WordLoHi::<Fr>::from(H256(keccak256(&input))).to_lo_hi();
+    last_final_row
+        .hash
+        .lo()
+        .assert_if_known(|value| *value == lo);
+    last_final_row
+        .hash
+        .hi()
+        .assert_if_known(|value| *value == hi);
+}
+
+#[test]
+fn keccak_witness_over_capacity() {
+    let challenges = Challenges::mock(Value::known(Fr::from(0x100)), Value::known(Fr::from(0x100)));
+    // The padding of an input of `2 * RATE` bytes needs a third keccak_f
+    let input = vec![0u8; 2 * RATE];
+    assert!(multi_keccak(&[input[1..].to_vec()], challenges, Some(2)).is_ok());
+    assert!(matches!(
+        multi_keccak(&[input.clone()], challenges, Some(2)),
+        Err(Error::BoundsFailure)
+    ));
+    assert!(matches!(
+        multi_keccak(&[input[RATE..].to_vec(), vec![]], challenges, Some(2)),
+        Err(Error::BoundsFailure)
+    ));
+}
+
 fn assigned_non_zero<F: Field>(cv: &CellValue<F>) -> bool {
     match *cv {
         CellValue::Assigned(v) => !v.is_zero_vartime(),
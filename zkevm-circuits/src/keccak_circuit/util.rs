@@ -92,13 +92,28 @@ pub(crate) fn get_absorb_positions() -> Vec<(usize, usize)> {
     absorb_positions
 }
 
+/// Bits of every byte value, least significant bit first.
+const BYTE_BITS: [[u8; NUM_BITS_PER_BYTE]; 256] = {
+    let mut table = [[0u8; NUM_BITS_PER_BYTE]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut idx = 0;
+        while idx < NUM_BITS_PER_BYTE {
+            table[byte][idx] = ((byte >> idx) & 1) as u8;
+            idx += 1;
+        }
+        byte += 1;
+    }
+    table
+};
+
 /// Converts bytes into bits
 pub(crate) fn into_bits(bytes: &[u8]) -> Vec<u8> {
-    let mut bits: Vec<u8> = vec![0; bytes.len() * 8];
-    for (byte_idx, byte) in bytes.iter().enumerate() {
-        for idx in 0u64..8 {
-            bits[byte_idx * 8 + (idx as usize)] = (*byte >> idx) & 1;
-        }
+    // Copying fixed size rows of a lookup table lets the compiler vectorize
+    // the conversion, unlike extracting the bits one by one.
+    let mut bits: Vec<u8> = Vec::with_capacity(bytes.len() * NUM_BITS_PER_BYTE);
+    for byte in bytes {
+        bits.extend_from_slice(&BYTE_BITS[*byte as usize]);
     }
     bits
 }
@@ -111,6 +126,18 @@ pub(crate) fn pack<F: Field>(bits: &[u8]) -> F {
 /// Pack bits in the range [0,BIT_SIZE[ into a sparse keccak word with the
 /// specified bit base
 pub(crate) fn pack_with_base<F: Field>(bits: &[u8], base: usize) -> F {
+    // With a power of two base the packed word usually fits in 256 bits, in
+    // which case accumulating with shifts on integers is much cheaper than
+    // field multiplications. The extra 8 bits leave room for the carries of
+    // values in [0, 256[.
+    let shift = base.trailing_zeros() as usize;
+    if base.is_power_of_two() && bits.len() * shift + 8 < 254 {
+        let packed = bits
+            .iter()
+            .rev()
+            .fold(Word::zero(), |acc, &bit| (acc << shift) + Word::from(bit));
+        return packed.to_scalar().unwrap();
+    }
     let base = F::from(base as u64);
     bits.iter()
         .rev()
@@ -269,6 +296,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn pack_with_base_matches_field_packing() {
+        let bits: Vec<u8> = (0..NUM_BITS_PER_WORD)
+            .map(|idx| (idx * 5 % 8) as u8)
+            .collect();
+        for base in [2, BIT_SIZE, 3] {
+            let expected = bits.iter().rev().fold(F::ZERO, |acc, &bit| {
+                acc * F::from(base as u64) + F::from(bit as u64)
+            });
+            assert_eq!(pack_with_base::<F>(&bits, base), expected);
+        }
+    }
+
     #[test]
     fn num_bits_per_lookup() {
         // Typical values.
@@ -1,4 +1,11 @@
 //! # zk_evm
+//!
+//! Each sub-circuit is built with a feature of its own: `evm`, `keccak`, `tx`,
+//! `copy`, `exp`, `bytecode`, `mpt` and `sig`.  The `super` feature builds the
+//! `SuperCircuit` together with all the sub-circuits it embeds.  The default
+//! features build every circuit.  The state, PI and root circuits, the tables,
+//! the witness and the helpers of the EVM circuit shared by the other circuits
+//! are always built.
 
 // We should try not to use incomplete_features unless it is really really needed and cannot be
 // avoided like `adt_const_params` used by DummyGadget
@@ -22,20 +29,36 @@ compile_error!("bus-mapping: notwasm feature must be disabled when target arch i
 #[cfg(all(not(target_arch = "wasm32"), not(feature = "notwasm")))]
 compile_error!("bus-mapping: notwasm feature must be enabled when target arch is not wasm");
 
+#[cfg(feature = "bytecode")]
 pub mod bytecode_circuit;
 #[allow(dead_code, reason = "under active development")]
 pub mod circuit_tools;
+#[cfg(feature = "copy")]
 pub mod copy_circuit;
+#[cfg(any(test, feature = "test-util"))]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dev;
+pub mod error;
 pub mod evm_circuit;
+#[cfg(feature = "exp")]
 pub mod exp_circuit;
+#[cfg(feature = "keccak")]
 pub mod keccak_circuit;
+#[cfg(feature = "super")]
+pub mod metrics;
+#[cfg(feature = "mpt")]
 #[allow(dead_code, reason = "under active development")]
 pub mod mpt_circuit;
 pub mod pi_circuit;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod proof;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod root_circuit;
+#[cfg(feature = "sig")]
 pub mod sig_circuit;
+pub mod srs;
 pub mod state_circuit;
+#[cfg(feature = "super")]
 pub mod super_circuit;
 pub mod table;
 
@@ -44,6 +67,7 @@ pub mod table;
 pub mod test_util;
 
 pub mod instance;
+#[cfg(feature = "tx")]
 pub mod tx_circuit;
 pub mod util;
 pub mod witness;
@@ -0,0 +1,112 @@
+//! Metrics of witness generation and circuit synthesis.
+//!
+//! Proving services run for a long time, so the counters here let operators
+//! monitor them without an external profiler: they are updated as circuits
+//! are configured and as blocks are built into circuits, and are read at any
+//! time with [`snapshot`].  The witness generation and the synthesis of each
+//! sub-circuit are also recorded as `tracing` spans.
+//!
+//! The counters are shared by the whole process.
+
+use serde::Serialize;
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Counters updated by the circuits
+#[derive(Debug)]
+struct Metrics {
+    blocks: u64,
+    rws: usize,
+    rows: BTreeMap<&'static str, usize>,
+    lookups: BTreeMap<&'static str, usize>,
+}
+
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics {
+    blocks: 0,
+    rws: 0,
+    rows: BTreeMap::new(),
+    lookups: BTreeMap::new(),
+});
+
+fn update(f: impl FnOnce(&mut Metrics)) {
+    // The counters stay consistent even if an update panicked
+    let mut metrics = METRICS.lock().unwrap_or_else(|err| err.into_inner());
+    f(&mut metrics)
+}
+
+/// Values of the metrics at a point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MetricsSnapshot {
+    /// Number of blocks built into a circuit
+    pub blocks: u64,
+    /// Number of rws of the last block
+    pub rws: usize,
+    /// Rows assigned by each sub-circuit, with padding, for the last block
+    pub rows: BTreeMap<&'static str, usize>,
+    /// Number of lookup arguments of each sub-circuit, as last configured
+    pub lookups: BTreeMap<&'static str, usize>,
+    /// Peak resident memory of the process in bytes, when the OS reports it
+    pub peak_memory: Option<u64>,
+}
+
+/// Read the current value of the metrics.
+pub fn snapshot() -> MetricsSnapshot {
+    let metrics = METRICS.lock().unwrap_or_else(|err| err.into_inner());
+    MetricsSnapshot {
+        blocks: metrics.blocks,
+        rws: metrics.rws,
+        rows: metrics.rows.clone(),
+        lookups: metrics.lookups.clone(),
+        peak_memory: peak_memory(),
+    }
+}
+
+/// Record a block of `rws` rws built into a circuit whose sub-circuits assign
+/// `rows`.
+pub(crate) fn record_block(rws: usize, rows: impl IntoIterator<Item = (&'static str, usize)>) {
+    update(|metrics| {
+        metrics.blocks += 1;
+        metrics.rws = rws;
+        metrics.rows.extend(rows);
+    })
+}
+
+/// Record the number of lookup arguments of the sub-circuit `circuit`.
+pub(crate) fn record_lookups(circuit: &'static str, lookups: usize) {
+    update(|metrics| {
+        metrics.lookups.insert(circuit, lookups);
+    })
+}
+
+/// Peak resident set size of the process, as reported by Linux.
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_snapshot() {
+        // Other tests update the same counters concurrently
+        let before = snapshot();
+        record_lookups("test", 3);
+        record_block(42, [("test", 7)]);
+        let after = snapshot();
+        assert!(after.blocks > before.blocks);
+        assert_eq!(after.rows["test"], 7);
+        assert_eq!(after.lookups["test"], 3);
+        if cfg!(target_os = "linux") {
+            assert!(after.peak_memory.unwrap() > 0);
+        }
+    }
+}
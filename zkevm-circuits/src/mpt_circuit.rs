@@ -1,16 +1,19 @@
 //! The MPT circuit implementation.
-use eth_types::Field;
-use gadgets::{impl_expr, util::Scalar};
+use eth_types::{Address, Field, H256};
+use gadgets::{
+    impl_expr,
+    util::{sum, Scalar},
+};
 use halo2_proofs::{
-    circuit::{Layouter, SimpleFloorPlanner, Value},
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
     plonk::{
-        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase,
+        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, SecondPhase,
         VirtualCells,
     },
     poly::Rotation,
 };
 
-use std::{convert::TryInto, env::var, io::Read, marker::PhantomData};
+use std::{convert::TryInto, env::var, fmt, io::Read, marker::PhantomData, ops::Deref};
 
 mod account_leaf;
 mod branch;
@@ -26,9 +29,12 @@ mod storage_leaf;
 /// MPT witness row
 pub mod witness_row;
 
+pub use helpers::{MptLookupTables, MptTableType};
+
 use self::{
     account_leaf::AccountLeafConfig,
     helpers::RLPItemView,
+    mod_extension::{check_node_mod_extension, ModExtensionError},
     param::RLP_UNIT_NUM_BYTES,
     rlp_gadgets::decode_rlp,
     witness_row::{
@@ -45,18 +51,18 @@ use crate::{
         memory::{Memory, RwBank},
     },
     mpt_circuit::{
-        helpers::{MPTConstraintBuilder, MainRLPGadget, MptCellType, MptTableType},
+        helpers::{MPTConstraintBuilder, MainRLPGadget, MptCellType},
         start::StartConfig,
         storage_leaf::StorageLeafConfig,
     },
     table::{KeccakTable, MPTProofType, MptTable},
-    util::Challenges,
+    util::{word::WordLoHi, Challenges},
 };
 
 use extension_branch::ExtensionBranchConfig;
 use param::HASH_WIDTH;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum MPTRegion {
     Default,
     RLP,
@@ -67,13 +73,18 @@ pub(crate) enum MPTRegion {
     Count,
 }
 
+impl MPTRegion {
+    /// Index of the state machine state of a node region
+    fn state_index(self) -> usize {
+        assert!(self as usize >= MPTRegion::Start as usize && self != MPTRegion::Count);
+        self as usize - MPTRegion::Start as usize
+    }
+}
+
 /// State machine config.
 #[derive(Clone, Debug)]
 pub struct StateMachineConfig<F> {
-    is_start: Column<Advice>,
-    is_branch: Column<Advice>,
-    is_account: Column<Advice>,
-    is_storage: Column<Advice>,
+    state_columns: StateColumns,
 
     start_config: StartConfig<F>,
     branch_config: ExtensionBranchConfig<F>,
@@ -81,14 +92,36 @@ pub struct StateMachineConfig<F> {
     account_config: AccountLeafConfig<F>,
 }
 
+/// Columns encoding the state of the state machine on the first row of each
+/// node. All the columns are zero on the other rows.
+#[derive(Clone, Copy, Debug)]
+enum StateColumns {
+    /// One boolean column per state
+    OneHot([Column<Advice>; 4]),
+    /// A boolean column enabled on the first row of each node, and the index
+    /// of the state in two bits
+    Binary {
+        is_node: Column<Advice>,
+        bits: [Column<Advice>; 2],
+    },
+}
+
 impl<F: Field> StateMachineConfig<F> {
     /// Construct a new StateMachine
-    pub(crate) fn construct(meta: &mut ConstraintSystem<F>) -> Self {
+    pub(crate) fn construct(
+        meta: &mut ConstraintSystem<F>,
+        compress_state_selectors: bool,
+    ) -> Self {
+        let state_columns = if compress_state_selectors {
+            StateColumns::Binary {
+                is_node: meta.advice_column(),
+                bits: [(); 2].map(|_| meta.advice_column()),
+            }
+        } else {
+            StateColumns::OneHot([(); 4].map(|_| meta.advice_column()))
+        };
         Self {
-            is_start: meta.advice_column(),
-            is_branch: meta.advice_column(),
-            is_account: meta.advice_column(),
-            is_storage: meta.advice_column(),
+            state_columns,
             start_config: StartConfig::default(),
             branch_config: ExtensionBranchConfig::default(),
             storage_config: StorageLeafConfig::default(),
@@ -96,14 +129,75 @@ impl<F: Field> StateMachineConfig<F> {
         }
     }
 
-    /// Returns all state selectors
-    pub(crate) fn state_selectors(&self) -> Vec<Column<Advice>> {
-        vec![
-            self.is_start,
-            self.is_branch,
-            self.is_account,
-            self.is_storage,
-        ]
+    /// Returns all state columns
+    pub(crate) fn state_columns(&self) -> Vec<Column<Advice>> {
+        match self.state_columns {
+            StateColumns::OneHot(columns) => columns.to_vec(),
+            StateColumns::Binary { is_node, bits } => vec![is_node, bits[0], bits[1]],
+        }
+    }
+
+    /// Returns 1 on the first row of a node in `state`, 0 otherwise
+    pub(crate) fn is_state(
+        &self,
+        meta: &mut VirtualCells<'_, F>,
+        state: MPTRegion,
+    ) -> Expression<F> {
+        let idx = state.state_index();
+        match self.state_columns {
+            StateColumns::OneHot(columns) => meta.query_advice(columns[idx], Rotation::cur()),
+            StateColumns::Binary { is_node, bits } => {
+                let is_node = meta.query_advice(is_node, Rotation::cur());
+                let [b0, b1] = bits.map(|bit| meta.query_advice(bit, Rotation::cur()));
+                // The bits are zero when is_node is zero, which keeps the decoding
+                // at degree 2
+                let b01 = b0.clone() * b1.clone();
+                match idx {
+                    0 => is_node - b0 - b1 + b01,
+                    1 => b0 - b01,
+                    2 => b1 - b01,
+                    _ => b01,
+                }
+            }
+        }
+    }
+
+    /// Returns 1 on the first row of a node, 0 otherwise
+    pub(crate) fn is_node(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        match self.state_columns {
+            StateColumns::OneHot(columns) => {
+                sum::expr(columns.map(|column| meta.query_advice(column, Rotation::cur())))
+            }
+            StateColumns::Binary { is_node, .. } => meta.query_advice(is_node, Rotation::cur()),
+        }
+    }
+
+    /// Constrains the state columns to encode at most one state
+    pub(crate) fn state_constraints(
+        &self,
+        meta: &mut VirtualCells<'_, F>,
+        cb: &mut MPTConstraintBuilder<F>,
+    ) {
+        circuit!([meta, cb], {
+            match self.state_columns {
+                StateColumns::OneHot(columns) => {
+                    for column in columns {
+                        require!(a!(column) => bool);
+                    }
+                    let is_node = self.is_node(meta);
+                    require!(is_node => bool);
+                }
+                StateColumns::Binary { is_node, bits } => {
+                    // Each combination of the bits is a state, so only the bits
+                    // need to be checked
+                    require!(a!(is_node) => bool);
+                    for bit in bits {
+                        require!(a!(bit) => bool);
+                        require!(a!(bit) * not!(a!(is_node)) => 0);
+                    }
+                }
+            }
+        })
     }
 
     pub(crate) fn step_constraints(
@@ -115,11 +209,11 @@ impl<F: Field> StateMachineConfig<F> {
         circuit!([meta, cb], {
             // Because the state machine state is this height, we're already querying cells
             // at all of these rotations, so may as well keep things simple.
-            // State selectors are already enforced to be boolean on each row.
+            // State columns are already enforced to be boolean on each row.
             let mut sum = 0.expr();
             for rot in 1..height {
-                for state_selector in self.state_selectors() {
-                    sum = sum + a!(state_selector, rot);
+                for state_column in self.state_columns() {
+                    sum = sum + a!(state_column, rot);
                 }
             }
             require!(sum => 0);
@@ -127,6 +221,30 @@ impl<F: Field> StateMachineConfig<F> {
             // because we never use relative offsets between state machine states.
         })
     }
+
+    /// Assigns `state` on the first row of the node at `offset`
+    pub(crate) fn assign_state(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        state: MPTRegion,
+    ) -> Result<(), Error> {
+        let idx = state.state_index();
+        match self.state_columns {
+            StateColumns::OneHot(columns) => {
+                let name = ["is_start", "is_branch", "is_account", "is_storage"][idx];
+                assign!(region, (columns[idx], offset) => name, true.scalar())?;
+            }
+            StateColumns::Binary { is_node, bits } => {
+                assign!(region, (is_node, offset) => "is_node", true.scalar())?;
+                for (bit_idx, bit) in bits.into_iter().enumerate() {
+                    let value = (idx >> bit_idx) & 1 == 1;
+                    assign!(region, (bit, offset) => "state_bit", value.scalar())?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 type MptMemory<F> = Memory<F, MptCellType, RwBank<F, MptCellType>>;
@@ -186,6 +304,9 @@ pub struct MPTConfig<F: Field> {
     params: MPTCircuitParams,
     cell_columns: Vec<CellColumn<F, MptCellType>>,
     cb: MPTConstraintBuilder<F>,
+    /// Public inputs with the roots of the first proof, when
+    /// [`MPTCircuitParams::expose_roots`] is set
+    pub instance: Option<Column<Instance>>,
 }
 
 /// Enumerator to determine the type of row in the fixed table.
@@ -221,6 +342,14 @@ impl<F: Field> MPTConfig<F> {
 
         let mpt_table = MptTable::construct(meta);
 
+        let instance = if params.expose_roots {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            Some(instance)
+        } else {
+            None
+        };
+
         let fixed_table: [Column<Fixed>; 6] = (0..6)
             .map(|_| meta.fixed_column())
             .collect::<Vec<_>>()
@@ -231,14 +360,17 @@ impl<F: Field> MPTConfig<F> {
             [meta.advice_column(), meta.advice_column_in(SecondPhase)];
 
         let mut cb = MPTConstraintBuilder::new(5, Some(challenges), None);
+        cb.set_lookup_tables(params.lookup_tables);
+        let lookup_tables = cb.lookup_tables();
 
         // Load premade lookup tables
-        cb.load_table(meta, MptTableType::Keccak, &keccak_table);
-        cb.load_table(meta, MptTableType::Byte, &[fixed_table[2]]);
-        cb.load_table(meta, MptTableType::Fixed, &fixed_table);
-        cb.load_table(meta, MptTableType::Mult, &mult_table);
+        cb.load_table(meta, lookup_tables.keccak, &keccak_table);
+        cb.load_table(meta, lookup_tables.byte, &[fixed_table[2]]);
+        cb.load_table(meta, lookup_tables.fixed, &fixed_table);
+        cb.load_table(meta, lookup_tables.mult, &mult_table);
 
-        let mut state_machine = StateMachineConfig::construct(meta);
+        let mut state_machine =
+            StateMachineConfig::construct(meta, params.compress_state_selectors);
         let mut rlp_item = MainRLPGadget::default();
 
         let lu = MptCellType::Lookup;
@@ -247,18 +379,29 @@ impl<F: Field> MPTConfig<F> {
         rlp_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase1, 0, false, 60);
         rlp_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase2, 1, false, 5);
         rlp_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase3, 2, false, 5);
-        rlp_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Byte), 0, false, 4);
-        rlp_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Fixed), 2, false, 4);
-        rlp_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Mult), 2, false, 2);
+        rlp_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.byte), 0, false, 4);
+        rlp_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.fixed), 2, false, 4);
+        rlp_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.mult), 2, false, 2);
 
         let mut state_cm = CellManager::new(50, 0);
         state_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase1, 0, false, 20);
         state_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase2, 1, false, 6);
         state_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase3, 2, false, 5);
-        state_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Byte), 0, false, 4);
-        state_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Fixed), 2, false, 3);
-        state_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Keccak), 2, false, 1);
-        state_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Mult), 2, false, 2);
+        state_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.byte), 0, false, 4);
+        state_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.fixed), 2, false, 3);
+        state_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.keccak), 2, false, 1);
+        state_cm.add_columns(meta, &mut cb.base, lu(lookup_tables.mult), 2, false, 2);
+        if params.expose_roots {
+            // The roots of the start node are copied to the instance column
+            state_cm.add_columns(
+                meta,
+                &mut cb.base,
+                MptCellType::StoragePermutation,
+                0,
+                true,
+                2,
+            );
+        }
 
         let mut memory = Memory::new();
         memory.add_memory_bank(meta, &mut cb.base, &mut state_cm, MptCellType::MemKeyC, 2);
@@ -285,6 +428,9 @@ impl<F: Field> MPTConfig<F> {
             memory: memory.clone(),
             params,
         };
+
+        cb.base
+            .set_extract_common_subexpressions(params.extract_subexpressions);
         meta.create_gate("MPT", |meta| {
             circuit!([meta, cb], {
                 ifx!{f!(q_enable) => {
@@ -306,43 +452,49 @@ impl<F: Field> MPTConfig<F> {
                     // Main MPT circuit
                     // State machine
                     cb.base.set_cell_manager(state_cm.clone());
+                    let is_start = state_machine.is_state(meta, MPTRegion::Start);
+                    let is_branch = state_machine.is_state(meta, MPTRegion::Branch);
+                    let is_account = state_machine.is_state(meta, MPTRegion::Account);
+                    let is_storage = state_machine.is_state(meta, MPTRegion::Storage);
+                    let is_node = state_machine.is_node(meta);
                     ifx! {f!(q_first) => {
-                        require!(a!(state_machine.is_start) => true);
+                        require!(is_start.expr() => true);
                     }};
                     // Main state machine
-                    matchx! {(
-                        a!(state_machine.is_start) => {
-                            state_machine.step_constraints(meta, &mut cb, StartRowType::Count as usize);
-                            cb.base.push_region(MPTRegion::Start as usize, StartRowType::Count as usize);
-                            state_machine.start_config = StartConfig::configure(meta, &mut cb, &mut ctx);
-                            ctx.memory.build_constraints(&mut cb.base, f!(q_first));
-                            cb.base.pop_region();
-                        },
-                        a!(state_machine.is_branch) => {
-                            state_machine.step_constraints(meta, &mut cb, ExtensionBranchRowType::Count as usize);
-                            cb.base.push_region(MPTRegion::Branch as usize, ExtensionBranchRowType::Count as usize);
-                            state_machine.branch_config = ExtensionBranchConfig::configure(meta, &mut cb, &mut ctx);
-                            ctx.memory.build_constraints(&mut cb.base, f!(q_first));
-                            cb.base.pop_region();
-                        },
-                        a!(state_machine.is_account) => {
-                            state_machine.step_constraints(meta, &mut cb, AccountRowType::Count as usize);
-                            cb.base.push_region(MPTRegion::Account as usize, AccountRowType::Count as usize);
-                            state_machine.account_config = AccountLeafConfig::configure(meta, &mut cb, &mut ctx);
-                            ctx.memory.build_constraints(&mut cb.base, f!(q_first));
-                            cb.base.pop_region();
-                        },
-                        a!(state_machine.is_storage) => {
-                            state_machine.step_constraints(meta, &mut cb, StorageRowType::Count as usize);
-                            cb.base.push_region(MPTRegion::Storage as usize, StorageRowType::Count as usize);
-                            state_machine.storage_config = StorageLeafConfig::configure(meta, &mut cb, &mut ctx);
-                            ctx.memory.build_constraints(&mut cb.base, f!(q_first));
-                            cb.base.pop_region();
-                        },
-                        _ => ctx.memory.build_constraints(&mut cb.base, f!(q_first)),
-                    )};
+                    state_machine.state_constraints(meta, &mut cb);
+                    ifx! {is_start => {
+                        state_machine.step_constraints(meta, &mut cb, StartRowType::Count as usize);
+                        cb.base.push_region(MPTRegion::Start as usize, StartRowType::Count as usize);
+                        state_machine.start_config = StartConfig::configure(meta, &mut cb, &mut ctx);
+                        ctx.memory.build_constraints(&mut cb.base, f!(q_first));
+                        cb.base.pop_region();
+                    }};
+                    ifx! {is_branch => {
+                        state_machine.step_constraints(meta, &mut cb, ExtensionBranchRowType::Count as usize);
+                        cb.base.push_region(MPTRegion::Branch as usize, ExtensionBranchRowType::Count as usize);
+                        state_machine.branch_config = ExtensionBranchConfig::configure(meta, &mut cb, &mut ctx);
+                        ctx.memory.build_constraints(&mut cb.base, f!(q_first));
+                        cb.base.pop_region();
+                    }};
+                    ifx! {is_account => {
+                        state_machine.step_constraints(meta, &mut cb, AccountRowType::Count as usize);
+                        cb.base.push_region(MPTRegion::Account as usize, AccountRowType::Count as usize);
+                        state_machine.account_config = AccountLeafConfig::configure(meta, &mut cb, &mut ctx);
+                        ctx.memory.build_constraints(&mut cb.base, f!(q_first));
+                        cb.base.pop_region();
+                    }};
+                    ifx! {is_storage => {
+                        state_machine.step_constraints(meta, &mut cb, StorageRowType::Count as usize);
+                        cb.base.push_region(MPTRegion::Storage as usize, StorageRowType::Count as usize);
+                        state_machine.storage_config = StorageLeafConfig::configure(meta, &mut cb, &mut ctx);
+                        ctx.memory.build_constraints(&mut cb.base, f!(q_first));
+                        cb.base.pop_region();
+                    }};
+                    ifx! {not!(is_node) => {
+                        ctx.memory.build_constraints(&mut cb.base, f!(q_first));
+                    }};
                     // Only account and storage rows can have lookups, disable lookups on all other rows
-                    ifx! {not!(a!(state_machine.is_account) + a!(state_machine.is_storage)) => {
+                    ifx! {not!(is_account + is_storage) => {
                         require!(a!(ctx.mpt_table.proof_type) => MPTProofType::Disabled.expr());
                     }}
                 }}
@@ -363,6 +515,9 @@ impl<F: Field> MPTConfig<F> {
         log::info!("num lookups: {}", meta.lookups().len());
         log::info!("num advices: {}", meta.num_advice_columns());
         log::info!("num fixed: {}", meta.num_fixed_columns());
+        for report in cb.base.subexpression_reports.iter() {
+            log::info!("{}", report);
+        }
         // cb.base.print_stats();
 
         MPTConfig {
@@ -378,16 +533,21 @@ impl<F: Field> MPTConfig<F> {
             mpt_table,
             cell_columns,
             cb,
+            instance,
         }
     }
 
-    /// Make the assignments to the MPTCircuit
+    /// Make the assignments to the MPTCircuit.
+    ///
+    /// Returns the cells of the S and C roots of each proof, in order, when
+    /// [`MPTCircuitParams::expose_roots`] is set, so that they can be
+    /// constrained to the public inputs or to the cells of another circuit.
     pub fn assign(
         &self,
         layouter: &mut impl Layouter<F>,
         nodes: &[Node],
         challenges: &Challenges<Value<F>>,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<[WordLoHi<AssignedCell<F, F>>; 2]>, Error> {
         layouter.assign_region(
             || "MPT",
             |mut region| {
@@ -395,6 +555,7 @@ impl<F: Field> MPTConfig<F> {
                 challenges.keccak_input().map(|v| keccak_r = v);
 
                 let mut memory = self.memory.clone();
+                let mut roots = Vec::new();
 
                 let mut offset = 0;
                 for node in nodes.iter() {
@@ -436,8 +597,8 @@ impl<F: Field> MPTConfig<F> {
                     if node.start.is_some() {
                         //println!("{}: start", offset);
                         cached_region.push_region(offset, MPTRegion::Start as usize);
-                        assign!(cached_region, (self.state_machine.is_start, offset) => "is_start", true.scalar())?;
-                        self.state_machine.start_config.assign(
+                        self.state_machine.assign_state(&mut cached_region, offset, MPTRegion::Start)?;
+                        let root = self.state_machine.start_config.assign(
                             &mut cached_region,
                             self,
                             &mut memory,
@@ -445,11 +606,12 @@ impl<F: Field> MPTConfig<F> {
                             node,
                             &rlp_values,
                         )?;
+                        roots.extend(root);
                         cached_region.pop_region();
                     } else if node.extension_branch.is_some() {
                         //println!("{}: branch", offset);
                         cached_region.push_region(offset, MPTRegion::Branch as usize);
-                        assign!(cached_region, (self.state_machine.is_branch, offset) => "is_branch", true.scalar())?;
+                        self.state_machine.assign_state(&mut cached_region, offset, MPTRegion::Branch)?;
                         self.state_machine.branch_config.assign(
                             &mut cached_region,
                             self,
@@ -462,7 +624,7 @@ impl<F: Field> MPTConfig<F> {
                     } else if node.account.is_some() {
                         //println!("{}: account", offset);
                         cached_region.push_region(offset, MPTRegion::Account as usize);
-                        assign!(cached_region, (self.state_machine.is_account, offset) => "is_account", true.scalar())?;
+                        self.state_machine.assign_state(&mut cached_region, offset, MPTRegion::Account)?;
                         self.state_machine.account_config.assign(
                             &mut cached_region,
                             self,
@@ -475,7 +637,7 @@ impl<F: Field> MPTConfig<F> {
                     } else if node.storage.is_some() {
                         //println!("{}: storage", offset);
                         cached_region.push_region(offset, MPTRegion::Storage as usize);
-                        assign!(cached_region, (self.state_machine.is_storage, offset) => "is_storage", true.scalar())?;
+                        self.state_machine.assign_state(&mut cached_region, offset, MPTRegion::Storage)?;
                         self.state_machine.storage_config.assign(
                             &mut cached_region,
                             self,
@@ -510,10 +672,26 @@ impl<F: Field> MPTConfig<F> {
                     assignf!(region, (self.q_first, offset) => (offset == 0).scalar())?;
                 }
 
-                Ok(())
+                Ok(roots)
             },
-        )?;
+        )
+    }
 
+    /// Constrain the S and C roots of the first proof to the instance column,
+    /// in the order of [`MPTCircuit::instance`].
+    pub fn constrain_roots(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        roots: &[[WordLoHi<AssignedCell<F, F>>; 2]],
+    ) -> Result<(), Error> {
+        if let (Some(instance), Some([old_root, new_root])) = (self.instance, roots.first()) {
+            for (row, cell) in [old_root.lo(), old_root.hi(), new_root.lo(), new_root.hi()]
+                .iter()
+                .enumerate()
+            {
+                layouter.constrain_instance(cell.cell(), instance, row)?;
+            }
+        }
         Ok(())
     }
 
@@ -658,10 +836,132 @@ pub struct MPTCircuit<F: Field> {
     /// Can be used to test artificially created tests with keys without known their known
     /// preimage. ONLY ENABLE FOR TESTS!
     pub disable_preimage_check: bool,
+    /// Encode the state of the state machine in binary instead of with one
+    /// column per state
+    pub compress_state_selectors: bool,
+    /// Store the common subexpressions of the constraints in cells
+    pub extract_subexpressions: bool,
+    /// Constrain the roots of the first proof to the public inputs
+    pub expose_roots: bool,
+    /// Types under which the lookup tables are loaded
+    pub lookup_tables: MptLookupTables,
     /// Marker
     pub _marker: PhantomData<F>,
 }
 
+/// Error of [`MPTCircuit::new_storage_series`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageSeriesError {
+    /// The proof at this index is not a storage proof
+    NotStorageProof(usize),
+    /// The proof at this index is for another account or slot
+    KeyMismatch(usize),
+}
+
+impl<F: Field> MPTCircuit<F> {
+    /// Create a circuit proving all the `proofs`, which share the fixed and
+    /// keccak tables.
+    pub fn new_from_proofs(
+        proofs: impl IntoIterator<Item = Vec<Node>>,
+        degree: usize,
+        max_nodes: usize,
+    ) -> Self {
+        let nodes: Vec<Node> = proofs.into_iter().flatten().collect();
+        let keccak_data = nodes
+            .iter()
+            .flat_map(|node| node.keccak_data.iter().map(|data| data.deref().clone()))
+            .collect();
+        let disable_preimage_check = nodes
+            .first()
+            .and_then(|node| node.start.as_ref())
+            .map_or(false, |start| start.disable_preimage_check);
+        Self {
+            nodes,
+            keccak_data,
+            degree,
+            max_nodes,
+            disable_preimage_check,
+            compress_state_selectors: false,
+            extract_subexpressions: false,
+            expose_roots: false,
+            lookup_tables: MptLookupTables::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a circuit proving the value of the storage `slot` of `address`
+    /// under the state root of every proof in `proofs`, for example to prove
+    /// a time series of the slot.
+    ///
+    /// Reading a slot is proven with a [`MPTProofType::StorageChanged`] proof
+    /// whose old and new values are equal, or with a
+    /// [`MPTProofType::StorageDoesNotExist`] proof.
+    pub fn new_storage_series(
+        address: Address,
+        slot: H256,
+        proofs: Vec<Vec<Node>>,
+        degree: usize,
+        max_nodes: usize,
+    ) -> Result<Self, StorageSeriesError> {
+        for (idx, proof) in proofs.iter().enumerate() {
+            let proof_type = proof
+                .first()
+                .and_then(|node| node.start.as_ref())
+                .map(|start| start.proof_type);
+            if !matches!(
+                proof_type,
+                Some(MPTProofType::StorageChanged | MPTProofType::StorageDoesNotExist)
+            ) {
+                return Err(StorageSeriesError::NotStorageProof(idx));
+            }
+            let accounts: Vec<_> = proof
+                .iter()
+                .filter_map(|node| node.account.as_ref())
+                .collect();
+            let storages: Vec<_> = proof
+                .iter()
+                .filter_map(|node| node.storage.as_ref())
+                .collect();
+            let key_matches = !accounts.is_empty()
+                && !storages.is_empty()
+                && accounts
+                    .iter()
+                    .all(|account| account.address.as_slice() == address.as_bytes())
+                && storages
+                    .iter()
+                    .all(|storage| storage.address.as_slice() == slot.as_bytes());
+            if !key_matches {
+                return Err(StorageSeriesError::KeyMismatch(idx));
+            }
+        }
+        Ok(Self::new_from_proofs(proofs, degree, max_nodes))
+    }
+
+    /// Public inputs of the circuit: the S and C roots of the first proof as
+    /// `[old_root.lo, old_root.hi, new_root.lo, new_root.hi]` when
+    /// `expose_roots` is set, none otherwise.
+    pub fn instance(&self) -> Vec<Vec<F>> {
+        if !self.expose_roots {
+            return vec![];
+        }
+        let roots = self
+            .nodes
+            .iter()
+            .find(|node| node.start.is_some())
+            .map(|node| {
+                [StartRowType::RootS, StartRowType::RootC].map(|row| {
+                    // The root is an RLP encoded hash
+                    WordLoHi::<F>::from(H256::from_slice(&node.values[row as usize][1..33]))
+                })
+            });
+        vec![roots
+            .into_iter()
+            .flatten()
+            .flat_map(|root| [root.lo(), root.hi()])
+            .collect()]
+    }
+}
+
 /// MPT Circuit configuration parameters
 #[derive(Copy, Clone, Debug, Default)]
 pub struct MPTCircuitParams {
@@ -671,6 +971,21 @@ pub struct MPTCircuitParams {
     pub disable_preimage_check: bool,
     /// Maximal number of nodes MPT can prove (for example, one branch has 16 nodes)
     pub max_nodes: usize,
+    /// Encode the state of the state machine with an `is_node` column and the
+    /// state index in two bits, instead of with one column per state.  This
+    /// saves a column, but the state conditions have degree 2 instead of 1,
+    /// which lowers the degree left to the constraints of each node.
+    pub compress_state_selectors: bool,
+    /// Store the subexpressions shared by the constraints of the MPT gate in
+    /// cells, which lowers the degree of the gate at the cost of more cells
+    /// per row.
+    pub extract_subexpressions: bool,
+    /// Copy the S and C roots of the first proof to an instance column, as
+    /// `[old_root.lo, old_root.hi, new_root.lo, new_root.hi]`, so that the
+    /// proven roots are public inputs and not only witnesses.
+    pub expose_roots: bool,
+    /// Types under which the lookup tables of the MPT gadgets are loaded
+    pub lookup_tables: MptLookupTables,
 }
 
 impl MPTCircuitParams {
@@ -699,6 +1014,10 @@ impl<F: Field> Circuit<F> for MPTCircuit<F> {
             degree: self.degree,
             disable_preimage_check: self.disable_preimage_check,
             max_nodes: self.max_nodes,
+            compress_state_selectors: self.compress_state_selectors,
+            extract_subexpressions: self.extract_subexpressions,
+            expose_roots: self.expose_roots,
+            lookup_tables: self.lookup_tables,
         }
     }
 
@@ -722,7 +1041,8 @@ impl<F: Field> Circuit<F> for MPTCircuit<F> {
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
         let challenges = _challenges.values(&mut layouter);
-        config.assign(&mut layouter, &self.nodes, &challenges)?;
+        let roots = config.assign(&mut layouter, &self.nodes, &challenges)?;
+        config.constrain_roots(&mut layouter, &roots)?;
         config.load_fixed_table(&mut layouter)?;
         config.load_mult_table(&mut layouter, &challenges, self.max_nodes)?;
         config
@@ -733,8 +1053,43 @@ impl<F: Field> Circuit<F> for MPTCircuit<F> {
     }
 }
 
+/// Error of [`load_proof`]
+#[derive(Debug)]
+pub enum LoadProofError {
+    /// The proof can't be deserialized
+    Json(serde_json::Error),
+    /// The node at this index has an inconsistent modified extension node
+    ModExtension(usize, ModExtensionError),
+}
+
+impl From<serde_json::Error> for LoadProofError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl fmt::Display for LoadProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid proof: {}", err),
+            Self::ModExtension(idx, err) => {
+                write!(
+                    f,
+                    "invalid modified extension node in node {}: {}",
+                    idx, err
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadProofError {}
+
 /// Loads an MPT proof from reader
-pub fn load_proof<R: Read>(reader: R) -> Result<Vec<Node>, serde_json::Error> {
+///
+/// The rows of the modified extension nodes are checked, so that an
+/// inconsistent witness is reported here instead of failing the synthesis.
+pub fn load_proof<R: Read>(reader: R) -> Result<Vec<Node>, LoadProofError> {
     let mut nodes: Vec<Node> = serde_json::from_reader(reader)?;
 
     // Add the address and the key to the list of values in the Account and Storage nodes
@@ -752,6 +1107,9 @@ pub fn load_proof<R: Read>(reader: R) -> Result<Vec<Node>, serde_json::Error> {
                 .push([vec![160], storage.key.to_vec()].concat().into());
         }
     }
+    for (idx, node) in nodes.iter().enumerate() {
+        check_node_mod_extension(node).map_err(|err| LoadProofError::ModExtension(idx, err))?;
+    }
     Ok(nodes)
 }
 
@@ -765,9 +1123,14 @@ pub fn load_proof_from_file(path: &str) -> Vec<Node> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+    };
     use itertools::Itertools;
-    use std::{fs, ops::Deref, path::PathBuf};
+    use mod_extension::{check_mod_extension, ModExtensionRows};
+    use rlp_gadgets::RLPItemWitness;
+    use std::{fs, path::PathBuf};
 
     #[test]
     fn test_mpt() {
@@ -783,6 +1146,80 @@ mod tests {
             });
     }
 
+    #[test]
+    fn test_mpt_compressed_state_selectors() {
+        let degree = 15;
+        get_witnesses().for_each(|(path, num_rows, mut circuit)| {
+            circuit.compress_state_selectors = true;
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            assert_eq!(
+                prover.verify_at_rows(0..num_rows, 0..num_rows),
+                Ok(()),
+                "{:?}",
+                path
+            );
+        });
+    }
+
+    #[test]
+    fn test_mpt_extract_subexpressions() {
+        let degree = 15;
+        get_witnesses().for_each(|(path, num_rows, mut circuit)| {
+            circuit.extract_subexpressions = true;
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            assert_eq!(
+                prover.verify_at_rows(0..num_rows, 0..num_rows),
+                Ok(()),
+                "{:?}",
+                path
+            );
+        });
+    }
+
+    #[test]
+    fn test_mpt_expose_roots() {
+        let degree = 15;
+        let (path, num_rows, mut circuit) = get_witnesses().next().unwrap();
+        circuit.expose_roots = true;
+        let instance = circuit.instance();
+        assert_eq!(instance[0].len(), 4);
+
+        let prover = MockProver::<Fr>::run(degree, &circuit, instance.clone()).unwrap();
+        assert_eq!(
+            prover.verify_at_rows(0..num_rows, 0..num_rows),
+            Ok(()),
+            "{:?}",
+            path
+        );
+
+        // The new root is not the one of the proof
+        let mut wrong_instance = instance;
+        wrong_instance[0][2] += Fr::one();
+        let prover = MockProver::<Fr>::run(degree, &circuit, wrong_instance).unwrap();
+        assert!(prover.verify_at_rows(0..num_rows, 0..num_rows).is_err());
+    }
+
+    #[test]
+    fn test_mpt_other_lookup_tables() {
+        let degree = 15;
+        let (path, num_rows, mut circuit) = get_witnesses().next().unwrap();
+        // None of the default tables is loaded, so a lookup into one of them
+        // fails to configure.
+        circuit.lookup_tables = MptLookupTables {
+            keccak: MptTableType::Other(0),
+            fixed: MptTableType::Other(1),
+            byte: MptTableType::Other(2),
+            mult: MptTableType::Other(3),
+        };
+        let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+        assert_eq!(
+            prover.verify_at_rows(0..num_rows, 0..num_rows),
+            Ok(()),
+            "{:?}",
+            path
+        );
+    }
+
     #[test]
     fn variadic_size_check() {
         let mut circuits = get_witnesses();
@@ -797,6 +1234,245 @@ mod tests {
         assert_eq!(prover_1.permutation(), prover_2.permutation());
     }
 
+    #[test]
+    fn storage_series() {
+        let proof = get_witnesses()
+            .map(|(_, _, circuit)| circuit.nodes)
+            .find(|nodes| {
+                nodes[0].start.as_ref().unwrap().proof_type == MPTProofType::StorageChanged
+            })
+            .unwrap();
+        let address = Address::from_slice(
+            &proof
+                .iter()
+                .find_map(|node| node.account.as_ref())
+                .unwrap()
+                .address,
+        );
+        let slot = H256::from_slice(
+            &proof
+                .iter()
+                .find_map(|node| node.storage.as_ref())
+                .unwrap()
+                .address,
+        );
+
+        let degree = 15;
+        let num_rows: usize = 2 * proof.iter().map(|node| node.values.len()).sum::<usize>();
+        let circuit = MPTCircuit::<Fr>::new_storage_series(
+            address,
+            slot,
+            vec![proof.clone(), proof.clone()],
+            degree,
+            520,
+        )
+        .unwrap();
+        let prover = MockProver::<Fr>::run(degree as u32, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify_at_rows(0..num_rows, 0..num_rows), Ok(()));
+
+        assert_eq!(
+            MPTCircuit::<Fr>::new_storage_series(
+                address,
+                H256::repeat_byte(0xff),
+                vec![proof],
+                degree,
+                520
+            )
+            .err(),
+            Some(StorageSeriesError::KeyMismatch(0))
+        );
+    }
+
+    #[test]
+    fn invalid_mod_extension() {
+        let (path, _, circuit) = get_witnesses()
+            .find(|(path, _, _)| path.ends_with("ExtNodeInsertedBefore5After2FirstLevel.json"))
+            .unwrap();
+        let (idx, node) = circuit
+            .nodes
+            .iter()
+            .find_position(|node| node.storage.is_some())
+            .unwrap();
+        assert!(node.storage.as_ref().unwrap().is_mod_extension[0]);
+        let row = StorageRowType::ShortExtNodeKey as usize;
+        assert_eq!(node.values[row][..3], [0x82, 0x00, 0x56]);
+
+        // Give the short extension node key the prefix of a leaf
+        let mut rlp_values: Vec<_> = node
+            .values
+            .iter()
+            .map(|bytes| RLPItemWitness::new(bytes))
+            .collect();
+        rlp_values[row] = RLPItemWitness::new(&[0x82, 0x30, 0x56]);
+        let expected = ModExtensionError::InvalidKeyPrefix {
+            node: "short",
+            byte: 0x30,
+        };
+        assert_eq!(
+            check_mod_extension(&rlp_values, ModExtensionRows::STORAGE),
+            Err(expected.clone())
+        );
+
+        // The witness is rejected when it is loaded
+        let json = fs::read_to_string(path).unwrap();
+        let short_key = format!("\"82005600{}\"", "00".repeat(RLP_UNIT_NUM_BYTES - 4));
+        assert_eq!(json.matches(&short_key).count(), 1);
+        let json = json.replace(&short_key, &short_key.replacen("820056", "823056", 1));
+        match load_proof(json.as_bytes()) {
+            Err(LoadProofError::ModExtension(err_idx, err)) => {
+                assert_eq!((err_idx, err), (idx, expected))
+            }
+            res => panic!("unexpected result {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn deleted_branch_collapses_into_extension() {
+        let degree = 15;
+        let witnesses = get_witnesses().filter(|(path, _, _)| {
+            path.file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .starts_with("ExtNodeDeleted")
+        });
+        let mut num_proofs = 0;
+        for (path, num_rows, mut circuit) in witnesses {
+            // The branch only exists before the deletion, after it the
+            // extension node key absorbs the branch nibble
+            let node = circuit
+                .nodes
+                .iter()
+                .find(|node| node.storage.is_some())
+                .unwrap();
+            assert_eq!(
+                node.storage.as_ref().unwrap().is_mod_extension,
+                [false, true],
+                "{:?}",
+                path
+            );
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            assert_eq!(
+                prover.verify_at_rows(0..num_rows, 0..num_rows),
+                Ok(()),
+                "{:?}",
+                path
+            );
+
+            // Change the last nibble of the recombined extension node key
+            let node = circuit
+                .nodes
+                .iter_mut()
+                .find(|node| node.storage.is_some())
+                .unwrap();
+            let row = StorageRowType::LongExtNodeKey as usize;
+            let mut key = node.values[row].to_vec();
+            let len = (key[0] - 0x80) as usize;
+            key[len] ^= 1;
+            node.values[row] = key.into();
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            assert!(
+                prover.verify_at_rows(0..num_rows, 0..num_rows).is_err(),
+                "{:?}",
+                path
+            );
+            num_proofs += 1;
+        }
+        assert_eq!(num_proofs, 4);
+    }
+
+    /// Witnesses of extension nodes modified in the account trie, which are
+    /// generated against a local geth node by `TestAccountExtNodeInserted` and
+    /// `TestAccountExtNodeDeleted` in `geth-utils/gethutil/mpt/witness`
+    const ACCOUNT_MOD_EXTENSION_WITNESSES: [&str; 2] =
+        ["AccountExtNodeInserted.json", "AccountExtNodeDeleted.json"];
+
+    #[test]
+    fn account_mod_extension() {
+        let degree = 15;
+        for name in ACCOUNT_MOD_EXTENSION_WITNESSES {
+            let (path, num_rows, circuit) = get_witnesses()
+                .find(|(path, _, _)| path.ends_with(name))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "missing witness {}: run `go test -run TestAccountExtNode` in \
+                         geth-utils/gethutil/mpt/witness with `geth --dev --http` running, \
+                         and copy it from geth-utils/gethutil/mpt/generated_witnesses to \
+                         src/mpt_circuit/tests",
+                        name
+                    )
+                });
+            assert!(
+                circuit.nodes.iter().any(|node| node
+                    .account
+                    .as_ref()
+                    .map_or(false, |account| account.is_mod_extension.contains(&true))),
+                "{:?} has no modified extension node in the account trie",
+                path
+            );
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            assert_eq!(
+                prover.verify_at_rows(0..num_rows, 0..num_rows),
+                Ok(()),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn non_existing_proofs_check_key_preimage() {
+        let degree = 15;
+        let mut num_proofs = 0;
+        for (path, num_rows, mut circuit) in get_witnesses().filter(|(path, _, _)| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("NonExisting")
+        }) {
+            assert!(!circuit.disable_preimage_check, "{:?}", path);
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            assert_eq!(
+                prover.verify_at_rows(0..num_rows, 0..num_rows),
+                Ok(()),
+                "{:?}",
+                path
+            );
+
+            // Claim the proof for another address or slot, whose keccak is not
+            // the key of the path
+            let (node, row) = circuit
+                .nodes
+                .iter_mut()
+                .rev()
+                .find_map(|node| {
+                    if node.storage.is_some() {
+                        Some((node, StorageRowType::Address as usize))
+                    } else if node.account.is_some() {
+                        Some((node, AccountRowType::Address as usize))
+                    } else {
+                        None
+                    }
+                })
+                .unwrap();
+            let mut address = node.values[row].to_vec();
+            address[1] ^= 1;
+            node.values[row] = address.into();
+            let prover = MockProver::<Fr>::run(degree, &circuit, vec![]).unwrap();
+            let failures = prover.verify_at_rows(0..num_rows, 0..num_rows).unwrap_err();
+            assert!(
+                failures
+                    .iter()
+                    .any(|failure| matches!(failure, VerifyFailure::Lookup { .. })),
+                "{:?}: {:?}",
+                path,
+                failures
+            );
+            num_proofs += 1;
+        }
+        assert_eq!(num_proofs, 8);
+    }
+
     fn get_witnesses() -> impl Iterator<Item = (PathBuf, usize, MPTCircuit<Fr>)> {
         let path = "src/mpt_circuit/tests";
         let files = fs::read_dir(path).unwrap();
@@ -836,6 +1512,10 @@ mod tests {
                         degree,
                         max_nodes,
                         disable_preimage_check,
+                        compress_state_selectors: false,
+                        extract_subexpressions: false,
+                        expose_roots: false,
+                        lookup_tables: MptLookupTables::default(),
                         _marker: PhantomData,
                     },
                 )
@@ -7,7 +7,7 @@ use halo2_proofs::{
 
 use super::{
     helpers::{KeyDataWitness, ListKeyGadget, MainData, ParentDataWitness},
-    mod_extension::ModExtensionGadget,
+    mod_extension::{ModExtensionGadget, ModExtensionRows},
     rlp_gadgets::RLPItemWitness,
     witness_row::{AccountRowType, Node},
 };
@@ -22,9 +22,9 @@ use crate::{
     evm_circuit::util::from_bytes,
     mpt_circuit::{
         helpers::{
-            key_memory, main_memory, num_nibbles, parent_memory, DriftedGadget, Indexable,
-            IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder, ParentData, WrongGadget,
-            KECCAK,
+            key_memory, main_memory, num_nibbles, parent_memory, require_key_path, DriftedGadget,
+            Indexable, IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder, ParentData,
+            WrongGadget,
         },
         param::{EMPTY_TRIE_HASH, KEY_LEN_IN_NIBBLES, RLP_LIST_LONG, RLP_LONG},
         MPTConfig, MPTContext, MptMemory, RlpItemType,
@@ -281,7 +281,7 @@ impl<F: Field> AccountLeafConfig<F> {
                     // branch.
                     ifx! {not!(config.is_placeholder_leaf[is_s.idx()]) => {
                         let hash = parent_data[is_s.idx()].hash.expr();
-                        require!((1.expr(), leaf_rlc, rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @KECCAK);
+                        require!((1.expr(), leaf_rlc, rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @cb.keccak());
                     } elsex {
                         // For NonExistingAccountProof prove there is no leaf.
 
@@ -346,6 +346,7 @@ impl<F: Field> AccountLeafConfig<F> {
                     ctx.clone(),
                     parent_data,
                     key_data,
+                    ModExtensionRows::ACCOUNT,
                 );
             }};
 
@@ -444,20 +445,20 @@ impl<F: Field> AccountLeafConfig<F> {
                 config.is_non_existing_account_proof => (MPTProofType::AccountDoesNotExist.expr(), 0.expr(), 0.expr(), 0.expr(), 0.expr()),
                 _ => (MPTProofType::Disabled.expr(), 0.expr(), 0.expr(), 0.expr(), 0.expr()),
             )};
-            ifx! {not!(config.is_non_existing_account_proof) => {
-                let key_rlc = ifx!{not!(config.parent_data[true.idx()].is_placeholder) => {
-                    key_rlc[true.idx()].expr()
-                } elsex {
-                    key_rlc[false.idx()].expr()
-                }};
-                // Check that the key item contains the correct key for the path that was taken
-                require!(key_item.hash_rlc() => key_rlc);
-                // Check if the key is correct for the given address
-                if ctx.params.is_preimage_check_enabled() {
-                    let key = key_item.word();
-                    require!((1.expr(), address_item.bytes_le()[1..21].rlc(&cb.keccak_r), 20.expr(), key.lo(), key.hi()) =>> @KECCAK);
-                }
-            }};
+            // Check that the key item is the key of the address, and the key of the path
+            // that was taken
+            let preimage = ctx
+                .params
+                .is_preimage_check_enabled()
+                .then(|| (address_item.bytes_le()[1..21].rlc(&cb.keccak_r), 20.expr()));
+            require_key_path(
+                cb,
+                &key_item,
+                preimage,
+                &key_rlc,
+                config.parent_data[true.idx()].is_placeholder.expr(),
+                config.is_non_existing_account_proof.expr(),
+            );
             let address = address_item.word().compress();
 
             ifx! {not!(config.parent_data[false.idx()].is_placeholder) => {
@@ -739,8 +740,13 @@ impl<F: Field> AccountLeafConfig<F> {
                 &account.mod_list_rlp_bytes[0],
                 &account.mod_list_rlp_bytes[1],
             ];
-            self.mod_extension
-                .assign(region, offset, rlp_values, mod_list_rlp_bytes)?;
+            self.mod_extension.assign(
+                region,
+                offset,
+                rlp_values,
+                mod_list_rlp_bytes,
+                ModExtensionRows::ACCOUNT,
+            )?;
         }
 
         let mut new_value = value[false.idx()];
@@ -18,7 +18,7 @@ use crate::{
         gadgets::LtGadget,
     },
     mpt_circuit::{
-        helpers::{nibble_rlc, Indexable, MptCellType, KECCAK},
+        helpers::{nibble_rlc, Indexable, MptCellType},
         param::{HASH_WIDTH, RLP_NIL},
         MPTConfig, MptMemory, RlpItemType,
     },
@@ -197,7 +197,7 @@ impl<F: Field> BranchGadget<F> {
                     ifx!{or::expr(&[is_root[is_s.idx()].expr(), not!(is_not_hashed)]) => {
                         // Hashed branch hash in parent branch
                         let hash = &parent_hash[is_s.idx()];
-                        require!((1.expr(), rlc.expr(), num_bytes, hash.lo(), hash.hi()) =>> @KECCAK);
+                        require!((1.expr(), rlc.expr(), num_bytes, hash.lo(), hash.hi()) =>> @cb.keccak());
                     } elsex {
                         // Non-hashed branch hash in parent branch
                         require!(rlc => parent_rlc[is_s.idx()].expr());
@@ -15,10 +15,7 @@ use crate::{
         gadgets::LtGadget,
     },
     mpt_circuit::{
-        helpers::{
-            ext_key_rlc_calc_value, ext_key_rlc_expr, num_nibbles, Indexable, KeyData, MptCellType,
-            ParentData, FIXED, KECCAK, MULT,
-        },
+        helpers::{ext_key_rlc, num_nibbles, Indexable, KeyData, MptCellType, ParentData},
         param::HASH_WIDTH,
         FixedTableTag, MPTConfig, MptMemory, RlpItemType,
     },
@@ -97,7 +94,7 @@ impl<F: Field> ExtensionGadget<F> {
                 key_items[true.idx()].is_long() => key_items[true.idx()].bytes_be()[1].expr(),
                 key_items[true.idx()].is_very_long() => key_items[true.idx()].bytes_be()[2].expr(),
             )};
-            require!((FixedTableTag::ExtOddKey.expr(), first_byte, config.is_key_part_odd.expr()) =>> @FIXED);
+            require!((FixedTableTag::ExtOddKey.expr(), first_byte, config.is_key_part_odd.expr()) =>> @cb.fixed());
 
             let mut branch_rlp_rlc = vec![0.expr(); 2];
             let mut branch_rlp_word = vec![WordLoHi::zero(); 2];
@@ -137,7 +134,7 @@ impl<F: Field> ExtensionGadget<F> {
                 ifx! {not!(is_placeholder[is_s.idx()]) => {
                     ifx!{or::expr(&[parent_data[is_s.idx()].is_root.expr(), not!(is_not_hashed)]) => {
                         // Hashed extension node in parent branch
-                        require!((1.expr(), rlc.expr(), num_bytes.expr(), parent_data[is_s.idx()].hash.lo().expr(), parent_data[is_s.idx()].hash.hi().expr()) =>> @KECCAK);
+                        require!((1.expr(), rlc.expr(), num_bytes.expr(), parent_data[is_s.idx()].hash.lo().expr(), parent_data[is_s.idx()].hash.hi().expr()) =>> @cb.keccak());
                     } elsex {
                         // Non-hashed extension node in parent branch
                         require!(rlc => parent_data[is_s.idx()].rlc);
@@ -158,7 +155,7 @@ impl<F: Field> ExtensionGadget<F> {
             // shorter extension node (in terms of nibbles), this is still to be
             // implemented.
             let key_rlc = key_data.rlc.expr()
-                + ext_key_rlc_expr(
+                + ext_key_rlc::expr(
                     cb,
                     config.rlp_key.key_value.clone(),
                     key_data.mult.expr(),
@@ -188,7 +185,7 @@ impl<F: Field> ExtensionGadget<F> {
                 - ifx! {not!(key_data.is_odd.expr() * config.is_key_part_odd.expr()) => { 1.expr() }};
             // Get the multiplier for this key length
             config.mult_key = cb.query_cell_with_type(MptCellType::StoragePhase2);
-            require!((key_num_bytes_for_mult, config.mult_key.expr()) =>> @MULT);
+            require!((key_num_bytes_for_mult, config.mult_key.expr()) =>> @cb.mult());
 
             // Store the post ext state
             config.post_state = Some(ExtState {
@@ -269,8 +266,8 @@ impl<F: Field> ExtensionGadget<F> {
         *num_nibbles += num_nibbles::value(rlp_key.key_item.len(), is_key_part_odd);
 
         // Key RLC
-        let (key_rlc_ext, _) = ext_key_rlc_calc_value(
-            rlp_key.key_item,
+        let key_rlc_ext = ext_key_rlc::value(
+            &rlp_key.key_item,
             key_data.mult,
             is_key_part_odd,
             *is_key_odd,
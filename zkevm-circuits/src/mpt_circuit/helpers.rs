@@ -45,12 +45,20 @@ impl<F: Field> ChallengeSet<F> for crate::util::Challenges<Value<F>> {
     }
 }
 
+/// Lookup table of the MPT gadgets
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, EnumIter)]
 pub enum MptTableType {
+    /// Fixed table of the MPT circuit
     Fixed,
+    /// Byte range table of the MPT circuit
     Byte,
+    /// Keccak table of the MPT circuit
     Keccak,
+    /// Multiplier table of the MPT circuit
     Mult,
+    /// Table loaded under an id chosen by the circuit, for example to load
+    /// the tables of the MPT gadgets next to the other tables of the circuit
+    Other(usize),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -103,10 +111,6 @@ impl CellType for MptCellType {
     }
 }
 
-pub const FIXED: MptCellType = MptCellType::Lookup(MptTableType::Fixed);
-pub const KECCAK: MptCellType = MptCellType::Lookup(MptTableType::Keccak);
-pub const MULT: MptCellType = MptCellType::Lookup(MptTableType::Mult);
-
 /// Indexable object
 pub trait Indexable {
     /// Convert to index
@@ -216,106 +220,6 @@ impl LeafKeyWitness {
     }
 }
 
-pub(crate) fn ext_key_rlc_expr<F: Field>(
-    cb: &mut MPTConstraintBuilder<F>,
-    key_value: RLPItemView<F>,
-    key_mult_prev: Expression<F>,
-    is_key_part_odd: Expression<F>,
-    is_key_odd: Expression<F>,
-    data: [Vec<Expression<F>>; 2],
-    r: &Expression<F>,
-) -> Expression<F> {
-    circuit!([meta, cb.base], {
-        let (is_short, is_long) = (key_value.is_short(), key_value.is_long());
-        let mult_first_odd = ifx! {is_key_odd.expr() => { 1.expr() } elsex { 16.expr() }};
-        let calc_rlc = |cb: &mut MPTConstraintBuilder<F>,
-                        bytes: &[Expression<F>],
-                        key_mult_first_even: Expression<F>| {
-            ext_key_rlc(
-                cb,
-                bytes,
-                key_mult_prev.expr(),
-                is_key_part_odd.expr(),
-                mult_first_odd.expr(),
-                key_mult_first_even,
-                r,
-            )
-        };
-        matchx! {(
-            is_long => {
-                ifx! {xor::expr(is_key_odd.expr(), is_key_part_odd.expr()) => {
-                    // Here we need to multiply nibbles over bytes with different r's so we need to rlc over separate nibbles.
-                    // Note that there can be at max 31 key bytes because 32 same bytes would mean
-                    // the two keys being the same - update operation, not splitting into extension node.
-                    // So, we do not need to look further than `s_main.bytes` even if `s_main.bytes[0]`
-                    // is not used (when even number of nibbles).
-                    let mut key_bytes = vec![data[0][1].expr()];
-                    key_bytes.append(&mut data[0][1..].iter().skip(1).zip(data[1][2..].iter()).map(|(byte, nibble_hi)| {
-                        let nibble_lo = (byte.expr() - nibble_hi.expr()) * invert!(16);
-                        // Check that `nibble_hi` is correct.
-                        require!(byte => nibble_lo.expr() * 16.expr() + nibble_hi.expr());
-                        // Collect bytes
-                        (nibble_hi.expr() * 16.expr() * r.expr()) + nibble_lo.expr()
-                    }).collect::<Vec<_>>());
-                    calc_rlc(cb, &key_bytes, 1.expr())
-                } elsex {
-                    let additional_mult = ifx! {is_key_part_odd => { r.expr() } elsex { 1.expr() }};
-                    calc_rlc(cb, &data[0][1..], additional_mult)
-                }}
-            },
-            is_short => {
-                calc_rlc(cb, &data[0][..1], 1.expr())
-            },
-        )}
-    })
-}
-
-pub(crate) fn ext_key_rlc_calc_value<F: Field>(
-    key_value: RLPItemWitness,
-    key_mult_prev: F,
-    is_key_part_odd: bool,
-    is_key_odd: bool,
-    data: [Vec<u8>; 2],
-    r: F,
-) -> (F, F) {
-    let (is_short, is_long) = (key_value.is_short(), key_value.is_long());
-    let mult_first_odd = if is_key_odd { 1.scalar() } else { 16.scalar() };
-    let calc_rlc = |bytes: &[F], key_mult_first_even: F| {
-        ext_key_rlc_value(
-            bytes,
-            key_mult_prev,
-            is_key_part_odd,
-            mult_first_odd,
-            key_mult_first_even,
-            r,
-        )
-    };
-    matchw! {
-        is_long => {
-            if is_key_odd != is_key_part_odd {
-                // Here we need to multiply nibbles over bytes with different r's so we need to rlc over separate nibbles.
-                // Note that there can be at max 31 key bytes because 32 same bytes would mean
-                // the two keys being the same - update operation, not splitting into extension node.
-                let mut key_bytes = vec![data[0][1].scalar()];
-                key_bytes.append(&mut data[0][1..].iter().skip(1).zip(data[1][2..].iter()).map(|(byte, nibble_hi)| {
-                    let nibble_lo = (byte - nibble_hi) >> 4;
-                    // Check that `nibble_hi` is correct.
-                    assert!(*byte == nibble_lo * 16 + nibble_hi);
-                    // Collect bytes
-                    (F::from(*nibble_hi as u64) * F::from(16_u64) * r) + F::from(nibble_lo as u64)
-                }).collect::<Vec<_>>());
-                calc_rlc(&key_bytes, 1.scalar())
-            } else {
-                let additional_mult = if is_key_part_odd { r } else { 1.scalar() };
-                calc_rlc(&data[0][1..].iter().map(|byte| byte.scalar()).collect::<Vec<_>>(), additional_mult)
-            }
-        },
-        is_short => {
-            calc_rlc(&data[0][..1].iter().map(|byte| byte.scalar()).collect::<Vec<_>>(), 1.scalar())
-        },
-    }
-}
-
 #[derive(Clone, Debug, Default)]
 pub(crate) struct ListKeyGadget<F> {
     pub(crate) rlp_list_bytes: [Cell<F>; 3],
@@ -833,48 +737,6 @@ pub(crate) fn leaf_key_rlc<F: Field>(
     })
 }
 
-pub(crate) fn ext_key_rlc<F: Field>(
-    cb: &mut MPTConstraintBuilder<F>,
-    bytes: &[Expression<F>],
-    key_mult_prev: Expression<F>,
-    is_odd: Expression<F>,
-    rlc_mult_first_odd: Expression<F>,
-    key_mult_first_odd: Expression<F>,
-    r: &Expression<F>,
-) -> Expression<F> {
-    circuit!([meta, cb.base], {
-        // Add the odd nibble first if we have one.
-        let (rlc, mult) = ifx! {is_odd => {
-            (get_ext_odd_nibble(bytes[0].expr()) * key_mult_prev.expr() * rlc_mult_first_odd, key_mult_first_odd.expr())
-        } elsex {
-            require!(bytes[0] => KEY_PREFIX_EVEN);
-            (0.expr(), 1.expr())
-        }};
-        (rlc, key_mult_prev * mult).rlc_chain(bytes[1..].rlc(r))
-    })
-}
-
-pub(crate) fn ext_key_rlc_value<F: Field>(
-    bytes: &[F],
-    key_mult_prev: F,
-    is_odd: bool,
-    rlc_mult_first_odd: F,
-    key_mult_first_odd: F,
-    r: F,
-) -> (F, F) {
-    // Add the odd nibble first if we have one.
-    let (rlc, mult) = if is_odd {
-        (
-            get_ext_odd_nibble_value(bytes[0]) * key_mult_prev * rlc_mult_first_odd,
-            key_mult_first_odd,
-        )
-    } else {
-        assert!(bytes[0] == KEY_PREFIX_EVEN.scalar());
-        (0.scalar(), 1.scalar())
-    };
-    (rlc, key_mult_prev * mult).rlc_chain_value(bytes[1..].iter().collect::<Vec<&F>>(), r)
-}
-
 // Returns the number of nibbles stored in a key value
 pub(crate) mod num_nibbles {
     use crate::{_cb, circuit, circuit_tools::constraint_builder::ConstraintBuilder};
@@ -902,6 +764,192 @@ pub(crate) mod num_nibbles {
     }
 }
 
+/// RLC of the key of an extension node, continuing the key RLC of the nodes
+/// above it.
+///
+/// `key_value` is the key item of the extension node and `data` holds its key
+/// row and its nibbles row, which has the low nibble of each key byte.  The
+/// key above has the multiplier `key_mult_prev` and an odd number of nibbles
+/// when `is_key_odd`, the extension node has an odd number of nibbles when
+/// `is_key_part_odd`.  When the parities differ, the nibbles of the extension
+/// node straddle the bytes of the key and are combined one by one.
+///
+/// `expr` and `value` follow the same steps and must be kept in sync.
+pub(crate) mod ext_key_rlc {
+    use super::*;
+    use std::ops::{Add, Mul};
+
+    pub(crate) fn expr<F: Field>(
+        cb: &mut MPTConstraintBuilder<F>,
+        key_value: RLPItemView<F>,
+        key_mult_prev: Expression<F>,
+        is_key_part_odd: Expression<F>,
+        is_key_odd: Expression<F>,
+        data: [Vec<Expression<F>>; 2],
+        r: &Expression<F>,
+    ) -> Expression<F> {
+        circuit!([meta, cb.base], {
+            let (is_short, is_long) = (key_value.is_short(), key_value.is_long());
+            let mult_first_odd = ifx! {is_key_odd.expr() => { 1.expr() } elsex { 16.expr() }};
+            let calc_rlc = |cb: &mut MPTConstraintBuilder<F>,
+                            bytes: &[Expression<F>],
+                            key_mult_first_even: Expression<F>| {
+                chain_expr(
+                    cb,
+                    bytes,
+                    key_mult_prev.expr(),
+                    is_key_part_odd.expr(),
+                    mult_first_odd.expr(),
+                    key_mult_first_even,
+                    r,
+                )
+            };
+            matchx! {(
+                is_long => {
+                    ifx! {xor::expr(is_key_odd.expr(), is_key_part_odd.expr()) => {
+                        // Here we need to multiply nibbles over bytes with different r's so we need to rlc over separate nibbles.
+                        // Note that there can be at max 31 key bytes because 32 same bytes would mean
+                        // the two keys being the same - update operation, not splitting into extension node.
+                        // So, we do not need to look further than `s_main.bytes` even if `s_main.bytes[0]`
+                        // is not used (when even number of nibbles).
+                        let key_bytes = shifted_key_bytes(&data, r, 16.expr(), |byte, nibble_hi| {
+                            let nibble_lo = (byte.expr() - nibble_hi.expr()) * invert!(16);
+                            // Check that `nibble_hi` is correct.
+                            require!(byte => nibble_lo.expr() * 16.expr() + nibble_hi.expr());
+                            nibble_lo
+                        });
+                        calc_rlc(cb, &key_bytes, 1.expr())
+                    } elsex {
+                        let additional_mult = ifx! {is_key_part_odd => { r.expr() } elsex { 1.expr() }};
+                        calc_rlc(cb, &data[0][1..], additional_mult)
+                    }}
+                },
+                is_short => {
+                    calc_rlc(cb, &data[0][..1], 1.expr())
+                },
+            )}
+        })
+    }
+
+    pub(crate) fn value<F: Field>(
+        key_value: &RLPItemWitness,
+        key_mult_prev: F,
+        is_key_part_odd: bool,
+        is_key_odd: bool,
+        data: [Vec<u8>; 2],
+        r: F,
+    ) -> F {
+        let (is_short, is_long) = (key_value.is_short(), key_value.is_long());
+        let data = data.map(|row| row.iter().map(|byte| byte.scalar()).collect::<Vec<F>>());
+        let mult_first_odd = if is_key_odd { 1.scalar() } else { 16.scalar() };
+        let calc_rlc = |bytes: &[F], key_mult_first_even: F| {
+            chain_value(
+                bytes,
+                key_mult_prev,
+                is_key_part_odd,
+                mult_first_odd,
+                key_mult_first_even,
+                r,
+            )
+        };
+        matchw! {
+            is_long => {
+                if is_key_odd != is_key_part_odd {
+                    // Here we need to multiply nibbles over bytes with different r's so we need to rlc over separate nibbles.
+                    // Note that there can be at max 31 key bytes because 32 same bytes would mean
+                    // the two keys being the same - update operation, not splitting into extension node.
+                    let key_bytes = shifted_key_bytes(&data, &r, 16.scalar(), |byte, nibble_hi| {
+                        let nibble_lo = (*byte - nibble_hi) * F::from(16).invert().unwrap();
+                        // Check that `nibble_hi` is correct.
+                        assert!(nibble_lo.get_lower_128() < 16);
+                        nibble_lo
+                    });
+                    calc_rlc(&key_bytes, 1.scalar())
+                } else {
+                    let additional_mult = if is_key_part_odd { r } else { 1.scalar() };
+                    calc_rlc(&data[0][1..], additional_mult)
+                }
+            },
+            is_short => {
+                calc_rlc(&data[0][..1], 1.scalar())
+            },
+        }
+    }
+
+    /// Key bytes of a long key whose nibbles are shifted by one relative to
+    /// the key bytes, from the key row and the nibbles row in `data`: the
+    /// prefix byte, followed by the high and low nibble of every next byte
+    /// recombined as `nibble_hi * 16 * r + nibble_lo`. `nibble_lo` computes
+    /// (and checks) the low nibble of a byte from the byte and its high
+    /// nibble.
+    fn shifted_key_bytes<T: Clone + Add<Output = T> + Mul<Output = T>>(
+        data: &[Vec<T>; 2],
+        r: &T,
+        sixteen: T,
+        mut nibble_lo: impl FnMut(&T, &T) -> T,
+    ) -> Vec<T> {
+        let mut key_bytes = vec![data[0][1].clone()];
+        key_bytes.extend(
+            data[0][2..]
+                .iter()
+                .zip(data[1][2..].iter())
+                .map(|(byte, nibble_hi)| {
+                    let nibble_lo = nibble_lo(byte, nibble_hi);
+                    nibble_hi.clone() * sixteen.clone() * r.clone() + nibble_lo
+                }),
+        );
+        key_bytes
+    }
+
+    /// RLC of `bytes`, whose first byte is the compact encoding prefix
+    /// holding the first nibble when `is_odd`.  The first nibble is
+    /// multiplied by `rlc_mult_first_odd`, and the next bytes start at
+    /// `key_mult_prev * key_mult_first_even` when `is_odd`.
+    fn chain_expr<F: Field>(
+        cb: &mut MPTConstraintBuilder<F>,
+        bytes: &[Expression<F>],
+        key_mult_prev: Expression<F>,
+        is_odd: Expression<F>,
+        rlc_mult_first_odd: Expression<F>,
+        key_mult_first_even: Expression<F>,
+        r: &Expression<F>,
+    ) -> Expression<F> {
+        circuit!([meta, cb.base], {
+            // Add the odd nibble first if we have one.
+            let (rlc, mult) = ifx! {is_odd => {
+                (get_ext_odd_nibble(bytes[0].expr()) * key_mult_prev.expr() * rlc_mult_first_odd, key_mult_first_even.expr())
+            } elsex {
+                require!(bytes[0] => KEY_PREFIX_EVEN);
+                (0.expr(), 1.expr())
+            }};
+            (rlc, key_mult_prev * mult).rlc_chain(bytes[1..].rlc(r))
+        })
+    }
+
+    fn chain_value<F: Field>(
+        bytes: &[F],
+        key_mult_prev: F,
+        is_odd: bool,
+        rlc_mult_first_odd: F,
+        key_mult_first_even: F,
+        r: F,
+    ) -> F {
+        // Add the odd nibble first if we have one.
+        let (rlc, mult) = if is_odd {
+            (
+                get_ext_odd_nibble_value(bytes[0]) * key_mult_prev * rlc_mult_first_odd,
+                key_mult_first_even,
+            )
+        } else {
+            assert!(bytes[0] == KEY_PREFIX_EVEN.scalar());
+            (0.scalar(), 1.scalar())
+        };
+        (rlc, key_mult_prev * mult)
+            .rlc_chain_value(bytes[1..].iter().collect::<Vec<&F>>(), r)
+            .0
+    }
+}
+
 pub(crate) fn parent_memory(is_s: bool) -> MptCellType {
     if is_s {
         MptCellType::MemParentS
@@ -922,6 +970,40 @@ pub(crate) fn main_memory() -> MptCellType {
     MptCellType::MemMain
 }
 
+/// Tables the MPT gadgets look up into.
+///
+/// The gadgets store their lookups into the tables set here, so that a
+/// circuit loading these tables under other types, for example
+/// [`MptTableType::Other`], can reuse them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MptLookupTables {
+    /// Keccak table, with `(is_enabled, input_rlc, input_len, output_lo,
+    /// output_hi)` rows
+    ///
+    /// Node preimages are always looked up as their RLC with the keccak input
+    /// challenge, which is the only encoding of the input `KeccakTable`
+    /// exposes. Looking them up as packed words instead would first need a
+    /// keccak table with input word columns.
+    pub keccak: MptTableType,
+    /// Fixed table, with rows starting with a `FixedTableTag`
+    pub fixed: MptTableType,
+    /// Byte range table, with a `0..256` column
+    pub byte: MptTableType,
+    /// Multiplier table, with `(exponent, keccak_r^exponent)` rows
+    pub mult: MptTableType,
+}
+
+impl Default for MptLookupTables {
+    fn default() -> Self {
+        Self {
+            keccak: MptTableType::Keccak,
+            fixed: MptTableType::Fixed,
+            byte: MptTableType::Byte,
+            mult: MptTableType::Mult,
+        }
+    }
+}
+
 /// MPTConstraintBuilder
 #[derive(Clone)]
 pub struct MPTConstraintBuilder<F> {
@@ -929,6 +1011,7 @@ pub struct MPTConstraintBuilder<F> {
     pub challenges: Option<Challenges<Expression<F>>>,
     pub key_r: Expression<F>,
     pub keccak_r: Expression<F>,
+    lookup_tables: MptLookupTables,
 }
 
 impl<F: Field> MPTConstraintBuilder<F> {
@@ -946,9 +1029,37 @@ impl<F: Field> MPTConstraintBuilder<F> {
             key_r: challenges.clone().unwrap().keccak_input().expr(),
             keccak_r: challenges.clone().unwrap().keccak_input().expr(),
             challenges,
+            lookup_tables: MptLookupTables::default(),
         }
     }
 
+    /// Tables the gadgets look up into
+    pub(crate) fn lookup_tables(&self) -> MptLookupTables {
+        self.lookup_tables
+    }
+
+    /// Set the tables the gadgets look up into. Byte cells are queried from
+    /// the byte table set here.
+    pub(crate) fn set_lookup_tables(&mut self, lookup_tables: MptLookupTables) {
+        self.lookup_tables = lookup_tables;
+        self.base.byte_type = Some(MptCellType::Lookup(lookup_tables.byte));
+    }
+
+    /// Destination of the keccak lookups
+    pub(crate) fn keccak(&self) -> MptCellType {
+        MptCellType::Lookup(self.lookup_tables.keccak)
+    }
+
+    /// Destination of the fixed table lookups
+    pub(crate) fn fixed(&self) -> MptCellType {
+        MptCellType::Lookup(self.lookup_tables.fixed)
+    }
+
+    /// Destination of the multiplier table lookups
+    pub(crate) fn mult(&self) -> MptCellType {
+        MptCellType::Lookup(self.lookup_tables.mult)
+    }
+
     pub(crate) fn push_condition(&mut self, condition: Expression<F>) {
         self.base.push_condition(condition)
     }
@@ -1115,6 +1226,14 @@ impl<F: Field> IsPlaceholderLeafGadget<F> {
 }
 
 /// Handles drifted leaves
+///
+/// When a leaf is added to or deleted from a branch with a single other
+/// leaf, that leaf drifts between the branch and the placeholder branch on
+/// the other side. When the other node is an extension node or a branch, the
+/// branch is instead collapsed into (or split out of) an extension node whose
+/// key absorbs the branch nibble: that case is flagged by `is_mod_extension`
+/// and its key recombination is checked by the `ModExtensionGadget`, so it is
+/// skipped here.
 #[derive(Clone, Debug, Default)]
 pub struct DriftedGadget<F> {
     drifted_rlp_key: ListKeyGadget<F>,
@@ -1177,7 +1296,7 @@ impl<F: Field> DriftedGadget<F> {
                             let leaf_rlc = config.drifted_rlp_key.rlc2(&cb.keccak_r).rlc_chain_rev((leaf_no_key_rlc[is_s.idx()].expr(), leaf_no_key_rlc_mult[is_s.idx()].expr()));
                             // The drifted leaf needs to be stored in the branch at `drifted_index`.
                             let hash = parent_data[is_s.idx()].drifted_parent_hash.expr();
-                            require!((1.expr(), leaf_rlc.expr(), config.drifted_rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @KECCAK);
+                            require!((1.expr(), leaf_rlc.expr(), config.drifted_rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @cb.keccak());
                         }
                     }}
                 }}
@@ -1293,6 +1412,46 @@ impl<F: Field> WrongGadget<F> {
     }
 }
 
+/// Checks that the key of the leaf is the key of the claimed address or slot
+///
+/// The key item holds the keccak of the address or slot, which is looked up in
+/// the keccak table. Its bytes are range checked by the RLP decoding, and the
+/// key RLC of the path is accumulated nibble by nibble with every nibble below
+/// 16, so the two RLCs are only equal when each nibble of the path taken in the
+/// trie is the corresponding nibble of the keccak output.
+///
+/// `preimage` is the RLC and the length of the address or slot, or `None`
+/// when the preimage check is disabled.
+pub(crate) fn require_key_path<F: Field>(
+    cb: &mut MPTConstraintBuilder<F>,
+    key_item: &RLPItemView<F>,
+    preimage: Option<(Expression<F>, Expression<F>)>,
+    key_rlc: &[Expression<F>],
+    is_placeholder_s: Expression<F>,
+    is_non_existing: Expression<F>,
+) {
+    circuit!([meta, cb], {
+        // For non-existing proofs the leaf is for another key, the key item
+        // is instead checked against the wrong leaf by `WrongGadget`
+        ifx! {not!(is_non_existing) => {
+            // The key of the path to the leaf that is not a placeholder
+            let path_key_rlc = ifx! {not!(is_placeholder_s) => {
+                key_rlc[true.idx()].expr()
+            } elsex {
+                key_rlc[false.idx()].expr()
+            }};
+            require!(key_item.hash_rlc() => path_key_rlc);
+        }}
+
+        // The key is the keccak of the address or slot, also for
+        // non-existing proofs
+        if let Some((preimage_rlc, preimage_len)) = preimage {
+            let key = key_item.word();
+            require!((1.expr(), preimage_rlc, preimage_len, key.lo(), key.hi()) =>> @cb.keccak());
+        }
+    })
+}
+
 /// Main RLP item
 #[derive(Clone, Debug, Default)]
 pub struct MainRLPGadget<F> {
@@ -1386,7 +1545,7 @@ impl<F: Field> MainRLPGadget<F> {
 
             // Check the multiplier values
             // `num_bytes - 1` because the RLP byte is handled separately
-            require!((config.rlp.num_bytes() - 1.expr(), config.mult_diff.expr()) =>> @MULT);
+            require!((config.rlp.num_bytes() - 1.expr(), config.mult_diff.expr()) =>> @cb.mult());
             require!(config.mult_inv.expr() * pow::expr(cb.keccak_r.expr(), RLP_UNIT_NUM_BYTES - 1) => config.mult_diff.expr());
 
             // Lists always need to be short
@@ -1410,7 +1569,7 @@ impl<F: Field> MainRLPGadget<F> {
                         config.num_bytes.expr() - idx.expr(),
                         all_bytes[idx],
                         all_bytes[idx + 1]
-                    ) => @cb.table(MptTableType::Fixed));
+                    ) => @cb.table(cb.lookup_tables.fixed));
                 }
             } else {
                 for (idx, byte) in all_bytes.iter().enumerate() {
@@ -1420,7 +1579,7 @@ impl<F: Field> MainRLPGadget<F> {
                     } else {
                         config.ensure_minimal_rlp.expr()
                     };
-                    require!((config.tag.expr(), config.num_bytes.expr() - idx.expr(), byte.expr(), nonzero_check) => @cb.table(MptTableType::Fixed));
+                    require!((config.tag.expr(), config.num_bytes.expr() - idx.expr(), byte.expr(), nonzero_check) => @cb.table(cb.lookup_tables.fixed));
                 }
             }
 
@@ -1687,3 +1846,96 @@ impl<F: Field> RLPItemView<F> {
         self.word.clone().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt_circuit::{param::KEY_PREFIX_ODD, rlp_gadgets::RLPValueWitness};
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    /// Key item, key row and nibbles row of an extension node with `nibbles`
+    fn ext_key(nibbles: &[u8]) -> (RLPItemWitness, [Vec<u8>; 2]) {
+        let is_odd = nibbles.len() % 2 == 1;
+        let mut key = if is_odd {
+            vec![KEY_PREFIX_ODD + nibbles[0]]
+        } else {
+            vec![KEY_PREFIX_EVEN]
+        };
+        key.extend(
+            nibbles[is_odd as usize..]
+                .chunks(2)
+                .map(|pair| pair[0] * 16 + pair[1]),
+        );
+        let is_short = key.len() == 1 && key[0] < 0x80;
+        let mut row = if is_short {
+            key
+        } else {
+            [vec![0x80 + key.len() as u8], key].concat()
+        };
+        row.resize(RLP_UNIT_NUM_BYTES, 0);
+        let mut nibbles_row = vec![0; RLP_UNIT_NUM_BYTES];
+        if !is_short {
+            for (nibble, byte) in nibbles_row.iter_mut().zip(row.iter()).skip(2) {
+                *nibble = byte & 0xf;
+            }
+        }
+        let key_item = RLPItemWitness {
+            value: RLPValueWitness {
+                is_short,
+                is_long: !is_short,
+                bytes: row.clone(),
+                ..Default::default()
+            },
+            list: RLPListWitness {
+                is_string: true,
+                bytes: row.clone(),
+                ..Default::default()
+            },
+            bytes: row.clone(),
+        };
+        (key_item, [row, nibbles_row])
+    }
+
+    /// Key RLC of `nibbles` added one by one after a key with the multiplier
+    /// `mult`, with an odd number of nibbles when `is_key_odd`
+    fn nibbles_rlc(nibbles: &[u8], mut mult: Fr, mut is_key_odd: bool, r: Fr) -> Fr {
+        let mut rlc = Fr::from(0);
+        for nibble in nibbles {
+            if is_key_odd {
+                // Low nibble of the current byte
+                rlc += Fr::from(*nibble as u64) * mult;
+                mult *= r;
+            } else {
+                rlc += Fr::from(*nibble as u64 * 16) * mult;
+            }
+            is_key_odd = !is_key_odd;
+        }
+        rlc
+    }
+
+    #[test]
+    fn ext_key_rlc_odd_even() {
+        let r = Fr::from(0x1234);
+        let key_mult_prev = Fr::from(7);
+        for num_nibbles in 1..8 {
+            let nibbles = (0..num_nibbles).map(|i| 15 - i).collect::<Vec<u8>>();
+            let (key_item, data) = ext_key(&nibbles);
+            for is_key_odd in [false, true] {
+                assert_eq!(
+                    ext_key_rlc::value(
+                        &key_item,
+                        key_mult_prev,
+                        num_nibbles % 2 == 1,
+                        is_key_odd,
+                        data.clone(),
+                        r
+                    ),
+                    nibbles_rlc(&nibbles, key_mult_prev, is_key_odd, r),
+                    "{} nibbles after a key with odd nibbles: {}",
+                    num_nibbles,
+                    is_key_odd
+                );
+            }
+        }
+    }
+}
@@ -1,11 +1,10 @@
 use eth_types::Field;
 use gadgets::util::Scalar;
 use halo2_proofs::plonk::{Error, VirtualCells};
+use std::fmt;
 
 use super::{
-    helpers::{
-        ext_key_rlc_calc_value, KeyData, ListKeyGadget, ListKeyWitness, MPTConstraintBuilder,
-    },
+    helpers::{ext_key_rlc, KeyData, ListKeyGadget, ListKeyWitness, MPTConstraintBuilder},
     rlp_gadgets::RLPItemWitness,
     MPTContext,
 };
@@ -18,12 +17,45 @@ use crate::{
         gadgets::{IsEqualGadget, LtGadget},
     },
     mpt_circuit::{
-        helpers::{ext_key_rlc_expr, Indexable, ParentData, FIXED, KECCAK},
+        helpers::{Indexable, ParentData},
         param::HASH_WIDTH,
-        witness_row::StorageRowType,
+        witness_row::{AccountRowType, Node, StorageRowType},
         FixedTableTag, RlpItemType,
     },
 };
+/// Rows of the long and short extension nodes in the leaf region, where the
+/// witness puts them when an extension node is modified
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ModExtensionRows {
+    long_key: usize,
+    long_nibbles: usize,
+    long_value: usize,
+    short_key: usize,
+    short_nibbles: usize,
+    short_value: usize,
+}
+
+impl ModExtensionRows {
+    /// Rows in the account leaf region
+    pub(crate) const ACCOUNT: Self = Self {
+        long_key: AccountRowType::LongExtNodeKey as usize,
+        long_nibbles: AccountRowType::LongExtNodeNibbles as usize,
+        long_value: AccountRowType::LongExtNodeValue as usize,
+        short_key: AccountRowType::ShortExtNodeKey as usize,
+        short_nibbles: AccountRowType::ShortExtNodeNibbles as usize,
+        short_value: AccountRowType::ShortExtNodeValue as usize,
+    };
+    /// Rows in the storage leaf region
+    pub(crate) const STORAGE: Self = Self {
+        long_key: StorageRowType::LongExtNodeKey as usize,
+        long_nibbles: StorageRowType::LongExtNodeNibbles as usize,
+        long_value: StorageRowType::LongExtNodeValue as usize,
+        short_key: StorageRowType::ShortExtNodeKey as usize,
+        short_nibbles: StorageRowType::ShortExtNodeNibbles as usize,
+        short_value: StorageRowType::ShortExtNodeValue as usize,
+    };
+}
+
 /// Validity conditions for ModExtensionGadget:
 /// 1. Both the long and short extension nodes are in the parent node.
 /// 2. Both the branches of the short extension node’s and the long extension node’s must be the
@@ -45,51 +77,22 @@ impl<F: Field> ModExtensionGadget<F> {
         ctx: MPTContext<F>,
         parent_data: &mut [ParentData<F>; 2],
         key_data: &mut [KeyData<F>; 2],
+        rows: ModExtensionRows,
     ) -> Self {
         let mut config = ModExtensionGadget::default();
 
         circuit!([meta, cb], {
             let key_items = [
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::LongExtNodeKey as usize,
-                    RlpItemType::Key,
-                ),
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::ShortExtNodeKey as usize,
-                    RlpItemType::Key,
-                ),
+                ctx.rlp_item(meta, cb, rows.long_key, RlpItemType::Key),
+                ctx.rlp_item(meta, cb, rows.short_key, RlpItemType::Key),
             ];
             let key_nibbles = [
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::LongExtNodeNibbles as usize,
-                    RlpItemType::Nibbles,
-                ),
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::ShortExtNodeNibbles as usize,
-                    RlpItemType::Nibbles,
-                ),
+                ctx.rlp_item(meta, cb, rows.long_nibbles, RlpItemType::Nibbles),
+                ctx.rlp_item(meta, cb, rows.short_nibbles, RlpItemType::Nibbles),
             ];
             let rlp_value = [
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::LongExtNodeValue as usize,
-                    RlpItemType::Value,
-                ),
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::ShortExtNodeValue as usize,
-                    RlpItemType::Value,
-                ),
+                ctx.rlp_item(meta, cb, rows.long_value, RlpItemType::Value),
+                ctx.rlp_item(meta, cb, rows.short_value, RlpItemType::Value),
             ];
 
             let is_insert = parent_data[0].is_placeholder.expr(); // insert or delete
@@ -143,7 +146,7 @@ impl<F: Field> ModExtensionGadget<F> {
                     key_items[is_s.idx()].clone(),
                     key_nibbles[is_s.idx()].clone(),
                 ];
-                let rlc = ext_key_rlc_expr(
+                let rlc = ext_key_rlc::expr(
                     cb,
                     config.rlp_key[is_s.idx()].key_value.clone(),
                     1.expr(),
@@ -170,7 +173,7 @@ impl<F: Field> ModExtensionGadget<F> {
                     key_items[is_s.idx()].is_very_long() => key_items[is_s.idx()].bytes_be()[2].expr(),
                 )};
                 require!((FixedTableTag::ExtOddKey.expr(),
-                    first_byte, config.is_key_part_odd[is_s.idx()].expr()) =>> @FIXED);
+                    first_byte, config.is_key_part_odd[is_s.idx()].expr()) =>> @cb.fixed());
 
                 // RLP encoding checks: [key, branch]
                 // Verify that the lengths are consistent.
@@ -196,7 +199,7 @@ impl<F: Field> ModExtensionGadget<F> {
                 ifx! {config.is_short_branch => {
                     if is_s {
                         ifx!{or::expr(&[parent_data[is_s.idx()].is_root.expr(), not!(is_not_hashed)]) => {
-                            require!((1.expr(), rlc.expr(), num_bytes.expr(), parent_data_lo[is_s.idx()].clone(), parent_data_hi[is_s.idx()].clone()) =>> @KECCAK);
+                            require!((1.expr(), rlc.expr(), num_bytes.expr(), parent_data_lo[is_s.idx()].clone(), parent_data_hi[is_s.idx()].clone()) =>> @cb.keccak());
                         } elsex {
                             require!(rlc => parent_data_rlc);
                         }}
@@ -212,7 +215,7 @@ impl<F: Field> ModExtensionGadget<F> {
                 } elsex {
                     ifx!{or::expr(&[parent_data[is_s.idx()].is_root.expr(), not!(is_not_hashed)]) => {
                         // Hashed extension node in long extension is in parent branch
-                        require!((1.expr(), rlc.expr(), num_bytes.expr(), parent_data_lo[is_s.idx()].clone(), parent_data_hi[is_s.idx()].clone()) =>> @KECCAK);
+                        require!((1.expr(), rlc.expr(), num_bytes.expr(), parent_data_lo[is_s.idx()].clone(), parent_data_hi[is_s.idx()].clone()) =>> @cb.keccak());
                     } elsex {
                         // Non-hashed extension node in parent branch
                         require!(rlc => parent_data_rlc);
@@ -222,7 +225,7 @@ impl<F: Field> ModExtensionGadget<F> {
 
             let data0 = [key_items[0].clone(), key_nibbles[0].clone()];
             let nibbles_rlc_long = key_rlc_before
-                + ext_key_rlc_expr(
+                + ext_key_rlc::expr(
                     cb,
                     config.rlp_key[0].key_value.clone(),
                     key_mult_before,
@@ -239,7 +242,7 @@ impl<F: Field> ModExtensionGadget<F> {
 
             let data1 = [key_items[1].clone(), key_nibbles[1].clone()];
             let rlc_after_short = middle_key_rlc.clone()
-                + ext_key_rlc_expr(
+                + ext_key_rlc::expr(
                     cb,
                     config.rlp_key[1].key_value.clone(),
                     middle_key_mult,
@@ -271,22 +274,23 @@ impl<F: Field> ModExtensionGadget<F> {
         offset: usize,
         rlp_values: &[RLPItemWitness],
         list_rlp_bytes: [&[u8]; 2],
+        rows: ModExtensionRows,
     ) -> Result<(), Error> {
         let key_items = [
-            rlp_values[StorageRowType::LongExtNodeKey as usize].clone(),
-            rlp_values[StorageRowType::ShortExtNodeKey as usize].clone(),
+            rlp_values[rows.long_key].clone(),
+            rlp_values[rows.short_key].clone(),
         ];
 
         let mut rlp_key = vec![ListKeyWitness::default(); 2];
         let mut key_rlc = vec![];
 
         let items_s = [
-            rlp_values[StorageRowType::LongExtNodeKey as usize].clone(),
-            rlp_values[StorageRowType::LongExtNodeNibbles as usize].clone(),
+            rlp_values[rows.long_key].clone(),
+            rlp_values[rows.long_nibbles].clone(),
         ];
         let items_c = [
-            rlp_values[StorageRowType::ShortExtNodeKey as usize].clone(),
-            rlp_values[StorageRowType::ShortExtNodeNibbles as usize].clone(),
+            rlp_values[rows.short_key].clone(),
+            rlp_values[rows.short_nibbles].clone(),
         ];
         let items = vec![items_s, items_c];
 
@@ -317,8 +321,8 @@ impl<F: Field> ModExtensionGadget<F> {
                 HASH_WIDTH.scalar(),
             )?;
 
-            let (key_rlc_ext, _) = ext_key_rlc_calc_value(
-                rlp_key[is_s.idx()].key_item.clone(),
+            let key_rlc_ext = ext_key_rlc::value(
+                &rlp_key[is_s.idx()].key_item,
                 1.scalar(),
                 is_key_part_odd,
                 false,
@@ -340,3 +344,204 @@ impl<F: Field> ModExtensionGadget<F> {
         Ok(())
     }
 }
+
+/// Inconsistency between the rows of the long and short extension nodes of a
+/// modified extension node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModExtensionError {
+    /// The key doesn't start with the compact encoding prefix of an extension
+    /// node
+    InvalidKeyPrefix {
+        /// Extension node, `"long"` or `"short"`
+        node: &'static str,
+        /// First byte of the key
+        byte: u8,
+    },
+    /// A byte of the nibbles row isn't the low nibble of the key byte
+    NibbleMismatch {
+        /// Extension node, `"long"` or `"short"`
+        node: &'static str,
+        /// Index of the byte in the row
+        index: usize,
+        /// Byte of the key row
+        key_byte: u8,
+        /// Byte of the nibbles row
+        nibble: u8,
+    },
+    /// The nibbles of the short extension node are not the last nibbles of
+    /// the long extension node
+    KeyNotSuffix {
+        /// Nibbles of the long extension node
+        long: Vec<u8>,
+        /// Nibbles of the short extension node
+        short: Vec<u8>,
+    },
+    /// The long and short extension nodes don't have the same branch
+    BranchMismatch,
+}
+
+impl fmt::Display for ModExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nibbles = |nibbles: &[u8]| {
+            nibbles
+                .iter()
+                .map(|n| format!("{:x}", n))
+                .collect::<String>()
+        };
+        match self {
+            Self::InvalidKeyPrefix { node, byte } => write!(
+                f,
+                "{} extension node key starts with {:#04x}, not an extension node prefix",
+                node, byte
+            ),
+            Self::NibbleMismatch {
+                node,
+                index,
+                key_byte,
+                nibble,
+            } => write!(
+                f,
+                "{} extension node nibbles row has {:#04x} at {}, but the key byte is {:#04x}",
+                node, nibble, index, key_byte
+            ),
+            Self::KeyNotSuffix { long, short } => write!(
+                f,
+                "short extension node key {} is not a suffix of the long extension node key {}",
+                nibbles(short),
+                nibbles(long)
+            ),
+            Self::BranchMismatch => {
+                write!(f, "long and short extension nodes have different branches")
+            }
+        }
+    }
+}
+
+/// Check that the `LongExtNode*` and `ShortExtNode*` rows describe an
+/// extension node split by a new branch: the nibbles rows match the keys, the
+/// short extension node key is the end of the long extension node key, and
+/// both extension nodes have the same branch.
+pub(crate) fn check_mod_extension(
+    rlp_values: &[RLPItemWitness],
+    rows: ModExtensionRows,
+) -> Result<(), ModExtensionError> {
+    let nodes = [
+        (
+            "long",
+            &rlp_values[rows.long_key],
+            &rlp_values[rows.long_nibbles],
+        ),
+        (
+            "short",
+            &rlp_values[rows.short_key],
+            &rlp_values[rows.short_nibbles],
+        ),
+    ];
+    let mut keys = vec![];
+    for (node, key_item, nibbles_item) in nodes {
+        let key_bytes = &key_item.bytes[key_item.num_rlp_bytes()..key_item.num_bytes()];
+        let (prefix, rest) = key_bytes
+            .split_first()
+            .ok_or(ModExtensionError::InvalidKeyPrefix { node, byte: 0 })?;
+        // The first nibble of the compact encoding is 1 for an odd number of
+        // nibbles, with the first nibble next to it, and 0 otherwise
+        let mut key = match prefix >> 4 {
+            1 => vec![prefix & 0xf],
+            0 if *prefix == 0 => vec![],
+            _ => {
+                return Err(ModExtensionError::InvalidKeyPrefix {
+                    node,
+                    byte: *prefix,
+                })
+            }
+        };
+        key.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0xf]));
+
+        // The nibbles row holds the low nibble of each key byte after the prefix
+        if key_item.is_long() {
+            for index in key_item.num_rlp_bytes() + 1..key_item.num_bytes() {
+                let key_byte = key_item.bytes[index];
+                let nibble = nibbles_item.bytes.get(index).copied().unwrap_or_default();
+                if nibble != key_byte & 0xf {
+                    return Err(ModExtensionError::NibbleMismatch {
+                        node,
+                        index,
+                        key_byte,
+                        nibble,
+                    });
+                }
+            }
+        }
+        keys.push(key);
+    }
+    // The long extension node is split into an extension node, the branch
+    // nibble and the short extension node, which is the whole long extension
+    // node when the branch is right below it
+    if !keys[0].ends_with(&keys[1]) {
+        return Err(ModExtensionError::KeyNotSuffix {
+            long: keys[0].clone(),
+            short: keys[1].clone(),
+        });
+    }
+
+    let branches = [&rlp_values[rows.long_value], &rlp_values[rows.short_value]]
+        .map(|item| &item.bytes[..item.num_bytes()]);
+    if branches[0] != branches[1] {
+        return Err(ModExtensionError::BranchMismatch);
+    }
+    Ok(())
+}
+
+/// Check the rows of the modified extension node of an account or storage
+/// leaf, if it has one.
+pub(crate) fn check_node_mod_extension(node: &Node) -> Result<(), ModExtensionError> {
+    let (is_mod_extension, rows) = match (&node.account, &node.storage) {
+        (Some(account), _) => (account.is_mod_extension, ModExtensionRows::ACCOUNT),
+        (_, Some(storage)) => (storage.is_mod_extension, ModExtensionRows::STORAGE),
+        _ => return Ok(()),
+    };
+    if !is_mod_extension.contains(&true) {
+        return Ok(());
+    }
+    let rlp_values: Vec<_> = node
+        .values
+        .iter()
+        .map(|bytes| RLPItemWitness::new(bytes))
+        .collect();
+    check_mod_extension(&rlp_values, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt_circuit::witness_row::{NODE_RLP_TYPES_ACCOUNT, NODE_RLP_TYPES_STORAGE};
+
+    #[test]
+    fn mod_extension_rows() {
+        for (rows, item_types) in [
+            (ModExtensionRows::ACCOUNT, &NODE_RLP_TYPES_ACCOUNT[..]),
+            (ModExtensionRows::STORAGE, &NODE_RLP_TYPES_STORAGE[..]),
+        ] {
+            let types = [
+                rows.long_key,
+                rows.long_nibbles,
+                rows.long_value,
+                rows.short_key,
+                rows.short_nibbles,
+                rows.short_value,
+            ]
+            .map(|row| item_types[row]);
+            assert_eq!(
+                types,
+                [
+                    RlpItemType::Key,
+                    RlpItemType::Nibbles,
+                    RlpItemType::Value,
+                    RlpItemType::Key,
+                    RlpItemType::Nibbles,
+                    RlpItemType::Value,
+                ]
+            );
+        }
+    }
+}
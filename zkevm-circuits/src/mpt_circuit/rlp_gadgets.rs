@@ -8,8 +8,7 @@ use crate::{
     evm_circuit::util::from_bytes,
     matchw,
     mpt_circuit::{
-        helpers::FIXED,
-        param::{RLP_LIST_LONG, RLP_LIST_SHORT, RLP_SHORT},
+        param::{RLP_LIST_LONG, RLP_LIST_SHORT, RLP_SHORT, RLP_UNIT_NUM_BYTES},
         FixedTableTag,
     },
     util::{word::WordLoHi, Expr},
@@ -91,7 +90,7 @@ impl<F: Field> RLPListGadget<F> {
                     is_short.expr(),
                     is_long.expr(),
                     is_very_long.expr()
-                ) =>> @FIXED
+                ) =>> @cb.fixed()
             );
 
             RLPListGadget {
@@ -349,7 +348,7 @@ impl<F: Field> RLPValueGadget<F> {
                     is_short.expr(),
                     is_long.expr(),
                     is_very_long.expr()
-                ) =>> @FIXED
+                ) =>> @cb.fixed()
             );
 
             RLPValueGadget {
@@ -769,6 +768,31 @@ impl<F: Field> RLPItemGadget<F> {
 }
 
 impl RLPItemWitness {
+    /// Decode the RLP item of a row outside of a region, with the bytes padded
+    /// with zeros like in [`MainRLPGadget`](super::helpers::MainRLPGadget)
+    pub(crate) fn new(bytes: &[u8]) -> Self {
+        let mut bytes = bytes.to_vec();
+        bytes.resize(bytes.len().max(RLP_UNIT_NUM_BYTES), 0);
+        let (is_list, is_short, is_long, is_very_long) = decode_rlp(bytes[0]);
+        RLPItemWitness {
+            value: RLPValueWitness {
+                is_short,
+                is_long,
+                is_very_long,
+                is_list,
+                bytes: bytes.clone(),
+            },
+            list: RLPListWitness {
+                is_short,
+                is_long,
+                is_very_long,
+                is_string: !is_list,
+                bytes: bytes.clone(),
+            },
+            bytes,
+        }
+    }
+
     /// Number of bytes in total (including RLP bytes)
     pub(crate) fn num_bytes(&self) -> usize {
         matchw! {
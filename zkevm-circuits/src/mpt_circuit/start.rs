@@ -9,7 +9,7 @@ use crate::{
     mpt_circuit::{
         helpers::{
             key_memory, main_memory, parent_memory, KeyData, MPTConstraintBuilder, MainData,
-            ParentData,
+            MptCellType, ParentData,
         },
         MPTConfig, MPTContext, MptMemory, RlpItemType,
     },
@@ -17,11 +17,16 @@ use crate::{
 };
 use eth_types::{Field, OpsIdentity};
 use gadgets::util::Scalar;
-use halo2_proofs::plonk::{Error, VirtualCells};
+use halo2_proofs::{
+    circuit::AssignedCell,
+    plonk::{Error, VirtualCells},
+};
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct StartConfig<F> {
     proof_type: Cell<F>,
+    /// Copies of the S and C roots, when they are exposed as public inputs
+    root: Option<[WordLoHi<Cell<F>>; 2]>,
 }
 
 impl<F: Field> StartConfig<F> {
@@ -45,6 +50,18 @@ impl<F: Field> StartConfig<F> {
                 root[is_s.idx()] = root_items[is_s.idx()].word();
             }
 
+            if ctx.params.expose_roots {
+                let root_copy = [true, false].map(|is_s| {
+                    let copy = WordLoHi::new(
+                        [(); 2].map(|_| cb.query_cell_with_type(MptCellType::StoragePermutation)),
+                    );
+                    require!(copy.lo() => root[is_s.idx()].lo());
+                    require!(copy.hi() => root[is_s.idx()].hi());
+                    copy
+                });
+                config.root = Some(root_copy);
+            }
+
             MainData::store(
                 cb,
                 &mut ctx.memory[main_memory()],
@@ -85,7 +102,7 @@ impl<F: Field> StartConfig<F> {
         offset: usize,
         node: &Node,
         rlp_values: &[RLPItemWitness],
-    ) -> Result<(), Error> {
+    ) -> Result<Option<[WordLoHi<AssignedCell<F, F>>; 2]>, Error> {
         let start = &node.start.clone().unwrap();
 
         let _root_items = [
@@ -136,6 +153,20 @@ impl<F: Field> StartConfig<F> {
             )?;
         }
 
-        Ok(())
+        let root_copy = match &self.root {
+            Some(cells) => {
+                let mut assign = |is_s: bool| -> Result<_, Error> {
+                    let (cells, root) = (&cells[is_s.idx()], root[is_s.idx()]);
+                    Ok(WordLoHi::new([
+                        cells.lo().assign(region, offset, root.lo())?,
+                        cells.hi().assign(region, offset, root.hi())?,
+                    ]))
+                };
+                Some([assign(true)?, assign(false)?])
+            }
+            None => None,
+        };
+
+        Ok(root_copy)
     }
 }
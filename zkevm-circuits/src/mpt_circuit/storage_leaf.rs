@@ -18,7 +18,7 @@ use crate::{
         helpers::{
             key_memory, main_memory, num_nibbles, parent_memory, DriftedGadget,
             IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder, MainData, ParentData,
-            ParentDataWitness, KECCAK,
+            ParentDataWitness,
         },
         param::{EMPTY_TRIE_HASH, KEY_LEN_IN_NIBBLES},
         MPTConfig, MPTContext, MptMemory, RlpItemType,
@@ -29,8 +29,8 @@ use crate::{
 };
 
 use super::{
-    helpers::{Indexable, KeyDataWitness, ListKeyGadget, WrongGadget},
-    mod_extension::ModExtensionGadget,
+    helpers::{require_key_path, Indexable, KeyDataWitness, ListKeyGadget, WrongGadget},
+    mod_extension::{ModExtensionGadget, ModExtensionRows},
     rlp_gadgets::{RLPItemWitness, RLPValueGadget},
     witness_row::{Node, StorageRowType},
 };
@@ -67,7 +67,7 @@ impl<F: Field> StorageLeafConfig<F> {
                 ctx.rlp_item(meta, cb, StorageRowType::KeyS as usize, RlpItemType::Key),
                 ctx.rlp_item(meta, cb, StorageRowType::KeyC as usize, RlpItemType::Key),
             ];
-            config.value_rlp_bytes = [cb.base.query_bytes(), cb.base.query_bytes()];
+            config.value_rlp_bytes = [cb.query_bytes(), cb.query_bytes()];
             let value_item = [
                 ctx.rlp_item(
                     meta,
@@ -208,7 +208,7 @@ impl<F: Field> StorageLeafConfig<F> {
                         ifx!{or::expr(&[parent_data[is_s.idx()].is_root.expr(), not!(config.is_not_hashed[is_s.idx()])]) => {
                             // Hashed leaf in parent branch
                             let hash = parent_data[is_s.idx()].hash.expr();
-                            require!((1.expr(), leaf_rlc.expr(), rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @KECCAK);
+                            require!((1.expr(), leaf_rlc.expr(), rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @cb.keccak());
                         } elsex {
                             // Non-hashed leaf in parent branch
                             require!(leaf_rlc => parent_data[is_s.idx()].rlc.expr());
@@ -261,6 +261,7 @@ impl<F: Field> StorageLeafConfig<F> {
                     ctx.clone(),
                     parent_data,
                     key_data,
+                    ModExtensionRows::STORAGE,
                 );
             }};
 
@@ -323,35 +324,20 @@ impl<F: Field> StorageLeafConfig<F> {
                 config.is_non_existing_storage_proof => MPTProofType::StorageDoesNotExist.expr(),
                 _ => MPTProofType::Disabled.expr(),
             )};
-            ifx! {not!(config.is_non_existing_storage_proof) => {
-                let key_rlc = ifx!{not!(config.parent_data[true.idx()].is_placeholder) => {
-                    key_rlc[true.idx()].expr()
-                } elsex {
-                    key_rlc[false.idx()].expr()
-                }};
-                // Check that the key item contains the correct key for the path that was taken
-                require!(key_item.hash_rlc() => key_rlc);
-                // Check if the key is correct for the given address
-                if ctx.params.is_preimage_check_enabled() {
-                    let key = key_item.word();
-                    require!((1.expr(), address_item.bytes_le()[1..33].rlc(&cb.keccak_r), 32.expr(), key.lo(), key.hi()) =>> @KECCAK);
-                }
-            }};
-
-            ifx! {not!(config.is_non_existing_storage_proof) => {
-                let key_rlc = ifx!{not!(config.parent_data[true.idx()].is_placeholder) => {
-                    key_rlc[true.idx()].expr()
-                } elsex {
-                    key_rlc[false.idx()].expr()
-                }};
-                // Check that the key item contains the correct key for the path that was taken
-                require!(key_item.hash_rlc() => key_rlc);
-                // Check if the key is correct for the given address
-                if ctx.params.is_preimage_check_enabled() {
-                    let key = key_item.word();
-                    require!((1.expr(), address_item.bytes_le()[1..33].rlc(&cb.keccak_r), 32.expr(), key.lo(), key.hi()) =>> @KECCAK);
-                }
-            }};
+            // Check that the key item is the key of the slot, and the key of the path
+            // that was taken
+            let preimage = ctx
+                .params
+                .is_preimage_check_enabled()
+                .then(|| (address_item.bytes_le()[1..33].rlc(&cb.keccak_r), 32.expr()));
+            require_key_path(
+                cb,
+                &key_item,
+                preimage,
+                &key_rlc,
+                config.parent_data[true.idx()].is_placeholder.expr(),
+                config.is_non_existing_storage_proof.expr(),
+            );
 
             ifx! {not!(config.parent_data[false.idx()].is_placeholder) => {
                 ifx! {not!(config.is_non_existing_storage_proof) => {
@@ -589,8 +575,13 @@ impl<F: Field> StorageLeafConfig<F> {
                 &storage.mod_list_rlp_bytes[0],
                 &storage.mod_list_rlp_bytes[1],
             ];
-            self.mod_extension
-                .assign(region, offset, rlp_values, mod_list_rlp_bytes)?;
+            self.mod_extension.assign(
+                region,
+                offset,
+                rlp_values,
+                mod_list_rlp_bytes,
+                ModExtensionRows::STORAGE,
+            )?;
         }
 
         let mut new_value = value_word[false.idx()];
@@ -29,10 +29,12 @@ use crate::{
     },
     instance::{
         public_data_convert, BlockValues, ExtraValues, PublicData, TxValues, NONZERO_BYTE_GAS_COST,
-        ZERO_BYTE_GAS_COST,
+        PI_VERSION, ZERO_BYTE_GAS_COST,
+    },
+    table::{
+        tx_table::TX_LEN, BlockTable, KeccakTable, LookupTable, TxFieldTag, TxTable, UXTable,
+        WdTable,
     },
-    table::{BlockTable, KeccakTable, LookupTable, TxFieldTag, TxTable, WdTable},
-    tx_circuit::TX_LEN,
     util::{word::WordLoHi, Challenges, SubCircuit, SubCircuitConfig},
     witness,
 };
@@ -60,6 +62,8 @@ pub struct PiCircuitConfig<F: Field> {
     q_digest_last: Selector,
     // q_bytes_last: will be 1 on last byte of raw public input last byte, others are 0
     q_bytes_last: Selector,
+    // q_pi_version: 1 on the version byte of raw public input, others are 0
+    q_pi_version: Selector,
     // q_tx_table: 1 on the rows where tx_table is activated, others are 0
     q_tx_table: Selector,
     // q_tx_calldata: 1 on the rows where tx_table calldata is activated, others are 0
@@ -82,7 +86,7 @@ pub struct PiCircuitConfig<F: Field> {
     // refer in tx_calldata constrains, and only need tx_value.lo() part
     tx_value_lo_inv: Column<Advice>,
     tx_id_diff_inv: Column<Advice>,
-    fixed_u16: Column<Fixed>,
+    u16_table: UXTable<16>,
     calldata_gas_cost: Column<Advice>,
     is_final: Column<Advice>,
 
@@ -127,6 +131,8 @@ pub struct PiCircuitConfigArgs<F: Field> {
     pub block_table: BlockTable,
     /// Keccak Table
     pub keccak_table: KeccakTable,
+    /// U16Table, shared with the other circuits
+    pub u16_table: UXTable<16>,
     /// Challenges
     pub challenges: Challenges<Expression<F>>,
 }
@@ -145,6 +151,7 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             tx_table,
             wd_table,
             keccak_table,
+            u16_table,
             challenges,
         }: Self::ConfigArgs,
     ) -> Self {
@@ -164,12 +171,12 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
         // lies in the interval [0, 2^16] if their tx_id both do not equal to zero.
         // We do not use 2^8 for the reason that a large block may have more than
         // 2^8 transfer transactions which have 21000*2^8 (~ 5.376M) gas.
-        let fixed_u16 = meta.fixed_column();
         let calldata_gas_cost = meta.advice_column_in(SecondPhase);
         let is_final = meta.advice_column();
 
         let q_digest_last = meta.complex_selector();
         let q_bytes_last = meta.complex_selector();
+        let q_pi_version = meta.selector();
         let q_rpi_byte_enable = meta.complex_selector();
         let q_rpi_value_start = meta.fixed_column();
         let q_digest_value_start = meta.fixed_column();
@@ -247,6 +254,20 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             },
         );
 
+        // the version byte is fixed by the circuit, so that a proof can't be checked
+        // against a verifier built for another public input layout
+        meta.create_gate("rpi_bytes[version] = PI_VERSION", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+
+            cb.require_equal(
+                "rpi_bytes[version] = PI_VERSION",
+                meta.query_advice(rpi_bytes, Rotation::cur()),
+                PI_VERSION.expr(),
+            );
+
+            cb.gate(meta.query_selector(q_pi_version))
+        });
+
         // gate 3 and gate 4 are compensation branch
         // 3: rpi_value_lc[i] = rpi_value_lc[i+1] * byte_pow_base
         // + rpi_bytes[i]
@@ -352,7 +373,7 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             let tx_id = meta.query_advice(tx_id, Rotation::cur());
             let tx_id_inv_next = meta.query_advice(tx_id_inv, Rotation::next());
             let tx_id_diff_inv = meta.query_advice(tx_id_diff_inv, Rotation::cur());
-            let fixed_u16_table = meta.query_fixed(fixed_u16, Rotation::cur());
+            let fixed_u16_table = u16_table.table_exprs(meta)[0].clone();
 
             let tx_id_next_nonzero = tx_id_next.expr() * tx_id_inv_next;
             let tx_id_not_equal_to_next = (tx_id_next.expr() - tx_id.expr()) * tx_id_diff_inv;
@@ -503,6 +524,7 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             block_table,
             q_digest_last,
             q_bytes_last,
+            q_pi_version,
             q_tx_calldata,
             q_calldata_start,
             q_rpi_keccak_lookup,
@@ -515,7 +537,7 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             tx_id_inv,
             tx_value_lo_inv,
             tx_id_diff_inv,
-            fixed_u16,
+            u16_table,
             calldata_gas_cost,
             is_final,
             rpi_bytes,
@@ -1501,21 +1523,6 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
         challenges: &Challenges<Value<F>>,
         layouter: &mut impl Layouter<F>,
     ) -> Result<(), Error> {
-        layouter.assign_region(
-            || "fixed u16 table",
-            |mut region| {
-                for i in 0..(1 << 16) {
-                    region.assign_fixed(
-                        || format!("row_{}", i),
-                        config.fixed_u16,
-                        i,
-                        || Value::known(F::from(i as u64)),
-                    )?;
-                }
-
-                Ok(())
-            },
-        )?;
         let digest_word_assigned = layouter.assign_region(
             || "region 0",
             |mut region| {
@@ -1536,7 +1543,7 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                 region.name_column(|| "tx_value_lo_inv", config.tx_value_lo_inv);
                 region.name_column(|| "tx_id_diff_inv", config.tx_id_diff_inv);
 
-                region.name_column(|| "fixed_u16", config.fixed_u16);
+                config.u16_table.annotate_columns_in_region(&mut region);
                 region.name_column(|| "calldata_gas_cost", config.calldata_gas_cost);
                 region.name_column(|| "is_final", config.is_final);
 
@@ -1571,15 +1578,21 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                     block_table_offset,
                 )?;
                 let zero_cell = zero_word.hi();
-                let (_, _) = config.assign_raw_bytes(
+
+                // Assign the version byte
+                config
+                    .q_pi_version
+                    .enable(&mut region, current_rpi_offset)?;
+                config.assign_raw_bytes(
                     &mut region,
-                    &0u8.to_le_bytes(),
+                    &PI_VERSION.to_le_bytes(),
                     &mut rpi_bytes_keccak_rlc,
                     &mut rpi_bytes,
                     &mut current_rpi_offset,
                     challenges,
                     zero_cell.clone(),
                 )?;
+
                 block_table_offset += 1;
                 config.assign_block_table(
                     &mut region,
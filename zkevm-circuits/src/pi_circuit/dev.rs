@@ -3,7 +3,7 @@ use super::{PiCircuit, PiCircuitConfig, PiCircuitConfigArgs};
 use eth_types::{self, Field};
 
 use crate::{
-    table::{BlockTable, KeccakTable, TxTable, WdTable},
+    table::{BlockTable, KeccakTable, TxTable, UXTable, WdTable},
     util::{Challenges, SubCircuit, SubCircuitConfig},
 };
 use halo2_proofs::{
@@ -44,6 +44,7 @@ impl<F: Field> Circuit<F> for PiCircuit<F> {
         let tx_table = TxTable::construct(meta);
         let wd_table = WdTable::construct(meta);
         let keccak_table = KeccakTable::construct(meta);
+        let u16_table = UXTable::construct(meta);
         let challenges = Challenges::construct(meta);
         let challenge_exprs = challenges.exprs(meta);
         (
@@ -57,6 +58,7 @@ impl<F: Field> Circuit<F> for PiCircuit<F> {
                     tx_table,
                     wd_table,
                     keccak_table,
+                    u16_table,
                     challenges: challenge_exprs,
                 },
             ),
@@ -83,6 +85,7 @@ impl<F: Field> Circuit<F> for PiCircuit<F> {
         config
             .keccak_table
             .dev_load(&mut layouter, vec![&rpi_bytes], &challenges)?;
+        config.u16_table.load(&mut layouter)?;
 
         self.synthesize_sub(&config, &challenges, &mut layouter)
     }
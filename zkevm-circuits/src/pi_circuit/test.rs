@@ -239,6 +239,49 @@ fn test_1wd_1wdmax() {
     assert_eq!(prover.verify(), Ok(()));
 }
 
+#[test]
+fn test_pi_bytes_version() {
+    let max_txs = 2;
+    let max_withdrawals = 2;
+    let max_calldata = 8;
+    let public_data = PublicData {
+        chain_id: *MOCK_CHAIN_ID,
+        ..Default::default()
+    };
+
+    let pi_bytes = public_data.get_pi_bytes(max_txs, max_withdrawals, max_calldata);
+    assert_eq!(
+        pi_bytes.len(),
+        PiCircuitConfig::<Fr>::circuit_len_all(max_txs, max_withdrawals, max_calldata)
+    );
+    assert_eq!(pi_bytes[0], PI_VERSION);
+}
+
+// The chain id of the block values is enough to separate the public inputs of
+// different chains
+#[test]
+fn test_pi_chain_id_separation() {
+    let max_txs = 2;
+    let max_withdrawals = 2;
+    let max_calldata = 8;
+    let public_data = PublicData {
+        chain_id: *MOCK_CHAIN_ID,
+        ..Default::default()
+    };
+    let other_chain_data = PublicData {
+        chain_id: *MOCK_CHAIN_ID + 1,
+        ..public_data.clone()
+    };
+
+    let circuit = PiCircuit::<Fr>::new(max_txs, max_withdrawals, max_calldata, public_data);
+    let other_chain_circuit =
+        PiCircuit::<Fr>::new(max_txs, max_withdrawals, max_calldata, other_chain_data);
+    assert_ne!(circuit.instance(), other_chain_circuit.instance());
+
+    let prover = MockProver::run(17, &circuit, other_chain_circuit.instance()).unwrap();
+    assert!(prover.verify().is_err());
+}
+
 fn run_size_check<F: Field>(
     max_txs: usize,
     max_withdrawals: usize,
@@ -0,0 +1,320 @@
+//! Creation, serialization and verification of circuit proofs.
+//!
+//! Proofs use the KZG commitment scheme with SHPLONK multiopen and the
+//! Poseidon transcript, which is what the [`RootCircuit`] expects when
+//! aggregating them.
+//!
+//! A [`ProvingJob`] identifies the statement proven by a proof, so that
+//! distributed provers can deduplicate work and check that they prove the
+//! same statement before accepting it.
+//!
+//! [`RootCircuit`]: crate::root_circuit::RootCircuit
+
+use crate::{instance::public_data_convert, root_circuit::PoseidonTranscript, witness::Block};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::{keccak256, H256};
+use halo2_proofs::{
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine},
+        group::ff::PrimeField,
+    },
+    plonk::{create_proof, verify_proof, Circuit, Error, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+};
+use rand::RngCore;
+
+/// Serialized proof of a circuit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof(Vec<u8>);
+
+impl Proof {
+    /// Prove `circuit` with the public inputs `instances`, one vector per
+    /// instance column.
+    pub fn create<C: Circuit<Fr>>(
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &[Vec<Fr>],
+        rng: impl RngCore,
+    ) -> Result<Self, Error> {
+        let instances: Vec<&[Fr]> = instances.iter().map(Vec::as_slice).collect();
+        let mut transcript = PoseidonTranscript::new(Vec::new());
+        create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _, _, _>(
+            params,
+            pk,
+            &[circuit],
+            &[&instances],
+            rng,
+            &mut transcript,
+        )?;
+        Ok(Self(transcript.finalize()))
+    }
+
+    /// Read a proof serialized with [`Proof::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+
+    /// Serialize the proof.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    /// Verify the proof with the verifying key `vk` and the public inputs
+    /// `instances`, one vector per instance column.
+    pub fn verify_with_vk(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &[Vec<Fr>],
+    ) -> Result<(), Error> {
+        let instances: Vec<&[Fr]> = instances.iter().map(Vec::as_slice).collect();
+        let mut transcript = PoseidonTranscript::new(self.0.as_slice());
+        verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, _, _, _>(
+            params.verifier_params(),
+            vk,
+            SingleStrategy::new(params),
+            &[&instances],
+            &mut transcript,
+        )
+    }
+}
+
+/// Descriptor of a proving job: the statement that a proof of the
+/// [`SuperCircuit`](crate::super_circuit::SuperCircuit) attests.
+///
+/// Two jobs with the same [`ProvingJob::id`] prove the same witness with the
+/// same circuit parameters and the same circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProvingJob {
+    /// Hash of the block witness, see [`witness_hash`]
+    pub witness_hash: H256,
+    /// Hash of the circuit parameters, see [`params_hash`]
+    pub params_hash: H256,
+    /// Digest of the circuit, see [`circuit_digest`]
+    pub circuit_digest: H256,
+}
+
+impl ProvingJob {
+    /// Create the job proving `block` with the circuit of verifying key `vk`.
+    pub fn new(block: &Block<Fr>, vk: &VerifyingKey<G1Affine>) -> Self {
+        Self {
+            witness_hash: witness_hash(block),
+            params_hash: params_hash(&block.circuits_params),
+            circuit_digest: circuit_digest(vk),
+        }
+    }
+
+    /// Identifier of the job, committing to all its fields.
+    pub fn id(&self) -> H256 {
+        let bytes: Vec<u8> = [self.witness_hash, self.params_hash, self.circuit_digest]
+            .iter()
+            .flat_map(|hash| hash.to_fixed_bytes())
+            .collect();
+        H256(keccak256(&bytes))
+    }
+}
+
+/// Digest of the circuit of verifying key `vk`, which commits to its
+/// constraint system and to its fixed columns.
+pub fn circuit_digest(vk: &VerifyingKey<G1Affine>) -> H256 {
+    H256::from_slice(vk.transcript_repr().to_repr().as_ref())
+}
+
+/// Canonical hash of a block witness.
+///
+/// The hash commits to the public input bytes, which include the block
+/// header and the transactions, to every row of the rw table and to every
+/// keccak input, which include the bytecodes. The randomness used for the
+/// random linear combinations is not part of the witness.
+pub fn witness_hash(block: &Block<Fr>) -> H256 {
+    let mut bytes = public_data_convert(block).get_pi_bytes(
+        block.circuits_params.max_txs,
+        block.circuits_params.max_withdrawals,
+        block.circuits_params.max_calldata,
+    );
+    for rw in block.rws.table_assignments() {
+        for value in rw.table_assignment::<Fr>().unwrap().values() {
+            bytes.extend_from_slice(value.to_repr().as_ref());
+        }
+    }
+    for input in &block.keccak_inputs {
+        bytes.extend_from_slice(&(input.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(input);
+    }
+    H256(keccak256(&bytes))
+}
+
+/// Canonical hash of the circuit parameters.
+pub fn params_hash(params: &FixedCParams) -> H256 {
+    let bytes: Vec<u8> = [
+        params.max_rws,
+        params.max_txs,
+        params.max_withdrawals,
+        params.max_calldata,
+        params.max_copy_rows,
+        params.max_exp_steps,
+        params.max_bytecode,
+        params.max_evm_rows,
+        params.max_keccak_rows,
+        params.max_vertical_circuit_rows,
+    ]
+    .iter()
+    .flat_map(|value| (*value as u64).to_be_bytes())
+    .collect();
+    H256(keccak256(&bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        instance::{instances_for, PiLayout},
+        super_circuit::{test::block_1tx, SuperCircuit},
+        util::SubCircuit,
+        witness::block_convert,
+    };
+    use bus_mapping::{circuit_input_builder::FixedCParams, mock::BlockData};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, Advice, Column, ConstraintSystem, Instance},
+    };
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    /// Circuit exposing a single advice cell as public input.
+    #[derive(Clone, Default)]
+    struct TestCircuit(Value<Fr>);
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = (Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let advice = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(advice);
+            meta.enable_equality(instance);
+            (advice, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (advice, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let cell = layouter.assign_region(
+                || "value",
+                |mut region| region.assign_advice(|| "value", advice, 0, || self.0),
+            )?;
+            layouter.constrain_instance(cell.cell(), instance, 0)
+        }
+    }
+
+    #[test]
+    fn proof_roundtrip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let params = ParamsKZG::<Bn256>::setup(4, &mut rng);
+        let vk = keygen_vk(&params, &TestCircuit::default()).unwrap();
+        let pk = keygen_pk(&params, vk.clone(), &TestCircuit::default()).unwrap();
+
+        let value = Fr::from(0xcafe);
+        let proof = Proof::create(
+            &params,
+            &pk,
+            TestCircuit(Value::known(value)),
+            &[vec![value]],
+            &mut rng,
+        )
+        .unwrap();
+        let proof = Proof::from_bytes(&proof.to_bytes());
+
+        assert!(proof.verify_with_vk(&params, &vk, &[vec![value]]).is_ok());
+        assert!(proof
+            .verify_with_vk(&params, &vk, &[vec![Fr::from(0xbeef)]])
+            .is_err());
+    }
+
+    #[test]
+    fn super_circuit_instances() {
+        let block = block_1tx();
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block.clone(),
+            FixedCParams {
+                max_txs: 1,
+                max_withdrawals: 5,
+                max_calldata: 32,
+                ..Default::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder).unwrap();
+
+        let layout = PiLayout::from(&block.circuits_params);
+        assert_eq!(
+            instances_for(&block, &layout),
+            SuperCircuit::new_from_block(&block).instance()
+        );
+    }
+
+    fn block_1tx_witness(params: FixedCParams) -> Block<Fr> {
+        let block = block_1tx();
+        let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), params)
+            .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        block_convert::<Fr>(&builder).unwrap()
+    }
+
+    #[test]
+    fn proving_job_id() {
+        let params = ParamsKZG::<Bn256>::setup(4, ChaCha20Rng::seed_from_u64(2));
+        let vk = keygen_vk(&params, &TestCircuit::default()).unwrap();
+        let circuit_params = FixedCParams {
+            max_txs: 1,
+            max_calldata: 32,
+            ..Default::default()
+        };
+
+        let job = ProvingJob::new(&block_1tx_witness(circuit_params), &vk);
+        assert_eq!(
+            job,
+            ProvingJob::new(&block_1tx_witness(circuit_params), &vk)
+        );
+
+        let mut block = block_1tx_witness(circuit_params);
+        block.randomness = Fr::from(0xbeef);
+        assert_eq!(job.id(), ProvingJob::new(&block, &vk).id());
+
+        let other = ProvingJob::new(
+            &block_1tx_witness(FixedCParams {
+                max_txs: 2,
+                ..circuit_params
+            }),
+            &vk,
+        );
+        assert_ne!(job.params_hash, other.params_hash);
+        assert_ne!(job.id(), other.id());
+
+        let mut block = block_1tx_witness(circuit_params);
+        block.keccak_inputs.pop();
+        let other = ProvingJob::new(&block, &vk);
+        assert_ne!(job.witness_hash, other.witness_hash);
+        assert_ne!(job.id(), other.id());
+    }
+}
@@ -0,0 +1,168 @@
+//! Management of the KZG structured reference string (SRS).
+//!
+//! Real proofs need the SRS of a trusted setup, such as the perpetual powers
+//! of tau converted to the halo2 format, instead of the parameters generated
+//! with a local randomness by `ParamsKZG::setup`. This module reads an SRS,
+//! checks it against a known hash, truncates it to the degree of a circuit
+//! and converts it between the serialization formats of halo2.
+//!
+//! Downloading the SRS is left to the caller, which can fetch the file for
+//! the largest degree it needs and truncate it with [`srs_for_degree`].
+
+use eth_types::{keccak256, H256};
+use halo2_proofs::{
+    halo2curves::bn256::Bn256,
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    SerdeFormat,
+};
+use std::{fmt, io};
+
+/// Error of the SRS utilities
+#[derive(Debug)]
+pub enum SrsError {
+    /// Error reading or writing the SRS
+    Io(io::Error),
+    /// The hash of the SRS is not the expected one
+    HashMismatch {
+        /// Expected keccak256 hash
+        expected: H256,
+        /// Hash of the SRS that was read
+        found: H256,
+    },
+    /// The SRS is too small for the requested degree
+    DegreeTooLarge {
+        /// Degree of the SRS
+        available: u32,
+        /// Requested degree
+        requested: u32,
+    },
+}
+
+impl fmt::Display for SrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for SrsError {}
+
+impl From<io::Error> for SrsError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Keccak256 hash of a serialized SRS.
+pub fn srs_hash(bytes: &[u8]) -> H256 {
+    H256(keccak256(bytes))
+}
+
+/// Read an SRS serialized in `format`. When `expected_hash` is given, the
+/// keccak256 hash of the serialized bytes must match it.
+pub fn read_srs(
+    mut reader: impl io::Read,
+    format: SerdeFormat,
+    expected_hash: Option<H256>,
+) -> Result<ParamsKZG<Bn256>, SrsError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if let Some(expected) = expected_hash {
+        let found = srs_hash(&bytes);
+        if found != expected {
+            return Err(SrsError::HashMismatch { expected, found });
+        }
+    }
+    Ok(ParamsKZG::read_custom(&mut bytes.as_slice(), format)?)
+}
+
+/// Return the SRS truncated to degree `k`.
+pub fn srs_for_degree(params: &ParamsKZG<Bn256>, k: u32) -> Result<ParamsKZG<Bn256>, SrsError> {
+    if k > params.k() {
+        return Err(SrsError::DegreeTooLarge {
+            available: params.k(),
+            requested: k,
+        });
+    }
+    let mut params = params.clone();
+    if k < params.k() {
+        params.downsize(k);
+    }
+    Ok(params)
+}
+
+/// Convert an SRS serialized in `from` into the `to` format.
+pub fn convert_srs(
+    reader: impl io::Read,
+    from: SerdeFormat,
+    mut writer: impl io::Write,
+    to: SerdeFormat,
+) -> Result<(), SrsError> {
+    let params = read_srs(reader, from, None)?;
+    params.write_custom(&mut writer, to)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    fn srs_bytes(k: u32, format: SerdeFormat) -> Vec<u8> {
+        let params = ParamsKZG::<Bn256>::setup(k, ChaCha20Rng::seed_from_u64(2));
+        let mut bytes = Vec::new();
+        params.write_custom(&mut bytes, format).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn read_srs_checks_hash() {
+        let bytes = srs_bytes(4, SerdeFormat::RawBytes);
+        let hash = srs_hash(&bytes);
+
+        let params = read_srs(bytes.as_slice(), SerdeFormat::RawBytes, Some(hash)).unwrap();
+        assert_eq!(params.k(), 4);
+
+        let err = read_srs(bytes.as_slice(), SerdeFormat::RawBytes, Some(H256::zero()));
+        assert!(matches!(err, Err(SrsError::HashMismatch { found, .. }) if found == hash));
+    }
+
+    #[test]
+    fn srs_truncation() {
+        let bytes = srs_bytes(4, SerdeFormat::RawBytes);
+        let params = read_srs(bytes.as_slice(), SerdeFormat::RawBytes, None).unwrap();
+
+        assert_eq!(srs_for_degree(&params, 3).unwrap().k(), 3);
+        assert_eq!(srs_for_degree(&params, 4).unwrap().k(), 4);
+        assert!(matches!(
+            srs_for_degree(&params, 5),
+            Err(SrsError::DegreeTooLarge {
+                available: 4,
+                requested: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn srs_format_conversion() {
+        let raw = srs_bytes(4, SerdeFormat::RawBytes);
+        let mut processed = Vec::new();
+        convert_srs(
+            raw.as_slice(),
+            SerdeFormat::RawBytes,
+            &mut processed,
+            SerdeFormat::Processed,
+        )
+        .unwrap();
+        assert_eq!(processed, srs_bytes(4, SerdeFormat::Processed));
+
+        let mut roundtrip = Vec::new();
+        convert_srs(
+            processed.as_slice(),
+            SerdeFormat::Processed,
+            &mut roundtrip,
+            SerdeFormat::RawBytes,
+        )
+        .unwrap();
+        assert_eq!(roundtrip, raw);
+    }
+}
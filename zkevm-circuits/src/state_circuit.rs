@@ -187,7 +187,8 @@ impl<F: Field> SubCircuitConfig<F> for StateCircuitConfig<F> {
 }
 
 impl<F: Field> StateCircuitConfig<F> {
-    /// load fixed tables
+    /// Load the range tables. They are shared with other circuits, so this
+    /// must only be called when the StateCircuit is used standalone.
     pub(crate) fn load_aux_tables(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         LookupsChip::construct(self.lookups).load(layouter)
     }
@@ -237,9 +238,14 @@ impl<F: Field> StateCircuitConfig<F> {
 
             tag_chip.assign(region, offset, &row.tag())?;
 
+            // A wrapped rw counter would break the ordering of the rows
+            let rw_counter = u32::try_from(row.rw_counter()).map_err(|_| {
+                log::error!("rw counter {} overflows u32", row.rw_counter());
+                Error::Synthesis
+            })?;
             self.sort_keys
                 .rw_counter
-                .assign(region, offset, row.rw_counter() as u32)?;
+                .assign(region, offset, rw_counter)?;
 
             if let Some(id) = row.id() {
                 self.sort_keys.id.assign(region, offset, id as u32)?;
@@ -481,8 +487,6 @@ impl<F: Field> SubCircuit<F> for StateCircuit<F> {
         _challenges: &Challenges<Value<F>>,
         layouter: &mut impl Layouter<F>,
     ) -> Result<(), Error> {
-        config.load_aux_tables(layouter)?;
-
         // Assigning to same columns in different regions should be avoided.
         // Here we use one single region to assign `overrides` to both rw table and
         // other parts.
@@ -56,6 +56,9 @@ where
     ) -> Result<(), Error> {
         let challenges = challenges.values(&mut layouter);
         config.mpt_table.load(&mut layouter, &self.updates)?;
+        // The range tables are shared with other circuits, so they are only
+        // loaded here and by the SuperCircuit.
+        config.load_aux_tables(&mut layouter)?;
         self.synthesize_sub(&config, &challenges, &mut layouter)
     }
 }
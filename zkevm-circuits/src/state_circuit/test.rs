@@ -646,6 +646,47 @@ fn read_inconsistency() {
     assert_error_matches(verify(rows), "non-first access reads don't change value");
 }
 
+#[test]
+fn storage_first_read_not_proven_by_mpt() {
+    let read = Rw::AccountStorage {
+        rw_counter: 1,
+        is_write: false,
+        account_address: Address::default(),
+        storage_key: U256::from(6),
+        value: U256::from(34),
+        value_prev: U256::from(34),
+        tx_id: 4,
+        committed_value: U256::from(34),
+    };
+    // The MPT proves another value for the slot before the block
+    let proven = Rw::AccountStorage {
+        rw_counter: 1,
+        is_write: false,
+        account_address: Address::default(),
+        storage_key: U256::from(6),
+        value: U256::from(35),
+        value_prev: U256::from(35),
+        tx_id: 4,
+        committed_value: U256::from(35),
+    };
+    let circuit = StateCircuit::<Fr> {
+        rows: vec![read],
+        updates: MptUpdates::mock_from(&[proven]),
+        overrides: HashMap::new(),
+        n_rows: N_ROWS,
+        _marker: std::marker::PhantomData,
+    };
+    let instance = circuit.instance();
+    let result = MockProver::<Fr>::run(17, &circuit, instance)
+        .unwrap()
+        .verify_at_rows(N_ROWS - 1..N_ROWS, N_ROWS - 1..N_ROWS);
+
+    assert_error_matches(
+        result,
+        "mpt_update exists in mpt circuit for AccountStorage last access",
+    );
+}
+
 #[test]
 fn all_padding() {
     assert_eq!(
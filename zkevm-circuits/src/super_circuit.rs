@@ -50,15 +50,22 @@
 //!   - [x] Tx Circuit
 //!   - [ ] MPT Circuit
 
+mod params;
 #[cfg(test)]
 pub(crate) mod test;
 
+pub use params::{ParamsPreset, ParamsViolation, ValidateParams};
+
 use crate::{
     bytecode_circuit::{BytecodeCircuit, BytecodeCircuitConfig, BytecodeCircuitConfigArgs},
     copy_circuit::{CopyCircuit, CopyCircuitConfig, CopyCircuitConfigArgs},
     evm_circuit::{EvmCircuit, EvmCircuitConfig, EvmCircuitConfigArgs},
-    exp_circuit::{ExpCircuit, ExpCircuitConfig},
+    exp_circuit::{
+        param::{OFFSET_INCREMENT, UNUSABLE_EXP_ROWS},
+        ExpCircuit, ExpCircuitConfig, ExpCircuitConfigArgs,
+    },
     keccak_circuit::{KeccakCircuit, KeccakCircuitConfig, KeccakCircuitConfigArgs},
+    metrics,
     pi_circuit::{PiCircuit, PiCircuitConfig, PiCircuitConfigArgs},
     state_circuit::{StateCircuit, StateCircuitConfig, StateCircuitConfigArgs},
     table::{
@@ -150,6 +157,8 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
 
         let sig_table = SigTable::construct(meta);
 
+        let mut lookups = LookupCounter::new(meta);
+
         let keccak_circuit = KeccakCircuitConfig::new(
             meta,
             KeccakCircuitConfigArgs {
@@ -157,6 +166,7 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 challenges: challenges_exprs.clone(),
             },
         );
+        lookups.record(meta, "keccak");
 
         let pi_circuit = PiCircuitConfig::new(
             meta,
@@ -168,9 +178,11 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 tx_table: tx_table.clone(),
                 wd_table,
                 keccak_table: keccak_table.clone(),
+                u16_table,
                 challenges: challenges_exprs.clone(),
             },
         );
+        lookups.record(meta, "pi");
         let tx_circuit = TxCircuitConfig::new(
             meta,
             TxCircuitConfigArgs {
@@ -179,6 +191,7 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 challenges: challenges_exprs.clone(),
             },
         );
+        lookups.record(meta, "tx");
         let bytecode_circuit = BytecodeCircuitConfig::new(
             meta,
             BytecodeCircuitConfigArgs {
@@ -187,6 +200,7 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 challenges: challenges_exprs.clone(),
             },
         );
+        lookups.record(meta, "bytecode");
         let copy_circuit = CopyCircuitConfig::new(
             meta,
             CopyCircuitConfigArgs {
@@ -198,6 +212,7 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 challenges: challenges_exprs.clone(),
             },
         );
+        lookups.record(meta, "copy");
         let state_circuit = StateCircuitConfig::new(
             meta,
             StateCircuitConfigArgs {
@@ -209,7 +224,15 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 challenges: challenges_exprs.clone(),
             },
         );
-        let exp_circuit = ExpCircuitConfig::new(meta, exp_table);
+        lookups.record(meta, "state");
+        let exp_circuit = ExpCircuitConfig::new(
+            meta,
+            ExpCircuitConfigArgs {
+                exp_table,
+                u8_table,
+            },
+        );
+        lookups.record(meta, "exp");
         let evm_circuit = EvmCircuitConfig::new(
             meta,
             EvmCircuitConfigArgs {
@@ -227,6 +250,7 @@ impl<F: Field> SubCircuitConfig<F> for SuperCircuitConfig<F> {
                 feature_config,
             },
         );
+        lookups.record(meta, "evm");
 
         Self {
             block_table,
@@ -273,6 +297,8 @@ pub struct SuperCircuit<F: Field> {
     pub feature_config: FeatureConfig,
     /// Mock randomness
     pub mock_randomness: F,
+    /// Rows required by each sub-circuit to prove the block
+    pub rows: SubCircuitRows,
 }
 
 impl<F: Field> SuperCircuit<F> {
@@ -325,8 +351,9 @@ impl<F: Field> SubCircuit<F> for SuperCircuit<F> {
             exp_circuit,
             keccak_circuit,
             circuits_params: block.circuits_params,
-            feature_config: block.feature_config,
+            feature_config: block.feature_config.clone(),
             mock_randomness: block.randomness,
+            rows: Self::sub_circuit_rows(block),
         }
     }
 
@@ -347,22 +374,7 @@ impl<F: Field> SubCircuit<F> for SuperCircuit<F> {
 
     /// Return the minimum number of rows required to prove the block
     fn min_num_rows_block(block: &Block<F>) -> (usize, usize) {
-        let evm = EvmCircuit::min_num_rows_block(block);
-        let state = StateCircuit::min_num_rows_block(block);
-        let bytecode = BytecodeCircuit::min_num_rows_block(block);
-        let copy = CopyCircuit::min_num_rows_block(block);
-        let keccak = KeccakCircuit::min_num_rows_block(block);
-        let tx = TxCircuit::min_num_rows_block(block);
-        let exp = ExpCircuit::min_num_rows_block(block);
-        let pi = PiCircuit::min_num_rows_block(block);
-
-        let rows: Vec<(usize, usize)> = vec![evm, state, bytecode, copy, keccak, tx, exp, pi];
-        let (rows_without_padding, rows_with_padding): (Vec<usize>, Vec<usize>) =
-            rows.into_iter().unzip();
-        (
-            itertools::max(rows_without_padding).unwrap(),
-            itertools::max(rows_with_padding).unwrap(),
-        )
+        Self::sub_circuit_rows(block).max()
     }
 
     /// Make the assignments to the SuperCircuit
@@ -372,26 +384,45 @@ impl<F: Field> SubCircuit<F> for SuperCircuit<F> {
         challenges: &Challenges<Value<F>>,
         layouter: &mut impl Layouter<F>,
     ) -> Result<(), Error> {
-        self.keccak_circuit
-            .synthesize_sub(&config.keccak_circuit, challenges, layouter)?;
-        self.bytecode_circuit
-            .synthesize_sub(&config.bytecode_circuit, challenges, layouter)?;
-        self.tx_circuit
-            .synthesize_sub(&config.tx_circuit, challenges, layouter)?;
-        self.state_circuit
-            .synthesize_sub(&config.state_circuit, challenges, layouter)?;
-        self.copy_circuit
-            .synthesize_sub(&config.copy_circuit, challenges, layouter)?;
-        self.exp_circuit
-            .synthesize_sub(&config.exp_circuit, challenges, layouter)?;
-        self.evm_circuit
-            .synthesize_sub(&config.evm_circuit, challenges, layouter)?;
-        self.pi_circuit
-            .synthesize_sub(&config.pi_circuit, challenges, layouter)?;
+        // Synthesize each sub-circuit in a tracing span of its own
+        macro_rules! synthesize {
+            ($name:literal, $circuit:ident) => {
+                tracing::info_span!("synthesize", circuit = $name).in_scope(|| {
+                    self.$circuit
+                        .synthesize_sub(&config.$circuit, challenges, layouter)
+                })?
+            };
+        }
+        synthesize!("keccak", keccak_circuit);
+        synthesize!("bytecode", bytecode_circuit);
+        synthesize!("tx", tx_circuit);
+        synthesize!("state", state_circuit);
+        synthesize!("copy", copy_circuit);
+        synthesize!("exp", exp_circuit);
+        synthesize!("evm", evm_circuit);
+        synthesize!("pi", pi_circuit);
         Ok(())
     }
 }
 
+/// Counts the lookup arguments added by each sub-circuit as it is configured,
+/// recording them in the [`metrics`].
+struct LookupCounter(usize);
+
+impl LookupCounter {
+    fn new<F: Field>(meta: &ConstraintSystem<F>) -> Self {
+        Self(meta.lookups().len())
+    }
+
+    /// Record the lookup arguments added since the last call as the ones of
+    /// the sub-circuit `circuit`.
+    fn record<F: Field>(&mut self, meta: &ConstraintSystem<F>, circuit: &'static str) {
+        let lookups = meta.lookups().len();
+        metrics::record_lookups(circuit, lookups - self.0);
+        self.0 = lookups;
+    }
+}
+
 /// Super Circuit configuration parameters
 #[derive(Default)]
 pub struct SuperCircuitParams<F: Field> {
@@ -417,7 +448,7 @@ impl<F: Field> Circuit<F> for SuperCircuit<F> {
             max_withdrawals: self.circuits_params.max_withdrawals,
             max_calldata: self.circuits_params.max_calldata,
             mock_randomness: self.mock_randomness,
-            feature_config: self.feature_config,
+            feature_config: self.feature_config.clone(),
         }
     }
 
@@ -459,7 +490,80 @@ impl<F: Field> Circuit<F> for SuperCircuit<F> {
     }
 }
 
+/// Minimum number of rows required by each sub-circuit of the
+/// [`SuperCircuit`] to prove a block, as returned by
+/// [`SubCircuit::min_num_rows_block`]: the rows without padding and the rows
+/// with padding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubCircuitRows {
+    /// EVM Circuit
+    pub evm: (usize, usize),
+    /// State Circuit
+    pub state: (usize, usize),
+    /// Bytecode Circuit
+    pub bytecode: (usize, usize),
+    /// Copy Circuit
+    pub copy: (usize, usize),
+    /// Keccak Circuit
+    pub keccak: (usize, usize),
+    /// Tx Circuit
+    pub tx: (usize, usize),
+    /// Exp Circuit
+    pub exp: (usize, usize),
+    /// Public Input Circuit
+    pub pi: (usize, usize),
+}
+
+impl SubCircuitRows {
+    /// Rows of each sub-circuit, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, (usize, usize))> {
+        [
+            ("evm", self.evm),
+            ("state", self.state),
+            ("bytecode", self.bytecode),
+            ("copy", self.copy),
+            ("keccak", self.keccak),
+            ("tx", self.tx),
+            ("exp", self.exp),
+            ("pi", self.pi),
+        ]
+        .into_iter()
+    }
+
+    /// Largest rows without padding and rows with padding over the
+    /// sub-circuits.
+    pub fn max(&self) -> (usize, usize) {
+        let (rows_without_padding, rows_with_padding): (Vec<usize>, Vec<usize>) =
+            self.iter().map(|(_, rows)| rows).unzip();
+        (
+            itertools::max(rows_without_padding).unwrap(),
+            itertools::max(rows_with_padding).unwrap(),
+        )
+    }
+}
+
 impl<F: Field> SuperCircuit<F> {
+    /// Return the minimum number of rows required by each sub-circuit to prove
+    /// the block.
+    pub fn sub_circuit_rows(block: &Block<F>) -> SubCircuitRows {
+        let (exp_rows, max_exp_steps) = ExpCircuit::min_num_rows_block(block);
+        SubCircuitRows {
+            evm: EvmCircuit::min_num_rows_block(block),
+            state: StateCircuit::min_num_rows_block(block),
+            bytecode: BytecodeCircuit::min_num_rows_block(block),
+            copy: CopyCircuit::min_num_rows_block(block),
+            keccak: KeccakCircuit::min_num_rows_block(block),
+            tx: TxCircuit::min_num_rows_block(block),
+            // The Exp circuit capacity is in steps, each assigned in several
+            // rows
+            exp: (
+                exp_rows,
+                max_exp_steps * OFFSET_INCREMENT + UNUSABLE_EXP_ROWS,
+            ),
+            pi: PiCircuit::min_num_rows_block(block),
+        }
+    }
+
     /// From the witness data, generate a SuperCircuit instance with all of the
     /// sub-circuits filled with their corresponding witnesses.
     ///
@@ -470,14 +574,12 @@ impl<F: Field> SuperCircuit<F> {
         geth_data: GethData,
         circuits_params: FixedCParams,
         mock_randomness: F,
-    ) -> Result<(u32, Self, Vec<Vec<F>>, CircuitInputBuilder<FixedCParams>), bus_mapping::Error>
+    ) -> Result<(u32, Self, Vec<Vec<F>>, CircuitInputBuilder<FixedCParams>), crate::error::Error>
     {
         let block_data =
             BlockData::new_from_geth_data_with_params(geth_data.clone(), circuits_params);
         let mut builder = block_data.new_circuit_input_builder();
-        builder
-            .handle_block(&geth_data.eth_block, &geth_data.geth_traces)
-            .expect("could not handle block tx");
+        builder.handle_block(&geth_data.eth_block, &geth_data.geth_traces)?;
 
         let ret = Self::build_from_circuit_input_builder(&builder, mock_randomness)?;
         Ok((ret.0, ret.1, ret.2, builder))
@@ -491,17 +593,48 @@ impl<F: Field> SuperCircuit<F> {
     pub fn build_from_circuit_input_builder(
         builder: &CircuitInputBuilder<FixedCParams>,
         mock_randomness: F,
-    ) -> Result<(u32, Self, Vec<Vec<F>>), bus_mapping::Error> {
-        let mut block = block_convert(builder).unwrap();
+    ) -> Result<(u32, Self, Vec<Vec<F>>), crate::error::Error> {
+        let mut block = tracing::info_span!("block_convert").in_scope(|| block_convert(builder))?;
         block.randomness = mock_randomness;
+        Ok(Self::build_from_witness_block(&block))
+    }
 
-        let (_, rows_needed) = Self::min_num_rows_block(&block);
+    /// Like [`Self::build_from_circuit_input_builder`], with the mock
+    /// randomness derived from `seed` and the seed recorded in the witness
+    /// block, so that a failing MockProver run can be replayed from it.
+    pub fn build_from_circuit_input_builder_with_seed(
+        builder: &CircuitInputBuilder<FixedCParams>,
+        seed: u64,
+    ) -> Result<(u32, Self, Vec<Vec<F>>), crate::error::Error> {
+        let mut block = tracing::info_span!("block_convert").in_scope(|| block_convert(builder))?;
+        block.seed_randomness(seed);
+        Ok(Self::build_from_witness_block(&block))
+    }
+
+    /// From a witness block, generate a SuperCircuit instance with all of the
+    /// sub-circuits filled with their corresponding witnesses.
+    ///
+    /// Also, return with it the minimum required SRS degree for the circuit and
+    /// the Public Inputs needed.
+    pub fn build_from_witness_block(block: &Block<F>) -> (u32, Self, Vec<Vec<F>>) {
+        let _span = tracing::info_span!("build_super_circuit").entered();
+        let circuit = SuperCircuit::new_from_block(block);
+
+        for (name, (rows, rows_with_padding)) in circuit.rows.iter() {
+            tracing::info!(circuit = name, rows, rows_with_padding, "sub-circuit rows");
+        }
+        metrics::record_block(
+            block.rws.0.values().map(Vec::len).sum(),
+            circuit
+                .rows
+                .iter()
+                .map(|(name, (_, rows_with_padding))| (name, rows_with_padding)),
+        );
+        let (_, rows_needed) = circuit.rows.max();
         let k = log2_ceil(Self::unusable_rows() + rows_needed);
         log::debug!("super circuit uses k = {}", k);
 
-        let circuit = SuperCircuit::new_from_block(&block);
-
         let instance = circuit.instance();
-        Ok((k, circuit, instance))
+        (k, circuit, instance)
     }
 }
@@ -0,0 +1,234 @@
+//! Named circuit parameters and their validation.
+//!
+//! Parameters that don't fit the degree of the circuit, or sub-circuit
+//! capacities below what the sub-circuit needs for an empty block, only fail
+//! at synthesis, with errors that don't point at the faulty parameter.
+//! [`ValidateParams::validate`] checks them upfront and reports every
+//! violation at once.
+
+use super::SuperCircuit;
+use crate::{
+    copy_circuit::{DISABLED_ROWS, UNUSED_ROWS},
+    keccak_circuit::KeccakCircuit,
+    util::SubCircuit,
+    witness::Block,
+};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use halo2_proofs::halo2curves::bn256::Fr;
+use std::{fmt, str::FromStr};
+
+/// Named [`FixedCParams`] with the degree they are meant to be proven with.
+///
+/// Presets come from parameters that are proven today.  Larger networks
+/// measure the rows of their blocks, for example with
+/// [`SuperCircuit::sub_circuit_rows`], and validate their own parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamsPreset {
+    /// Small blocks, to iterate on a circuit with the MockProver.
+    ///
+    /// The parameters of the circuit integration tests in
+    /// `integration-tests/src/integration_test_circuits.rs`, which prove
+    /// blocks of up to 4 transactions.
+    Dev,
+}
+
+impl ParamsPreset {
+    /// All the presets, from the smallest to the largest.
+    pub const ALL: [Self; 1] = [Self::Dev];
+
+    /// Name of the preset
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dev => "dev",
+        }
+    }
+
+    /// Degree of the [`SuperCircuit`] with the parameters of the preset
+    pub fn degree(&self) -> u32 {
+        match self {
+            Self::Dev => 19,
+        }
+    }
+
+    /// Parameters of the preset
+    pub fn params(&self) -> FixedCParams {
+        match self {
+            Self::Dev => FixedCParams {
+                max_rws: 5888,
+                max_txs: 4,
+                max_withdrawals: 4,
+                max_calldata: 512,
+                max_copy_rows: 5888,
+                max_exp_steps: 1000,
+                max_bytecode: 5000,
+                max_evm_rows: 10000,
+                max_keccak_rows: 38000,
+                max_vertical_circuit_rows: 0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ParamsPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ParamsPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name() == s)
+            .ok_or_else(|| format!("unknown circuit parameters preset {:?}", s))
+    }
+}
+
+/// Parameter that can't be proven
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamsViolation {
+    /// A sub-circuit needs more rows than the circuit of degree `k` has
+    DegreeTooSmall {
+        /// Name of the sub-circuit
+        circuit: &'static str,
+        /// Rows needed by the sub-circuit, including the unusable ones
+        rows: usize,
+        /// Degree of the circuit
+        k: u32,
+    },
+    /// A capacity is below the minimum needed by its sub-circuit
+    CapacityTooSmall {
+        /// Name of the [`FixedCParams`] field
+        param: &'static str,
+        /// Value of the parameter
+        value: usize,
+        /// Minimum value
+        min: usize,
+    },
+}
+
+impl fmt::Display for ParamsViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DegreeTooSmall { circuit, rows, k } => write!(
+                f,
+                "{} circuit needs {} rows, more than the {} rows of degree {}",
+                circuit,
+                rows,
+                1usize << k,
+                k
+            ),
+            Self::CapacityTooSmall { param, value, min } => {
+                write!(f, "{} is {}, below the minimum {}", param, value, min)
+            }
+        }
+    }
+}
+
+/// Validation of circuit parameters against a degree
+pub trait ValidateParams {
+    /// Check that the [`SuperCircuit`] of degree `k` fits the parameters,
+    /// returning all the violations.
+    fn validate(&self, k: u32) -> Result<(), Vec<ParamsViolation>>;
+}
+
+impl ValidateParams for FixedCParams {
+    fn validate(&self, k: u32) -> Result<(), Vec<ParamsViolation>> {
+        let mut violations = Vec::new();
+
+        let mut capacity = |param, value, min, dynamic| {
+            if value < min && !(dynamic && value == 0) {
+                violations.push(ParamsViolation::CapacityTooSmall { param, value, min });
+            }
+        };
+        // The Start row
+        capacity("max_rws", self.max_rws, 1, false);
+        capacity(
+            "max_copy_rows",
+            self.max_copy_rows,
+            DISABLED_ROWS + UNUSED_ROWS,
+            false,
+        );
+        // The Keccak circuit reserves two keccak_f's, and needs one for the
+        // padding
+        capacity(
+            "max_keccak_rows",
+            self.max_keccak_rows,
+            3 * KeccakCircuit::<Fr>::rows_per_keccak_f(),
+            true,
+        );
+
+        // The rows without padding are the ones needed by an empty block
+        let block = Block::<Fr> {
+            circuits_params: *self,
+            ..Default::default()
+        };
+        let rows = SuperCircuit::sub_circuit_rows(&block);
+        let unusable_rows = SuperCircuit::<Fr>::unusable_rows();
+        for (circuit, (min_rows, padded_rows)) in rows.iter() {
+            let rows = min_rows.max(padded_rows) + unusable_rows;
+            if rows > 1 << k {
+                violations.push(ParamsViolation::DegreeTooSmall { circuit, rows, k });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_presets_are_valid() {
+        for preset in ParamsPreset::ALL {
+            assert_eq!(preset.name().parse(), Ok(preset));
+            if let Err(violations) = preset.params().validate(preset.degree()) {
+                panic!("{} preset: {:?}", preset, violations);
+            }
+        }
+        assert!("production".parse::<ParamsPreset>().is_err());
+    }
+
+    #[test]
+    fn params_violations() {
+        let params = FixedCParams {
+            max_copy_rows: 3,
+            max_keccak_rows: 1,
+            ..ParamsPreset::Dev.params()
+        };
+        let violations = params.validate(18).unwrap_err();
+        assert!(violations.contains(&ParamsViolation::CapacityTooSmall {
+            param: "max_copy_rows",
+            value: 3,
+            min: 4,
+        }));
+        assert!(violations.iter().any(|violation| matches!(
+            violation,
+            ParamsViolation::CapacityTooSmall {
+                param: "max_keccak_rows",
+                ..
+            }
+        )));
+        // The Tx circuit needs more than 2^18 rows for its range table
+        assert!(violations.iter().any(|violation| matches!(
+            violation,
+            ParamsViolation::DegreeTooSmall { circuit: "tx", .. }
+        )));
+
+        // Dynamic sizes are not checked
+        let params = FixedCParams {
+            max_keccak_rows: 0,
+            max_evm_rows: 0,
+            ..ParamsPreset::Dev.params()
+        };
+        assert_eq!(params.validate(19), Ok(()));
+    }
+}
@@ -1,4 +1,5 @@
 pub use super::*;
+use crate::witness::{randomness_from_seed, Rw};
 use ethers_signers::{LocalWallet, Signer};
 use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
 use log::error;
@@ -23,6 +24,16 @@ fn super_circuit_degree() {
     log::info!("super circuit degree: {}", cs.degree());
     log::info!("super circuit minimum_rows: {}", cs.minimum_rows());
     assert!(cs.degree() <= 10);
+
+    // Other tests configure circuits concurrently, with the same lookups
+    let lookups = metrics::snapshot().lookups;
+    assert_eq!(
+        ["keccak", "pi", "tx", "bytecode", "copy", "state", "exp", "evm"]
+            .iter()
+            .map(|circuit| lookups[*circuit])
+            .sum::<usize>(),
+        cs.lookups().len()
+    );
 }
 
 fn test_super_circuit(block: GethData, circuits_params: FixedCParams, mock_randomness: Fr) {
@@ -36,6 +47,48 @@ fn test_super_circuit(block: GethData, circuits_params: FixedCParams, mock_rando
     }
 }
 
+#[test]
+fn super_circuit_rows() {
+    let circuits_params = FixedCParams {
+        max_txs: 1,
+        max_withdrawals: 5,
+        max_calldata: 32,
+        max_rws: 256,
+        max_copy_rows: 256,
+        max_exp_steps: 256,
+        max_bytecode: 512,
+        max_evm_rows: 0,
+        max_keccak_rows: 0,
+        max_vertical_circuit_rows: 0,
+    };
+    let (k, circuit, _, _) =
+        SuperCircuit::<Fr>::build(block_1tx(), circuits_params, Fr::from(TEST_MOCK_RANDOMNESS))
+            .unwrap();
+    let block = circuit.evm_circuit.block.as_ref().unwrap();
+
+    assert_eq!(circuit.rows, SuperCircuit::sub_circuit_rows(block));
+    // The Exp circuit capacity is reported in rows, not in steps
+    assert_eq!(
+        circuit.rows.exp.1,
+        256 * OFFSET_INCREMENT + UNUSABLE_EXP_ROWS
+    );
+    assert!(circuit.rows.exp.0 <= circuit.rows.exp.1);
+    assert_eq!(
+        circuit.rows.max(),
+        SuperCircuit::<Fr>::min_num_rows_block(block)
+    );
+    assert_eq!(
+        k,
+        log2_ceil(SuperCircuit::<Fr>::unusable_rows() + circuit.rows.max().1)
+    );
+    let snapshot = metrics::snapshot();
+    assert!(snapshot.blocks > 0);
+    assert!(circuit
+        .rows
+        .iter()
+        .all(|(name, _)| snapshot.rows.contains_key(name)));
+}
+
 pub(crate) fn block_1tx() -> GethData {
     let mut rng = ChaCha20Rng::seed_from_u64(2);
 
@@ -124,6 +177,66 @@ fn block_2tx() -> GethData {
 
 const TEST_MOCK_RANDOMNESS: u64 = 0x100;
 
+// A failing MockProver run is replayed from the seed recorded in its witness
+// block, with the same challenges and the same failures.
+#[cfg(feature = "mock-challenge")]
+#[ignore]
+#[test]
+fn serial_test_super_circuit_replay_from_seed() {
+    let block = block_1tx();
+    let circuits_params = FixedCParams {
+        max_txs: 1,
+        max_withdrawals: 5,
+        max_calldata: 32,
+        max_rws: 256,
+        max_copy_rows: 256,
+        max_exp_steps: 256,
+        max_bytecode: 512,
+        max_evm_rows: 0,
+        max_keccak_rows: 0,
+        max_vertical_circuit_rows: 0,
+    };
+    let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), circuits_params)
+        .new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+
+    // Run the circuit with a stack value changed in the rw table, which
+    // breaks the rw lookups of the EVM circuit
+    let run = |seed| {
+        let (k, mut circuit, instance) =
+            SuperCircuit::<Fr>::build_from_circuit_input_builder_with_seed(&builder, seed).unwrap();
+        let block = circuit.evm_circuit.block.clone().unwrap();
+        let value = circuit
+            .state_circuit
+            .rows
+            .iter_mut()
+            .find_map(|rw| match rw {
+                Rw::Stack { value, .. } => Some(value),
+                _ => None,
+            })
+            .unwrap();
+        *value = *value + Word::one();
+        let failures = MockProver::run(k, &circuit, instance)
+            .unwrap()
+            .verify()
+            .unwrap_err();
+        (block, failures)
+    };
+
+    let seed = rand::random();
+    let (block, failures) = run(seed);
+    assert_eq!(block.randomness_seed, Some(seed));
+    assert_eq!(block.randomness, randomness_from_seed::<Fr>(seed));
+
+    // The seed is recovered from the serialized witness block
+    let block: Block<Fr> = serde_json::from_str(&serde_json::to_string(&block).unwrap()).unwrap();
+    let (replayed_block, replayed_failures) = run(block.randomness_seed.unwrap());
+    assert_eq!(replayed_block.randomness, block.randomness);
+    assert_eq!(replayed_failures, failures, "seed {}", seed);
+}
+
 // High memory usage test.  Run in serial with:
 // `cargo test [...] serial_ -- --ignored --test-threads 1`
 #[ignore]
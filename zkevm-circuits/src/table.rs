@@ -1,7 +1,6 @@
 //! Table definitions used cross-circuits
 
 use crate::{
-    copy_circuit::util::number_or_hash_to_word,
     evm_circuit::util::rlc,
     impl_expr,
     util::{build_tx_log_address, keccak, word::WordLoHi, Challenges},
@@ -20,6 +19,7 @@ use halo2_proofs::{
     poly::Rotation,
 };
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::array;
 use strum_macros::{EnumCount, EnumIter};
 
@@ -120,7 +120,9 @@ impl<F: Field, C: Into<Column<Any>> + Copy, const W: usize> LookupTable<F> for [
 }
 
 /// Tag for an AccountField in RwTable
-#[derive(Clone, Copy, Debug, EnumIter, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    Clone, Copy, Debug, EnumIter, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
 pub enum AccountFieldTag {
     /// Nonce field
     Nonce = 1,
@@ -134,7 +136,7 @@ pub enum AccountFieldTag {
 impl_expr!(AccountFieldTag);
 
 /// Tag for a CallContextField in RwTable
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum CallContextFieldTag {
     /// RwCounterEndOfReversion
     RwCounterEndOfReversion = 1,
@@ -28,6 +28,21 @@ pub enum BlockContextFieldTag {
 }
 impl_expr!(BlockContextFieldTag);
 
+impl BlockContextFieldTag {
+    /// Fields with a single value per block, in the order of the block table.
+    /// [`BlockContextFieldTag::BlockHash`] is indexed by block number instead.
+    pub const SINGLE_VALUE_FIELDS: [Self; 8] = [
+        Self::Coinbase,
+        Self::Timestamp,
+        Self::Number,
+        Self::Difficulty,
+        Self::GasLimit,
+        Self::BaseFee,
+        Self::ChainId,
+        Self::WithdrawalRoot,
+    ];
+}
+
 /// Table with Block header fields
 #[derive(Clone, Debug)]
 pub struct BlockTable {
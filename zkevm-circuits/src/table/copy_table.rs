@@ -1,4 +1,5 @@
 use super::*;
+use bus_mapping::circuit_input_builder::NumberOrHash;
 use gadgets::binary_number::AsBits;
 
 // The row also includes the 3 column bits from the tag
@@ -318,3 +319,11 @@ impl<F: Field> LookupTable<F> for CopyTable {
         ]
     }
 }
+
+/// Encode the type `NumberOrHash` into a field element
+pub fn number_or_hash_to_word<F: Field>(v: &NumberOrHash) -> WordLoHi<Value<F>> {
+    match v {
+        NumberOrHash::Number(n) => WordLoHi::from(*n as u64).into_value(),
+        NumberOrHash::Hash(h) => WordLoHi::from(*h).into_value(),
+    }
+}
@@ -9,7 +9,8 @@ pub struct KeccakTable {
     pub input_rlc: Column<Advice>, // RLC of input bytes
     /// Byte array input length
     pub input_len: Column<Advice>,
-    /// Output hash word
+    /// Output hash word, as its lo/hi 128-bit halves.  Consumers look it up
+    /// directly, without a RLC of the digest.
     pub output: WordLoHi<Column<Advice>>,
 }
 
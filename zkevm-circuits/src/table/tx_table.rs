@@ -1,5 +1,12 @@
 use super::*;
 
+/// Number of static fields per tx: [nonce, gas, gas_price,
+/// caller_address, callee_address, is_create, value, call_data_length,
+/// call_data_gas_cost, tx_sign_hash].
+/// Note that call data bytes are laid out in the TxTable after all the static
+/// fields arranged by txs.
+pub(crate) const TX_LEN: usize = 10;
+
 /// Tag used to identify each field in the transaction in a row of the
 /// transaction table.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -33,7 +40,7 @@ pub enum TxFieldTag {
 impl_expr!(TxFieldTag);
 
 /// Tag for a TxLogField in RwTable
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum TxLogFieldTag {
     /// Address field
     Address = 1,
@@ -45,7 +52,7 @@ pub enum TxLogFieldTag {
 impl_expr!(TxLogFieldTag);
 
 /// Tag for a TxReceiptField in RwTable
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumCount)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumCount, Serialize, Deserialize)]
 pub enum TxReceiptFieldTag {
     /// Tx result
     PostStateOrStatus = 1,
@@ -34,6 +34,12 @@ impl<const N_BITS: usize> UXTable<N_BITS> {
     }
 }
 
+impl<const N_BITS: usize> From<UXTable<N_BITS>> for Column<Fixed> {
+    fn from(table: UXTable<N_BITS>) -> Self {
+        table.col
+    }
+}
+
 impl<F: Field, const N_BITS: usize> LookupTable<F> for UXTable<N_BITS> {
     fn columns(&self) -> Vec<Column<Any>> {
         vec![self.col.into()]
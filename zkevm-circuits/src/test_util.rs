@@ -9,6 +9,7 @@ use crate::{
 use bus_mapping::{
     circuit_input_builder::{FeatureConfig, FixedCParams},
     mock::BlockData,
+    state_db::CodeDB,
 };
 use eth_types::geth_types::GethData;
 use itertools::all;
@@ -22,6 +23,35 @@ use halo2_proofs::{
 };
 use mock::TestContext;
 
+/// Call `$check::<C>(name)` for every sub-circuit `C` and the `SuperCircuit`,
+/// to run an audit of their configuration.
+#[cfg(test)]
+macro_rules! for_each_circuit {
+    ($check:ident) => {{
+        use crate::{
+            bytecode_circuit::BytecodeCircuit, copy_circuit::CopyCircuit, evm_circuit::EvmCircuit,
+            exp_circuit::ExpCircuit, keccak_circuit::KeccakCircuit, pi_circuit::PiCircuit,
+            state_circuit::StateCircuit, super_circuit::SuperCircuit, tx_circuit::TxCircuit,
+        };
+        use halo2_proofs::halo2curves::bn256::Fr;
+
+        $check::<BytecodeCircuit<Fr>>("bytecode");
+        $check::<CopyCircuit<Fr>>("copy");
+        $check::<EvmCircuit<Fr>>("evm");
+        $check::<ExpCircuit<Fr>>("exp");
+        $check::<KeccakCircuit<Fr>>("keccak");
+        $check::<PiCircuit<Fr>>("pi");
+        $check::<StateCircuit<Fr>>("state");
+        $check::<TxCircuit<Fr>>("tx");
+        $check::<SuperCircuit<Fr>>("super");
+    }};
+}
+
+pub mod challenge_phase;
+pub mod gas_meter;
+pub mod layout;
+pub mod lookup_integrity;
+
 #[cfg(test)]
 #[ctor::ctor]
 fn init_env_logger() {
@@ -169,7 +199,9 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
             .ok_or(CircuitTestError::NotEnoughAttributes)?;
         let block: GethData = block.clone().into();
         let builder = BlockData::new_from_geth_data(block.clone())
-            .new_circuit_input_builder_with_feature(self.feature_config.unwrap_or_default());
+            .new_circuit_input_builder_with_feature(
+                self.feature_config.clone().unwrap_or_default(),
+            );
         let builder = builder
             .handle_block(&block.eth_block, &block.geth_traces)
             .map_err(|err| CircuitTestError::CannotHandleBlock(err.to_string()))?;
@@ -247,7 +279,22 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
     pub fn run_with_result(self) -> Result<(), CircuitTestError> {
         let block = self.build_block()?;
 
-        self.run_evm_circuit_test(block.clone())?;
+        let bytecodes = block.bytecodes.clone();
+        // The traces are only known when the block is built from the test context
+        let geth_traces = match (&self.block, &self.test_ctx) {
+            (None, Some(ctx)) => GethData::from(ctx.clone()).geth_traces,
+            _ => Vec::new(),
+        };
+        let gas_divergence = gas_meter::check_gas_meter(&block, &geth_traces).err();
+        self.run_evm_circuit_test(block.clone()).map_err(|err| {
+            if matches!(err, CircuitTestError::VerificationFailed { .. }) {
+                log_disassembly(bytecodes);
+                if let Some(divergence) = gas_divergence {
+                    log::error!("first gas divergence: {}", divergence);
+                }
+            }
+            err
+        })?;
         self.run_state_circuit_test(block)
     }
 
@@ -257,7 +304,15 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
     }
 }
 
-#[derive(Debug)]
+/// Log the disassembly of the codes of a block whose EVM circuit failed, to
+/// locate the failing steps by their pc.
+fn log_disassembly(bytecodes: CodeDB) {
+    for code in bytecodes.into_iter().filter(|code| code.codesize() > 0) {
+        log::error!("code {:?}:\n{}", code.hash_h256(), code.disassemble());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Circuits to test in [`CircuitTestBuilder`]
 pub enum Circuit {
     /// EVM circuit
@@ -0,0 +1,177 @@
+//! Challenge phase audit of circuit configurations.
+//!
+//! A challenge usable after a phase is sampled once the advice columns of
+//! that phase and the previous ones are committed.  A constraint that
+//! combines a challenge only with advice columns committed before it is
+//! sampled, like an RLC of first phase bytes compared against a first phase
+//! accumulator, can't be assigned by an honest prover.  It is only caught
+//! when proving for real, since the MockProver knows every challenge from the
+//! start.
+//! [`check_challenge_phases`] scans the gates and lookups of a configured
+//! [`ConstraintSystem`] for such expressions.
+
+use eth_types::Field;
+use halo2_proofs::{
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, ConstraintSystem, Expression},
+};
+use std::fmt;
+
+/// Gate constraint or lookup that uses a challenge without any advice column
+/// committed after the challenge is sampled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengePhaseMisuse {
+    /// Name of the gate constraint or of the lookup
+    pub name: String,
+    /// Latest phase after which a used challenge is usable
+    pub challenge_phase: u8,
+    /// Latest phase of the queried advice columns, if any
+    pub advice_phase: Option<u8>,
+}
+
+impl fmt::Display for ChallengePhaseMisuse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: uses a challenge of phase {} with advice of phase {:?}",
+            self.name, self.challenge_phase, self.advice_phase
+        )
+    }
+}
+
+/// Latest phases of the advice columns and challenges of expressions
+#[derive(Debug, Clone, Copy, Default)]
+struct Phases {
+    advice: Option<u8>,
+    challenge: Option<u8>,
+}
+
+impl Phases {
+    fn merge(self, other: Self) -> Self {
+        Self {
+            advice: self.advice.max(other.advice),
+            challenge: self.challenge.max(other.challenge),
+        }
+    }
+
+    fn misuse(self, name: String) -> Option<ChallengePhaseMisuse> {
+        let challenge_phase = self.challenge?;
+        let before_challenge = self.advice.map_or(true, |advice| advice <= challenge_phase);
+        before_challenge.then_some(ChallengePhaseMisuse {
+            name,
+            challenge_phase,
+            advice_phase: self.advice,
+        })
+    }
+}
+
+fn phases<F: Field>(advice_column_phase: &[u8], expr: &Expression<F>) -> Phases {
+    expr.evaluate(
+        &|_| Phases::default(),
+        &|_| Phases::default(),
+        &|_| Phases::default(),
+        &|advice_query| Phases {
+            advice: Some(advice_column_phase[advice_query.column_index()]),
+            challenge: None,
+        },
+        &|_| Phases::default(),
+        &|challenge| Phases {
+            advice: None,
+            challenge: Some(challenge.phase()),
+        },
+        &|a| a,
+        &|a, b| a.merge(b),
+        &|a, b| a.merge(b),
+        &|a, _| a,
+    )
+}
+
+/// Check the gates and lookups of `cs`, returning every constraint and lookup
+/// that uses a challenge with only advice columns committed before the
+/// challenge is sampled.
+///
+/// The input and table expressions of a lookup are checked together, since
+/// an RLC input is usually matched against a later phase table column.
+pub fn check_challenge_phases<F: Field>(
+    cs: &ConstraintSystem<F>,
+) -> Result<(), Vec<ChallengePhaseMisuse>> {
+    let advice_column_phase = cs.advice_column_phase();
+    let mut misuses = Vec::new();
+    for gate in cs.gates() {
+        for (index, polynomial) in gate.polynomials().iter().enumerate() {
+            let name = format!("{} constraint {}", gate.name(), index);
+            misuses.extend(phases(&advice_column_phase, polynomial).misuse(name));
+        }
+    }
+    for lookup in cs.lookups() {
+        let lookup_phases = lookup
+            .input_expressions()
+            .iter()
+            .chain(lookup.table_expressions())
+            .map(|expr| phases(&advice_column_phase, expr))
+            .fold(Phases::default(), Phases::merge);
+        misuses.extend(lookup_phases.misuse(lookup.name().to_string()));
+    }
+    if misuses.is_empty() {
+        Ok(())
+    } else {
+        Err(misuses)
+    }
+}
+
+/// Check the challenge phases of the circuit `C` configured with `params`.
+pub fn check_circuit_challenge_phases<C: Circuit<Fr>>(
+    params: C::Params,
+) -> Result<(), Vec<ChallengePhaseMisuse>> {
+    let mut cs = ConstraintSystem::default();
+    C::configure_with_params(&mut cs, params);
+    check_challenge_phases(&cs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        plonk::{FirstPhase, SecondPhase},
+        poly::Rotation,
+    };
+
+    #[test]
+    fn challenge_phase_misuse() {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        let byte = cs.advice_column_in(FirstPhase);
+        let early_acc = cs.advice_column_in(FirstPhase);
+        let acc = cs.advice_column_in(SecondPhase);
+        let challenge = cs.challenge_usable_after(FirstPhase);
+        cs.create_gate("rlc", |meta| {
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let challenge = meta.query_challenge(challenge);
+            let early_acc = meta.query_advice(early_acc, Rotation::cur());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            let early_acc_next = meta.query_advice(early_acc, Rotation::next());
+            let acc_next = meta.query_advice(acc, Rotation::next());
+            vec![
+                ("sound", acc_next - (acc * challenge.clone() + byte.clone())),
+                ("unsound", early_acc_next - (early_acc * challenge + byte)),
+            ]
+        });
+
+        let misuses = check_challenge_phases(&cs).unwrap_err();
+        assert_eq!(misuses.len(), 1);
+        assert_eq!(misuses[0].name, "rlc constraint 1");
+        assert_eq!(misuses[0].challenge_phase, 0);
+        assert_eq!(misuses[0].advice_phase, Some(0));
+    }
+
+    fn check<C: Circuit<Fr>>(name: &str) {
+        if let Err(misuses) = check_circuit_challenge_phases::<C>(C::Params::default()) {
+            let misuses: Vec<String> = misuses.iter().map(ToString::to_string).collect();
+            panic!("{} circuit:\n{}", name, misuses.join("\n"));
+        }
+    }
+
+    #[test]
+    fn circuits_challenge_phases() {
+        for_each_circuit!(check);
+    }
+}
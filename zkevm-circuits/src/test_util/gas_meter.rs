@@ -0,0 +1,272 @@
+//! Check of the gas metering of a witness block.
+//!
+//! A gas mismatch only surfaces in the MockProver as an unsatisfied
+//! constraint of the step transition, far from its cause.
+//! [`check_gas_meter`] walks the opcode steps of the witness [`Block`] along
+//! the geth traces of its transactions and reports the first step whose gas
+//! diverges from geth or from what the EVM circuit constrains, with its pc
+//! and opcode.
+
+use crate::{evm_circuit::step::HasExecutionState, witness::Block};
+use bus_mapping::{
+    circuit_input_builder::{ExecState, ExecStep, FeatureConfig},
+    error::{ExecError, OogError},
+};
+use eth_types::{evm_types::OpcodeId, Field, GethExecStep, GethExecTrace};
+use std::fmt;
+
+/// Way in which the gas of a step diverges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasDivergenceKind {
+    /// The step is not the geth step at its position in the trace
+    Trace {
+        /// pc of the geth step
+        pc: u64,
+        /// Opcode of the geth step
+        opcode: OpcodeId,
+    },
+    /// The gas left or the gas cost of the step is not the one of its geth
+    /// step
+    Geth {
+        /// Gas left of the geth step
+        gas: u64,
+        /// Gas cost of the geth step
+        gas_cost: u64,
+    },
+    /// The gas cost of the step is not the one fixed by the gadget of its
+    /// execution state
+    GasCost {
+        /// Gas cost fixed by the gadget
+        gadget: u64,
+    },
+    /// The gas left of the step is not the gas left minus the gas cost of the
+    /// previous step in the same call, as the EVM circuit constrains
+    Transition {
+        /// Gas left expected from the previous step
+        expected: u64,
+    },
+}
+
+/// First opcode step of a transaction whose gas in the witness diverges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasDivergence {
+    /// Id of the transaction, starting at 1
+    pub tx_id: u64,
+    /// Index of the step in the steps of the transaction
+    pub step_index: usize,
+    /// pc of the step
+    pub pc: u64,
+    /// Opcode of the step
+    pub opcode: OpcodeId,
+    /// Gas left before the step
+    pub gas_left: u64,
+    /// Gas cost of the step
+    pub gas_cost: u64,
+    /// Divergence of the step
+    pub kind: GasDivergenceKind,
+}
+
+impl fmt::Display for GasDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tx {} step {} at pc {} ({:?}, gas left {}, gas cost {}): {:?}",
+            self.tx_id,
+            self.step_index,
+            self.pc,
+            self.opcode,
+            self.gas_left,
+            self.gas_cost,
+            self.kind
+        )
+    }
+}
+
+/// Check that the gas of every opcode step of the witness matches its step in
+/// the geth trace of the transaction, the gas cost of its gadget, when the
+/// gadget fixes it, and the gas left by the previous step, returning the first
+/// divergent step otherwise.
+///
+/// The transactions without a trace in `geth_traces` are only checked against
+/// the gadgets.
+pub fn check_gas_meter<F: Field>(
+    block: &Block<F>,
+    geth_traces: &[GethExecTrace],
+) -> Result<(), GasDivergence> {
+    for (tx_index, tx) in block.txs.iter().enumerate() {
+        // Each opcode step is generated from the next geth step of the trace
+        let mut geth_steps = geth_traces
+            .get(tx_index)
+            .map(|trace| trace.struct_logs.as_slice())
+            .unwrap_or_default()
+            .iter();
+        // Precompiles and virtual steps are not opcode steps
+        let mut prev_step = None;
+        for (step_index, step) in tx.steps().iter().enumerate() {
+            let ExecState::Op(opcode) = step.exec_state else {
+                continue;
+            };
+            // The out of gas step of a precompile call is generated from the
+            // geth step following the call, with the gas of the precompile
+            let geth_step = if step.error == Some(ExecError::OutOfGas(OogError::Precompile)) {
+                None
+            } else {
+                geth_steps.next()
+            };
+            if let Some(kind) = geth_divergence(step, opcode, geth_step)
+                .or_else(|| step_divergence(step, prev_step, &block.feature_config))
+            {
+                return Err(GasDivergence {
+                    tx_id: tx.id,
+                    step_index,
+                    pc: step.pc,
+                    opcode,
+                    gas_left: step.gas_left,
+                    gas_cost: step.gas_cost,
+                    kind,
+                });
+            }
+            prev_step = Some(step);
+        }
+    }
+    Ok(())
+}
+
+fn geth_divergence(
+    step: &ExecStep,
+    opcode: OpcodeId,
+    geth_step: Option<&GethExecStep>,
+) -> Option<GasDivergenceKind> {
+    let geth_step = geth_step?;
+    if (geth_step.pc, geth_step.op) != (step.pc, opcode) {
+        return Some(GasDivergenceKind::Trace {
+            pc: geth_step.pc,
+            opcode: geth_step.op,
+        });
+    }
+    (geth_step.gas != step.gas_left || geth_step.gas_cost != step.gas_cost).then_some(
+        GasDivergenceKind::Geth {
+            gas: geth_step.gas,
+            gas_cost: geth_step.gas_cost,
+        },
+    )
+}
+
+fn step_divergence(
+    step: &ExecStep,
+    prev_step: Option<&ExecStep>,
+    feature_config: &FeatureConfig,
+) -> Option<GasDivergenceKind> {
+    if let Some(usage) = step.execution_state().fixed_usage(feature_config) {
+        if step.gas_cost != usage.gas_cost {
+            return Some(GasDivergenceKind::GasCost {
+                gadget: usage.gas_cost,
+            });
+        }
+    }
+    // Calls and creations forward gas to the callee and get the unused gas
+    // back, and errors consume all the gas of the call.
+    let prev_step = prev_step.filter(|prev_step| {
+        prev_step.call_index == step.call_index
+            && prev_step.error.is_none()
+            && !matches!(prev_step.exec_state, ExecState::Op(op) if op.is_call_or_create())
+    })?;
+    let expected = prev_step.gas_left.checked_sub(prev_step.gas_cost);
+    (expected != Some(step.gas_left)).then(|| GasDivergenceKind::Transition {
+        expected: expected.unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::block_convert;
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use mock::TestContext;
+
+    fn block() -> (Block<Fr>, Vec<GethExecTrace>) {
+        let code = bytecode! {
+            PUSH1(0x20)
+            PUSH1(0x00)
+            SHA3
+            POP
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        (block_convert(&builder).unwrap(), block.geth_traces)
+    }
+
+    #[test]
+    fn gas_meter_ok() {
+        let (block, geth_traces) = block();
+        assert_eq!(check_gas_meter(&block, &geth_traces), Ok(()));
+    }
+
+    #[test]
+    fn gas_meter_divergent_geth() {
+        let (mut block, geth_traces) = block();
+        let step = &mut block.txs[0].steps_mut()[3];
+        assert_eq!(step.exec_state, ExecState::Op(OpcodeId::SHA3));
+        step.gas_cost += 1;
+
+        let divergence = check_gas_meter(&block, &geth_traces).unwrap_err();
+        let geth_step = &geth_traces[0].struct_logs[2];
+        assert_eq!(divergence.step_index, 3);
+        assert_eq!(divergence.pc, geth_step.pc);
+        assert_eq!(divergence.opcode, OpcodeId::SHA3);
+        assert_eq!(
+            divergence.kind,
+            GasDivergenceKind::Geth {
+                gas: geth_step.gas,
+                gas_cost: geth_step.gas_cost,
+            }
+        );
+    }
+
+    #[test]
+    fn gas_meter_divergent_gas_cost() {
+        let (mut block, _) = block();
+        // The POP step, after BeginTx, the two pushes and SHA3
+        let step = &mut block.txs[0].steps_mut()[4];
+        assert_eq!(step.exec_state, ExecState::Op(OpcodeId::POP));
+        step.gas_cost += 1;
+
+        // Without the trace, only the gadget can tell the divergence
+        let divergence = check_gas_meter(&block, &[]).unwrap_err();
+        assert_eq!(divergence.step_index, 4);
+        assert_eq!(divergence.opcode, OpcodeId::POP);
+        assert_eq!(
+            divergence.kind,
+            GasDivergenceKind::GasCost {
+                gadget: OpcodeId::POP.constant_gas_cost()
+            }
+        );
+    }
+
+    #[test]
+    fn gas_meter_divergent_transition() {
+        let (mut block, _) = block();
+        // SHA3 has a dynamic gas cost, so only the next step diverges
+        let step = &mut block.txs[0].steps_mut()[3];
+        assert_eq!(step.exec_state, ExecState::Op(OpcodeId::SHA3));
+        step.gas_cost += 1;
+
+        let divergence = check_gas_meter(&block, &[]).unwrap_err();
+        assert_eq!(divergence.step_index, 4);
+        assert_eq!(divergence.opcode, OpcodeId::POP);
+        assert_eq!(
+            divergence.kind,
+            GasDivergenceKind::Transition {
+                expected: divergence.gas_left - 1
+            }
+        );
+    }
+}
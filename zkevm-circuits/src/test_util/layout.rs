@@ -0,0 +1,359 @@
+//! Layout snapshots of circuit configurations.
+//!
+//! [`layout_summary`] describes the columns, gates, lookups and shuffles of
+//! a circuit configuration, and [`region_layout`] the regions assigned by a
+//! circuit. [`check_layout_snapshot`] compares them against a golden file in
+//! `src/test_util/layouts`, so that a change altering the layout of a
+//! circuit is noticed in review. Run the tests with `UPDATE_LAYOUTS=1` to
+//! write the golden files after an intended change.
+
+use halo2_proofs::{
+    circuit::Value,
+    halo2curves::bn256::Fr,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
+        Fixed, FloorPlanner, Instance, Selector,
+    },
+};
+use std::{collections::HashSet, env, fmt::Write, fs, path::PathBuf};
+
+/// Environment variable that makes [`check_layout_snapshot`] overwrite the
+/// golden files.
+pub const UPDATE_LAYOUTS_ENV: &str = "UPDATE_LAYOUTS";
+
+/// Human readable summary of the layout of the circuit `C` configured with
+/// `params`.
+pub fn layout_summary<C: Circuit<Fr>>(params: C::Params) -> String {
+    let mut cs = ConstraintSystem::default();
+    C::configure_with_params(&mut cs, params);
+
+    let mut summary = String::new();
+    let mut line = |text: String| writeln!(summary, "{}", text).unwrap();
+    line(format!("degree: {}", cs.degree()));
+    line(format!("instance columns: {}", cs.num_instance_columns()));
+    line(format!("advice columns: {}", cs.num_advice_columns()));
+    line(format!("fixed columns: {}", cs.num_fixed_columns()));
+    line(format!("selectors: {}", cs.num_selectors()));
+    line(format!("challenges: {}", cs.num_challenges()));
+    line(format!(
+        "permutation columns: {}",
+        cs.permutation().get_columns().len()
+    ));
+    line(format!("gates: {}", cs.gates().len()));
+    for gate in cs.gates() {
+        line(format!(
+            "  {}: {} constraints",
+            gate.name(),
+            gate.polynomials().len()
+        ));
+    }
+    line(format!("lookups: {}", cs.lookups().len()));
+    for lookup in cs.lookups() {
+        line(format!("  {}", lookup.name()));
+    }
+    line(format!("shuffles: {}", cs.shuffles().len()));
+    for shuffle in cs.shuffles() {
+        line(format!("  {}", shuffle.name()));
+    }
+    summary
+}
+
+/// Rows and columns assigned in a region
+#[derive(Debug, Default)]
+struct RegionUsage {
+    name: String,
+    rows: Option<(usize, usize)>,
+    columns: HashSet<Column<Any>>,
+}
+
+/// [`Assignment`] recording the cells assigned in each region, without
+/// their values.
+#[derive(Debug, Default)]
+struct RegionRecorder {
+    regions: Vec<RegionUsage>,
+    current: Option<usize>,
+}
+
+impl RegionRecorder {
+    /// Extend the current region to `row`, if in a region
+    fn use_row(&mut self, row: usize) -> Option<&mut RegionUsage> {
+        let region = &mut self.regions[self.current?];
+        region.rows = Some(match region.rows {
+            Some((first, last)) => (first.min(row), last.max(row)),
+            None => (row, row),
+        });
+        Some(region)
+    }
+
+    fn assign_cell(&mut self, column: Column<Any>, row: usize) {
+        if let Some(region) = self.use_row(row) {
+            region.columns.insert(column);
+        }
+    }
+}
+
+impl Assignment<Fr> for RegionRecorder {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current = Some(self.regions.len());
+        self.regions.push(RegionUsage {
+            name: name_fn().into(),
+            ..Default::default()
+        });
+    }
+
+    fn annotate_column<A, AR>(&mut self, _annotation: A, _column: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+    }
+
+    fn exit_region(&mut self) {
+        self.current = None;
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _annotation: A,
+        _selector: &Selector,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Selectors only become fixed columns when they are compressed, so
+        // they only count in the rows of the region
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _column: Column<Instance>, _row: usize) -> Result<Value<Fr>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<Fr>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.assign_cell(column.into(), row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<Fr>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.assign_cell(column.into(), row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _left_column: Column<Any>,
+        _left_row: usize,
+        _right_column: Column<Any>,
+        _right_row: usize,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _column: Column<Fixed>,
+        _row: usize,
+        _to: Value<Assigned<Fr>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_challenge(&self, _challenge: Challenge) -> Value<Fr> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, _name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _gadget_name: Option<String>) {}
+}
+
+/// Human readable summary of the regions assigned by `circuit`: for each
+/// region in assignment order, its first row, its number of rows and its
+/// number of advice and fixed columns.
+///
+/// The values are not computed, like during key generation, so the layout
+/// only depends on the shape of the witness of `circuit`.
+pub fn region_layout<C: Circuit<Fr>>(circuit: &C) -> String {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure_with_params(&mut cs, circuit.params());
+    let mut recorder = RegionRecorder::default();
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, cs.constants().clone())
+        .expect("circuit synthesis failed");
+
+    let mut layout = String::new();
+    writeln!(layout, "regions: {}", recorder.regions.len()).unwrap();
+    for region in recorder.regions {
+        let (offset, rows) = region
+            .rows
+            .map_or((0, 0), |(first, last)| (first, last - first + 1));
+        writeln!(
+            layout,
+            "  {}: offset {}, {} rows, {} columns",
+            region.name,
+            offset,
+            rows,
+            region.columns.len()
+        )
+        .unwrap();
+    }
+    layout
+}
+
+fn golden_file(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/test_util/layouts")
+        .join(format!("{}.txt", name))
+}
+
+/// Line diff between `expected` and `actual`, with removed lines prefixed by
+/// `-` and added lines by `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    // lcs[i][j] is the longest common subsequence of expected[i..] and
+    // actual[j..]
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = String::new();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            writeln!(diff, "-{}", expected[i]).unwrap();
+            i += 1;
+        } else {
+            writeln!(diff, "+{}", actual[j]).unwrap();
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Compare `summary` with the golden file of the circuit `name`, panicking
+/// with a diff when they differ.
+///
+/// The golden file is only written when [`UPDATE_LAYOUTS_ENV`] is set, a
+/// missing golden file is an error.
+pub fn check_layout_snapshot(name: &str, summary: &str) {
+    let path = golden_file(name);
+    if env::var_os(UPDATE_LAYOUTS_ENV).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, summary).unwrap();
+        log::warn!("layout snapshot written to {}", path.display());
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "no layout snapshot of the {} circuit at {} ({}), rerun with {}=1 to write it",
+            name,
+            path.display(),
+            err,
+            UPDATE_LAYOUTS_ENV
+        )
+    });
+    if expected != summary {
+        panic!(
+            "layout of the {} circuit changed, rerun with {}=1 if intended:\n{}",
+            name,
+            UPDATE_LAYOUTS_ENV,
+            diff(&expected, summary)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        super_circuit::test::block_1tx,
+        util::SubCircuit,
+        witness::{block_convert, Block},
+    };
+    use bus_mapping::{circuit_input_builder::FixedCParams, mock::BlockData};
+
+    /// Witness of the one-transaction block whose regions are snapshotted
+    fn snapshot_block() -> Block<Fr> {
+        let block = block_1tx();
+        let params = FixedCParams {
+            max_txs: 1,
+            max_withdrawals: 5,
+            max_calldata: 32,
+            max_rws: 256,
+            max_copy_rows: 256,
+            max_exp_steps: 256,
+            max_bytecode: 512,
+            max_evm_rows: 0,
+            max_keccak_rows: 0,
+            max_vertical_circuit_rows: 0,
+        };
+        let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), params)
+            .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        block_convert(&builder).unwrap()
+    }
+
+    fn check<C: Circuit<Fr> + SubCircuit<Fr>>(name: &str) {
+        let circuit = C::new_from_block(&snapshot_block());
+        let summary = layout_summary::<C>(C::Params::default()) + &region_layout(&circuit);
+        check_layout_snapshot(name, &summary);
+    }
+
+    #[test]
+    fn layout_diff() {
+        assert_eq!(diff("a\nb\nc\n", "a\nc\nd\n"), "-b\n+d\n");
+        assert_eq!(diff("a\n", "a\n"), "");
+    }
+
+    #[test]
+    fn layout_snapshots() {
+        for_each_circuit!(check);
+    }
+}
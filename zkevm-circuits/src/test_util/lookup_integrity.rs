@@ -0,0 +1,418 @@
+//! Cross-circuit lookup integrity checks.
+//!
+//! A lookup whose input is missing from the destination table only surfaces
+//! in the MockProver as an opaque `VerifyFailure::Lookup` pointing at a row
+//! of the source circuit. [`check_lookup_integrity`] recomputes, from the
+//! witness [`Block`], the inputs that each circuit looks up into the keccak,
+//! rw, bytecode and mpt tables and reports every input that the destination
+//! table does not contain.
+//!
+//! The MPT table is assigned by the MptCircuit from its own witness, which is
+//! not part of the [`Block`], so the updates it proves are passed separately.
+
+use crate::{
+    instance::public_data_convert,
+    witness::{Block, MptUpdates, Rw},
+};
+use bus_mapping::circuit_input_builder::{
+    keccak_inputs_tx_circuit, CopyDataType, CopyEvent, ExecState, NumberOrHash,
+};
+use eth_types::{geth_types, Field};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// Table targeted by a cross-circuit lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetTable {
+    /// Keccak table, assigned by the Keccak circuit
+    Keccak,
+    /// Rw table, assigned by the State circuit
+    Rw,
+    /// Bytecode table, assigned by the Bytecode circuit
+    Bytecode,
+    /// Mpt table, assigned by the MPT circuit
+    Mpt,
+}
+
+/// Lookup input that does not appear in the table it targets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingLookupEntry {
+    /// Circuit doing the lookup
+    pub source: &'static str,
+    /// Table targeted by the lookup
+    pub table: TargetTable,
+    /// Description of the missing entry
+    pub entry: String,
+}
+
+impl fmt::Display for MissingLookupEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} lookup into {:?} table: missing {}",
+            self.source, self.table, self.entry
+        )
+    }
+}
+
+/// Check that every lookup input of the circuits appears in its destination
+/// table, returning all the missing entries otherwise.
+///
+/// `mpt_updates` are the updates proven by the MptCircuit, which the
+/// StateCircuit looks up.
+pub fn check_lookup_integrity<F: Field>(
+    block: &Block<F>,
+    mpt_updates: &MptUpdates,
+) -> Result<(), Vec<MissingLookupEntry>> {
+    let rws = block.rws.table_assignments();
+    let mut missing = keccak_lookups(block);
+    missing.extend(rw_lookups(block, &rws));
+    missing.extend(bytecode_lookups(block));
+    missing.extend(mpt_lookups(&rws, mpt_updates));
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing)
+    }
+}
+
+/// Check that `updates` contain the update that the StateCircuit looks up for
+/// every account and storage key accessed in `rws`.
+pub fn mpt_lookups(rws: &[Rw], updates: &MptUpdates) -> Vec<MissingLookupEntry> {
+    updates
+        .lookup_mismatches(rws)
+        .into_iter()
+        .map(|(rw, mismatch)| MissingLookupEntry {
+            source: "state circuit",
+            table: TargetTable::Mpt,
+            entry: format!("{} for {:?}", mismatch, rw),
+        })
+        .collect()
+}
+
+/// Short description of a keccak input, eliding long inputs.
+fn describe_keccak_input(input: &[u8]) -> String {
+    const MAX_BYTES: usize = 32;
+    if input.len() <= MAX_BYTES {
+        format!("input 0x{}", hex::encode(input))
+    } else {
+        format!(
+            "input 0x{}.. ({} bytes)",
+            hex::encode(&input[..MAX_BYTES]),
+            input.len()
+        )
+    }
+}
+
+/// Step that generated a copy event, if known.
+fn describe_copy_origin(copy_event: &CopyEvent) -> String {
+    copy_event
+        .origin
+        .map(|origin| {
+            format!(
+                ", from tx {} call {} pc {}",
+                origin.tx_id, origin.call_index, origin.pc
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn keccak_lookups<F: Field>(block: &Block<F>) -> Vec<MissingLookupEntry> {
+    let mut missing = Vec::new();
+    let mut inputs: Vec<(&'static str, Vec<u8>)> = Vec::new();
+
+    // Bytecode circuit: code hashes
+    for bytecode in block.bytecodes.clone() {
+        inputs.push(("bytecode circuit", bytecode.code()));
+    }
+    // Tx circuit: signed messages and public keys
+    let txs: Vec<geth_types::Transaction> = block.txs.iter().map(|tx| tx.tx.clone()).collect();
+    match keccak_inputs_tx_circuit(&txs, block.context.chain_id.as_u64()) {
+        Ok(tx_inputs) => inputs.extend(tx_inputs.into_iter().map(|input| ("tx circuit", input))),
+        Err(err) => missing.push(MissingLookupEntry {
+            source: "tx circuit",
+            table: TargetTable::Keccak,
+            entry: format!("signature inputs ({:?})", err),
+        }),
+    }
+    // EVM circuit: SHA3 inputs, accumulated by the copy circuit
+    for copy_event in &block.copy_events {
+        if copy_event.dst_type == CopyDataType::RlcAcc {
+            let input = copy_event.bytes.iter().map(|(byte, _)| *byte).collect();
+            inputs.push(("evm circuit", input));
+        }
+    }
+    // PI circuit: raw public inputs
+    let rpi_bytes = public_data_convert(block).get_pi_bytes(
+        block.circuits_params.max_txs,
+        block.circuits_params.max_withdrawals,
+        block.circuits_params.max_calldata,
+    );
+    inputs.push(("pi circuit", rpi_bytes));
+
+    let keccak_table: HashSet<&[u8]> = block.keccak_inputs.iter().map(Vec::as_slice).collect();
+    missing.extend(
+        inputs
+            .into_iter()
+            .filter(|(_, input)| !keccak_table.contains(input.as_slice()))
+            .map(|(source, input)| MissingLookupEntry {
+                source,
+                table: TargetTable::Keccak,
+                entry: describe_keccak_input(&input),
+            }),
+    );
+    missing
+}
+
+fn rw_lookups<F: Field>(block: &Block<F>, rws: &[Rw]) -> Vec<MissingLookupEntry> {
+    let mut missing = Vec::new();
+
+    let rws_by_counter: HashMap<usize, &Rw> = rws
+        .iter()
+        .filter(|rw| !matches!(rw, Rw::Start { .. }))
+        .map(|rw| (rw.rw_counter(), rw))
+        .collect();
+
+    // EVM circuit: rws of every step, at the rw counters of the step.  The rws
+    // reverting the writes of a failed call are looked up at the end of the
+    // reversion of the call instead.
+    for tx in &block.txs {
+        for (step_idx, step) in tx.steps().iter().enumerate() {
+            let call = &tx.calls()[step.call_index];
+            let rw_counters = step.rwc.0
+                ..step.rwc.0
+                    + step.bus_mapping_instance.len()
+                    + step.copy_rw_counter_delta as usize;
+            let reversion_rw_counters = if call.is_persistent {
+                0..0
+            } else {
+                rw_counters.end..call.rw_counter_end_of_reversion + 1
+            };
+            for operation in &step.bus_mapping_instance {
+                let rw = block
+                    .rws
+                    .0
+                    .get(&operation.0)
+                    .and_then(|rws| rws.get(operation.1));
+                let found = rw.map_or(false, |rw| {
+                    let rw_counter = rw.rw_counter();
+                    (rw_counters.contains(&rw_counter)
+                        || reversion_rw_counters.contains(&rw_counter))
+                        && rws_by_counter.get(&rw_counter) == Some(&rw)
+                });
+                if !found {
+                    missing.push(MissingLookupEntry {
+                        source: "evm circuit",
+                        table: TargetTable::Rw,
+                        entry: format!(
+                            "{:?} of tx {} step {} ({:?}) at rw counters {:?}: {:?}",
+                            operation, tx.id, step_idx, step.exec_state, rw_counters, rw
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    // Copy circuit: memory and log rws of every copy event
+    for (event_idx, copy_event) in block.copy_events.iter().enumerate() {
+        let rw_counter_start = copy_event.rw_counter(0);
+        for rw_counter in rw_counter_start..rw_counter_start + copy_event.rw_counter_delta() {
+            let found = rws_by_counter
+                .get(&(rw_counter as usize))
+                .map_or(false, |rw| {
+                    matches!(rw, Rw::Memory { .. } | Rw::TxLog { .. })
+                });
+            if !found {
+                missing.push(MissingLookupEntry {
+                    source: "copy circuit",
+                    table: TargetTable::Rw,
+                    entry: format!(
+                        "memory or log rw at rw_counter {} of copy event {} ({:?} -> {:?}{})",
+                        rw_counter,
+                        event_idx,
+                        copy_event.src_type,
+                        copy_event.dst_type,
+                        describe_copy_origin(copy_event)
+                    ),
+                });
+            }
+        }
+    }
+
+    missing
+}
+
+fn bytecode_lookups<F: Field>(block: &Block<F>) -> Vec<MissingLookupEntry> {
+    let mut missing = Vec::new();
+
+    // EVM circuit: opcode of every step
+    for tx in &block.txs {
+        for (step_idx, step) in tx.steps().iter().enumerate() {
+            let ExecState::Op(opcode) = step.exec_state else {
+                continue;
+            };
+            let code_hash = tx.calls()[step.call_index].code_hash;
+            let found = match block.bytecodes.get_from_h256(&code_hash) {
+                // Executing past the end of the code is an implicit STOP, which
+                // is not looked up.
+                Some(bytecode) => bytecode
+                    .get_byte(step.pc as usize)
+                    .map_or(true, |byte| byte == opcode.as_u8()),
+                None => false,
+            };
+            if !found {
+                missing.push(MissingLookupEntry {
+                    source: "evm circuit",
+                    table: TargetTable::Bytecode,
+                    entry: format!(
+                        "{:?} at pc {} of code {:?} (tx {} step {})",
+                        opcode, step.pc, code_hash, tx.id, step_idx
+                    ),
+                });
+            }
+        }
+    }
+
+    // Copy circuit: code copied from the bytecode table
+    for (event_idx, copy_event) in block.copy_events.iter().enumerate() {
+        if copy_event.src_type != CopyDataType::Bytecode {
+            continue;
+        }
+        let NumberOrHash::Hash(code_hash) = copy_event.src_id else {
+            continue;
+        };
+        if block.bytecodes.get_from_h256(&code_hash).is_none() {
+            missing.push(MissingLookupEntry {
+                source: "copy circuit",
+                table: TargetTable::Bytecode,
+                entry: format!(
+                    "code {:#x} of copy event {}{}",
+                    code_hash,
+                    event_idx,
+                    describe_copy_origin(copy_event)
+                ),
+            });
+        }
+    }
+
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::block_convert;
+    use bus_mapping::{circuit_input_builder::ExecStep, mock::BlockData};
+    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, Word};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use mock::TestContext;
+
+    fn sha3_block() -> Block<Fr> {
+        let code = bytecode! {
+            PUSH1(0x20)
+            PUSH1(0x00)
+            SHA3
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        block_convert(&builder).unwrap()
+    }
+
+    /// Check the block against the MPT table loaded by the SuperCircuit.
+    fn check(block: &Block<Fr>) -> Result<(), Vec<MissingLookupEntry>> {
+        check_lookup_integrity(
+            block,
+            &MptUpdates::mock_from(&block.rws.table_assignments()),
+        )
+    }
+
+    fn sha3_step(block: &mut Block<Fr>) -> &mut ExecStep {
+        block.txs[0]
+            .steps_mut()
+            .iter_mut()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::SHA3))
+            .unwrap()
+    }
+
+    fn assert_missing_from(missing: &[MissingLookupEntry], source: &str, table: TargetTable) {
+        assert!(!missing.is_empty());
+        for entry in missing {
+            assert_eq!((entry.source, entry.table), (source, table), "{}", entry);
+        }
+    }
+
+    #[test]
+    fn lookup_integrity_ok() {
+        assert_eq!(check(&sha3_block()), Ok(()));
+    }
+
+    #[test]
+    fn lookup_integrity_missing_keccak_input() {
+        let mut block = sha3_block();
+        let sha3_input = vec![0u8; 0x20];
+        block.keccak_inputs.retain(|input| input != &sha3_input);
+
+        let missing = check(&block).unwrap_err();
+        assert_eq!(
+            missing,
+            vec![MissingLookupEntry {
+                source: "evm circuit",
+                table: TargetTable::Keccak,
+                entry: describe_keccak_input(&sha3_input),
+            }]
+        );
+    }
+
+    #[test]
+    fn lookup_integrity_wrong_rw_counter() {
+        let mut block = sha3_block();
+        let num_block_rws: usize = block.rws.0.values().map(Vec::len).sum();
+        let step = sha3_step(&mut block);
+        // The rws of the step are now looked up past the last rw of the block
+        step.rwc.0 += num_block_rws;
+        let num_rws = step.bus_mapping_instance.len();
+
+        let missing = check(&block).unwrap_err();
+        assert_missing_from(&missing, "evm circuit", TargetTable::Rw);
+        assert_eq!(missing.len(), num_rws);
+    }
+
+    #[test]
+    fn lookup_integrity_wrong_opcode() {
+        let mut block = sha3_block();
+        // PUSH1 is at pc 0
+        sha3_step(&mut block).pc = 0;
+
+        let missing = check(&block).unwrap_err();
+        assert_missing_from(&missing, "evm circuit", TargetTable::Bytecode);
+        assert_eq!(missing.len(), 1);
+    }
+
+    #[test]
+    fn lookup_integrity_wrong_mpt_update() {
+        let block = sha3_block();
+        let mut rws = block.rws.table_assignments();
+        // The MptCircuit proves a different initial value for the first account field
+        let first_account_rw = rws
+            .iter_mut()
+            .find_map(|rw| match rw {
+                Rw::Account { value_prev, .. } => Some(value_prev),
+                _ => None,
+            })
+            .unwrap();
+        *first_account_rw += Word::one();
+
+        let missing = check_lookup_integrity(&block, &MptUpdates::mock_from(&rws)).unwrap_err();
+        assert_missing_from(&missing, "state circuit", TargetTable::Mpt);
+        assert_eq!(missing.len(), 1);
+    }
+}
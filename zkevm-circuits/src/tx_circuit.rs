@@ -14,26 +14,34 @@ mod test;
 pub use dev::TxCircuit as TestTxCircuit;
 
 use crate::{
-    table::{KeccakTable, TxFieldTag, TxTable},
+    table::{tx_table::TX_LEN, KeccakTable, TxFieldTag, TxTable},
     util::{word::WordLoHi, Challenges, SubCircuit, SubCircuitConfig},
     witness,
 };
 use eth_types::{geth_types::Transaction, sign_types::SignData, Field};
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Region, Value},
-    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
 };
 use itertools::Itertools;
 use log::error;
 use sign_verify::{AssignedSignatureVerify, SignVerifyChip, SignVerifyConfig};
 use std::{marker::PhantomData, ops::Deref};
 
-/// Number of static fields per tx: [nonce, gas, gas_price,
-/// caller_address, callee_address, is_create, value, call_data_length,
-/// call_data_gas_cost, tx_sign_hash].
-/// Note that call data bytes are laid out in the TxTable after all the static
-/// fields arranged by txs.
-pub(crate) const TX_LEN: usize = 10;
+/// Fields of a tx which must be zero when its caller address is zero, so that
+/// only padding txs skip the keccak lookup of the signer public key.  A tx with
+/// zero gas can't pay its intrinsic gas, so a zero caller can't sign a tx that
+/// executes.  `IsCreate` is excluded since padding txs have no callee.
+const PADDING_ZERO_FIELDS: [TxFieldTag; 7] = [
+    TxFieldTag::Nonce,
+    TxFieldTag::Gas,
+    TxFieldTag::GasPrice,
+    TxFieldTag::CalleeAddress,
+    TxFieldTag::Value,
+    TxFieldTag::CallDataLength,
+    TxFieldTag::CallDataGasCost,
+];
 
 /// Config for TxCircuit
 #[derive(Clone, Debug)]
@@ -42,6 +50,10 @@ pub struct TxCircuitConfig<F: Field> {
     tag: Column<Fixed>,
     index: Column<Advice>,
     value: WordLoHi<Column<Advice>>,
+    /// Copy of the zero address flag of the SignVerifyChip for the tx of the row
+    is_caller_zero: Column<Advice>,
+    /// Enabled on the rows of [`PADDING_ZERO_FIELDS`]
+    q_padding_field: Selector,
     sign_verify: SignVerifyConfig,
     _marker: PhantomData<F>,
 }
@@ -75,6 +87,22 @@ impl<F: Field> SubCircuitConfig<F> for TxCircuitConfig<F> {
         meta.enable_equality(value.lo());
         meta.enable_equality(value.hi());
 
+        let is_caller_zero = meta.advice_column();
+        meta.enable_equality(is_caller_zero);
+        let q_padding_field = meta.selector();
+
+        // A zero caller address disables the keccak lookup of the public key
+        // hash in the SignVerifyChip, which is only sound for padding txs.
+        meta.create_gate("zero caller only in padding txs", |meta| {
+            let q_padding_field = meta.query_selector(q_padding_field);
+            let is_caller_zero = meta.query_advice(is_caller_zero, Rotation::cur());
+            let value = value.query_advice(meta, Rotation::cur());
+            vec![
+                q_padding_field.clone() * is_caller_zero.clone() * value.lo(),
+                q_padding_field * is_caller_zero * value.hi(),
+            ]
+        });
+
         let sign_verify = SignVerifyConfig::new(meta, keccak_table, challenges);
 
         Self {
@@ -82,6 +110,8 @@ impl<F: Field> SubCircuitConfig<F> for TxCircuitConfig<F> {
             tag,
             index,
             value,
+            is_caller_zero,
+            q_padding_field,
             sign_verify,
             _marker: PhantomData,
         }
@@ -236,6 +266,15 @@ impl<F: Field> TxCircuit<F> {
                     ] {
                         let assigned_cell =
                             config.assign_row(&mut region, offset, i + 1, tag, 0, value)?;
+                        assigned_sig_verif.is_address_zero.copy_advice(
+                            || "is_caller_zero",
+                            &mut region,
+                            config.is_caller_zero,
+                            offset,
+                        )?;
+                        if PADDING_ZERO_FIELDS.contains(&tag) {
+                            config.q_padding_field.enable(&mut region, offset)?;
+                        }
                         offset += 1;
 
                         // Ref. spec 0. Copy constraints using fixed offsets between the tx rows and
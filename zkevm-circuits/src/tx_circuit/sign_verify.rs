@@ -294,6 +294,9 @@ pub(crate) struct AssignedECDSA<F: Field> {
 pub(crate) struct AssignedSignatureVerify<F: Field> {
     pub(crate) address: WordLoHi<AssignedValue<F>>,
     pub(crate) msg_hash: WordLoHi<AssignedValue<F>>,
+    /// Whether the address is zero, which disables the keccak lookup of the
+    /// public key hash
+    pub(crate) is_address_zero: AssignedValue<F>,
 }
 
 // Return an array of bytes that corresponds to the little endian representation
@@ -623,6 +626,7 @@ impl<F: Field> SignVerifyChip<F> {
         Ok(AssignedSignatureVerify {
             address: address_cells,
             msg_hash: msg_hash_cells,
+            is_address_zero,
         })
     }
 
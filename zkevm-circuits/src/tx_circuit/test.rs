@@ -1,9 +1,12 @@
 use super::*;
 use crate::util::{log2_ceil, unusable_rows};
-use eth_types::address;
+use bus_mapping::circuit_input_builder::keccak_inputs_tx_circuit;
+use eth_types::{address, Address};
 use halo2_proofs::{
+    circuit::SimpleFloorPlanner,
     dev::{MockProver, VerifyFailure},
     halo2curves::bn256::Fr,
+    plonk::Circuit,
 };
 use mock::AddrOrWallet;
 
@@ -96,6 +99,92 @@ fn tx_circuit_bad_address() {
     .is_err(),);
 }
 
+#[test]
+fn tx_circuit_zero_address() {
+    const MAX_TXS: usize = 1;
+    const MAX_CALLDATA: usize = 32;
+
+    let mut tx = mock::CORRECT_MOCK_TXS[0].clone();
+    // The zero address disables the signature check of padding txs, but the
+    // public key recovered from the signature still hashes to the signer.
+    tx.from = AddrOrWallet::from(Address::zero());
+
+    assert!(run::<Fr>(
+        vec![tx.into()],
+        mock::MOCK_CHAIN_ID.as_u64(),
+        MAX_TXS,
+        MAX_CALLDATA
+    )
+    .is_err());
+}
+
+/// TxCircuit whose SignVerifyChip assigns every signature as padding, as a
+/// malicious prover claiming the zero caller for a tx it can't sign would.
+struct PaddingSignaturesTxCircuit(TxCircuit<Fr>);
+
+impl Circuit<Fr> for PaddingSignaturesTxCircuit {
+    type Config = <TxCircuit<Fr> as Circuit<Fr>>::Config;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self(TxCircuit::default())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        TxCircuit::<Fr>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        (config, challenges, keccak_table): Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let challenges = challenges.values(&mut layouter);
+        let keccak_inputs = keccak_inputs_tx_circuit(&self.0.txs, self.0.chain_id).unwrap();
+        keccak_table.dev_load(&mut layouter, &keccak_inputs, &challenges)?;
+        config.load_aux_tables(&mut layouter)?;
+        let assigned_sig_verifs =
+            self.0
+                .sign_verify
+                .assign(&config.sign_verify, &mut layouter, &[], &challenges)?;
+        self.0
+            .assign_tx_table(&config, &mut layouter, assigned_sig_verifs)
+    }
+}
+
+#[test]
+fn tx_circuit_zero_caller_without_signature() {
+    const MAX_TXS: usize = 1;
+    const MAX_CALLDATA: usize = 32;
+
+    let chain_id = mock::MOCK_CHAIN_ID.as_u64();
+    let k = log2_ceil(
+        TxCircuit::<Fr>::unusable_rows() + TxCircuit::<Fr>::min_num_rows(MAX_TXS, MAX_CALLDATA),
+    );
+    let verify = |txs: Vec<Transaction>| {
+        let circuit =
+            PaddingSignaturesTxCircuit(TxCircuit::new(MAX_TXS, MAX_CALLDATA, chain_id, txs));
+        MockProver::run(k, &circuit, vec![vec![]]).unwrap().verify()
+    };
+
+    // Padding txs skip the signature check
+    assert_eq!(verify(vec![]), Ok(()));
+
+    let mut tx = mock::CORRECT_MOCK_TXS[0].clone();
+    tx.from = AddrOrWallet::from(Address::zero());
+    let errors = verify(vec![tx.into()]).expect_err("zero caller accepted without a signature");
+    assert!(
+        errors.iter().all(|error| matches!(
+            error,
+            VerifyFailure::ConstraintNotSatisfied { constraint, .. }
+                if constraint.to_string().contains("zero caller only in padding txs")
+        )),
+        "{:?}",
+        errors
+    );
+}
+
 #[test]
 fn variadic_size_check() {
     const MAX_TXS: usize = 2;
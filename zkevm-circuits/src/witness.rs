@@ -3,9 +3,11 @@
 //! used to generate witnesses for circuits.
 
 mod block;
-pub use block::{block_convert, Block, BlockContext};
+pub use block::{block_convert, randomness_from_seed, Block, BlockContext};
 mod mpt;
 pub use mpt::{MptUpdate, MptUpdateRow, MptUpdates};
+mod profile;
+pub use profile::{CircuitRows, UsageProfile};
 mod rw;
 pub use bus_mapping::circuit_input_builder::{Call, ExecStep, Transaction, Withdrawal};
 pub use rw::{Rw, RwMap, RwRow};
@@ -1,29 +1,42 @@
 use super::{ExecStep, Rw, RwMap, Transaction};
 use crate::{
-    evm_circuit::{detect_fixed_table_tags, EvmCircuit},
-    exp_circuit::param::OFFSET_INCREMENT,
+    error::Error,
+    evm_circuit::step::{HasExecutionState, StepUsage},
     instance::public_data_convert,
     table::BlockContextFieldTag,
-    util::{log2_ceil, word::WordLoHi, SubCircuit},
+    util::word::WordLoHi,
+};
+#[cfg(feature = "evm")]
+use crate::{
+    evm_circuit::{detect_fixed_table_tags, EvmCircuit},
+    table::exp_table::OFFSET_INCREMENT,
+    util::{log2_ceil, SubCircuit},
 };
 use bus_mapping::{
     circuit_input_builder::{
         self, CopyEvent, ExpEvent, FeatureConfig, FixedCParams, PrecompileEvents, Withdrawal,
     },
     state_db::CodeDB,
-    Error,
 };
-use eth_types::{sign_types::SignData, Address, Field, ToScalar, Word, H256};
+use eth_types::{sign_types::SignData, Address, Field, ToScalar, ToWord, Word, H256};
 use halo2_proofs::circuit::Value;
 use itertools::Itertools;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use serde::{Deserialize, Serialize};
 
 // TODO: Remove fields that are duplicated in`eth_block`
 /// Block is the struct used by all circuits, which contains all the needed
 /// data for witness generation.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(bound = "F: Field")]
 pub struct Block<F> {
     /// The randomness for random linear combination
+    #[serde(with = "field_repr")]
     pub randomness: F,
+    /// Seed of the randomness, when it was derived by
+    /// [`Block::seed_randomness`]
+    #[serde(default)]
+    pub randomness_seed: Option<u64>,
     /// Transactions in the block
     pub txs: Vec<Transaction>,
     /// EndBlock step that is repeated after the last transaction and before
@@ -59,7 +72,42 @@ pub struct Block<F> {
     pub eth_block: eth_types::Block<eth_types::Transaction>,
 }
 
+/// Serialize a field element by its canonical little endian representation.
+mod field_repr {
+    use eth_types::Field;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<F: Field, S: Serializer>(
+        value: &F,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.to_repr().serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, F: Field, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<F, D::Error> {
+        let repr = <[u8; 32]>::deserialize(deserializer)?;
+        Option::from(F::from_repr(repr))
+            .ok_or_else(|| D::Error::custom("non canonical field element"))
+    }
+}
+
+/// Randomness derived from `seed`, the same on every machine.
+pub fn randomness_from_seed<F: Field>(seed: u64) -> F {
+    <F as halo2_proofs::arithmetic::Field>::random(ChaCha20Rng::seed_from_u64(seed))
+}
+
 impl<F: Field> Block<F> {
+    /// Set the randomness to the one derived from `seed`, and record the seed
+    /// in the block.  With the `mock-challenge` feature the randomness is the
+    /// value of the challenges of the SuperCircuit, so that a MockProver run
+    /// of the block can be replayed from the recorded seed.
+    pub fn seed_randomness(&mut self, seed: u64) {
+        self.randomness = randomness_from_seed(seed);
+        self.randomness_seed = Some(seed);
+    }
+
     /// For each tx, for each step, print the rwc at the beginning of the step,
     /// and all the rw operations of the step.
     #[allow(dead_code, reason = "useful debug function")]
@@ -129,6 +177,7 @@ impl<F: Field> Block<F> {
     /// Obtains the expected Circuit degree needed in order to be able to test
     /// the EvmCircuit with this block without needing to configure the
     /// `ConstraintSystem`.
+    #[cfg(feature = "evm")]
     pub fn get_test_degree(&self) -> u32 {
         let num_rows_required_for_execution_steps: usize =
             EvmCircuit::<F>::get_num_rows_required(self);
@@ -181,7 +230,7 @@ impl<F: Field> Block<F> {
 }
 
 /// Block context for execution
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct BlockContext {
     /// The address of the miner for the block
     pub coinbase: Address,
@@ -204,59 +253,37 @@ pub struct BlockContext {
 }
 
 impl BlockContext {
+    /// Value of the field `tag`, or `None` for the indexed
+    /// [`BlockContextFieldTag::BlockHash`].
+    pub fn field(&self, tag: BlockContextFieldTag) -> Option<Word> {
+        Some(match tag {
+            BlockContextFieldTag::Coinbase => self.coinbase.to_word(),
+            BlockContextFieldTag::Timestamp => self.timestamp,
+            BlockContextFieldTag::Number => self.number,
+            BlockContextFieldTag::Difficulty => self.difficulty,
+            BlockContextFieldTag::GasLimit => Word::from(self.gas_limit),
+            BlockContextFieldTag::BaseFee => self.base_fee,
+            BlockContextFieldTag::BlockHash => return None,
+            BlockContextFieldTag::ChainId => self.chain_id,
+            BlockContextFieldTag::WithdrawalRoot => self.withdrawals_root,
+        })
+    }
+
     /// Assignments for block table
     pub fn table_assignments<F: Field>(&self) -> Vec<[Value<F>; 4]> {
         [
-            vec![
-                [
-                    Value::known(F::from(BlockContextFieldTag::Coinbase as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(WordLoHi::from(self.coinbase).lo()),
-                    Value::known(WordLoHi::from(self.coinbase).hi()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::Timestamp as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(self.timestamp.to_scalar().unwrap()),
-                    Value::known(F::ZERO),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::Number as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(self.number.to_scalar().unwrap()),
-                    Value::known(F::ZERO),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::Difficulty as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(WordLoHi::from(self.difficulty).lo()),
-                    Value::known(WordLoHi::from(self.difficulty).hi()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::GasLimit as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(F::from(self.gas_limit)),
-                    Value::known(F::ZERO),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::BaseFee as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(WordLoHi::from(self.base_fee).lo()),
-                    Value::known(WordLoHi::from(self.base_fee).hi()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::ChainId as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(WordLoHi::from(self.chain_id).lo()),
-                    Value::known(WordLoHi::from(self.chain_id).hi()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::WithdrawalRoot as u64)),
-                    Value::known(F::ZERO),
-                    Value::known(WordLoHi::from(self.withdrawals_root).lo()),
-                    Value::known(WordLoHi::from(self.withdrawals_root).hi()),
-                ],
-            ],
+            BlockContextFieldTag::SINGLE_VALUE_FIELDS
+                .iter()
+                .map(|tag| {
+                    let value = WordLoHi::<F>::from(self.field(*tag).unwrap());
+                    [
+                        Value::known(F::from(*tag as u64)),
+                        Value::known(F::ZERO),
+                        Value::known(value.lo()),
+                        Value::known(value.hi()),
+                    ]
+                })
+                .collect(),
             {
                 let len_history = self.history_hashes.len();
                 self.history_hashes
@@ -293,6 +320,49 @@ impl From<&circuit_input_builder::Block> for BlockContext {
     }
 }
 
+/// Check that the rw and gas usage of every step matches the usage fixed by
+/// the gadget of its execution state, for the states where it is fixed. A
+/// mismatch means that bus-mapping and the EVM circuit gadget disagree on the
+/// step. Every mismatch is logged and the first one is returned.
+pub(crate) fn check_step_usage(
+    txs: &[Transaction],
+    feature_config: &FeatureConfig,
+) -> Result<(), Error> {
+    let mut mismatch = None;
+    for tx in txs {
+        for step in tx.steps() {
+            let execution_state = step.execution_state();
+            let Some(expected) = execution_state.fixed_usage(feature_config) else {
+                continue;
+            };
+            let usage = StepUsage {
+                rw_count: step.rw_indices_len(),
+                gas_cost: step.gas_cost,
+            };
+            if usage != expected {
+                log::error!(
+                    "{:?} step at pc {} of tx {} uses {:?}, expected {:?}",
+                    execution_state,
+                    step.pc,
+                    tx.id,
+                    usage,
+                    expected
+                );
+                mismatch.get_or_insert(Error::StepUsageMismatch {
+                    tx: tx.id as usize,
+                    pc: step.pc,
+                    execution_state: format!("{:?}", execution_state),
+                    rw_count: usage.rw_count,
+                    expected_rw_count: expected.rw_count,
+                    gas_cost: usage.gas_cost,
+                    expected_gas_cost: expected.gas_cost,
+                });
+            }
+        }
+    }
+    mismatch.map_or(Ok(()), Err)
+}
+
 /// Convert a block struct in bus-mapping to a witness block used in circuits
 pub fn block_convert<F: Field>(
     builder: &circuit_input_builder::CircuitInputBuilder<FixedCParams>,
@@ -301,9 +371,11 @@ pub fn block_convert<F: Field>(
     let code_db = &builder.code_db;
     let rws = RwMap::from(&block.container);
     rws.check_value();
+    check_step_usage(block.txs(), &builder.feature_config)?;
     let mut block = Block {
         // randomness: F::from(0x100), // Special value to reveal elements after RLC
         randomness: F::from(0xcafeu64),
+        randomness_seed: None,
         context: block.into(),
         rws,
         txs: block.txs().to_vec(),
@@ -314,7 +386,7 @@ pub fn block_convert<F: Field>(
         exp_events: block.exp_events.clone(),
         sha3_inputs: block.sha3_inputs.clone(),
         circuits_params: builder.circuits_params,
-        feature_config: builder.feature_config,
+        feature_config: builder.feature_config.clone(),
         exp_circuit_pad_to: <usize>::default(),
         prev_state_root: block.prev_state_root,
         keccak_inputs: circuit_input_builder::keccak_inputs(block, code_db)?,
@@ -331,3 +403,57 @@ pub fn block_convert<F: Field>(
     block.keccak_inputs.extend_from_slice(&[rpi_bytes]);
     Ok(block)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData};
+    use mock::TestContext;
+
+    #[test]
+    fn step_usage_mismatch() {
+        let code = bytecode! {
+            PUSH1(0x02)
+            PUSH1(0x03)
+            ADD
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let mut txs = builder.block.txs().to_vec();
+        check_step_usage(&txs, &builder.feature_config).unwrap();
+
+        let add = txs[0]
+            .steps_mut()
+            .iter_mut()
+            .find(|step| step.opcode() == Some(OpcodeId::ADD))
+            .unwrap();
+        let pc = add.pc;
+        add.gas_cost += 1;
+
+        match check_step_usage(&txs, &builder.feature_config) {
+            Err(Error::StepUsageMismatch {
+                tx,
+                pc: mismatch_pc,
+                execution_state,
+                rw_count,
+                expected_rw_count,
+                gas_cost,
+                expected_gas_cost,
+            }) => {
+                assert_eq!(tx, 1);
+                assert_eq!(mismatch_pc, pc);
+                assert_eq!(execution_state, "ADD_SUB");
+                assert_eq!(rw_count, expected_rw_count);
+                assert_eq!(gas_cost, expected_gas_cost + 1);
+            }
+            result => panic!("expected a step usage mismatch, got {:?}", result),
+        }
+    }
+}
@@ -58,6 +58,60 @@ impl MptUpdates {
         self.old_root
     }
 
+    /// Return true if `row` does not need an MPT update or its update is
+    /// present.
+    pub fn contains(&self, row: &Rw) -> bool {
+        key(row).map_or(true, |key| self.updates.contains_key(&key))
+    }
+
+    /// Compare the updates with the lookups done by the StateCircuit for
+    /// `rows`, which must be sorted as in the RwTable.  The StateCircuit looks
+    /// up one update per key, from the value before its first access to the
+    /// value after its last access, with the root chained from the previous
+    /// key.  Return the first row of every key whose update is missing or
+    /// differs, with a description of the difference.
+    pub fn lookup_mismatches(&self, rows: &[Rw]) -> Vec<(Rw, String)> {
+        let mut mismatches = Vec::new();
+        let mut root = self.old_root;
+        for (key, rows) in &rows.iter().group_by(|row| key(row)) {
+            let Some(key) = key else {
+                continue;
+            };
+            let rows = rows.collect_vec();
+            let (first, last) = (rows[0], rows[rows.len() - 1]);
+            let (old_value, new_value) = (value_prev(first), value(last));
+            let Some(update) = self.updates.get(&key) else {
+                mismatches.push((*first, "no update".to_string()));
+                continue;
+            };
+            let expected_key = key.set_non_exists(old_value, new_value);
+            if update.key != expected_key {
+                mismatches.push((
+                    *first,
+                    format!("update of {:?}, expected {:?}", update.key, expected_key),
+                ));
+            } else if (update.old_value, update.new_value) != (old_value, new_value) {
+                mismatches.push((
+                    *first,
+                    format!(
+                        "update from {:#x} to {:#x}, expected from {:#x} to {:#x}",
+                        update.old_value, update.new_value, old_value, new_value
+                    ),
+                ));
+            } else if update.old_root != root {
+                mismatches.push((
+                    *first,
+                    format!(
+                        "update from root {:#x}, expected the previous root {:#x}",
+                        update.old_root, root
+                    ),
+                ));
+            }
+            root = update.new_root;
+        }
+        mismatches
+    }
+
     pub(crate) fn get(&self, row: &Rw) -> Option<MptUpdate> {
         key(row).map(|key| *self.updates.get(&key).expect("missing key in mpt updates"))
     }
@@ -0,0 +1,174 @@
+//! Attribution of the rows used by the sub-circuits to execution states.
+
+use super::Block;
+use crate::{
+    evm_circuit::step::{ExecutionState, HasExecutionState},
+    table::exp_table::OFFSET_INCREMENT,
+};
+use eth_types::Field;
+use std::{collections::HashMap, fmt};
+
+/// Rows used in each sub-circuit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CircuitRows {
+    /// Rows of the EVM circuit
+    pub evm: usize,
+    /// Rows of the State circuit
+    pub state: usize,
+    /// Rows of the Copy circuit
+    pub copy: usize,
+    /// Rows of the Exponentiation circuit
+    pub exp: usize,
+}
+
+impl CircuitRows {
+    fn add(&mut self, other: &Self) {
+        self.evm += other.evm;
+        self.state += other.state;
+        self.copy += other.copy;
+        self.exp += other.exp;
+    }
+}
+
+/// Rows used by every execution state of a block.
+///
+/// Copy and exponentiation events are attributed to the step that emitted
+/// them, found by their rw counter. Rows used for padding or by the fixed
+/// parts of the circuits are not attributed.
+#[derive(Debug, Clone, Default)]
+pub struct UsageProfile {
+    /// Rows used by each execution state
+    pub by_state: HashMap<ExecutionState, CircuitRows>,
+    /// Sum of the rows of all the execution states
+    pub total: CircuitRows,
+}
+
+impl UsageProfile {
+    /// Compute the profile of the witness `block`.
+    pub fn from_block<F: Field>(block: &Block<F>) -> Self {
+        let mut by_state: HashMap<ExecutionState, CircuitRows> = HashMap::new();
+        // Rw counter at the beginning of every step, in execution order
+        let mut step_rwcs = Vec::new();
+        for tx in &block.txs {
+            for step in tx.steps() {
+                let state = step.execution_state();
+                let rows = by_state.entry(state).or_default();
+                rows.evm += state.get_step_height();
+                rows.state += step.bus_mapping_instance.len();
+                step_rwcs.push((step.rwc.0, state));
+            }
+        }
+        let step_at = |rw_counter: usize| {
+            let idx = step_rwcs.partition_point(|(rwc, _)| *rwc <= rw_counter);
+            idx.checked_sub(1).map(|idx| step_rwcs[idx].1)
+        };
+        for copy_event in &block.copy_events {
+            if let Some(state) = step_at(copy_event.rw_counter_start.0) {
+                by_state.entry(state).or_default().copy += copy_event.bytes.len() * 2;
+            }
+        }
+        for exp_event in &block.exp_events {
+            // The identifier is the rw counter right after the EXP step.
+            if let Some(state) = step_at(exp_event.identifier.saturating_sub(1)) {
+                by_state.entry(state).or_default().exp += exp_event.steps.len() * OFFSET_INCREMENT;
+            }
+        }
+
+        let mut total = CircuitRows::default();
+        for rows in by_state.values() {
+            total.add(rows);
+        }
+        Self { by_state, total }
+    }
+
+    /// Execution states sorted by decreasing number of rows in the EVM
+    /// circuit, then in the State circuit.
+    pub fn sorted(&self) -> Vec<(ExecutionState, CircuitRows)> {
+        let mut states: Vec<_> = self
+            .by_state
+            .iter()
+            .map(|(state, rows)| (*state, *rows))
+            .collect();
+        states.sort_by_key(|(state, rows)| {
+            (
+                std::cmp::Reverse((rows.evm, rows.state)),
+                format!("{:?}", state),
+            )
+        });
+        states
+    }
+}
+
+impl fmt::Display for UsageProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |rows: usize, total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                100.0 * rows as f64 / total as f64
+            }
+        };
+        for (state, rows) in self.sorted() {
+            writeln!(
+                f,
+                "{:?}: {:.1}% of evm rows, {:.1}% of state rows, {:.1}% of copy rows, {:.1}% of exp rows",
+                state,
+                percent(rows.evm, self.total.evm),
+                percent(rows.state, self.total.state),
+                percent(rows.copy, self.total.copy),
+                percent(rows.exp, self.total.exp),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::block_convert;
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use mock::TestContext;
+
+    #[test]
+    fn usage_profile() {
+        let code = bytecode! {
+            PUSH1(0x40)
+            PUSH1(0x00)
+            SHA3
+            PUSH1(0x03)
+            PUSH1(0x02)
+            EXP
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let builder = BlockData::new_from_geth_data(block.clone())
+            .new_circuit_input_builder()
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder).unwrap();
+        let profile = UsageProfile::from_block(&block);
+
+        let sha3 = profile.by_state[&ExecutionState::SHA3];
+        assert_eq!(sha3.evm, ExecutionState::SHA3.get_step_height());
+        assert_eq!(sha3.copy, 0x40 * 2);
+        assert_eq!(sha3.copy, profile.total.copy);
+
+        let exp = profile.by_state[&ExecutionState::EXP];
+        assert_eq!(exp.exp, profile.total.exp);
+        assert!(exp.exp > 0);
+
+        let num_rws: usize = block
+            .txs
+            .iter()
+            .flat_map(|tx| tx.steps())
+            .map(|step| step.bus_mapping_instance.len())
+            .sum();
+        assert_eq!(profile.total.state, num_rws);
+        assert!(profile.to_string().contains("SHA3: "));
+    }
+}
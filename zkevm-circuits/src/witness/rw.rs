@@ -8,6 +8,7 @@ use bus_mapping::{
 use eth_types::{Address, Field, ToAddress, ToScalar, Word, U256};
 use halo2_proofs::circuit::Value;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     table::{AccountFieldTag, CallContextFieldTag, TxLogFieldTag, TxReceiptFieldTag},
@@ -17,7 +18,7 @@ use crate::{
 use super::MptUpdates;
 
 /// Rw container for a witness block
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RwMap(pub HashMap<Target, Vec<Rw>>);
 
 impl std::ops::Index<(Target, usize)> for RwMap {
@@ -158,7 +159,7 @@ impl RwMap {
 )]
 /// Read-write records in execution. Rws are used for connecting evm circuit and
 /// state circuits.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Rw {
     /// Start
     Start { rw_counter: usize },